@@ -11,14 +11,17 @@
 // A CLI that provides tools to manage Pravega streams.
 
 use clap::Clap;
+use std::io::{Cursor, Seek, SeekFrom, Write};
 use std::time::{Duration, SystemTime};
 
 use pravega_client::client_factory::ClientFactory;
 use pravega_client_config::ClientConfigBuilder;
-use pravega_client_shared::{Scope, Stream, Segment, ScopedSegment};
+use pravega_client_shared::{Scope, Stream, StreamConfiguration, ScopedStream};
 use pravega_video::index::{IndexSearcher, SearchMethod, get_index_stream_name};
-use pravega_video::utils::parse_controller_uri;
+use pravega_video::index_rebuilder::IndexRebuilder;
+use pravega_video::utils::{byte_stream_scaling, get_byte_stream_segment, parse_controller_uri};
 use pravega_video::timestamp::PravegaTimestamp;
+use pravega_video::verifier::StreamVerifier;
 
 /// Tools to manage Pravega streams.
 #[derive(Clap)]
@@ -33,6 +36,8 @@ struct Opts {
 #[derive(Clap)]
 enum SubCommand {
     TruncateStream(TruncateStream),
+    VerifyStream(VerifyStream),
+    RebuildIndex(RebuildIndex),
 }
 
 /// Truncate a stream written by the pravegasink GStreamer plugin.
@@ -49,6 +54,37 @@ struct TruncateStream {
     age_days: f64,
 }
 
+/// Verify that the index and data stream written by the pravegasink GStreamer plugin are consistent.
+/// Exits with status 1 if any violations are found.
+#[derive(Clap)]
+struct VerifyStream {
+    /// Pravega scope
+    #[clap(long)]
+    scope: String,
+    /// Pravega stream
+    #[clap(long)]
+    stream: String,
+}
+
+/// Rebuild the index of a stream written by the pravegasink GStreamer plugin by scanning the data stream.
+/// Any existing records in the index stream will be truncated.
+/// The index stream must not be sealed and pravegasink must not be writing to the stream.
+#[derive(Clap)]
+struct RebuildIndex {
+    /// Pravega scope
+    #[clap(long)]
+    scope: String,
+    /// Pravega stream
+    #[clap(long)]
+    stream: String,
+    /// Minimum duration between index records, in seconds. Same as pravegasink index-min-sec.
+    #[clap(long, default_value = "0.5")]
+    index_min_sec: f64,
+    /// Maximum duration between index records, in seconds. Same as pravegasink index-max-sec.
+    #[clap(long, default_value = "10.0")]
+    index_max_sec: f64,
+}
+
 fn main() {
     env_logger::init();
     let opts: Opts = Opts::parse();
@@ -56,6 +92,15 @@ fn main() {
         SubCommand::TruncateStream(c) => {
             truncate_stream(opts.controller, c.scope, c.stream, c.age_days);
         }
+        SubCommand::VerifyStream(c) => {
+            let ok = verify_stream(opts.controller, c.scope, c.stream);
+            if !ok {
+                std::process::exit(1);
+            }
+        }
+        SubCommand::RebuildIndex(c) => {
+            rebuild_index(opts.controller, c.scope, c.stream, c.index_min_sec, c.index_max_sec);
+        }
     }
 }
 
@@ -76,17 +121,15 @@ fn truncate_stream(controller: String, scope_name: String, stream_name: String,
         .expect("creating config");
     let client_factory = ClientFactory::new(client_config);
     let runtime = client_factory.get_runtime();
-    let scoped_segment = ScopedSegment {
+    let scoped_segment = get_byte_stream_segment(&client_factory, &ScopedStream {
         scope: scope.clone(),
         stream: stream.clone(),
-        segment: Segment::from(0),
-    };
+    }).unwrap();
     let writer = client_factory.create_byte_stream_writer(scoped_segment);
-    let index_scoped_segment = ScopedSegment {
+    let index_scoped_segment = get_byte_stream_segment(&client_factory, &ScopedStream {
         scope: scope.clone(),
         stream: index_stream.clone(),
-        segment: Segment::from(0),
-    };
+    }).unwrap();
     let index_writer = client_factory.create_byte_stream_writer(index_scoped_segment.clone());
     let index_reader = client_factory.create_byte_stream_reader(index_scoped_segment.clone());
     let mut index_searcher = IndexSearcher::new(index_reader);
@@ -98,3 +141,93 @@ fn truncate_stream(controller: String, scope_name: String, stream_name: String,
     runtime.block_on(writer.truncate_data_before(index_record.0.offset as i64)).unwrap();
     println!("Data truncated at offset {}", index_record.0.offset);
 }
+
+fn verify_stream(controller: String, scope_name: String, stream_name: String) -> bool {
+    println!("Verifying stream {}/{}", scope_name, stream_name);
+    let index_stream_name = get_index_stream_name(&stream_name);
+    let scope = Scope::from(scope_name);
+    let stream = Stream::from(stream_name);
+    let index_stream = Stream::from(index_stream_name);
+    let controller_uri = parse_controller_uri(controller).unwrap();
+    let client_config = ClientConfigBuilder::default()
+        .controller_uri(controller_uri)
+        .build()
+        .expect("creating config");
+    let client_factory = ClientFactory::new(client_config);
+    let scoped_segment = get_byte_stream_segment(&client_factory, &ScopedStream {
+        scope: scope.clone(),
+        stream: stream.clone(),
+    }).unwrap();
+    let index_scoped_segment = get_byte_stream_segment(&client_factory, &ScopedStream {
+        scope: scope.clone(),
+        stream: index_stream.clone(),
+    }).unwrap();
+    let reader = client_factory.create_byte_stream_reader(scoped_segment);
+    let index_reader = client_factory.create_byte_stream_reader(index_scoped_segment);
+    let mut verifier = StreamVerifier::new(reader, index_reader);
+    let report = verifier.verify().unwrap();
+    print!("{}", report);
+    report.is_ok()
+}
+
+fn rebuild_index(controller: String, scope_name: String, stream_name: String, index_min_sec: f64, index_max_sec: f64) {
+    println!("Rebuilding index of stream {}/{}", scope_name, stream_name);
+    let index_stream_name = get_index_stream_name(&stream_name);
+    let scope = Scope::from(scope_name);
+    let stream = Stream::from(stream_name);
+    let index_stream = Stream::from(index_stream_name);
+    let controller_uri = parse_controller_uri(controller).unwrap();
+    let client_config = ClientConfigBuilder::default()
+        .controller_uri(controller_uri)
+        .build()
+        .expect("creating config");
+    let client_factory = ClientFactory::new(client_config);
+    let controller_client = client_factory.get_controller_client();
+    let runtime = client_factory.get_runtime();
+
+    // Create the index stream if it was deleted.
+    let index_stream_config = StreamConfiguration {
+        scoped_stream: ScopedStream {
+            scope: scope.clone(),
+            stream: index_stream.clone(),
+        },
+        scaling: byte_stream_scaling(),
+        retention: Default::default(),
+    };
+    runtime.block_on(controller_client.create_stream(&index_stream_config)).unwrap();
+
+    let scoped_segment = get_byte_stream_segment(&client_factory, &ScopedStream {
+        scope: scope.clone(),
+        stream: stream.clone(),
+    }).unwrap();
+    let index_scoped_segment = get_byte_stream_segment(&client_factory, &ScopedStream {
+        scope: scope.clone(),
+        stream: index_stream.clone(),
+    }).unwrap();
+
+    // Rebuild the index in memory so that the existing index is not modified if the rebuild fails.
+    let reader = client_factory.create_byte_stream_reader(scoped_segment);
+    let mut rebuilder = IndexRebuilder::new(reader,
+        (index_min_sec * 1e9) as u64, (index_max_sec * 1e9) as u64);
+    let mut new_index = Cursor::new(Vec::new());
+    let summary = rebuilder.rebuild(&mut new_index).unwrap();
+
+    // Append the new index records and then truncate the existing ones.
+    // Readers will only see records written after the truncation point.
+    // Until then, readers may see the existing records followed by the new ones.
+    let mut index_reader = client_factory.create_byte_stream_reader(index_scoped_segment.clone());
+    let index_tail = index_reader.seek(SeekFrom::End(0)).unwrap();
+    let mut index_writer = client_factory.create_byte_stream_writer(index_scoped_segment);
+    index_writer.seek_to_tail();
+    index_writer.write_all(new_index.get_ref()).unwrap();
+    index_writer.flush().unwrap();
+    runtime.block_on(index_writer.truncate_data_before(index_tail as i64)).unwrap();
+    println!("Index truncated at offset {}", index_tail);
+    println!("Read {} events from offset {} to {}", summary.events_read, summary.data_head, summary.data_tail);
+    println!("Wrote {} index records", summary.index_records_written);
+    println!("First index record: {:?}", summary.first_index_record);
+    println!("Last index record: {:?}", summary.last_index_record);
+    if summary.incomplete_tail_bytes > 0 {
+        println!("Ignored {} bytes at the tail of the data stream", summary.incomplete_tail_bytes);
+    }
+}
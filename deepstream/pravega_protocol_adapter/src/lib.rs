@@ -104,6 +104,8 @@ impl EventStreamWriterPool {
                 let controller_client = self.client_factory.get_controller_client();
                 // This StreamConfiguration will be used only if the stream does not yet exist.
                 // If the stream already exists, it will not be changed.
+                // This is an event stream, not a byte stream, but it is pinned to a single fixed segment
+                // because the adapter has no configuration for a scaling policy.
                 let stream_config = StreamConfiguration {
                     scoped_stream: scoped_stream.clone(),
                     scaling: Scaling {
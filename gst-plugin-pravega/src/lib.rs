@@ -10,11 +10,14 @@
 
 mod counting_reader;
 mod counting_writer;
+pub mod meta_serializer;
 mod numeric;
 mod pravegasink;
 mod pravegasrc;
 mod seekable_byte_stream_writer;
 mod seekable_take;
+mod stats;
+pub mod structure_meta;
 pub mod utils;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
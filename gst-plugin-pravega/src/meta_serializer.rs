@@ -0,0 +1,179 @@
+//
+// Copyright (c) Dell Inc., or its subsidiaries. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+
+// A registry of serializers that allow pravegasink to persist GstMeta in event header extensions
+// and pravegasrc to attach them to the buffers that it creates.
+// See pravega_video::event_serde::HeaderExtension::gst_meta.
+//
+// Serializers are registered for GstReferenceTimestampMeta, GstProtectionMeta, GstVideoRegionOfInterestMeta
+// (bounding boxes) and GstPravegaStructureMeta (arbitrary application data, see structure_meta.rs).
+// KLV and SEI are out of scope because the supported GStreamer versions have no standard meta for them.
+// Applications can carry them in a GstPravegaStructureMeta or register a serializer for their own meta.
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use crate::structure_meta::{StructureMeta, STRUCTURE_META_API_NAME};
+
+/// Converts the metas of one API to and from bytes.
+pub trait MetaSerializer: Send + Sync {
+    /// Returns the serialized form of each meta of this API that is attached to buffer.
+    fn serialize(&self, buffer: &gst::BufferRef) -> Vec<Vec<u8>>;
+
+    /// Attaches a meta to buffer from data that was returned by serialize().
+    fn deserialize(&self, buffer: &mut gst::BufferRef, data: &[u8]) -> Result<(), String>;
+}
+
+pub const REFERENCE_TIMESTAMP_META_API_NAME: &str = "GstReferenceTimestampMetaAPI";
+pub const PROTECTION_META_API_NAME: &str = "GstProtectionMetaAPI";
+pub const VIDEO_REGION_OF_INTEREST_META_API_NAME: &str = "GstVideoRegionOfInterestMetaAPI";
+
+static REGISTRY: Lazy<RwLock<HashMap<String, Arc<dyn MetaSerializer>>>> = Lazy::new(|| {
+    let mut serializers: HashMap<String, Arc<dyn MetaSerializer>> = HashMap::new();
+    serializers.insert(REFERENCE_TIMESTAMP_META_API_NAME.to_owned(), Arc::new(ReferenceTimestampMetaSerializer {}));
+    serializers.insert(PROTECTION_META_API_NAME.to_owned(), Arc::new(ProtectionMetaSerializer {}));
+    serializers.insert(VIDEO_REGION_OF_INTEREST_META_API_NAME.to_owned(), Arc::new(VideoRegionOfInterestMetaSerializer {}));
+    serializers.insert(STRUCTURE_META_API_NAME.to_owned(), Arc::new(StructureMetaSerializer {}));
+    RwLock::new(serializers)
+});
+
+/// Registers a serializer for the metas with the given API name, replacing any previous serializer for this name.
+/// Applications can use this to persist their own metas.
+/// The same serializers must be registered in the process that writes and the process that reads.
+pub fn register(api_name: &str, serializer: Arc<dyn MetaSerializer>) {
+    REGISTRY.write().unwrap().insert(api_name.to_owned(), serializer);
+}
+
+/// Returns the serializer registered for the API name.
+pub fn get(api_name: &str) -> Option<Arc<dyn MetaSerializer>> {
+    REGISTRY.read().unwrap().get(api_name).cloned()
+}
+
+/// Serializes GstReferenceTimestampMeta as the timestamp (64-bit BE), the duration (64-bit BE, u64::MAX if none),
+/// and the reference caps as a UTF-8 string.
+struct ReferenceTimestampMetaSerializer {}
+
+impl MetaSerializer for ReferenceTimestampMetaSerializer {
+    fn serialize(&self, buffer: &gst::BufferRef) -> Vec<Vec<u8>> {
+        buffer.iter_meta::<gst::ReferenceTimestampMeta>().map(|meta| {
+            let mut data = Vec::new();
+            data.extend_from_slice(&meta.timestamp().nseconds().unwrap_or(u64::MAX).to_be_bytes()[..]);
+            data.extend_from_slice(&meta.duration().nseconds().unwrap_or(u64::MAX).to_be_bytes()[..]);
+            data.extend_from_slice(meta.reference().to_string().as_bytes());
+            data
+        }).collect()
+    }
+
+    fn deserialize(&self, buffer: &mut gst::BufferRef, data: &[u8]) -> Result<(), String> {
+        if data.len() < 16 {
+            return Err(format!("Invalid reference timestamp meta of {} bytes", data.len()));
+        }
+        let clock_time = |bytes: &[u8]| match u64::from_be_bytes(bytes.try_into().unwrap()) {
+            u64::MAX => gst::ClockTime(None),
+            nanoseconds => gst::ClockTime(Some(nanoseconds)),
+        };
+        let timestamp = clock_time(&data[0..8]);
+        let duration = clock_time(&data[8..16]);
+        let reference = std::str::from_utf8(&data[16..]).map_err(|err| err.to_string())?;
+        let reference = gst::Caps::from_str(reference).map_err(|err| err.to_string())?;
+        gst::ReferenceTimestampMeta::add(buffer, &reference, timestamp, duration);
+        Ok(())
+    }
+}
+
+/// Serializes GstProtectionMeta as its GstStructure in string form.
+/// Decryptors interpret this meta as the encryption parameters of the buffer, so it must only be used for that purpose.
+/// Use GstPravegaStructureMeta for other per-buffer metadata.
+struct ProtectionMetaSerializer {}
+
+impl MetaSerializer for ProtectionMetaSerializer {
+    fn serialize(&self, buffer: &gst::BufferRef) -> Vec<Vec<u8>> {
+        buffer.iter_meta::<gst::ProtectionMeta>().map(|meta| {
+            meta.info().to_string().into_bytes()
+        }).collect()
+    }
+
+    fn deserialize(&self, buffer: &mut gst::BufferRef, data: &[u8]) -> Result<(), String> {
+        let info = std::str::from_utf8(data).map_err(|err| err.to_string())?;
+        let info = gst::Structure::from_str(info).map_err(|err| err.to_string())?;
+        gst::ProtectionMeta::add(buffer, info);
+        Ok(())
+    }
+}
+
+/// Serializes GstVideoRegionOfInterestMeta, which is commonly used for the bounding boxes of detected objects.
+/// The format is x, y, width, height (32-bit BE), id, parent id (32-bit signed BE), the ROI type,
+/// and each parameter structure in string form, with the ROI type and each parameter followed by a NUL byte.
+struct VideoRegionOfInterestMetaSerializer {}
+
+impl MetaSerializer for VideoRegionOfInterestMetaSerializer {
+    fn serialize(&self, buffer: &gst::BufferRef) -> Vec<Vec<u8>> {
+        buffer.iter_meta::<gst_video::VideoRegionOfInterestMeta>().map(|meta| {
+            let mut data = Vec::new();
+            let (x, y, width, height) = meta.rect();
+            for value in [x, y, width, height].iter() {
+                data.extend_from_slice(&value.to_be_bytes()[..]);
+            }
+            data.extend_from_slice(&meta.id().to_be_bytes()[..]);
+            data.extend_from_slice(&meta.parent_id().to_be_bytes()[..]);
+            data.extend_from_slice(meta.roi_type().as_bytes());
+            data.push(0);
+            for param in meta.params() {
+                data.extend_from_slice(param.to_string().as_bytes());
+                data.push(0);
+            }
+            data
+        }).collect()
+    }
+
+    fn deserialize(&self, buffer: &mut gst::BufferRef, data: &[u8]) -> Result<(), String> {
+        if data.len() < 25 || data.last() != Some(&0) {
+            return Err(format!("Invalid region of interest meta of {} bytes", data.len()));
+        }
+        let u32_at = |pos: usize| u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap());
+        let i32_at = |pos: usize| i32::from_be_bytes(data[pos..pos + 4].try_into().unwrap());
+        let rect = (u32_at(0), u32_at(4), u32_at(8), u32_at(12));
+        let id = i32_at(16);
+        let parent_id = i32_at(20);
+        let mut strings = data[24..data.len() - 1].split(|byte| *byte == 0)
+            .map(|bytes| std::str::from_utf8(bytes).map_err(|err| err.to_string()));
+        let roi_type = strings.next().unwrap()?;
+        let params = strings
+            .map(|param| gst::Structure::from_str(param?).map_err(|err| err.to_string()))
+            .collect::<Result<Vec<_>, String>>()?;
+        let mut meta = gst_video::VideoRegionOfInterestMeta::add(buffer, roi_type, rect);
+        meta.set_id(id);
+        meta.set_parent_id(parent_id);
+        for param in params {
+            meta.add_param(param);
+        }
+        Ok(())
+    }
+}
+
+/// Serializes GstPravegaStructureMeta as its GstStructure in string form.
+struct StructureMetaSerializer {}
+
+impl MetaSerializer for StructureMetaSerializer {
+    fn serialize(&self, buffer: &gst::BufferRef) -> Vec<Vec<u8>> {
+        buffer.iter_meta::<StructureMeta>().map(|meta| {
+            meta.structure().to_string().into_bytes()
+        }).collect()
+    }
+
+    fn deserialize(&self, buffer: &mut gst::BufferRef, data: &[u8]) -> Result<(), String> {
+        let structure = std::str::from_utf8(data).map_err(|err| err.to_string())?;
+        let structure = gst::Structure::from_str(structure).map_err(|err| err.to_string())?;
+        StructureMeta::add(buffer, structure);
+        Ok(())
+    }
+}
@@ -19,9 +19,18 @@ pub fn u64_to_i64_saturating_sub(a: u64, b: u64) -> i64 {
     }
 }
 
+/// Compute a - b as a u64, returning None on overflow.
+pub fn u64_checked_sub_i64(a: u64, b: i64) -> Option<u64> {
+    if b < 0 {
+        a.checked_add(b.unsigned_abs())
+    } else {
+        a.checked_sub(b as u64)
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::numeric::u64_to_i64_saturating_sub;
+    use crate::numeric::{u64_checked_sub_i64, u64_to_i64_saturating_sub};
 
     #[test]
     fn test_u64_to_i64_saturating_sub() {
@@ -39,4 +48,15 @@ mod test {
         assert_eq!(u64_to_i64_saturating_sub(0, i64::MAX as u64 + 1), -i64::MAX - 1);
         assert_eq!(u64_to_i64_saturating_sub(0, i64::MAX as u64 - 1), -i64::MAX + 1);
     }
+
+    #[test]
+    fn test_u64_checked_sub_i64() {
+        assert_eq!(u64_checked_sub_i64(0, 0), Some(0));
+        assert_eq!(u64_checked_sub_i64(10, 3), Some(7));
+        assert_eq!(u64_checked_sub_i64(10, -3), Some(13));
+        assert_eq!(u64_checked_sub_i64(3, 10), None);
+        assert_eq!(u64_checked_sub_i64(u64::MAX, -1), None);
+        assert_eq!(u64_checked_sub_i64(u64::MAX, i64::MAX), Some(u64::MAX - i64::MAX as u64));
+        assert_eq!(u64_checked_sub_i64(0, i64::MIN), Some(i64::MAX as u64 + 1));
+    }
 }
@@ -15,37 +15,69 @@
 use glib::subclass::prelude::*;
 use gst::prelude::*;
 use gst::subclass::prelude::*;
-use gst::{gst_debug, gst_error, gst_fixme, gst_info, gst_log, gst_trace, gst_memdump};
+use gst::{gst_debug, gst_error, gst_fixme, gst_info, gst_log, gst_trace, gst_memdump, gst_warning};
 use gst_base::subclass::prelude::*;
 
 use std::cmp;
 use std::convert::TryInto;
-use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::io::{BufWriter, Error, ErrorKind, Seek, SeekFrom, Write};
 use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use once_cell::sync::Lazy;
 
 use pravega_client::client_factory::ClientFactory;
 use pravega_client::byte_stream::ByteStreamWriter;
-use pravega_client_shared::{Scope, Stream, Segment, ScopedSegment, StreamConfiguration, ScopedStream, Scaling, ScaleType};
-use pravega_video::event_serde::{EventWithHeader, EventWriter};
-use pravega_video::index::{IndexRecord, IndexRecordWriter, get_index_stream_name};
+use pravega_client_shared::{Scope, Stream, StreamConfiguration, ScopedSegment, ScopedStream};
+use pravega_video::catalog::{CatalogWriter, PartitionRecord, Rollover, format_partition_name, get_catalog_stream_name};
+use pravega_video::event_serde::{BufferTiming, EventWithHeader, EventWriter, HeaderExtension};
+use pravega_video::index::{IndexRecord, IndexRecordWriter, IndexSearcher, get_index_stream_name};
+use pravega_video::index_rebuilder::IndexRebuilder;
+use pravega_video::lease::{AcquireResult, WriterLease, get_lease_table_name, now_millis};
+use pravega_video::lease_table::TableLeaseStore;
+use pravega_video::metadata::{StreamMetadata, StreamMetadataWriter, get_metadata_stream_name};
+use pravega_video::spool::{Spool, SpooledEvent};
 use pravega_video::timestamp::PravegaTimestamp;
 use pravega_video::utils;
 
 use crate::counting_writer::CountingWriter;
+use crate::meta_serializer;
 use crate::numeric::u64_to_i64_saturating_sub;
 use crate::seekable_byte_stream_writer::SeekableByteStreamWriter;
+use crate::stats::{StatsReporter, get_head_and_tail_offsets, DEFAULT_STATS_INTERVAL_SEC, PROPERTY_NAME_STATS,
+    PROPERTY_NAME_STATS_INTERVAL_SEC};
+use crate::utils::{create_retention, index_retention, PravegaRetentionType, StreamPolicy,
+    DEFAULT_ALLOW_CREATE_STREAM, DEFAULT_RETENTION_TYPE, DEFAULT_RETENTION_DAYS, DEFAULT_RETENTION_BYTES};
 
 const PROPERTY_NAME_STREAM: &str = "stream";
 const PROPERTY_NAME_CONTROLLER: &str = "controller";
 const PROPERTY_NAME_SEAL: &str = "seal";
 const PROPERTY_NAME_BUFFER_SIZE: &str = "buffer-size";
 const PROPERTY_NAME_TIMESTAMP_MODE: &str = "timestamp-mode";
+const PROPERTY_NAME_REFERENCE_TIMESTAMP_CAPS: &str = "reference-timestamp-caps";
+const PROPERTY_NAME_REFERENCE_TIMESTAMP_FALLBACK: &str = "reference-timestamp-fallback";
 const PROPERTY_NAME_INDEX_MIN_SEC: &str = "index-min-sec";
 const PROPERTY_NAME_INDEX_MAX_SEC: &str = "index-max-sec";
+const PROPERTY_NAME_FORCE_KEY_UNIT_SEC: &str = "force-key-unit-sec";
 const PROPERTY_NAME_ALLOW_CREATE_SCOPE: &str = "allow-create-scope";
 const PROPERTY_NAME_KEYCLOAK_FILE: &str = "keycloak-file";
+const PROPERTY_NAME_ALLOW_CREATE_STREAM: &str = "allow-create-stream";
+const PROPERTY_NAME_RETENTION_TYPE: &str = "retention-type";
+const PROPERTY_NAME_RETENTION_DAYS: &str = "retention-days";
+const PROPERTY_NAME_RETENTION_BYTES: &str = "retention-bytes";
+const PROPERTY_NAME_CHECKSUM: &str = "checksum";
+const PROPERTY_NAME_TRANSACTIONAL: &str = "transactional";
+const PROPERTY_NAME_LEASE_DURATION_SEC: &str = "lease-duration-sec";
+const PROPERTY_NAME_LEASE_WAIT_SEC: &str = "lease-wait-sec";
+const PROPERTY_NAME_SPOOL_DIR: &str = "spool-dir";
+const PROPERTY_NAME_SPOOL_MAX_SIZE: &str = "spool-max-size";
+const PROPERTY_NAME_SPOOL_MAX_AGE_SEC: &str = "spool-max-age-sec";
+const PROPERTY_NAME_ROLLOVER: &str = "rollover";
+const PROPERTY_NAME_ROLLOVER_SIZE: &str = "rollover-size";
+const PROPERTY_NAME_PARTITION_TEMPLATE: &str = "partition-template";
+const PROPERTY_NAME_PERSIST_META_APIS: &str = "persist-meta-apis";
+const PROPERTY_NAME_PERSIST_BUFFER_TIMING: &str = "persist-buffer-timing";
 
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::GEnum)]
 #[repr(u32)]
@@ -71,13 +103,84 @@ pub enum TimestampMode {
         nick = "tai"
     )]
     Tai = 2,
+    #[genum(
+        name = "Use the timestamp in the GstReferenceTimestampMeta that matches reference-timestamp-caps. \
+                The references timestamp/x-ntp, timestamp/x-unix, and timestamp/x-ptp are supported. \
+                Buffers without a matching meta are handled according to reference-timestamp-fallback.",
+        nick = "reference-timestamp-meta"
+    )]
+    ReferenceTimestampMeta = 3,
+}
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::GEnum)]
+#[repr(u32)]
+#[genum(type_name = "GstReferenceTimestampFallback")]
+pub enum ReferenceTimestampFallback {
+    #[genum(
+        name = "Add the PTS difference to the most recent reference timestamp. \
+                Before the first reference timestamp, use the realtime clock.",
+        nick = "interpolate"
+    )]
+    Interpolate = 0,
+    #[genum(
+        name = "Use the realtime clock as in timestamp-mode=realtime-clock.",
+        nick = "realtime-clock"
+    )]
+    RealtimeClock = 1,
+    #[genum(
+        name = "Drop the buffer.",
+        nick = "drop"
+    )]
+    Drop = 2,
+}
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::GEnum)]
+#[repr(u32)]
+#[genum(type_name = "GstRolloverMode")]
+pub enum RolloverMode {
+    #[genum(
+        name = "All data is written to a single data stream.",
+        nick = "none"
+    )]
+    None = 0,
+    #[genum(
+        name = "A new partition is started for each UTC hour.",
+        nick = "hourly"
+    )]
+    Hourly = 1,
+    #[genum(
+        name = "A new partition is started for each UTC day.",
+        nick = "daily"
+    )]
+    Daily = 2,
+    #[genum(
+        name = "A new partition is started when the data stream reaches rollover-size bytes.",
+        nick = "size"
+    )]
+    Size = 3,
 }
 
 const DEFAULT_CONTROLLER: &str = "127.0.0.1:9090";
 const DEFAULT_BUFFER_SIZE: usize = 128*1024;
 const DEFAULT_TIMESTAMP_MODE: TimestampMode = TimestampMode::RealtimeClock;
+const DEFAULT_REFERENCE_TIMESTAMP_CAPS: &str = "timestamp/x-ntp";
+const DEFAULT_REFERENCE_TIMESTAMP_FALLBACK: ReferenceTimestampFallback = ReferenceTimestampFallback::Interpolate;
 const DEFAULT_INDEX_MIN_SEC: f64 = 0.5;
 const DEFAULT_INDEX_MAX_SEC: f64 = 10.0;
+const DEFAULT_FORCE_KEY_UNIT_SEC: f64 = 0.0;
+const DEFAULT_PERSIST_BUFFER_TIMING: bool = false;
+const DEFAULT_CHECKSUM: bool = false;
+const DEFAULT_TRANSACTIONAL: bool = false;
+const DEFAULT_LEASE_DURATION_SEC: f64 = 0.0;
+const DEFAULT_LEASE_WAIT_SEC: f64 = 0.0;
+// How often to check a lease held by another writer when waiting for it.
+const LEASE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_SPOOL_MAX_SIZE: u64 = 1024*1024*1024;
+const DEFAULT_SPOOL_MAX_AGE_SEC: f64 = 0.0;
+const DEFAULT_ROLLOVER_MODE: RolloverMode = RolloverMode::None;
+const DEFAULT_ROLLOVER_SIZE: u64 = 10*1024*1024*1024;
+// While Pravega is unavailable, how often to attempt to write the spooled events.
+const SPOOL_RETRY_INTERVAL: Duration = Duration::from_secs(5);
 
 #[derive(Debug)]
 struct Settings {
@@ -87,10 +190,30 @@ struct Settings {
     seal: bool,
     buffer_size: usize,
     timestamp_mode: TimestampMode,
+    reference_timestamp_caps: gst::Caps,
+    reference_timestamp_fallback: ReferenceTimestampFallback,
     index_min_nanos: u64,
     index_max_nanos: u64,
+    force_key_unit_nanos: u64,
     allow_create_scope: bool,
     keycloak_file: Option<String>,
+    allow_create_stream: bool,
+    retention_type: PravegaRetentionType,
+    retention_days: f64,
+    retention_bytes: u64,
+    checksum: bool,
+    transactional: bool,
+    lease_duration_nanos: u64,
+    lease_wait_nanos: u64,
+    spool_dir: Option<String>,
+    spool_max_size: u64,
+    spool_max_age_nanos: u64,
+    rollover_mode: RolloverMode,
+    rollover_size: u64,
+    partition_template: Option<String>,
+    persist_meta_apis: Vec<String>,
+    persist_buffer_timing: bool,
+    stats_interval_nanos: u64,
 }
 
 impl Default for Settings {
@@ -102,21 +225,62 @@ impl Default for Settings {
             seal: false,
             buffer_size: DEFAULT_BUFFER_SIZE,
             timestamp_mode: DEFAULT_TIMESTAMP_MODE,
+            reference_timestamp_caps: DEFAULT_REFERENCE_TIMESTAMP_CAPS.parse().unwrap(),
+            reference_timestamp_fallback: DEFAULT_REFERENCE_TIMESTAMP_FALLBACK,
             index_min_nanos: (DEFAULT_INDEX_MIN_SEC * 1e9) as u64,
             index_max_nanos: (DEFAULT_INDEX_MAX_SEC * 1e9) as u64,
+            force_key_unit_nanos: (DEFAULT_FORCE_KEY_UNIT_SEC * 1e9) as u64,
             allow_create_scope: true,
             keycloak_file: None,
+            allow_create_stream: DEFAULT_ALLOW_CREATE_STREAM,
+            retention_type: DEFAULT_RETENTION_TYPE,
+            retention_days: DEFAULT_RETENTION_DAYS,
+            retention_bytes: DEFAULT_RETENTION_BYTES,
+            checksum: DEFAULT_CHECKSUM,
+            transactional: DEFAULT_TRANSACTIONAL,
+            lease_duration_nanos: (DEFAULT_LEASE_DURATION_SEC * 1e9) as u64,
+            lease_wait_nanos: (DEFAULT_LEASE_WAIT_SEC * 1e9) as u64,
+            spool_dir: None,
+            spool_max_size: DEFAULT_SPOOL_MAX_SIZE,
+            spool_max_age_nanos: (DEFAULT_SPOOL_MAX_AGE_SEC * 1e9) as u64,
+            rollover_mode: DEFAULT_ROLLOVER_MODE,
+            rollover_size: DEFAULT_ROLLOVER_SIZE,
+            partition_template: None,
+            persist_meta_apis: Vec::new(),
+            persist_buffer_timing: DEFAULT_PERSIST_BUFFER_TIMING,
+            stats_interval_nanos: (DEFAULT_STATS_INTERVAL_SEC * 1e9) as u64,
         }
     }
 }
 
+type DataWriter = CountingWriter<BufWriter<SeekableByteStreamWriter>>;
+
 enum State {
     Stopped,
     Started {
         client_factory: ClientFactory,
-        writer: CountingWriter<BufWriter<SeekableByteStreamWriter>>,
+        writer: DataWriter,
         index_writer: ByteStreamWriter,
+        metadata_writer: ByteStreamWriter,
+        // The lease that permits this instance to write to the streams, if enabled.
+        lease: Option<WriterLease<TableLeaseStore>>,
+        // Used to buffer events on local disk while Pravega is unavailable, if enabled.
+        spool_state: Option<SpoolState>,
+        // The partition being written, if rollover is enabled.
+        partition: Option<PartitionState>,
+        // Used to create the streams of each new partition.
+        stream_policy: StreamPolicy,
+        // The description of the data stream that will be written to the metadata stream.
+        metadata: StreamMetadata,
+        // The tags received from upstream, merged.
+        tags: Option<gst::TagList>,
+        // True if metadata has changed since it was last written.
+        metadata_changed: bool,
         last_index_time: PravegaTimestamp,
+        // True if a GstForceKeyUnit event has been sent upstream since the last index record.
+        key_unit_requested: bool,
+        // The most recent timestamp from a GstReferenceTimestampMeta and the PTS of its buffer.
+        last_reference_timestamp: Option<(PravegaTimestamp, gst::ClockTime)>,
         // The timestamp that will be written to the index upon end-of-stream.
         final_timestamp: PravegaTimestamp,
         // The offset that will be written to the index upon end-of-stream.
@@ -131,9 +295,451 @@ impl Default for State {
     }
 }
 
+struct SpoolState {
+    spool: Spool,
+    // The events that were written to Pravega since the data stream was last flushed, with the offset after each one.
+    // If Pravega becomes unavailable, these may not have been written, so they will be spooled.
+    unflushed: Vec<(u64, SpooledEvent)>,
+    resume: SpoolResume,
+    // While Pravega is unavailable, this is the time of the next attempt to write the spooled events.
+    retry_time: Option<Instant>,
+    data_segment: ScopedSegment,
+    index_segment: ScopedSegment,
+    buffer_size: usize,
+    checksum: bool,
+    transactional: bool,
+}
+
+impl SpoolState {
+    fn is_spooling(&self) -> bool {
+        self.retry_time.is_some()
+    }
+
+    fn is_retry_due(&self) -> bool {
+        self.retry_time.map_or(false, |retry_time| Instant::now() >= retry_time)
+    }
+
+    fn schedule_retry(&mut self) {
+        self.retry_time = Some(Instant::now() + SPOOL_RETRY_INTERVAL);
+    }
+
+    // Called when new writers are opened at the tail of the data stream at offset.
+    fn opened(&mut self, offset: u64) {
+        self.unflushed.clear();
+        self.resume = SpoolResume {
+            committed_offset: Some(offset),
+            uncommitted: Vec::new(),
+        };
+    }
+
+    // Record an event that was written to Pravega at offset, ending at end_offset.
+    fn written(&mut self, event: &EventWithHeader, offset: u64, end_offset: u64, flushed: bool) {
+        // The data stream is flushed before writing an event that is included in the index.
+        if event.header.include_in_index || flushed {
+            self.unflushed.clear();
+            self.resume.committed_offset = Some(if flushed { end_offset } else { offset });
+        }
+        if !flushed {
+            self.unflushed.push((end_offset, SpooledEvent::new(event)));
+        }
+    }
+
+    // Called when Pravega becomes unavailable. Subsequent events must be appended to the spool.
+    // The unflushed events are spooled, but they are remembered so that they will not be written again
+    // if they reached the data stream.
+    fn begin_outage(&mut self) -> Result<(), Error> {
+        for (end_offset, event) in self.unflushed.drain(..) {
+            self.spool.append(&event.as_event())?;
+            self.resume.uncommitted.push((end_offset, event));
+        }
+        self.schedule_retry();
+        Ok(())
+    }
+
+    // Opens new writers at the tail of the data and index streams and writes all spooled events.
+    // Returns the new writers and the offset after the last event.
+    fn drain(&mut self, client_factory: &ClientFactory) -> Result<(DataWriter, ByteStreamWriter, u64), Error> {
+        // Ensure that the segment store is available before creating writers.
+        let mut reader = client_factory.create_byte_stream_reader(self.data_segment.clone());
+        let tail_offset = reader.seek(SeekFrom::End(0))?;
+        let index_reader = client_factory.create_byte_stream_reader(self.index_segment.clone());
+        let last_index_record = match IndexSearcher::new(index_reader).get_last_record() {
+            Ok(record) => Some(record),
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => None,
+            Err(error) => return Err(error),
+        };
+        let mut writer = client_factory.create_byte_stream_writer(self.data_segment.clone());
+        writer.seek_to_tail();
+        let mut index_writer = client_factory.create_byte_stream_writer(self.index_segment.clone());
+        index_writer.seek_to_tail();
+        let buf_writer = BufWriter::with_capacity(self.buffer_size, SeekableByteStreamWriter::new(writer)?);
+        let mut writer = CountingWriter::new(buf_writer)?;
+        let result = drain_spool_events(&mut self.spool, &mut self.resume, &mut writer, &mut index_writer,
+            tail_offset, last_index_record, self.checksum, self.transactional);
+        match result {
+            Ok(offset) => {
+                self.retry_time = None;
+                self.unflushed.clear();
+                Ok((writer, index_writer, offset))
+            },
+            Err(error) => {
+                // Buffered data must be discarded because the next attempt will determine what was written.
+                writer.get_mut().get_mut().fence();
+                Err(error)
+            },
+        }
+    }
+}
+
+// The state needed to resume writing spooled events where the data stream ends.
+// When Pravega becomes unavailable, or when an attempt to write the spooled events fails,
+// some of the events may have been written to the data stream even though the write failed.
+// These are skipped by comparing the offset after each one with the tail of the data stream.
+#[derive(Default)]
+struct SpoolResume {
+    // The offset at which the data stream ends if none of the uncommitted events were written.
+    // None if this is unknown, such as when the spool was written by a previous instance.
+    committed_offset: Option<u64>,
+    // The oldest events in the spool, as they were written to the data stream, with the offset after each one.
+    uncommitted: Vec<(u64, SpooledEvent)>,
+}
+
+// Returns true if the events have the same timestamp and payload.
+// The DIS flag is ignored because the spool may set it.
+fn is_same_event(a: &SpooledEvent, b: &SpooledEvent) -> bool {
+    a.header.timestamp == b.header.timestamp && a.payload == b.payload
+}
+
+// Writes all spooled events, starting at tail_offset, which must be the tail of the data stream.
+// Spooled events that were already written before tail_offset are skipped.
+// If the data stream does not end exactly where the first event must be written, it is written with a discontinuity
+// and it is included in the index only if it is a random-access event.
+// last_index_record must be the last record in the index. If it was written for the first event, it is not written again.
+// Each chunk is removed from the spool after it has been flushed.
+// Returns the offset after the last event.
+#[allow(clippy::too_many_arguments)]
+fn drain_spool_events<W: Write + Seek, I: Write>(
+    spool: &mut Spool,
+    resume: &mut SpoolResume,
+    writer: &mut CountingWriter<BufWriter<W>>,
+    index_writer: &mut I,
+    tail_offset: u64,
+    last_index_record: Option<IndexRecord>,
+    checksum: bool,
+    transactional: bool,
+) -> Result<u64, Error> {
+    let mut uncommitted = std::mem::take(&mut resume.uncommitted).into_iter().peekable();
+    // The offset at which the data stream ends if the events processed so far are the last ones written.
+    let mut position = resume.committed_offset;
+    let mut first = true;
+    loop {
+        let events = match spool.read_oldest_chunk() {
+            Ok(Some(events)) => events,
+            Ok(None) => break,
+            Err(error) => {
+                resume.committed_offset = position;
+                resume.uncommitted = uncommitted.collect();
+                return Err(error);
+            },
+        };
+        let chunk_position = position;
+        // The events of this chunk that were written, with the offset after each one.
+        let mut chunk_events = Vec::new();
+        let result = (|| -> Result<(), Error> {
+            for spooled_event in events.iter() {
+                let written = match uncommitted.peek() {
+                    Some((_, written)) if is_same_event(written, spooled_event) => uncommitted.next(),
+                    _ => {
+                        uncommitted.by_ref().for_each(drop);
+                        None
+                    },
+                };
+                if let Some((end_offset, ref written)) = written {
+                    if end_offset <= tail_offset {
+                        // This event was written before Pravega became unavailable.
+                        position = Some(end_offset);
+                        chunk_events.push((end_offset, written.clone()));
+                        continue;
+                    }
+                    // Appends are ordered, so no subsequent event was written.
+                    uncommitted.by_ref().for_each(drop);
+                }
+                let mut header = written.as_ref().map_or(&spooled_event.header, |(_, written)| &written.header).clone();
+                let mut already_indexed = false;
+                if first {
+                    first = false;
+                    if position != Some(tail_offset) {
+                        // Events may have been lost or partially written.
+                        header.discontinuity = true;
+                        // Readers that seek to an index record must be able to decode from it.
+                        header.include_in_index = header.timestamp.is_some() && header.random_access;
+                    }
+                    // The index record of this event may have been written before the write of the event failed.
+                    already_indexed = header.include_in_index && last_index_record.map_or(false, |record| {
+                        record.offset == tail_offset
+                            && record.timestamp == header.timestamp
+                            && record.random_access == header.random_access
+                            && record.discontinuity == header.discontinuity
+                    });
+                }
+                let mut event = EventWithHeader {
+                    header,
+                    payload: &spooled_event.payload[..],
+                };
+                if !already_indexed && must_index_to_fit(writer, &event, checksum, transactional) {
+                    event.header.include_in_index = true;
+                }
+                if already_indexed {
+                    write_event_data(writer, &event, checksum, transactional)?;
+                } else {
+                    write_event(writer, index_writer, &event, checksum, transactional)?;
+                }
+                let end_offset = writer.seek(SeekFrom::Current(0))?;
+                position = Some(end_offset);
+                chunk_events.push((end_offset, SpooledEvent::new(&event)));
+            }
+            writer.flush()?;
+            index_writer.flush()?;
+            spool.remove_oldest_chunk()
+        })();
+        if let Err(error) = result {
+            // The next attempt will skip the events of this chunk that reached the data stream.
+            resume.committed_offset = chunk_position;
+            resume.uncommitted = chunk_events;
+            return Err(error);
+        }
+    }
+    let offset = writer.seek(SeekFrom::Current(0))?;
+    *resume = SpoolResume {
+        committed_offset: Some(offset),
+        uncommitted: Vec::new(),
+    };
+    Ok(offset)
+}
+
+// The writers for a data stream and its index and metadata streams.
+struct StreamWriters {
+    writer: DataWriter,
+    index_writer: ByteStreamWriter,
+    metadata_writer: ByteStreamWriter,
+    data_segment: ScopedSegment,
+    index_segment: ScopedSegment,
+    metadata_segment: ScopedSegment,
+}
+
+// The partition being written when rollover is enabled.
+struct PartitionState {
+    rollover: Rollover,
+    template: String,
+    scope: Scope,
+    // The logical stream name.
+    stream_name: String,
+    record: PartitionRecord,
+    // False until the partition has been recorded in the catalog.
+    recorded: bool,
+    // The partition to seal after this partition has been recorded.
+    previous: Option<PartitionRecord>,
+    catalog_writer: ByteStreamWriter,
+}
+
+impl PartitionState {
+    // Prepares a new partition that begins at timestamp and follows previous.
+    fn begin_next(&mut self, client_factory: &ClientFactory, previous: Option<PartitionRecord>, timestamp: PravegaTimestamp) -> Result<(), Error> {
+        let (sequence, index_record_base) = match previous {
+            Some(ref previous) => {
+                let (_, index_segment, _) = utils::get_partition_segments(&self.scope, previous);
+                let mut index_reader = client_factory.create_byte_stream_reader(index_segment);
+                let index_tail_offset = index_reader.seek(SeekFrom::End(0))?;
+                (previous.sequence + 1, previous.index_record_base + index_tail_offset / IndexRecord::RECORD_SIZE as u64)
+            },
+            None => (0, 0),
+        };
+        self.record = PartitionRecord {
+            sequence,
+            stream: format_partition_name(&self.template, &self.stream_name, timestamp, sequence),
+            begin_timestamp: timestamp.nanoseconds(),
+            index_record_base,
+            ..Default::default()
+        };
+        self.recorded = false;
+        self.previous = previous;
+        Ok(())
+    }
+}
+
+fn default_partition_template(rollover: Rollover) -> &'static str {
+    match rollover {
+        Rollover::Hourly => "{stream}-{date}-{hour}",
+        Rollover::Daily => "{stream}-{date}",
+        Rollover::Size(_) => "{stream}-{seq}",
+    }
+}
+
+// Returns the timestamp in the first GstReferenceTimestampMeta of buffer whose reference intersects reference_caps.
+// Returns None if there is no such meta or if its reference is not a supported clock.
+fn get_reference_timestamp(buffer: &gst::BufferRef, reference_caps: &gst::Caps) -> Option<PravegaTimestamp> {
+    buffer.iter_meta::<gst::ReferenceTimestampMeta>()
+        .filter(|meta| meta.reference().can_intersect(reference_caps))
+        .find_map(|meta| {
+            let nanoseconds = meta.timestamp().nseconds();
+            match meta.reference().structure(0)?.name() {
+                "timestamp/x-ntp" => Some(PravegaTimestamp::from_ntp_nanoseconds(nanoseconds)),
+                "timestamp/x-unix" => Some(PravegaTimestamp::from_unix_nanoseconds(nanoseconds)),
+                // PTP time is TAI.
+                "timestamp/x-ptp" => Some(PravegaTimestamp::from_nanoseconds(nanoseconds)),
+                _ => None,
+            }
+        })
+}
+
+// Returns an upstream GstForceKeyUnit event that requests a key frame as soon as possible.
+// This is the same event that is created by gst_video_event_new_upstream_force_key_unit().
+fn new_upstream_force_key_unit_event() -> gst::Event {
+    let structure = gst::Structure::builder("GstForceKeyUnit")
+        // GST_CLOCK_TIME_NONE requests a key frame immediately.
+        .field("running-time", &u64::MAX)
+        .field("all-headers", &true)
+        .field("count", &0u32)
+        .build();
+    gst::event::CustomUpstream::new(structure)
+}
+
+// Writes an index record that ends the data stream, so that readers can determine the timestamp at the end of the final event.
+// The timestamp will be the the buffer timestamp + duration of the final buffer.
+// The offset will be current write position.
+fn write_final_index_record(index_writer: &mut ByteStreamWriter, final_timestamp: PravegaTimestamp, final_offset: Option<u64>)
+        -> Result<Option<IndexRecord>, Error> {
+    match final_offset {
+        Some(final_offset) if final_timestamp.is_some() => {
+            let index_record = IndexRecord::new(final_timestamp, final_offset, false, false);
+            IndexRecordWriter::new().write(&index_record, index_writer)?;
+            Ok(Some(index_record))
+        },
+        _ => Ok(None),
+    }
+}
+
+// Writes an event to the data stream. If the event is included in the index, an index record is written first.
+// Returns the offset of the event in the data stream and, if the data stream was flushed before the index record,
+// the time that the flush took.
+fn write_event<W: Write + Seek, I: Write>(writer: &mut CountingWriter<BufWriter<W>>, index_writer: &mut I,
+        event: &EventWithHeader, checksum: bool, transactional: bool) -> Result<(u64, Option<Duration>), Error> {
+    // Get the writer offset before writing. This offset will be used in the index.
+    let writer_offset = writer.seek(SeekFrom::Current(0))?;
+
+    let mut flush_latency = None;
+    if event.header.include_in_index {
+        // Per the index constraints defined in index.rs, if we are writing an index record now,
+        // we must flush any data writes prior to this buffer, so that reads do not block waiting on this writer.
+        // In transactional mode, this commits the data since the previous index record.
+        let flush_start = Instant::now();
+        writer.flush().map_err(|error| {
+            Error::new(error.kind(), format!("Failed to flush Pravega data stream: {}", error))
+        })?;
+        flush_latency = Some(flush_start.elapsed());
+
+        // Write index record.
+        // We write the index record before the buffer so that any readers blocked on reading the
+        // index will unblock as soon as possible.
+        let index_record = IndexRecord::new(event.header.timestamp, writer_offset,
+            event.header.random_access, event.header.discontinuity);
+        IndexRecordWriter::new().write(&index_record, index_writer).map_err(|error| {
+            Error::new(error.kind(), format!("Failed to write index: {}", error))
+        })?;
+    }
+
+    write_event_data(writer, event, checksum, transactional)?;
+    Ok((writer_offset, flush_latency))
+}
+
+// Returns true if, in transactional mode, the event must be included in the index because the data since
+// the previous index record would otherwise exceed the buffer, which is written with a single append.
+// Index records can only be written for events with timestamps.
+fn must_index_to_fit<W: Write + Seek>(writer: &CountingWriter<BufWriter<W>>, event: &EventWithHeader,
+        checksum: bool, transactional: bool) -> bool {
+    transactional && !event.header.include_in_index && event.header.timestamp.is_some()
+        && writer.get_ref().buffer().len() + EventWriter::with_checksum(checksum).serialized_length(event)
+            > EventWithHeader::MAX_ATOMIC_WRITE_SIZE
+}
+
+// Fences the data writer and returns an error of kind PermissionDenied if the writer lease has expired,
+// because another writer may have acquired it.
+// The lease is renewed only when needs_renewal() is true, so this is checked locally before each flush.
+fn check_lease_expiry(lease: &Option<WriterLease<TableLeaseStore>>, writer: &mut DataWriter) -> Result<(), Error> {
+    match lease {
+        Some(lease) if lease.is_expired(now_millis()) => {
+            writer.get_mut().get_mut().fence();
+            Err(Error::new(ErrorKind::PermissionDenied, "Writer lease has expired"))
+        },
+        _ => Ok(()),
+    }
+}
+
+// Writes an event to the data stream without an index record.
+fn write_event_data<W: Write + Seek>(writer: &mut CountingWriter<BufWriter<W>>, event: &EventWithHeader,
+        checksum: bool, transactional: bool) -> Result<(), Error> {
+    // Write buffer to Pravega byte stream.
+    let mut event_writer = EventWriter::with_checksum(checksum);
+    // In transactional mode, the data since the previous index record must be written with a single append.
+    // Callers use must_index_to_fit to begin a new interval before the buffer overflows,
+    // so this fails only if events without timestamps exceed the buffer.
+    if transactional
+        && writer.get_ref().buffer().len() + event_writer.serialized_length(event) > EventWithHeader::MAX_ATOMIC_WRITE_SIZE {
+        return Err(Error::new(ErrorKind::InvalidData,
+            format!("The data since the previous index record exceeds {} bytes and cannot be written in a single append; \
+                events without timestamps cannot be indexed", EventWithHeader::MAX_ATOMIC_WRITE_SIZE)));
+    }
+    event_writer.write(event, writer).map_err(|error| {
+        Error::new(error.kind(), format!("Failed to write buffer: {}", error))
+    })
+}
+
+// Statistics that are returned by the stats property and posted in stats messages.
+#[derive(Default)]
+struct Stats {
+    bytes_written: u64,
+    events_written: u64,
+    index_records_written: u64,
+    // Index records that were written at a delta unit because no key frame was received within index-max-sec.
+    forced_index_records: u64,
+    // GstForceKeyUnit events that were sent upstream.
+    key_unit_requests: u64,
+    last_flush_latency: Duration,
+    max_flush_latency: Duration,
+    spooling: bool,
+    // The data segment being written, used to get the head and tail offsets.
+    data_segment: Option<ScopedSegment>,
+    head_offset: Option<u64>,
+    tail_offset: Option<u64>,
+}
+
+impl Stats {
+    fn to_structure(&self) -> gst::Structure {
+        let mut structure = gst::Structure::builder("application/x-pravegasink-stats")
+            .field("bytes-written", &self.bytes_written)
+            .field("events-written", &self.events_written)
+            .field("index-records-written", &self.index_records_written)
+            .field("forced-index-records", &self.forced_index_records)
+            .field("key-unit-requests", &self.key_unit_requests)
+            .field("last-flush-latency", &(self.last_flush_latency.as_nanos() as u64))
+            .field("max-flush-latency", &(self.max_flush_latency.as_nanos() as u64))
+            .field("spooling", &self.spooling)
+            .build();
+        if let Some(head_offset) = self.head_offset {
+            structure.set("head-offset", &head_offset);
+        }
+        if let Some(tail_offset) = self.tail_offset {
+            structure.set("tail-offset", &tail_offset);
+        }
+        structure
+    }
+}
+
 pub struct PravegaSink {
     settings: Mutex<Settings>,
     state: Mutex<State>,
+    stats: Mutex<Stats>,
+    stats_reporter: Mutex<Option<StatsReporter>>,
 }
 
 static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
@@ -183,6 +789,273 @@ impl PravegaSink {
         settings.controller = controller;
         Ok(())
     }
+
+    // Attempts to write all spooled events to Pravega using new writers.
+    // Returns the offset after the last event if the spool was drained, or None if Pravega is still unavailable.
+    // Returns an error if the writer lease has been lost.
+    fn drain_spool(
+        &self,
+        element: &super::PravegaSink,
+        spool_state: &mut SpoolState,
+        client_factory: &ClientFactory,
+        writer: &mut DataWriter,
+        index_writer: &mut ByteStreamWriter,
+        lease: &mut Option<WriterLease<TableLeaseStore>>,
+    ) -> Result<Option<u64>, Error> {
+        gst_info!(CAT, obj: element, "drain_spool: Writing {} spooled bytes", spool_state.spool.size());
+        if let Some(lease) = lease {
+            match lease.renew(now_millis()) {
+                Ok(()) => {},
+                Err(error) if error.kind() == ErrorKind::PermissionDenied => return Err(error),
+                Err(error) => {
+                    gst_info!(CAT, obj: element, "drain_spool: Pravega is still unavailable: {}", error);
+                    spool_state.schedule_retry();
+                    return Ok(None);
+                },
+            }
+        }
+        match spool_state.drain(client_factory) {
+            Ok((new_writer, new_index_writer, offset)) => {
+                gst_info!(CAT, obj: element, "drain_spool: Pravega is available; all spooled events have been written");
+                *writer = new_writer;
+                *index_writer = new_index_writer;
+                Ok(Some(offset))
+            },
+            Err(error) => {
+                gst_info!(CAT, obj: element, "drain_spool: Pravega is still unavailable: {}", error);
+                spool_state.schedule_retry();
+                Ok(None)
+            },
+        }
+    }
+
+    // Updates the head and tail offsets of the data stream and posts the stats as an element message.
+    // This is called periodically by the stats thread.
+    fn post_stats(&self, element: &super::PravegaSink, client_factory: &ClientFactory) {
+        let data_segment = self.stats.lock().unwrap().data_segment.clone();
+        let offsets = data_segment.map(|data_segment| get_head_and_tail_offsets(client_factory, &data_segment));
+        let structure = {
+            let mut stats = self.stats.lock().unwrap();
+            match offsets {
+                Some(Ok((head_offset, tail_offset))) => {
+                    stats.head_offset = Some(head_offset);
+                    stats.tail_offset = Some(tail_offset);
+                },
+                Some(Err(error)) => {
+                    gst_debug!(CAT, obj: element, "post_stats: Unable to get offsets of data stream: {}", error);
+                },
+                None => {},
+            }
+            stats.to_structure()
+        };
+        gst_trace!(CAT, obj: element, "post_stats: {}", structure);
+        let _ = element.post_message(gst::message::Element::builder(structure).src(element).build());
+    }
+
+    // Creates a data stream and its index and metadata streams if needed and allowed by policy,
+    // and opens writers at their tails.
+    fn open_stream_writers(
+        &self,
+        element: &super::PravegaSink,
+        client_factory: &ClientFactory,
+        scope: &Scope,
+        stream_name: &str,
+        policy: &StreamPolicy,
+        buffer_size: usize,
+    ) -> Result<StreamWriters, gst::ErrorMessage> {
+        let controller_client = client_factory.get_controller_client();
+        let runtime = client_factory.get_runtime();
+        let stream = Stream::from(stream_name.to_owned());
+        let index_stream = Stream::from(get_index_stream_name(stream_name));
+        let metadata_stream = Stream::from(get_metadata_stream_name(stream_name));
+        gst_info!(CAT, obj: element, "open_stream_writers: scope={}, stream={}, index_stream={}, metadata_stream={}",
+            scope, stream, index_stream, metadata_stream);
+
+        // Create data stream.
+        let stream_config = StreamConfiguration {
+            scoped_stream: ScopedStream {
+                scope: scope.clone(),
+                stream,
+            },
+            scaling: utils::byte_stream_scaling(),
+            retention: policy.retention.clone(),
+        };
+        if policy.create {
+            runtime.block_on(controller_client.create_stream(&stream_config)).map_err(|error| {
+                gst::error_msg!(gst::ResourceError::Settings, ["Failed to create Pravega data stream: {:?}", error])
+            })?;
+        }
+
+        // Create index stream.
+        let index_stream_config = StreamConfiguration {
+            scoped_stream: ScopedStream {
+                scope: scope.clone(),
+                stream: index_stream,
+            },
+            scaling: utils::byte_stream_scaling(),
+            retention: index_retention(&policy.retention),
+        };
+        if policy.create {
+            runtime.block_on(controller_client.create_stream(&index_stream_config)).map_err(|error| {
+                gst::error_msg!(gst::ResourceError::Settings, ["Failed to create Pravega index stream: {:?}", error])
+            })?;
+        }
+
+        // Create metadata stream.
+        let metadata_stream_config = StreamConfiguration {
+            scoped_stream: ScopedStream {
+                scope: scope.clone(),
+                stream: metadata_stream,
+            },
+            scaling: utils::byte_stream_scaling(),
+            retention: Default::default(),
+        };
+        if policy.create {
+            runtime.block_on(controller_client.create_stream(&metadata_stream_config)).map_err(|error| {
+                gst::error_msg!(gst::ResourceError::Settings, ["Failed to create Pravega metadata stream: {:?}", error])
+            })?;
+        }
+
+        // A failure to get the segment of a stream is reported as NotFound only if the controller confirms
+        // that the stream does not exist. Other failures, such as an unavailable controller, are reported as OpenWrite.
+        let segment_error = |kind: &str, scoped_stream: &ScopedStream, error: String| {
+            match runtime.block_on(controller_client.check_stream_exists(scoped_stream)) {
+                Ok(false) => gst::error_msg!(gst::ResourceError::NotFound, ["Pravega {} stream {}/{} does not exist: {}",
+                    kind, scoped_stream.scope, scoped_stream.stream, error]),
+                _ => gst::error_msg!(gst::ResourceError::OpenWrite, ["Failed to get segment of Pravega {} stream: {}", kind, error]),
+            }
+        };
+
+        let data_segment = utils::get_byte_stream_segment(client_factory, &stream_config.scoped_stream).map_err(|error| {
+            segment_error("data", &stream_config.scoped_stream, error.to_string())
+        })?;
+        gst_info!(CAT, obj: element, "open_stream_writers: Using data segment {:?}", data_segment);
+        let mut writer = client_factory.create_byte_stream_writer(data_segment.clone());
+        gst_info!(CAT, obj: element, "open_stream_writers: Opened Pravega writer for data");
+        writer.seek_to_tail();
+        let seekable_writer = SeekableByteStreamWriter::new(writer).map_err(|error| {
+            gst::error_msg!(gst::ResourceError::OpenWrite, ["Failed to open Pravega data stream: {}", error])
+        })?;
+        let buf_writer = BufWriter::with_capacity(buffer_size, seekable_writer);
+        let writer = CountingWriter::new(buf_writer).map_err(|error| {
+            gst::error_msg!(gst::ResourceError::OpenWrite, ["Failed to open Pravega data stream: {}", error])
+        })?;
+
+        let index_segment = utils::get_byte_stream_segment(client_factory, &index_stream_config.scoped_stream).map_err(|error| {
+            segment_error("index", &index_stream_config.scoped_stream, error.to_string())
+        })?;
+        gst_info!(CAT, obj: element, "open_stream_writers: Using index segment {:?}", index_segment);
+        let mut index_writer = client_factory.create_byte_stream_writer(index_segment.clone());
+        gst_info!(CAT, obj: element, "open_stream_writers: Opened Pravega writer for index");
+        index_writer.seek_to_tail();
+
+        let metadata_segment = utils::get_byte_stream_segment(client_factory, &metadata_stream_config.scoped_stream).map_err(|error| {
+            segment_error("metadata", &metadata_stream_config.scoped_stream, error.to_string())
+        })?;
+        let mut metadata_writer = client_factory.create_byte_stream_writer(metadata_segment.clone());
+        gst_info!(CAT, obj: element, "open_stream_writers: Opened Pravega writer for metadata");
+        metadata_writer.seek_to_tail();
+
+        Ok(StreamWriters {
+            writer,
+            index_writer,
+            metadata_writer,
+            data_segment,
+            index_segment,
+            metadata_segment,
+        })
+    }
+
+    // Returns the partition to write when starting with rollover enabled.
+    // This is the last partition in the catalog, or a new partition if it is time to roll over.
+    // A new partition must be recorded with record_partition after its streams have been created.
+    fn open_partition(
+        &self,
+        element: &super::PravegaSink,
+        client_factory: &ClientFactory,
+        rollover: Rollover,
+        template: String,
+        scope: &Scope,
+        stream_name: &str,
+    ) -> Result<PartitionState, gst::ErrorMessage> {
+        let catalog_stream_config = StreamConfiguration {
+            scoped_stream: ScopedStream {
+                scope: scope.clone(),
+                stream: Stream::from(get_catalog_stream_name(stream_name)),
+            },
+            scaling: utils::byte_stream_scaling(),
+            retention: Default::default(),
+        };
+        let controller_client = client_factory.get_controller_client();
+        client_factory.get_runtime().block_on(controller_client.create_stream(&catalog_stream_config)).map_err(|error| {
+            gst::error_msg!(gst::ResourceError::Settings, ["Failed to create Pravega catalog stream: {:?}", error])
+        })?;
+        let records = utils::read_catalog(client_factory, scope, stream_name).map_err(|error| {
+            gst::error_msg!(gst::ResourceError::OpenRead, ["Failed to read Pravega catalog stream: {}", error])
+        })?;
+        let catalog_segment = utils::get_byte_stream_segment(client_factory, &catalog_stream_config.scoped_stream).map_err(|error| {
+            gst::error_msg!(gst::ResourceError::OpenWrite, ["Failed to get segment of Pravega catalog stream: {}", error])
+        })?;
+        let mut catalog_writer = client_factory.create_byte_stream_writer(catalog_segment);
+        catalog_writer.seek_to_tail();
+
+        let mut partition = PartitionState {
+            rollover,
+            template,
+            scope: scope.clone(),
+            stream_name: stream_name.to_owned(),
+            record: Default::default(),
+            recorded: true,
+            previous: None,
+            catalog_writer,
+        };
+        let now = PravegaTimestamp::now();
+        match records.last() {
+            Some(last) if !rollover.is_due(last.begin_timestamp(), now, 0) => {
+                gst_info!(CAT, obj: element, "open_partition: Continuing partition {:?}", last);
+                partition.record = last.clone();
+            },
+            last => {
+                partition.begin_next(client_factory, last.cloned(), now).map_err(|error| {
+                    gst::error_msg!(gst::ResourceError::OpenRead, ["Failed to read index of previous partition: {}", error])
+                })?;
+                gst_info!(CAT, obj: element, "open_partition: Beginning partition {:?}", partition.record);
+            },
+        }
+        Ok(partition)
+    }
+
+    // Records a new partition in the catalog. Then the streams of the previous partition are sealed,
+    // which allows readers to reach the end of it.
+    fn record_partition(
+        &self,
+        element: &super::PravegaSink,
+        client_factory: &ClientFactory,
+        partition: &mut PartitionState,
+        writers: &StreamWriters,
+    ) -> Result<(), gst::ErrorMessage> {
+        partition.record.data_segment = writers.data_segment.segment.number;
+        partition.record.index_segment = writers.index_segment.segment.number;
+        partition.record.metadata_segment = writers.metadata_segment.segment.number;
+        CatalogWriter::new().write(&partition.record, &mut partition.catalog_writer)
+            .and_then(|_| partition.catalog_writer.flush())
+            .map_err(|error| {
+                gst::error_msg!(gst::ResourceError::Write, ["Failed to write Pravega catalog stream: {}", error])
+            })?;
+        partition.recorded = true;
+        gst_info!(CAT, obj: element, "record_partition: Recorded partition {:?}", partition.record);
+        if let Some(previous) = partition.previous.take() {
+            let (data_segment, index_segment, metadata_segment) = utils::get_partition_segments(&partition.scope, &previous);
+            for segment in [data_segment, index_segment, metadata_segment] {
+                let mut writer = client_factory.create_byte_stream_writer(segment.clone());
+                if let Err(error) = client_factory.get_runtime().block_on(writer.seal()) {
+                    gst_warning!(CAT, obj: element, "record_partition: Failed to seal {:?}: {}", segment, error);
+                }
+            }
+            gst_info!(CAT, obj: element, "record_partition: Sealed partition {}", previous.stream);
+        }
+        Ok(())
+    }
 }
 
 #[glib::object_subclass]
@@ -196,6 +1069,8 @@ impl ObjectSubclass for PravegaSink {
         Self {
             settings: Mutex::new(Default::default()),
             state: Mutex::new(Default::default()),
+            stats: Mutex::new(Default::default()),
+            stats_reporter: Mutex::new(None),
         }
     }
 }
@@ -246,6 +1121,22 @@ impl ObjectImpl for PravegaSink {
                 DEFAULT_TIMESTAMP_MODE as i32,
                 glib::ParamFlags::WRITABLE,
             ),
+            glib::ParamSpec::new_string(
+                PROPERTY_NAME_REFERENCE_TIMESTAMP_CAPS,
+                "Reference timestamp caps",
+                "If timestamp-mode is reference-timestamp-meta, the timestamp will be obtained from the first \
+                GstReferenceTimestampMeta with a reference that intersects these caps",
+                Some(DEFAULT_REFERENCE_TIMESTAMP_CAPS),
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_enum(
+                PROPERTY_NAME_REFERENCE_TIMESTAMP_FALLBACK,
+                "Reference timestamp fallback",
+                "If timestamp-mode is reference-timestamp-meta, how to timestamp a buffer without a matching meta",
+                ReferenceTimestampFallback::static_type(),
+                DEFAULT_REFERENCE_TIMESTAMP_FALLBACK as i32,
+                glib::ParamFlags::WRITABLE,
+            ),
             glib::ParamSpec::new_double(
                 PROPERTY_NAME_INDEX_MIN_SEC,
                 "Minimum index interval",
@@ -264,6 +1155,16 @@ impl ObjectImpl for PravegaSink {
                 DEFAULT_INDEX_MAX_SEC.try_into().unwrap(),
                 glib::ParamFlags::WRITABLE,
             ),
+            glib::ParamSpec::new_double(
+                PROPERTY_NAME_FORCE_KEY_UNIT_SEC,
+                "Force key unit interval",
+                "If greater than 0, request a key frame from upstream with a GstForceKeyUnit event if one has not been received in this many seconds. \
+                This must be less than index-max-sec. An index record will be forced at a delta frame only if upstream ignores the request.",
+                0.0,
+                std::f64::INFINITY,
+                DEFAULT_FORCE_KEY_UNIT_SEC,
+                glib::ParamFlags::WRITABLE,
+            ),
             glib::ParamSpec::new_boolean(
                 PROPERTY_NAME_ALLOW_CREATE_SCOPE,
                 "Allow create scope",
@@ -278,6 +1179,176 @@ impl ObjectImpl for PravegaSink {
                 None,
                 glib::ParamFlags::WRITABLE,
             ),
+            glib::ParamSpec::new_boolean(
+                PROPERTY_NAME_ALLOW_CREATE_STREAM,
+                "Allow create stream",
+                "If true, the data stream and its index will be created if they do not exist. \
+                If false, they must already exist.",
+                DEFAULT_ALLOW_CREATE_STREAM,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_enum(
+                PROPERTY_NAME_RETENTION_TYPE,
+                "Retention type",
+                "The retention policy of the data stream if it is created by this element",
+                PravegaRetentionType::static_type(),
+                DEFAULT_RETENTION_TYPE as i32,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_double(
+                PROPERTY_NAME_RETENTION_DAYS,
+                "Retention days",
+                "The number of days to retain data if retention-type is days",
+                0.0,
+                std::f64::INFINITY,
+                DEFAULT_RETENTION_DAYS,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_uint64(
+                PROPERTY_NAME_RETENTION_BYTES,
+                "Retention bytes",
+                "The number of bytes to retain if retention-type is bytes",
+                0,
+                std::u64::MAX,
+                DEFAULT_RETENTION_BYTES,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_boolean(
+                PROPERTY_NAME_CHECKSUM,
+                "Checksum",
+                "If true, a CRC-32C checksum will be written with each event so that readers can detect corruption.",
+                DEFAULT_CHECKSUM,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_boolean(
+                PROPERTY_NAME_TRANSACTIONAL,
+                "Transactional",
+                "If true, the data between index records will be buffered in memory and written to the data stream \
+                in a single append before the index record is written. \
+                Each interval between index records is limited to 8 MiB. If an event would exceed this, \
+                an index record is written at that event, even if it is a delta unit. \
+                so that each interval is written with a single append. \
+                A failure between writing the data and the index record can leave a complete interval \
+                at the tail of the data stream without an index record. \
+                When starting, any data that was written by a failed writer but not indexed will be added to the index. \
+                The property buffer-size is ignored.",
+                DEFAULT_TRANSACTIONAL,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_double(
+                PROPERTY_NAME_LEASE_DURATION_SEC,
+                "Lease duration",
+                "If greater than 0, a writer lease must be acquired before writing, so that only one pravegasink \
+                can write to the stream at a time. The lease expires if it is not renewed within this many seconds. \
+                The lease is renewed when buffers are written. If the lease is lost to another writer, an error is posted.",
+                0.0,
+                std::f64::INFINITY,
+                DEFAULT_LEASE_DURATION_SEC.try_into().unwrap(),
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_double(
+                PROPERTY_NAME_LEASE_WAIT_SEC,
+                "Lease wait",
+                "If the writer lease is held by another writer, wait up to this many seconds for it to be released or to expire. \
+                If 0, fail immediately.",
+                0.0,
+                std::f64::INFINITY,
+                DEFAULT_LEASE_WAIT_SEC.try_into().unwrap(),
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_string(
+                PROPERTY_NAME_SPOOL_DIR,
+                "Spool directory",
+                "If set, buffers will be written to files in this directory while Pravega is unavailable, \
+                and written to Pravega when it becomes available. \
+                Spooled buffers that remain when stopped will be written when started again. \
+                If missing or empty, an error will be posted when Pravega is unavailable.",
+                None,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_uint64(
+                PROPERTY_NAME_SPOOL_MAX_SIZE,
+                "Spool maximum size",
+                "The maximum number of bytes in the spool directory. When exceeded, the oldest buffers will be discarded.",
+                0,
+                std::u64::MAX,
+                DEFAULT_SPOOL_MAX_SIZE,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_double(
+                PROPERTY_NAME_SPOOL_MAX_AGE_SEC,
+                "Spool maximum age",
+                "If greater than 0, spooled buffers older than this many seconds will be discarded.",
+                0.0,
+                std::f64::INFINITY,
+                DEFAULT_SPOOL_MAX_AGE_SEC,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_enum(
+                PROPERTY_NAME_ROLLOVER,
+                "Rollover",
+                "If not none, the data will be written to a sequence of partitions. \
+                Each partition is a data stream with its own index and metadata streams. \
+                The partitions are recorded in a catalog stream named after the stream property, \
+                and pravegasrc will read them as a single stream. \
+                A new partition always begins at an index record. The streams of the previous partition are sealed.",
+                RolloverMode::static_type(),
+                DEFAULT_ROLLOVER_MODE as i32,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_uint64(
+                PROPERTY_NAME_ROLLOVER_SIZE,
+                "Rollover size",
+                "When rollover=size, a new partition will begin when the data stream has at least this many bytes.",
+                1,
+                std::u64::MAX,
+                DEFAULT_ROLLOVER_SIZE,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_string(
+                PROPERTY_NAME_PARTITION_TEMPLATE,
+                "Partition template",
+                "The name of each partition data stream. \
+                The placeholders {stream}, {date} (YYYY-MM-DD), {hour} (HH), and {seq} (partition sequence number) will be replaced. \
+                If missing or empty, the default is {stream}-{date}-{hour} for hourly, {stream}-{date} for daily, and {stream}-{seq} for size.",
+                None,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_string(
+                PROPERTY_NAME_PERSIST_META_APIS,
+                "Persist meta APIs",
+                "A comma-separated list of GstMeta API names. \
+                Metas of these APIs will be written with each buffer and attached by pravegasrc. \
+                Serializers are registered for GstReferenceTimestampMetaAPI, GstProtectionMetaAPI, \
+                GstVideoRegionOfInterestMetaAPI and GstPravegaStructureMetaAPI. \
+                Applications must register a serializer for any other API.",
+                None,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_boolean(
+                PROPERTY_NAME_PERSIST_BUFFER_TIMING,
+                "Persist buffer timing",
+                "If true, the DTS and duration of each buffer will be written with the buffer and restored by pravegasrc. \
+                This is needed for elementary streams with B-frames.",
+                DEFAULT_PERSIST_BUFFER_TIMING,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_double(
+                PROPERTY_NAME_STATS_INTERVAL_SEC,
+                "Stats interval in seconds",
+                "The interval at which an element message with the stats will be posted to the bus. 0 disables these messages.",
+                0.0,
+                std::f64::MAX,
+                DEFAULT_STATS_INTERVAL_SEC,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_boxed(
+                PROPERTY_NAME_STATS,
+                "Stats",
+                "Statistics about the data written by this element since it was started",
+                gst::Structure::static_type(),
+                glib::ParamFlags::READABLE,
+            ),
         ]});
         PROPERTIES.as_ref()
     }
@@ -296,112 +1367,391 @@ impl ObjectImpl for PravegaSink {
                     Err(_) => unreachable!("type checked upstream"),
                 };
                 if let Err(err) = res {
-                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_STREAM, err);
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_STREAM, err);
+                }
+            },
+            PROPERTY_NAME_CONTROLLER => {
+                let res = match value.get::<String>() {
+                    Ok(controller) => self.set_controller(&obj, Some(controller)),
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_CONTROLLER, err);
+                }
+            },
+            PROPERTY_NAME_SEAL => {
+                let res: Result<(), glib::Error> = match value.get::<bool>() {
+                    Ok(seal) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.seal = seal;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_SEAL, err);
+                }
+            },
+            PROPERTY_NAME_BUFFER_SIZE => {
+                let res: Result<(), glib::Error> = match value.get::<u32>() {
+                    Ok(buffer_size) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.buffer_size = buffer_size.try_into().unwrap_or_default();
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_BUFFER_SIZE, err);
+                }
+            },
+            PROPERTY_NAME_TIMESTAMP_MODE => {
+                let res: Result<(), glib::Error> = match value.get::<TimestampMode>() {
+                    Ok(timestamp_mode) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.timestamp_mode = timestamp_mode;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_TIMESTAMP_MODE, err);
+                }
+            },
+            PROPERTY_NAME_REFERENCE_TIMESTAMP_CAPS => {
+                let res: Result<(), glib::Error> = match value.get::<String>() {
+                    Ok(reference_timestamp_caps) => {
+                        match reference_timestamp_caps.parse::<gst::Caps>() {
+                            Ok(reference_timestamp_caps) => {
+                                let mut settings = self.settings.lock().unwrap();
+                                settings.reference_timestamp_caps = reference_timestamp_caps;
+                                Ok(())
+                            },
+                            Err(_) => Err(glib::Error::new(
+                                gst::CoreError::Failed,
+                                format!("Invalid caps: {}", reference_timestamp_caps).as_str(),
+                            )),
+                        }
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_REFERENCE_TIMESTAMP_CAPS, err);
+                }
+            },
+            PROPERTY_NAME_REFERENCE_TIMESTAMP_FALLBACK => {
+                let res: Result<(), glib::Error> = match value.get::<ReferenceTimestampFallback>() {
+                    Ok(reference_timestamp_fallback) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.reference_timestamp_fallback = reference_timestamp_fallback;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_REFERENCE_TIMESTAMP_FALLBACK, err);
+                }
+            },
+            PROPERTY_NAME_INDEX_MIN_SEC => {
+                let res: Result<(), glib::Error> = match value.get::<f64>() {
+                    Ok(index_min_sec) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.index_min_nanos = (index_min_sec * 1e9) as u64;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_INDEX_MIN_SEC, err);
+                }
+            },
+            PROPERTY_NAME_INDEX_MAX_SEC => {
+                let res: Result<(), glib::Error> = match value.get::<f64>() {
+                    Ok(index_max_sec) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.index_max_nanos = (index_max_sec * 1e9) as u64;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_INDEX_MAX_SEC, err);
+                }
+            },
+            PROPERTY_NAME_FORCE_KEY_UNIT_SEC => {
+                let res: Result<(), glib::Error> = match value.get::<f64>() {
+                    Ok(force_key_unit_sec) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.force_key_unit_nanos = (force_key_unit_sec * 1e9) as u64;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_FORCE_KEY_UNIT_SEC, err);
+                }
+            },
+            PROPERTY_NAME_ALLOW_CREATE_SCOPE => {
+                let res: Result<(), glib::Error> = match value.get::<bool>() {
+                    Ok(allow_create_scope) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.allow_create_scope = allow_create_scope;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_ALLOW_CREATE_SCOPE, err);
+                }
+            },
+            PROPERTY_NAME_KEYCLOAK_FILE => {
+                let res: Result<(), glib::Error> = match value.get::<String>() {
+                    Ok(keycloak_file) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.keycloak_file = Some(keycloak_file);
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_KEYCLOAK_FILE, err);
+                }
+            },
+            PROPERTY_NAME_ALLOW_CREATE_STREAM => {
+                let res: Result<(), glib::Error> = match value.get::<bool>() {
+                    Ok(allow_create_stream) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.allow_create_stream = allow_create_stream;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_ALLOW_CREATE_STREAM, err);
+                }
+            },
+            PROPERTY_NAME_RETENTION_TYPE => {
+                let res: Result<(), glib::Error> = match value.get::<PravegaRetentionType>() {
+                    Ok(retention_type) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.retention_type = retention_type;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_RETENTION_TYPE, err);
+                }
+            },
+            PROPERTY_NAME_RETENTION_DAYS => {
+                let res: Result<(), glib::Error> = match value.get::<f64>() {
+                    Ok(retention_days) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.retention_days = retention_days;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_RETENTION_DAYS, err);
+                }
+            },
+            PROPERTY_NAME_RETENTION_BYTES => {
+                let res: Result<(), glib::Error> = match value.get::<u64>() {
+                    Ok(retention_bytes) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.retention_bytes = retention_bytes;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_RETENTION_BYTES, err);
+                }
+            },
+            PROPERTY_NAME_CHECKSUM => {
+                let res: Result<(), glib::Error> = match value.get::<bool>() {
+                    Ok(checksum) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.checksum = checksum;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_CHECKSUM, err);
+                }
+            },
+            PROPERTY_NAME_TRANSACTIONAL => {
+                let res: Result<(), glib::Error> = match value.get::<bool>() {
+                    Ok(transactional) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.transactional = transactional;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_TRANSACTIONAL, err);
+                }
+            },
+            PROPERTY_NAME_LEASE_DURATION_SEC => {
+                let res: Result<(), glib::Error> = match value.get::<f64>() {
+                    Ok(lease_duration_sec) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.lease_duration_nanos = (lease_duration_sec * 1e9) as u64;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_LEASE_DURATION_SEC, err);
+                }
+            },
+            PROPERTY_NAME_LEASE_WAIT_SEC => {
+                let res: Result<(), glib::Error> = match value.get::<f64>() {
+                    Ok(lease_wait_sec) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.lease_wait_nanos = (lease_wait_sec * 1e9) as u64;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_LEASE_WAIT_SEC, err);
                 }
             },
-            PROPERTY_NAME_CONTROLLER => {
-                let res = match value.get::<String>() {
-                    Ok(controller) => self.set_controller(&obj, Some(controller)),
+            PROPERTY_NAME_SPOOL_DIR => {
+                let res: Result<(), glib::Error> = match value.get::<String>() {
+                    Ok(spool_dir) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.spool_dir = Some(spool_dir);
+                        Ok(())
+                    },
                     Err(_) => unreachable!("type checked upstream"),
                 };
                 if let Err(err) = res {
-                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_CONTROLLER, err);
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_SPOOL_DIR, err);
                 }
             },
-            PROPERTY_NAME_SEAL => {
-                let res: Result<(), glib::Error> = match value.get::<bool>() {
-                    Ok(seal) => {
+            PROPERTY_NAME_SPOOL_MAX_SIZE => {
+                let res: Result<(), glib::Error> = match value.get::<u64>() {
+                    Ok(spool_max_size) => {
                         let mut settings = self.settings.lock().unwrap();
-                        settings.seal = seal;
+                        settings.spool_max_size = spool_max_size;
                         Ok(())
                     },
                     Err(_) => unreachable!("type checked upstream"),
                 };
                 if let Err(err) = res {
-                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_SEAL, err);
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_SPOOL_MAX_SIZE, err);
                 }
             },
-            PROPERTY_NAME_BUFFER_SIZE => {
-                let res: Result<(), glib::Error> = match value.get::<u32>() {
-                    Ok(buffer_size) => {
+            PROPERTY_NAME_SPOOL_MAX_AGE_SEC => {
+                let res: Result<(), glib::Error> = match value.get::<f64>() {
+                    Ok(spool_max_age_sec) => {
                         let mut settings = self.settings.lock().unwrap();
-                        settings.buffer_size = buffer_size.try_into().unwrap_or_default();
+                        settings.spool_max_age_nanos = (spool_max_age_sec * 1e9) as u64;
                         Ok(())
                     },
                     Err(_) => unreachable!("type checked upstream"),
                 };
                 if let Err(err) = res {
-                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_BUFFER_SIZE, err);
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_SPOOL_MAX_AGE_SEC, err);
                 }
             },
-            PROPERTY_NAME_TIMESTAMP_MODE => {
-                let res: Result<(), glib::Error> = match value.get::<TimestampMode>() {
-                    Ok(timestamp_mode) => {
+            PROPERTY_NAME_ROLLOVER => {
+                let res: Result<(), glib::Error> = match value.get::<RolloverMode>() {
+                    Ok(rollover_mode) => {
                         let mut settings = self.settings.lock().unwrap();
-                        settings.timestamp_mode = timestamp_mode;
+                        settings.rollover_mode = rollover_mode;
                         Ok(())
                     },
                     Err(_) => unreachable!("type checked upstream"),
                 };
                 if let Err(err) = res {
-                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_TIMESTAMP_MODE, err);
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_ROLLOVER, err);
                 }
             },
-            PROPERTY_NAME_INDEX_MIN_SEC => {
-                let res: Result<(), glib::Error> = match value.get::<f64>() {
-                    Ok(index_min_sec) => {
+            PROPERTY_NAME_ROLLOVER_SIZE => {
+                let res: Result<(), glib::Error> = match value.get::<u64>() {
+                    Ok(rollover_size) => {
                         let mut settings = self.settings.lock().unwrap();
-                        settings.index_min_nanos = (index_min_sec * 1e9) as u64;
+                        settings.rollover_size = rollover_size;
                         Ok(())
                     },
                     Err(_) => unreachable!("type checked upstream"),
                 };
                 if let Err(err) = res {
-                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_INDEX_MIN_SEC, err);
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_ROLLOVER_SIZE, err);
                 }
             },
-            PROPERTY_NAME_INDEX_MAX_SEC => {
-                let res: Result<(), glib::Error> = match value.get::<f64>() {
-                    Ok(index_max_sec) => {
+            PROPERTY_NAME_PARTITION_TEMPLATE => {
+                let res: Result<(), glib::Error> = match value.get::<String>() {
+                    Ok(partition_template) => {
                         let mut settings = self.settings.lock().unwrap();
-                        settings.index_max_nanos = (index_max_sec * 1e9) as u64;
+                        settings.partition_template = Some(partition_template);
                         Ok(())
                     },
                     Err(_) => unreachable!("type checked upstream"),
                 };
                 if let Err(err) = res {
-                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_INDEX_MAX_SEC, err);
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_PARTITION_TEMPLATE, err);
                 }
             },
-            PROPERTY_NAME_ALLOW_CREATE_SCOPE => {
+            PROPERTY_NAME_PERSIST_META_APIS => {
+                let res: Result<(), glib::Error> = match value.get::<Option<String>>() {
+                    Ok(persist_meta_apis) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.persist_meta_apis = persist_meta_apis.unwrap_or_default()
+                            .split(',')
+                            .map(|api_name| api_name.trim().to_owned())
+                            .filter(|api_name| !api_name.is_empty())
+                            .collect();
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_PERSIST_META_APIS, err);
+                }
+            },
+            PROPERTY_NAME_PERSIST_BUFFER_TIMING => {
                 let res: Result<(), glib::Error> = match value.get::<bool>() {
-                    Ok(allow_create_scope) => {
+                    Ok(persist_buffer_timing) => {
                         let mut settings = self.settings.lock().unwrap();
-                        settings.allow_create_scope = allow_create_scope;
+                        settings.persist_buffer_timing = persist_buffer_timing;
                         Ok(())
                     },
                     Err(_) => unreachable!("type checked upstream"),
                 };
                 if let Err(err) = res {
-                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_ALLOW_CREATE_SCOPE, err);
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_PERSIST_BUFFER_TIMING, err);
                 }
             },
-            PROPERTY_NAME_KEYCLOAK_FILE => {
-                let res: Result<(), glib::Error> = match value.get::<String>() {
-                    Ok(keycloak_file) => {
+            PROPERTY_NAME_STATS_INTERVAL_SEC => {
+                let res: Result<(), glib::Error> = match value.get::<f64>() {
+                    Ok(stats_interval_sec) => {
                         let mut settings = self.settings.lock().unwrap();
-                        settings.keycloak_file = Some(keycloak_file);
+                        settings.stats_interval_nanos = (stats_interval_sec * 1e9) as u64;
                         Ok(())
                     },
                     Err(_) => unreachable!("type checked upstream"),
                 };
                 if let Err(err) = res {
-                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_KEYCLOAK_FILE, err);
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_STATS_INTERVAL_SEC, err);
                 }
             },
         _ => unimplemented!(),
         };
     }
+
+    fn property(&self, _obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            PROPERTY_NAME_STATS => self.stats.lock().unwrap().to_structure().to_value(),
+            _ => unimplemented!(),
+        }
+    }
 }
 
 impl ElementImpl for PravegaSink {
@@ -459,18 +1809,29 @@ impl BaseSinkImpl for PravegaSink {
                 return Err(gst::error_msg!(gst::ResourceError::Settings,
                     ["{} must be <= {}", PROPERTY_NAME_INDEX_MIN_SEC, PROPERTY_NAME_INDEX_MAX_SEC]))
             };
+            gst_info!(CAT, obj: element, "start: force_key_unit_nanos={}", settings.force_key_unit_nanos);
+            if settings.force_key_unit_nanos > 0 && !(settings.force_key_unit_nanos < settings.index_max_nanos) {
+                return Err(gst::error_msg!(gst::ResourceError::Settings,
+                    ["{} must be < {}", PROPERTY_NAME_FORCE_KEY_UNIT_SEC, PROPERTY_NAME_INDEX_MAX_SEC]))
+            };
             let scope_name: String = settings.scope.clone().ok_or_else(|| {
                 gst::error_msg!(gst::ResourceError::Settings, ["Scope is not defined"])
             })?;
             let stream_name = settings.stream.clone().ok_or_else(|| {
                 gst::error_msg!(gst::ResourceError::Settings, ["Stream is not defined"])
             })?;
-            let index_stream_name = get_index_stream_name(&stream_name);
+            let lease_table_name = get_lease_table_name(&stream_name);
             let scope = Scope::from(scope_name);
-            let stream = Stream::from(stream_name);
-            let index_stream = Stream::from(index_stream_name);
-            gst_info!(CAT, obj: element, "start: scope={}, stream={}, index_stream={}", scope, stream, index_stream);
+            gst_info!(CAT, obj: element, "start: scope={}, stream={}", scope, stream_name);
             gst_info!(CAT, obj: element, "start: timestamp_mode={:?}", settings.timestamp_mode);
+            if settings.timestamp_mode == TimestampMode::ReferenceTimestampMeta {
+                gst_info!(CAT, obj: element, "start: reference_timestamp_caps={}, reference_timestamp_fallback={:?}",
+                    settings.reference_timestamp_caps, settings.reference_timestamp_fallback);
+            }
+            gst_info!(CAT, obj: element, "start: persist_meta_apis={:?}", settings.persist_meta_apis);
+            if let Some(api_name) = settings.persist_meta_apis.iter().find(|api_name| meta_serializer::get(api_name).is_none()) {
+                return Err(gst::error_msg!(gst::ResourceError::Settings, ["No serializer is registered for meta API {}", api_name]));
+            }
 
             let controller = settings.controller.clone().ok_or_else(|| {
                 gst::error_msg!(gst::ResourceError::Settings, ["Controller is not defined"])
@@ -498,68 +1859,223 @@ impl BaseSinkImpl for PravegaSink {
                 })?;
             }
 
-            // Create data stream.
-            let stream_config = StreamConfiguration {
-                scoped_stream: ScopedStream {
-                    scope: scope.clone(),
-                    stream: stream.clone(),
-                },
-                scaling: Scaling {
-                    scale_type: ScaleType::FixedNumSegments,
-                    min_num_segments: 1,
-                    ..Default::default()
-                },
-                retention: Default::default(),
+            // Acquire the writer lease before opening any writers.
+            gst_info!(CAT, obj: element, "start: lease_duration_nanos={}, lease_wait_nanos={}",
+                settings.lease_duration_nanos, settings.lease_wait_nanos);
+            let lease = if settings.lease_duration_nanos > 0 {
+                let holder = format!("{}:{}:{}", glib::host_name(), std::process::id(), now_millis());
+                let store = TableLeaseStore::new(&client_factory, scope.clone(), lease_table_name);
+                let mut lease = WriterLease::new(store, holder, Duration::from_nanos(settings.lease_duration_nanos));
+                let wait_deadline_millis = now_millis() + settings.lease_wait_nanos / 1_000_000;
+                loop {
+                    let result = lease.try_acquire(now_millis()).map_err(|error| {
+                        gst::error_msg!(gst::ResourceError::OpenWrite, ["Failed to acquire writer lease: {}", error])
+                    })?;
+                    match result {
+                        AcquireResult::Acquired => break,
+                        AcquireResult::Held(current_lease) => {
+                            let now = now_millis();
+                            if now >= wait_deadline_millis {
+                                return Err(gst::error_msg!(gst::ResourceError::Busy,
+                                    ["Stream is being written by another writer; lease={:?}", current_lease]));
+                            }
+                            gst_info!(CAT, obj: element, "start: Waiting for writer lease held by {:?}", current_lease);
+                            thread::sleep(cmp::min(LEASE_POLL_INTERVAL, Duration::from_millis(wait_deadline_millis - now)));
+                        },
+                    }
+                }
+                gst_info!(CAT, obj: element, "start: Acquired writer lease as {}", lease.holder());
+                Some(lease)
+            } else {
+                None
             };
-            runtime.block_on(controller_client.create_stream(&stream_config)).map_err(|error| {
-                gst::error_msg!(gst::ResourceError::Settings, ["Failed to create Pravega data stream: {:?}", error])
+
+            // In transactional mode, the buffer holds all uncommitted data and it is written with a single append.
+            let buffer_size = if settings.transactional {
+                EventWithHeader::MAX_ATOMIC_WRITE_SIZE
+            } else {
+                settings.buffer_size
+            };
+            gst_info!(CAT, obj: element, "start: Buffer size is {}", buffer_size);
+
+            gst_info!(CAT, obj: element, "start: allow_create_stream={}", settings.allow_create_stream);
+            gst_info!(CAT, obj: element, "start: retention_type={:?}, retention_days={}, retention_bytes={}",
+                settings.retention_type, settings.retention_days, settings.retention_bytes);
+            let retention = create_retention(settings.retention_type, settings.retention_days, settings.retention_bytes).map_err(|error| {
+                gst::error_msg!(gst::ResourceError::Settings, ["{}", error])
             })?;
+            let stream_policy = StreamPolicy {
+                create: settings.allow_create_stream,
+                retention,
+            };
 
-            // Create index stream.
-            let index_stream_config = StreamConfiguration {
-                scoped_stream: ScopedStream {
-                    scope: scope.clone(),
-                    stream: index_stream.clone(),
+            // When rollover is enabled, the data is written to the partition recorded last in the catalog,
+            // or to a new partition if it is time to roll over.
+            gst_info!(CAT, obj: element, "start: rollover_mode={:?}, rollover_size={}, partition_template={:?}",
+                settings.rollover_mode, settings.rollover_size, settings.partition_template);
+            let rollover = match settings.rollover_mode {
+                RolloverMode::None => None,
+                RolloverMode::Hourly => Some(Rollover::Hourly),
+                RolloverMode::Daily => Some(Rollover::Daily),
+                RolloverMode::Size => Some(Rollover::Size(settings.rollover_size)),
+            };
+            let mut partition = match rollover {
+                Some(_) if !stream_policy.create => {
+                    return Err(gst::error_msg!(gst::ResourceError::Settings,
+                        ["Rollover creates a stream for each partition so it requires allow-create-stream to be true"]));
                 },
-                scaling: Scaling {
-                    scale_type: ScaleType::FixedNumSegments,
-                    min_num_segments: 1,
-                    ..Default::default()
+                Some(rollover) => {
+                    let template = match settings.partition_template {
+                        Some(ref template) if !template.is_empty() => template.clone(),
+                        _ => default_partition_template(rollover).to_owned(),
+                    };
+                    rollover.validate_template(&template).map_err(|error| {
+                        gst::error_msg!(gst::ResourceError::Settings, ["{}", error])
+                    })?;
+                    Some(self.open_partition(element, &client_factory, rollover, template, &scope, &stream_name)?)
                 },
-                retention: Default::default(),
+                None => None,
+            };
+            let data_stream_name = match partition {
+                Some(ref partition) => partition.record.stream.clone(),
+                None => stream_name,
             };
-            runtime.block_on(controller_client.create_stream(&index_stream_config)).map_err(|error| {
-                gst::error_msg!(gst::ResourceError::Settings, ["Failed to create Pravega index stream: {:?}", error])
-            })?;
 
-            let scoped_segment = ScopedSegment {
-                scope: scope.clone(),
-                stream: stream.clone(),
-                segment: Segment::from(0),
+            let StreamWriters {
+                mut writer,
+                mut index_writer,
+                metadata_writer,
+                data_segment: scoped_segment,
+                index_segment: index_scoped_segment,
+                metadata_segment: _,
+            } = {
+                let writers = self.open_stream_writers(element, &client_factory, &scope, &data_stream_name, &stream_policy, buffer_size)?;
+                if let Some(ref mut partition) = partition {
+                    // If this is a new partition, record it in the catalog now that its streams exist.
+                    if !partition.recorded {
+                        self.record_partition(element, &client_factory, partition, &writers)?;
+                    }
+                }
+                writers
+            };
+            *self.stats.lock().unwrap() = Stats {
+                data_segment: Some(scoped_segment.clone()),
+                ..Default::default()
             };
-            let mut writer = client_factory.create_byte_stream_writer(scoped_segment);
-            gst_info!(CAT, obj: element, "start: Opened Pravega writer for data");
-            writer.seek_to_tail();
 
-            let index_scoped_segment = ScopedSegment {
-                scope: scope.clone(),
-                stream: index_stream.clone(),
-                segment: Segment::from(0),
+            gst_info!(CAT, obj: element, "start: transactional={}", settings.transactional);
+            if settings.transactional {
+                // A previous transactional writer may have failed after committing data but before writing
+                // the index record that ends it. Add this data to the index so that the index covers the entire data stream.
+                let index_reader = client_factory.create_byte_stream_reader(index_scoped_segment.clone());
+                let mut index_searcher = IndexSearcher::new(index_reader);
+                match index_searcher.get_last_record() {
+                    Ok(last_index_record) => {
+                        let data_reader = client_factory.create_byte_stream_reader(scoped_segment.clone());
+                        let mut rebuilder = IndexRebuilder::new(data_reader, settings.index_min_nanos, settings.index_max_nanos);
+                        let summary = rebuilder.rebuild_tail(&last_index_record, &mut index_writer).map_err(|error| {
+                            gst::error_msg!(gst::ResourceError::Write, ["Failed to index data at the tail of the Pravega data stream: {}", error])
+                        })?;
+                        if summary.index_records_written > 0 {
+                            gst_warning!(CAT, obj: element, "start: Indexed {} events that were not indexed by a previous writer; last index record is {:?}",
+                                summary.events_read, summary.last_index_record);
+                        }
+                        if summary.incomplete_tail_bytes > 0 {
+                            gst_warning!(CAT, obj: element,
+                                "start: The data stream ends with {} bytes that do not contain a complete event. These were probably written by a non-transactional writer.",
+                                summary.incomplete_tail_bytes);
+                        }
+                    },
+                    Err(error) if error.kind() == ErrorKind::UnexpectedEof => {
+                        gst_info!(CAT, obj: element, "start: Index is empty");
+                    },
+                    Err(error) => {
+                        return Err(gst::error_msg!(gst::ResourceError::Read, ["Failed to read Pravega index stream: {}", error]));
+                    },
+                }
+            }
+
+            let metadata = StreamMetadata {
+                host: Some(glib::host_name().to_string()),
+                writer: Some(format!("pravegasink {}", env!("CARGO_PKG_VERSION"))),
+                ..Default::default()
             };
-            let mut index_writer = client_factory.create_byte_stream_writer(index_scoped_segment);
-            gst_info!(CAT, obj: element, "start: Opened Pravega writer for index");
-            index_writer.seek_to_tail();
 
-            let seekable_writer = SeekableByteStreamWriter::new(writer).unwrap();
-            gst_info!(CAT, obj: element, "start: Buffer size is {}", settings.buffer_size);
-            let buf_writer = BufWriter::with_capacity(settings.buffer_size, seekable_writer);
-            let counting_writer = CountingWriter::new(buf_writer).unwrap();
+            gst_info!(CAT, obj: element, "start: spool_dir={:?}, spool_max_size={}, spool_max_age_nanos={}",
+                settings.spool_dir, settings.spool_max_size, settings.spool_max_age_nanos);
+            let spool_state = match settings.spool_dir {
+                Some(ref spool_dir) if !spool_dir.is_empty() => {
+                    let max_age = if settings.spool_max_age_nanos > 0 {
+                        Some(Duration::from_nanos(settings.spool_max_age_nanos))
+                    } else {
+                        None
+                    };
+                    let spool = Spool::open(spool_dir, settings.spool_max_size, max_age).map_err(|error| {
+                        gst::error_msg!(gst::ResourceError::OpenWrite, ["Failed to open spool directory {}: {}", spool_dir, error])
+                    })?;
+                    // Buffers spooled by a previous instance were not written, but the data stream may not end
+                    // where they would have been written.
+                    let committed_offset = if spool.is_empty() {
+                        Some(writer.seek(SeekFrom::Current(0)).map_err(|error| {
+                            gst::error_msg!(gst::ResourceError::OpenWrite, ["Failed to get the write offset of the Pravega data stream: {}", error])
+                        })?)
+                    } else {
+                        None
+                    };
+                    let mut spool_state = SpoolState {
+                        spool,
+                        unflushed: Vec::new(),
+                        resume: SpoolResume {
+                            committed_offset,
+                            uncommitted: Vec::new(),
+                        },
+                        retry_time: None,
+                        data_segment: scoped_segment,
+                        index_segment: index_scoped_segment,
+                        buffer_size,
+                        checksum: settings.checksum,
+                        transactional: settings.transactional,
+                    };
+                    // Buffers spooled by a previous instance will be written before the first buffer from this instance.
+                    if !spool_state.spool.is_empty() {
+                        gst_info!(CAT, obj: element, "start: Spool contains {} bytes from a previous instance", spool_state.spool.size());
+                        spool_state.retry_time = Some(Instant::now());
+                    }
+                    Some(spool_state)
+                },
+                _ => None,
+            };
+
+            gst_info!(CAT, obj: element, "start: stats_interval_nanos={}", settings.stats_interval_nanos);
+            if settings.stats_interval_nanos > 0 {
+                let element_weak = element.downgrade();
+                let client_factory = client_factory.clone();
+                let reporter = StatsReporter::start("pravegasink-stats", Duration::from_nanos(settings.stats_interval_nanos), move || {
+                    if let Some(element) = element_weak.upgrade() {
+                        PravegaSink::from_instance(&element).post_stats(&element, &client_factory);
+                    }
+                }).map_err(|error| {
+                    gst::error_msg!(gst::CoreError::Failed, ["Failed to start stats thread: {}", error])
+                })?;
+                *self.stats_reporter.lock().unwrap() = Some(reporter);
+            }
 
             *state = State::Started {
                 client_factory,
-                writer: counting_writer,
+                writer,
                 index_writer,
+                metadata_writer,
+                lease,
+                spool_state,
+                partition,
+                stream_policy,
+                metadata,
+                tags: None,
+                // A metadata record will be written before the first buffer from this instance.
+                metadata_changed: true,
                 last_index_time: PravegaTimestamp::NONE,
+                key_unit_requested: false,
+                last_reference_timestamp: None,
                 final_timestamp: PravegaTimestamp::NONE,
                 final_offset: None,
                 buffers_written: 0,
@@ -580,23 +2096,53 @@ impl BaseSinkImpl for PravegaSink {
         gst_trace!(CAT, obj: element, "render: BEGIN: Rendering {:?}", buffer);
         let result = (|| {
             let mut state = self.state.lock().unwrap();
-            let (writer,
+            let (client_factory,
+                writer,
                 index_writer,
+                metadata_writer,
+                lease,
+                spool_state,
+                partition,
+                stream_policy,
+                metadata,
+                metadata_changed,
                 last_index_time,
+                key_unit_requested,
+                last_reference_timestamp,
                 final_timestamp,
                 final_offset,
                 buffers_written) = match *state {
                 State::Started {
+                    ref client_factory,
                     ref mut writer,
                     ref mut index_writer,
+                    ref mut metadata_writer,
+                    ref mut lease,
+                    ref mut spool_state,
+                    ref mut partition,
+                    ref stream_policy,
+                    ref mut metadata,
+                    ref mut metadata_changed,
                     ref mut last_index_time,
+                    ref mut key_unit_requested,
+                    ref mut last_reference_timestamp,
                     ref mut final_timestamp,
                     ref mut final_offset,
                     ref mut buffers_written,
                     ..
-                } => (writer,
+                } => (client_factory,
+                    writer,
                     index_writer,
+                    metadata_writer,
+                    lease,
+                    spool_state,
+                    partition,
+                    stream_policy,
+                    metadata,
+                    metadata_changed,
                     last_index_time,
+                    key_unit_requested,
+                    last_reference_timestamp,
                     final_timestamp,
                     final_offset,
                     buffers_written),
@@ -606,6 +2152,33 @@ impl BaseSinkImpl for PravegaSink {
                 }
             };
 
+            let spooling = spool_state.as_ref().map_or(false, |spool_state| spool_state.is_spooling());
+
+            // An error that indicates that Pravega is unavailable. This is only used when the spool is enabled.
+            let mut pravega_error = None;
+
+            // Renew the writer lease. If it has been lost, another writer may be writing to the streams,
+            // so this instance must not write anything else.
+            // While spooling, the lease is renewed when the spooled events are written.
+            if !spooling {
+                if let Some(lease) = lease {
+                    let now = now_millis();
+                    if lease.needs_renewal(now) {
+                        match lease.renew(now) {
+                            Ok(()) => gst_debug!(CAT, obj: element, "render: Renewed writer lease"),
+                            Err(error) if error.kind() != ErrorKind::PermissionDenied && spool_state.is_some() => {
+                                pravega_error = Some(error);
+                            },
+                            Err(error) => {
+                                writer.get_mut().get_mut().fence();
+                                gst::element_error!(element, gst::ResourceError::Write, ["Writer lease lost: {}", error]);
+                                return Err(gst::FlowError::Error);
+                            },
+                        }
+                    }
+                }
+            }
+
             let pts = buffer.pts();
             let duration = buffer.duration();
 
@@ -615,16 +2188,22 @@ impl BaseSinkImpl for PravegaSink {
             })?;
             let payload = map.as_ref();
 
-            let (timestamp_mode, index_min_nanos, index_max_nanos) = {
+            let (timestamp_mode, reference_timestamp_caps, reference_timestamp_fallback,
+                    index_min_nanos, index_max_nanos, force_key_unit_nanos, checksum, transactional,
+                    persist_meta_apis, persist_buffer_timing) = {
                 let settings = self.settings.lock().unwrap();
-                (settings.timestamp_mode, settings.index_min_nanos, settings.index_max_nanos)
+                (settings.timestamp_mode, settings.reference_timestamp_caps.clone(), settings.reference_timestamp_fallback,
+                    settings.index_min_nanos, settings.index_max_nanos, settings.force_key_unit_nanos,
+                    settings.checksum, settings.transactional, settings.persist_meta_apis.clone(), settings.persist_buffer_timing)
             };
 
+            // pts is time between beginning of play and beginning of this buffer.
+            // base_time is the value of the pipeline clock (time since Unix epoch) at the beginning of play.
+            let realtime_clock_timestamp = || PravegaTimestamp::from_unix_nanoseconds((element.base_time() + pts).nseconds());
+
             let timestamp = match timestamp_mode {
                 TimestampMode::RealtimeClock => {
-                    // pts is time between beginning of play and beginning of this buffer.
-                    // base_time is the value of the pipeline clock (time since Unix epoch) at the beginning of play.
-                    PravegaTimestamp::from_unix_nanoseconds((element.base_time() + pts).nseconds())
+                    realtime_clock_timestamp()
                 },
                 TimestampMode::Ntp => {
                     // When receiving from rtspsrc (ntp-sync=true ntp-time-source=running-time),
@@ -635,32 +2214,77 @@ impl BaseSinkImpl for PravegaSink {
                 },
                 TimestampMode::Tai => {
                     PravegaTimestamp::from_nanoseconds(pts.nseconds())
-                }
+                },
+                TimestampMode::ReferenceTimestampMeta => {
+                    match get_reference_timestamp(buffer, &reference_timestamp_caps) {
+                        Some(timestamp) => {
+                            *last_reference_timestamp = Some((timestamp, pts));
+                            timestamp
+                        },
+                        None => {
+                            gst_debug!(CAT, obj: element, "render: Buffer does not have a reference timestamp; fallback={:?}",
+                                reference_timestamp_fallback);
+                            match reference_timestamp_fallback {
+                                ReferenceTimestampFallback::Interpolate => {
+                                    match *last_reference_timestamp {
+                                        Some((reference_timestamp, reference_pts)) => {
+                                            match (reference_timestamp.nanoseconds(), pts.nseconds(), reference_pts.nseconds()) {
+                                                (Some(reference_timestamp), Some(pts), Some(reference_pts)) => {
+                                                    PravegaTimestamp::from_nanoseconds((reference_timestamp + pts).checked_sub(reference_pts))
+                                                },
+                                                _ => realtime_clock_timestamp(),
+                                            }
+                                        },
+                                        None => realtime_clock_timestamp(),
+                                    }
+                                },
+                                ReferenceTimestampFallback::RealtimeClock => realtime_clock_timestamp(),
+                                ReferenceTimestampFallback::Drop => {
+                                    gst_log!(CAT, obj: element, "render: Dropping buffer without a reference timestamp");
+                                    return Ok(gst::FlowSuccess::Ok);
+                                },
+                            }
+                        },
+                    }
+                },
             };
 
-            // Get the writer offset before writing. This offset will be used in the index.
-            let writer_offset = writer.seek(SeekFrom::Current(0)).unwrap();
-
-            gst_log!(CAT, obj: element, "render: timestamp={:?}, pts={}, base_time={}, duration={}, size={}, writer_offset={}",
-                timestamp, pts, element.base_time(), buffer.duration(), buffer.size(), writer_offset);
+            gst_log!(CAT, obj: element, "render: timestamp={:?}, pts={}, base_time={}, duration={}, size={}, spooling={}",
+                timestamp, pts, element.base_time(), buffer.duration(), buffer.size(), spooling);
 
             // We only want to include key frames (non-delta units) in the index.
-            // However, if no key frame has been received in a while, force an index record.
-            // This is required for nvv4l2h264enc because it identifies all buffers as DELTA_UNIT.
+            // If force-key-unit-sec is set and no key frame has been received in that time, request one from upstream.
+            // If no key frame has been received in a while, force an index record.
+            // This is required for nvv4l2h264enc because it identifies all buffers as DELTA_UNIT,
+            // and for encoders that ignore requests for key frames.
             let buffer_flags = buffer.flags();
             let is_delta_unit = buffer_flags.contains(gst::BufferFlags::DELTA_UNIT);
             let random_access = !is_delta_unit;
-            let include_in_index = match timestamp.nanoseconds() {
+            let mut forced_index_record = false;
+            let mut request_key_unit = false;
+            let mut include_in_index = match timestamp.nanoseconds() {
                 Some(timestamp) => {
                     match last_index_time.nanoseconds() {
                         Some(last_index_time) => {
                             let interval_sec = u64_to_i64_saturating_sub(timestamp, last_index_time) as f64 * 1e-9;
                             if is_delta_unit {
                                 if timestamp > last_index_time + index_max_nanos {
-                                    gst_fixme!(CAT, obj: element,
-                                        "render: Forcing index record at delta unit because no key frame has been received for {} sec", interval_sec);
+                                    if *key_unit_requested {
+                                        gst_fixme!(CAT, obj: element,
+                                            "render: Forcing index record at delta unit because no key frame has been received for {} sec; upstream ignored the request for a key frame",
+                                            interval_sec);
+                                    } else {
+                                        gst_fixme!(CAT, obj: element,
+                                            "render: Forcing index record at delta unit because no key frame has been received for {} sec", interval_sec);
+                                    }
+                                    forced_index_record = true;
                                     true
                                 } else {
+                                    if force_key_unit_nanos > 0 && !*key_unit_requested && timestamp > last_index_time + force_key_unit_nanos {
+                                        gst_debug!(CAT, obj: element,
+                                            "render: Requesting key frame because no key frame has been received for {} sec", interval_sec);
+                                        request_key_unit = true;
+                                    }
                                     false
                                 }
                             } else {
@@ -688,14 +2312,13 @@ impl BaseSinkImpl for PravegaSink {
                 },
             };
 
-            // Per the index constraints defined in index.rs, if we are writing an index record now,
-            // we must flush any data writes prior to this buffer, so that reads do not block waiting on this writer.
-            let flush = include_in_index;
-            if flush {
-                writer.flush().map_err(|error| {
-                    gst::element_error!(element, gst::CoreError::Failed, ["Failed to flush Pravega data stream: {}", error]);
-                    gst::FlowError::Error
-                })?;
+            if request_key_unit {
+                let sink_pad = element.static_pad("sink").unwrap();
+                if !sink_pad.push_event(new_upstream_force_key_unit_event()) {
+                    gst_debug!(CAT, obj: element, "render: GstForceKeyUnit event was not handled upstream");
+                }
+                *key_unit_requested = true;
+                self.stats.lock().unwrap().key_unit_requests += 1;
             }
 
             // Record a discontinuity if any of the following are true:
@@ -711,58 +2334,112 @@ impl BaseSinkImpl for PravegaSink {
                 gst_debug!(CAT, obj: element, "render: Recording discontinuity");
             }
 
-            // Write index record.
-            // We write the index record before the buffer so that any readers blocked on reading the
-            // index will unblock as soon as possible.
-            if include_in_index {
-                let index_record = IndexRecord::new(timestamp, writer_offset,
-                    random_access, discontinuity);
-                let mut index_record_writer = IndexRecordWriter::new();
-                index_record_writer.write(&index_record, index_writer).map_err(|err| {
-                    gst::element_error!(
-                        element,
-                        gst::ResourceError::Write,
-                        ["Failed to write index: {}", err]
-                    );
-                    gst::FlowError::Error
-                })?;
-                gst_debug!(CAT, obj: element, "render: Wrote index record {:?}", index_record);
-                *last_index_time = timestamp;
+            // When rollover is enabled, a new partition may begin at an index record of a key frame,
+            // so that the partition can be decoded from its first buffer.
+            // Rollover is deferred while Pravega is unavailable.
+            if let Some(partition) = partition {
+                let data_offset = writer.seek(SeekFrom::Current(0)).unwrap();
+                if include_in_index && random_access && !spooling && pravega_error.is_none()
+                        && partition.rollover.is_due(partition.record.begin_timestamp(), timestamp, data_offset) {
+                    gst_info!(CAT, obj: element, "render: Rolling over from partition {}", partition.record.stream);
+                    let buffer_size = {
+                        let settings = self.settings.lock().unwrap();
+                        if settings.transactional {
+                            EventWithHeader::MAX_ATOMIC_WRITE_SIZE
+                        } else {
+                            settings.buffer_size
+                        }
+                    };
+                    let result = (|| -> Result<StreamWriters, gst::ErrorMessage> {
+                        // End the current partition as if stopping.
+                        check_lease_expiry(lease, writer).map_err(|error| {
+                            gst::error_msg!(gst::ResourceError::Write, ["Writer lease lost: {}", error])
+                        })?;
+                        writer.flush().map_err(|error| {
+                            gst::error_msg!(gst::ResourceError::Write, ["Failed to flush Pravega data stream: {}", error])
+                        })?;
+                        write_final_index_record(index_writer, *final_timestamp, *final_offset)
+                            .and_then(|_| index_writer.flush())
+                            .map_err(|error| {
+                                gst::error_msg!(gst::ResourceError::Write, ["Failed to write Pravega index stream: {}", error])
+                            })?;
+                        let previous = partition.record.clone();
+                        partition.begin_next(client_factory, Some(previous), timestamp).map_err(|error| {
+                            gst::error_msg!(gst::ResourceError::Read, ["Failed to read Pravega index stream: {}", error])
+                        })?;
+                        let writers = self.open_stream_writers(element, client_factory, &partition.scope,
+                            &partition.record.stream, stream_policy, buffer_size)?;
+                        self.record_partition(element, client_factory, partition, &writers)?;
+                        Ok(writers)
+                    })();
+                    match result {
+                        Ok(writers) => {
+                            *writer = writers.writer;
+                            *index_writer = writers.index_writer;
+                            *metadata_writer = writers.metadata_writer;
+                            {
+                                let mut stats = self.stats.lock().unwrap();
+                                stats.data_segment = Some(writers.data_segment.clone());
+                                stats.head_offset = None;
+                                stats.tail_offset = None;
+                            }
+                            if let Some(spool_state) = spool_state {
+                                spool_state.data_segment = writers.data_segment;
+                                spool_state.index_segment = writers.index_segment;
+                                spool_state.buffer_size = buffer_size;
+                                spool_state.opened(writer.seek(SeekFrom::Current(0)).unwrap());
+                            }
+                            // A metadata record will be written to the new partition before this buffer.
+                            *metadata_changed = true;
+                            *final_offset = None;
+                        },
+                        Err(error) => {
+                            element.post_error_message(error);
+                            return Err(gst::FlowError::Error);
+                        },
+                    }
+                }
             }
 
-            // Write buffer to Pravega byte stream.
-            let event = EventWithHeader::new(payload, timestamp,
+            let mut event = EventWithHeader::new(payload, timestamp,
                 include_in_index, random_access, discontinuity);
+            // Persist the selected metas in header extensions.
+            for api_name in persist_meta_apis.iter() {
+                if let Some(serializer) = meta_serializer::get(api_name) {
+                    for data in serializer.serialize(buffer) {
+                        event.header.extensions.push(HeaderExtension::gst_meta(api_name, &data));
+                    }
+                }
+            }
+            // Persist the DTS relative to the PTS, and the duration.
+            if persist_buffer_timing {
+                let timing = BufferTiming {
+                    dts_offset: match (pts.nseconds(), buffer.dts().nseconds()) {
+                        (Some(pts), Some(dts)) => Some(u64_to_i64_saturating_sub(pts, dts)),
+                        _ => None,
+                    },
+                    duration: duration.nseconds(),
+                };
+                if timing != BufferTiming::default() {
+                    event.header.extensions.push(HeaderExtension::buffer_timing(&timing));
+                }
+            }
+            // In transactional mode, begin a new interval if this event would not fit in the current one.
+            // While spooling, this is done when the spooled events are written.
+            if !spooling && pravega_error.is_none() && must_index_to_fit(writer, &event, checksum, transactional) {
+                gst_fixme!(CAT, obj: element,
+                    "render: Forcing index record because the data since the previous index record would exceed {} bytes",
+                    EventWithHeader::MAX_ATOMIC_WRITE_SIZE);
+                event.header.include_in_index = true;
+                include_in_index = true;
+                forced_index_record = true;
+            }
             gst_memdump!(CAT, obj: element, "render: writing event={:?}", event);
-            let mut event_writer = EventWriter::new();
-            event_writer.write(&event, writer).map_err(|err| {
-                gst::element_error!(
-                    element,
-                    gst::ResourceError::Write,
-                    ["Failed to write buffer: {}", err]
-                );
-                gst::FlowError::Error
-            })?;
-            *buffers_written += 1;
-
-            // Get the writer offset after writing.
-            let writer_offset_end = writer.seek(SeekFrom::Current(0)).unwrap();
-            gst_trace!(CAT, obj: element, "render: wrote {} bytes from offset {} to {}",
-                writer_offset_end - writer_offset, writer_offset, writer_offset_end);
-
-            // Flush after writing if the buffer contains the SYNC_AFTER flag. This is normally not used.
-            let sync_after = buffer_flags.contains(gst::BufferFlags::SYNC_AFTER);
-            if sync_after {
-                writer.flush().map_err(|error| {
-                    gst::element_error!(element, gst::CoreError::Failed, ["Failed to flush Pravega data stream: {}", error]);
-                    gst::FlowError::Error
-                })?;
-                index_writer.flush().map_err(|error| {
-                    gst::element_error!(element, gst::CoreError::Failed, ["Failed to flush Pravega index stream: {}", error]);
-                    gst::FlowError::Error
-                })?;
-                gst_debug!(CAT, obj: element, "render: Streams flushed because SYNC_AFTER flag was set");
+            if include_in_index {
+                *last_index_time = timestamp;
+                *key_unit_requested = false;
             }
+            *buffers_written += 1;
 
             // Maintain values that may be written to the index on end-of-stream.
             // Per the index constraints defined in index.rs, the timestamp in the index record must
@@ -773,7 +2450,108 @@ impl BaseSinkImpl for PravegaSink {
                 *final_timestamp = PravegaTimestamp::from_nanoseconds(
                     timestamp.nanoseconds().map(|t| t + duration));
             }
-            *final_offset = Some(writer_offset_end);
+
+            // Flush after writing if the buffer contains the SYNC_AFTER flag. This is normally not used.
+            let sync_after = buffer_flags.contains(gst::BufferFlags::SYNC_AFTER);
+
+            if !spooling && pravega_error.is_none() {
+                let result = (|| -> Result<(u64, Option<Duration>), Error> {
+                    // Writing this event may flush the data stream.
+                    check_lease_expiry(lease, writer)?;
+                    // If caps or tags have changed, write a metadata record that applies to this and subsequent buffers.
+                    if *metadata_changed {
+                        metadata.data_offset = writer.seek(SeekFrom::Current(0))?;
+                        StreamMetadataWriter::new().write(metadata, metadata_writer)
+                            .and_then(|_| metadata_writer.flush())
+                            .map_err(|error| {
+                                Error::new(error.kind(), format!("Failed to write Pravega metadata stream: {}", error))
+                            })?;
+                        gst_info!(CAT, obj: element, "render: Wrote metadata record {:?}", metadata);
+                        *metadata_changed = false;
+                    }
+                    let (writer_offset, flush_latency) = write_event(writer, index_writer, &event, checksum, transactional)?;
+                    if include_in_index {
+                        gst_debug!(CAT, obj: element, "render: Wrote index record at offset {}", writer_offset);
+                    }
+                    if sync_after {
+                        writer.flush().map_err(|error| {
+                            Error::new(error.kind(), format!("Failed to flush Pravega data stream: {}", error))
+                        })?;
+                        index_writer.flush().map_err(|error| {
+                            Error::new(error.kind(), format!("Failed to flush Pravega index stream: {}", error))
+                        })?;
+                        gst_debug!(CAT, obj: element, "render: Streams flushed because SYNC_AFTER flag was set");
+                    }
+                    Ok((writer_offset, flush_latency))
+                })();
+                match result {
+                    Ok((writer_offset, flush_latency)) => {
+                        // Get the writer offset after writing.
+                        let writer_offset_end = writer.seek(SeekFrom::Current(0)).unwrap();
+                        gst_trace!(CAT, obj: element, "render: wrote {} bytes from offset {} to {}",
+                            writer_offset_end - writer_offset, writer_offset, writer_offset_end);
+                        *final_offset = Some(writer_offset_end);
+                        {
+                            let mut stats = self.stats.lock().unwrap();
+                            stats.bytes_written += writer_offset_end - writer_offset;
+                            stats.events_written += 1;
+                            if include_in_index {
+                                stats.index_records_written += 1;
+                                if forced_index_record {
+                                    stats.forced_index_records += 1;
+                                }
+                            }
+                            if let Some(flush_latency) = flush_latency {
+                                stats.last_flush_latency = flush_latency;
+                                stats.max_flush_latency = cmp::max(stats.max_flush_latency, flush_latency);
+                            }
+                            stats.spooling = false;
+                        }
+                        if let Some(spool_state) = spool_state {
+                            spool_state.written(&event, writer_offset, writer_offset_end, sync_after);
+                        }
+                        return Ok(gst::FlowSuccess::Ok);
+                    },
+                    Err(error) if error.kind() == ErrorKind::PermissionDenied => {
+                        gst::element_error!(element, gst::ResourceError::Write, ["Writer lease lost: {}", error]);
+                        return Err(gst::FlowError::Error);
+                    },
+                    Err(error) if spool_state.is_some() => {
+                        pravega_error = Some(error);
+                    },
+                    Err(error) => {
+                        gst::element_error!(element, gst::ResourceError::Write, ["{}", error]);
+                        return Err(gst::FlowError::Error);
+                    },
+                }
+            }
+
+            // Pravega is unavailable, so append the event to the spool.
+            let spool_state = spool_state.as_mut().unwrap();
+            if let Some(error) = pravega_error {
+                gst_warning!(CAT, obj: element, "render: Pravega is unavailable; buffers will be written to the spool: {}", error);
+                // Buffered data may not have been written, so it is discarded and the unflushed events are spooled instead.
+                writer.get_mut().get_mut().fence();
+                spool_state.begin_outage().map_err(|error| {
+                    gst::element_error!(element, gst::ResourceError::Write, ["Failed to write to spool: {}", error]);
+                    gst::FlowError::Error
+                })?;
+            }
+            spool_state.spool.append(&event).map_err(|error| {
+                gst::element_error!(element, gst::ResourceError::Write, ["Failed to write to spool: {}", error]);
+                gst::FlowError::Error
+            })?;
+            self.stats.lock().unwrap().spooling = true;
+            if spool_state.is_retry_due() {
+                match self.drain_spool(element, spool_state, client_factory, writer, index_writer, lease) {
+                    Ok(Some(offset)) => *final_offset = Some(offset),
+                    Ok(None) => {},
+                    Err(error) => {
+                        gst::element_error!(element, gst::ResourceError::Write, ["Writer lease lost: {}", error]);
+                        return Err(gst::FlowError::Error);
+                    },
+                }
+            }
 
             Ok(gst::FlowSuccess::Ok)
         })();
@@ -781,8 +2559,46 @@ impl BaseSinkImpl for PravegaSink {
         result
     }
 
+    fn set_caps(&self, element: &Self::Type, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        gst_info!(CAT, obj: element, "set_caps: caps={:?}", caps);
+        let mut state = self.state.lock().unwrap();
+        if let State::Started { ref mut metadata, ref mut metadata_changed, .. } = *state {
+            let caps = Some(caps.to_string());
+            if metadata.caps != caps {
+                metadata.caps = caps;
+                *metadata_changed = true;
+            }
+        }
+        drop(state);
+        self.parent_set_caps(element, caps)
+    }
+
+    fn event(&self, element: &Self::Type, event: gst::Event) -> bool {
+        if let gst::EventView::Tag(tag_event) = event.view() {
+            let mut state = self.state.lock().unwrap();
+            if let State::Started { ref mut metadata, ref mut tags, ref mut metadata_changed, .. } = *state {
+                let merged_tags = match tags {
+                    Some(tags) => tags.merge(tag_event.tag(), gst::TagMergeMode::Replace),
+                    None => tag_event.tag().to_owned(),
+                };
+                gst_debug!(CAT, obj: element, "event: tags={:?}", merged_tags);
+                let tags_string = Some(merged_tags.to_string());
+                if metadata.tags != tags_string {
+                    metadata.encoder = merged_tags.get::<gst::tags::Encoder>().map(|encoder| encoder.get().to_owned());
+                    metadata.tags = tags_string;
+                    *metadata_changed = true;
+                }
+                *tags = Some(merged_tags);
+            }
+        }
+        self.parent_event(element, event)
+    }
+
     fn stop(&self, element: &Self::Type) -> Result<(), gst::ErrorMessage> {
         gst_info!(CAT, obj: element, "stop: BEGIN");
+        if let Some(mut reporter) = self.stats_reporter.lock().unwrap().take() {
+            reporter.stop();
+        }
         let result = (|| {
             let seal = {
                 let settings = self.settings.lock().unwrap();
@@ -792,18 +2608,27 @@ impl BaseSinkImpl for PravegaSink {
             let mut state = self.state.lock().unwrap();
             let (writer,
                 index_writer,
+                metadata_writer,
+                lease,
+                spool_state,
                 client_factory,
                 final_timestamp,
                 final_offset) = match *state {
                 State::Started {
                     ref mut writer,
                     ref mut index_writer,
+                    ref mut metadata_writer,
+                    ref mut lease,
+                    ref mut spool_state,
                     ref mut client_factory,
                     ref mut final_timestamp,
                     ref mut final_offset,
                     ..
                 } => (writer,
                     index_writer,
+                    metadata_writer,
+                    lease,
+                    spool_state,
                     client_factory,
                     final_timestamp,
                     final_offset),
@@ -815,23 +2640,62 @@ impl BaseSinkImpl for PravegaSink {
                 }
             };
 
-            writer.flush().map_err(|error| {
-                gst::error_msg!(gst::ResourceError::Write, ["Failed to flush Pravega data stream: {}", error])
-            })?;
+            // If Pravega was unavailable, attempt to write the spooled buffers now.
+            // If this fails, they remain in the spool and will be written when this element is started again.
+            if let Some(spool_state) = spool_state {
+                if spool_state.is_spooling() {
+                    match self.drain_spool(element, spool_state, client_factory, writer, index_writer, lease) {
+                        Ok(Some(offset)) => *final_offset = Some(offset),
+                        Ok(None) => {
+                            gst_warning!(CAT, obj: element, "stop: Pravega is unavailable; {} bytes remain in the spool",
+                                spool_state.spool.size());
+                            *state = State::Stopped;
+                            return Ok(());
+                        },
+                        Err(error) => {
+                            *state = State::Stopped;
+                            return Err(gst::error_msg!(gst::ResourceError::Write, ["Writer lease lost: {}", error]));
+                        },
+                    }
+                }
+            }
 
-            // Write final index record.
-            // The timestamp will be the the buffer timestamp + duration of the final buffer.
-            // The offset will be current write position.
-            if let Some(final_offset) = *final_offset {
-                if final_timestamp.is_some() {
-                    let index_record = IndexRecord::new(*final_timestamp, final_offset,
-                        false, false);
-                    let mut index_record_writer = IndexRecordWriter::new();
-                    index_record_writer.write(&index_record, index_writer).map_err(|error| {
-                        gst::error_msg!(gst::ResourceError::Write, ["Failed to write Pravega index stream: {}", error])
+            // Ensure that the writer lease is still held before writing buffered data and the final index record.
+            if let Some(lease) = lease {
+                if lease.is_held() {
+                    if let Err(error) = lease.renew(now_millis()) {
+                        writer.get_mut().get_mut().fence();
+                        *state = State::Stopped;
+                        return Err(gst::error_msg!(gst::ResourceError::Write, ["Writer lease lost: {}", error]));
+                    }
+                } else {
+                    // The lease was lost while rendering. Discard the buffered data.
+                    *state = State::Stopped;
+                    return Ok(());
+                }
+            }
+
+            if let Err(error) = writer.flush() {
+                if let Some(spool_state) = spool_state {
+                    // The buffered data may not have been written, so spool the unflushed buffers.
+                    writer.get_mut().get_mut().fence();
+                    spool_state.begin_outage().map_err(|error| {
+                        gst::error_msg!(gst::ResourceError::Write, ["Failed to write to spool: {}", error])
                     })?;
-                    gst_info!(CAT, obj: element, "stop: Wrote final index record {:?}", index_record);
+                    gst_warning!(CAT, obj: element, "stop: Pravega is unavailable; {} bytes remain in the spool: {}",
+                        spool_state.spool.size(), error);
+                    *state = State::Stopped;
+                    return Ok(());
                 }
+                return Err(gst::error_msg!(gst::ResourceError::Write, ["Failed to flush Pravega data stream: {}", error]));
+            }
+
+            // Write final index record.
+            let index_record = write_final_index_record(index_writer, *final_timestamp, *final_offset).map_err(|error| {
+                gst::error_msg!(gst::ResourceError::Write, ["Failed to write Pravega index stream: {}", error])
+            })?;
+            if let Some(index_record) = index_record {
+                gst_info!(CAT, obj: element, "stop: Wrote final index record {:?}", index_record);
             }
 
             index_writer.flush().map_err(|error| {
@@ -847,9 +2711,18 @@ impl BaseSinkImpl for PravegaSink {
                 client_factory.get_runtime().block_on(index_writer.seal()).map_err(|error| {
                     gst::error_msg!(gst::ResourceError::Write, ["Failed to seal Pravega index stream: {}", error])
                 })?;
+                client_factory.get_runtime().block_on(metadata_writer.seal()).map_err(|error| {
+                    gst::error_msg!(gst::ResourceError::Write, ["Failed to seal Pravega metadata stream: {}", error])
+                })?;
                 gst_info!(CAT, obj: element, "stop: Streams sealed");
             }
 
+            if let Some(lease) = lease {
+                lease.release().map_err(|error| {
+                    gst::error_msg!(gst::ResourceError::Write, ["Failed to release writer lease: {}", error])
+                })?;
+            }
+
             *state = State::Stopped;
             Ok(())
         })();
@@ -857,3 +2730,187 @@ impl BaseSinkImpl for PravegaSink {
         result
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pravega_video::event_serde::EventReader;
+    use pravega_video::verifier::StreamVerifier;
+    use std::cell::{Cell, RefCell};
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    /// An in-memory data stream whose appends fail once the allowed number of appends has been used.
+    struct FlakyStream {
+        data: Rc<RefCell<Vec<u8>>>,
+        appends_allowed: Rc<Cell<usize>>,
+    }
+
+    impl Write for FlakyStream {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            if self.appends_allowed.get() == 0 {
+                return Err(Error::new(ErrorKind::Other, "injected failure"));
+            }
+            self.appends_allowed.set(self.appends_allowed.get() - 1);
+            self.data.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl Seek for FlakyStream {
+        fn seek(&mut self, _pos: SeekFrom) -> Result<u64, Error> {
+            Ok(self.data.borrow().len() as u64)
+        }
+    }
+
+    fn flaky_writer(data: &Rc<RefCell<Vec<u8>>>, appends_allowed: &Rc<Cell<usize>>) -> CountingWriter<BufWriter<FlakyStream>> {
+        let stream = FlakyStream {
+            data: data.clone(),
+            appends_allowed: appends_allowed.clone(),
+        };
+        CountingWriter::new(BufWriter::with_capacity(16 * 1024, stream)).unwrap()
+    }
+
+    fn read_timestamps(data: &[u8]) -> Vec<u64> {
+        let mut reader = Cursor::new(data);
+        let mut event_reader = EventReader::new();
+        let mut timestamps = Vec::new();
+        while (reader.position() as usize) < data.len() {
+            let required_buffer_length = event_reader.read_required_buffer_length(&mut reader).unwrap();
+            let mut buffer = vec![0; required_buffer_length];
+            let event = event_reader.read_event(&mut reader, &mut buffer[..]).unwrap();
+            timestamps.push(event.header.timestamp.nanoseconds().unwrap());
+        }
+        timestamps
+    }
+
+    /// Pravega becomes unavailable while the sink is writing, and again while the spool is being drained.
+    /// Every event must be written exactly once, and no index record may be written twice.
+    #[test]
+    fn test_drain_spool_events_after_failure() {
+        let num_events = 30;
+        let timestamps: Vec<u64> = (0..num_events).map(|i| 1000 + i * 100).collect();
+        let payloads: Vec<Vec<u8>> = (0..num_events).map(|i| vec![i as u8; 10_000]).collect();
+        let event = |i: usize| {
+            let random_access = i % 5 == 0;
+            EventWithHeader::new(&payloads[i][..], PravegaTimestamp::from_nanoseconds(Some(timestamps[i])),
+                random_access, random_access, i == 0)
+        };
+        for failed_drain_appends in 0..15 {
+            let spool_dir = std::env::temp_dir().join(
+                format!("pravegasink-drain-test-{}-{}", failed_drain_appends, std::process::id()));
+            let _ = std::fs::remove_dir_all(&spool_dir);
+            let data = Rc::new(RefCell::new(Vec::new()));
+            let mut index = Cursor::new(Vec::new());
+            let appends_allowed = Rc::new(Cell::new(usize::MAX));
+
+            // Write events 0 through 11. Event 10 reaches the data stream when event 11 is buffered.
+            let mut resume = SpoolResume::default();
+            let mut unflushed = Vec::new();
+            {
+                let mut writer = flaky_writer(&data, &appends_allowed);
+                for i in 0..12 {
+                    let (offset, _) = write_event(&mut writer, &mut index, &event(i), false, false).unwrap();
+                    let end_offset = writer.seek(SeekFrom::Current(0)).unwrap();
+                    if event(i).header.include_in_index {
+                        unflushed.clear();
+                        resume.committed_offset = Some(offset);
+                    }
+                    unflushed.push((end_offset, SpooledEvent::new(&event(i))));
+                }
+                // Pravega becomes unavailable, so event 11 is not written.
+                appends_allowed.set(0);
+            }
+            assert_eq!(read_timestamps(&data.borrow()), timestamps[..11].to_vec());
+
+            // Spool the unflushed events and the remaining events.
+            let mut spool = Spool::open(&spool_dir, 1024 * 1024 * 1024, None).unwrap();
+            for (end_offset, event) in unflushed.drain(..) {
+                spool.append(&event.as_event()).unwrap();
+                resume.uncommitted.push((end_offset, event));
+            }
+            for i in 12..num_events as usize {
+                spool.append(&event(i)).unwrap();
+            }
+
+            // Pravega becomes unavailable again while draining the spool.
+            appends_allowed.set(failed_drain_appends);
+            {
+                let tail_offset = data.borrow().len() as u64;
+                let last_index_record = IndexSearcher::new(Cursor::new(index.get_ref().clone())).get_last_record().ok();
+                let mut writer = flaky_writer(&data, &appends_allowed);
+                let result = drain_spool_events(&mut spool, &mut resume, &mut writer, &mut index,
+                    tail_offset, last_index_record, false, false);
+                assert!(result.is_err(), "failed_drain_appends={}", failed_drain_appends);
+            }
+
+            // Pravega is available.
+            appends_allowed.set(usize::MAX);
+            let tail_offset = data.borrow().len() as u64;
+            let last_index_record = IndexSearcher::new(Cursor::new(index.get_ref().clone())).get_last_record().ok();
+            let mut writer = flaky_writer(&data, &appends_allowed);
+            let offset = drain_spool_events(&mut spool, &mut resume, &mut writer, &mut index,
+                tail_offset, last_index_record, false, false).unwrap();
+            assert!(spool.is_empty());
+            drop(writer);
+            assert_eq!(offset, data.borrow().len() as u64);
+            let final_record = IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(1000 + num_events * 100)),
+                offset, false, false);
+            IndexRecordWriter::new().write(&final_record, &mut index).unwrap();
+
+            assert_eq!(read_timestamps(&data.borrow()), timestamps, "failed_drain_appends={}", failed_drain_appends);
+            let mut index_searcher = IndexSearcher::new(Cursor::new(index.get_ref().clone()));
+            let index_records = index_searcher.get_index_records().unwrap();
+            assert!(index_records.windows(2).all(|w| w[0].0.offset < w[1].0.offset),
+                "failed_drain_appends={}, index_records={:?}", failed_drain_appends, index_records);
+            let report = StreamVerifier::new(Cursor::new(data.borrow().clone()), index_searcher.into_inner()).verify().unwrap();
+            assert!(report.violations.is_empty(), "failed_drain_appends={}, violations={:?}", failed_drain_appends, report.violations);
+            assert_eq!(report.events_checked, num_events);
+            std::fs::remove_dir_all(&spool_dir).unwrap();
+        }
+    }
+
+    /// In transactional mode, an event that would overflow the buffer begins a new interval,
+    /// so that each interval between index records is written with a single append.
+    #[test]
+    fn test_transactional_interval_is_one_append() {
+        let payload = vec![0; 3 * 1024 * 1024];
+        let data = Rc::new(RefCell::new(Vec::new()));
+        let appends_allowed = Rc::new(Cell::new(usize::MAX));
+        let stream = FlakyStream {
+            data: data.clone(),
+            appends_allowed: appends_allowed.clone(),
+        };
+        let mut writer = CountingWriter::new(BufWriter::with_capacity(EventWithHeader::MAX_ATOMIC_WRITE_SIZE, stream)).unwrap();
+        let mut index = Cursor::new(Vec::new());
+        let mut index_offsets = Vec::new();
+        for i in 0..10 {
+            let mut event = EventWithHeader::new(&payload[..], PravegaTimestamp::from_nanoseconds(Some(1000 + i * 100)),
+                i == 0, i == 0, i == 0);
+            if must_index_to_fit(&writer, &event, false, true) {
+                event.header.include_in_index = true;
+            }
+            let (offset, _) = write_event(&mut writer, &mut index, &event, false, true).unwrap();
+            if event.header.include_in_index {
+                index_offsets.push(offset);
+            }
+        }
+        writer.flush().unwrap();
+        // Two events fit in each interval.
+        assert_eq!(index_offsets.len(), 5);
+        assert_eq!(usize::MAX - appends_allowed.get(), 5);
+        assert_eq!(read_timestamps(&data.borrow()).len(), 10);
+
+        // Events without timestamps cannot begin a new interval, so they cannot exceed the buffer.
+        let event = EventWithHeader::new(&payload[..], PravegaTimestamp::NONE, false, false, false);
+        assert!(!must_index_to_fit(&writer, &event, false, true));
+        write_event_data(&mut writer, &event, false, true).unwrap();
+        write_event_data(&mut writer, &event, false, true).unwrap();
+        let error = write_event_data(&mut writer, &event, false, true).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -16,27 +16,36 @@ use glib::subclass::prelude::*;
 use gst::ClockTime;
 use gst::prelude::*;
 use gst::subclass::prelude::*;
-use gst::{gst_debug, gst_error, gst_info, gst_log, gst_trace, gst_memdump};
+use gst::{gst_debug, gst_error, gst_info, gst_log, gst_trace, gst_memdump, gst_warning};
 use gst_base::prelude::*;
 use gst_base::subclass::prelude::*;
 
 use std::convert::{TryInto, TryFrom};
-use std::io::{BufReader, ErrorKind, Seek, SeekFrom};
-use std::sync::{Arc, Mutex};
+use std::io::{BufReader, ErrorKind, Read, Seek, SeekFrom};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 use std::u8;
 
 use once_cell::sync::Lazy;
 
 use pravega_client::client_factory::ClientFactory;
 use pravega_client::byte_stream::ByteStreamReader;
-use pravega_client_shared::{Scope, Stream, Segment, ScopedSegment, StreamConfiguration, ScopedStream, Scaling, ScaleType};
-use pravega_video::event_serde::EventReader;
-use pravega_video::index::{IndexSearcher, get_index_stream_name};
-use pravega_video::timestamp::PravegaTimestamp;
+use pravega_client_shared::{Scope, Stream, StreamConfiguration, ScopedSegment, ScopedStream};
+use pravega_video::catalog::{PartitionRecord, find_partition};
+use pravega_video::event_serde::{EventReader, ReadEventError};
+use pravega_video::index::{IndexSearcher, SearchFilter, SearchMethod, get_index_stream_name};
+use pravega_video::metadata::{StreamMetadata, StreamMetadataReader, get_metadata_stream_name};
+use pravega_video::index::IndexRecord;
+use pravega_video::timestamp::{PravegaTimestamp, TimeRange};
 use pravega_video::utils;
 use crate::counting_reader::CountingReader;
+use crate::meta_serializer;
+use crate::numeric::u64_checked_sub_i64;
 use crate::seekable_take::SeekableTake;
-use crate::utils::{clocktime_to_pravega, pravega_to_clocktime};
+use crate::stats::{StatsReporter, get_head_and_tail_offsets, DEFAULT_STATS_INTERVAL_SEC, PROPERTY_NAME_STATS,
+    PROPERTY_NAME_STATS_INTERVAL_SEC};
+use crate::utils::{clocktime_to_pravega, pravega_to_clocktime, create_retention, index_retention, PravegaRetentionType,
+    DEFAULT_ALLOW_CREATE_STREAM, DEFAULT_RETENTION_TYPE, DEFAULT_RETENTION_DAYS, DEFAULT_RETENTION_BYTES};
 
 const PROPERTY_NAME_STREAM: &str = "stream";
 const PROPERTY_NAME_CONTROLLER: &str = "controller";
@@ -49,6 +58,16 @@ const PROPERTY_NAME_START_UTC: &str = "start-utc";
 const PROPERTY_NAME_END_UTC: &str = "end-utc";
 const PROPERTY_NAME_ALLOW_CREATE_SCOPE: &str = "allow-create-scope";
 const PROPERTY_NAME_KEYCLOAK_FILE: &str = "keycloak-file";
+const PROPERTY_NAME_SEEK_RANDOM_ACCESS_ONLY: &str = "seek-random-access-only";
+const PROPERTY_NAME_ALLOW_CREATE_STREAM: &str = "allow-create-stream";
+const PROPERTY_NAME_RETENTION_TYPE: &str = "retention-type";
+const PROPERTY_NAME_RETENTION_DAYS: &str = "retention-days";
+const PROPERTY_NAME_RETENTION_BYTES: &str = "retention-bytes";
+const PROPERTY_NAME_CHECKSUM_FAILURE_POLICY: &str = "checksum-failure-policy";
+const PROPERTY_NAME_ATTACH_METAS: &str = "attach-metas";
+const PROPERTY_NAME_MAX_LAG_SEC: &str = "max-lag-sec";
+const PROPERTY_NAME_WAIT_FOR_INDEX_SEC: &str = "wait-for-index-sec";
+const PROPERTY_NAME_TIME_RANGES: &str = "time-ranges";
 
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::GEnum)]
 #[repr(u32)]
@@ -111,12 +130,44 @@ pub enum EndMode {
     Timestamp = 3,
 }
 
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::GEnum)]
+#[repr(u32)]
+#[genum(type_name = "GstChecksumFailurePolicy")]
+pub enum ChecksumFailurePolicy {
+    #[genum(
+        name = "Post an error message and stop.",
+        nick = "error"
+    )]
+    Error = 0,
+    #[genum(
+        name = "Skip the corrupt event and continue with the next event.",
+        nick = "skip"
+    )]
+    Skip = 1,
+    #[genum(
+        name = "Skip the corrupt event and set the DISCONT flag on the next buffer.",
+        nick = "discont"
+    )]
+    Discont = 2,
+}
+
 const DEFAULT_CONTROLLER: &str = "127.0.0.1:9090";
 const DEFAULT_BUFFER_SIZE: usize = 128*1024;
 const DEFAULT_START_MODE: StartMode = StartMode::Earliest;
 const DEFAULT_END_MODE: EndMode = EndMode::Unbounded;
 const DEFAULT_START_TIMESTAMP: u64 = 0;
 const DEFAULT_END_TIMESTAMP: u64 = u64::MAX;
+const DEFAULT_SEEK_RANDOM_ACCESS_ONLY: bool = false;
+const DEFAULT_CHECKSUM_FAILURE_POLICY: ChecksumFailurePolicy = ChecksumFailurePolicy::Error;
+const DEFAULT_ATTACH_METAS: bool = true;
+const DEFAULT_MAX_LAG_SEC: f64 = 0.0;
+const DEFAULT_WAIT_FOR_INDEX_SEC: f64 = 0.0;
+
+// How often the index is read while waiting for its first record.
+const WAIT_FOR_INDEX_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// How often the metadata stream is checked for new records while reading.
+const METADATA_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Debug)]
 struct Settings {
@@ -130,6 +181,17 @@ struct Settings {
     end_timestamp: u64,
     allow_create_scope: bool,
     keycloak_file: Option<String>,
+    allow_create_stream: bool,
+    retention_type: PravegaRetentionType,
+    retention_days: f64,
+    retention_bytes: u64,
+    seek_random_access_only: bool,
+    checksum_failure_policy: ChecksumFailurePolicy,
+    attach_metas: bool,
+    max_lag_nanos: u64,
+    wait_for_index_nanos: u64,
+    time_ranges: Vec<TimeRange>,
+    stats_interval_nanos: u64,
 }
 
 impl Default for Settings {
@@ -145,15 +207,43 @@ impl Default for Settings {
             end_timestamp: DEFAULT_END_TIMESTAMP,
             allow_create_scope: true,
             keycloak_file: None,
+            seek_random_access_only: DEFAULT_SEEK_RANDOM_ACCESS_ONLY,
+            checksum_failure_policy: DEFAULT_CHECKSUM_FAILURE_POLICY,
+            attach_metas: DEFAULT_ATTACH_METAS,
+            max_lag_nanos: (DEFAULT_MAX_LAG_SEC * 1e9) as u64,
+            wait_for_index_nanos: (DEFAULT_WAIT_FOR_INDEX_SEC * 1e9) as u64,
+            time_ranges: Vec::new(),
+            stats_interval_nanos: (DEFAULT_STATS_INTERVAL_SEC * 1e9) as u64,
+            allow_create_stream: DEFAULT_ALLOW_CREATE_STREAM,
+            retention_type: DEFAULT_RETENTION_TYPE,
+            retention_days: DEFAULT_RETENTION_DAYS,
+            retention_bytes: DEFAULT_RETENTION_BYTES,
         }
     }
 }
 
+type DataReader = CountingReader<BufReader<SeekableTake<ByteStreamReader>>>;
+
 enum State {
     Stopped,
     Started {
-        reader: Arc<Mutex<CountingReader<BufReader<SeekableTake<ByteStreamReader>>>>>,
+        reader: Arc<Mutex<DataReader>>,
         index_searcher: Arc<Mutex<IndexSearcher<ByteStreamReader>>>,
+        // The caps recorded in the metadata stream of the current data stream.
+        metadata: Arc<Mutex<MetadataCaps>>,
+        // The caps from the metadata record that applies to the most recent buffer.
+        caps: Option<gst::Caps>,
+        // Set if the stream is partitioned. The reader and index_searcher belong to the current partition.
+        partitions: Option<Arc<Mutex<Partitions>>>,
+        // Set while playing a trick-mode segment.
+        trick_mode: Option<TrickMode>,
+        // Set if the time-ranges property is set.
+        clip: Option<Clip>,
+        // Set if a seek is waiting for the first index record.
+        pending_seek: Option<PendingSeek>,
+        // The segment most recently pushed by new_segment(). Until the next seek, this replaces the segment
+        // of the base class, which is not updated, for the position query and for subsequent segments.
+        pushed_segment: Option<gst::FormattedSegment<gst::format::Time>>,
     },
 }
 
@@ -163,9 +253,275 @@ impl Default for State {
     }
 }
 
+/// The caps recorded in a metadata stream.
+/// A writer appends a record when the caps change, so while tailing, the metadata stream is polled for new records.
+struct MetadataCaps {
+    reader: Option<ByteStreamReader>,
+    // The data offset and caps of each record in the metadata stream that has caps.
+    caps: Vec<(u64, gst::Caps)>,
+    // The offset in the metadata stream following the last record read.
+    tail_offset: u64,
+    last_poll: Instant,
+}
+
+impl MetadataCaps {
+    fn empty() -> MetadataCaps {
+        MetadataCaps {
+            reader: None,
+            caps: Vec::new(),
+            tail_offset: 0,
+            last_poll: Instant::now(),
+        }
+    }
+
+    // Reads all records from the metadata stream.
+    fn open(element: &super::PravegaSrc, mut reader: ByteStreamReader) -> Result<MetadataCaps, std::io::Error> {
+        let records = StreamMetadataReader::new().read_all(&mut reader)?;
+        let tail_offset = reader.stream_position()?;
+        gst_info!(CAT, obj: element, "MetadataCaps::open: metadata={:?}", records);
+        let mut metadata = MetadataCaps {
+            reader: Some(reader),
+            caps: Vec::new(),
+            tail_offset,
+            last_poll: Instant::now(),
+        };
+        metadata.append(element, &records);
+        Ok(metadata)
+    }
+
+    fn append(&mut self, element: &super::PravegaSrc, records: &[StreamMetadata]) {
+        for record in records {
+            if let Some(caps) = record.caps.as_ref() {
+                match caps.parse::<gst::Caps>() {
+                    Ok(caps) => self.caps.push((record.data_offset, caps)),
+                    Err(_) => {
+                        gst_warning!(CAT, obj: element, "MetadataCaps: Ignoring invalid caps in metadata: {}", caps);
+                    },
+                }
+            }
+        }
+    }
+
+    // If the poll interval has elapsed, reads any records appended to the metadata stream since the last read.
+    fn poll(&mut self, element: &super::PravegaSrc) -> Result<(), std::io::Error> {
+        let reader = match self.reader {
+            Some(ref mut reader) => reader,
+            None => return Ok(()),
+        };
+        let now = Instant::now();
+        if now.duration_since(self.last_poll) < METADATA_POLL_INTERVAL {
+            return Ok(());
+        }
+        self.last_poll = now;
+        let tail_offset = reader.seek(SeekFrom::End(0))?;
+        if tail_offset <= self.tail_offset {
+            return Ok(());
+        }
+        reader.seek(SeekFrom::Start(self.tail_offset))?;
+        // Ensure EOF instead of waiting for appends at the tail.
+        let mut limited_reader = reader.take(tail_offset - self.tail_offset);
+        let mut metadata_reader = StreamMetadataReader::new();
+        let mut records = Vec::new();
+        let mut read_offset = self.tail_offset;
+        loop {
+            match metadata_reader.read(&mut limited_reader) {
+                Ok(record) => {
+                    records.push(record);
+                    read_offset = tail_offset - limited_reader.limit();
+                },
+                // A partial record at the tail will be read by a later poll.
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            }
+        }
+        self.tail_offset = read_offset;
+        if !records.is_empty() {
+            gst_info!(CAT, obj: element, "MetadataCaps::poll: new metadata={:?}", records);
+            self.append(element, &records);
+        }
+        Ok(())
+    }
+
+    // Returns the caps of the last record at or before the event at offset in the data stream.
+    fn find(&self, offset: u64) -> Option<&gst::Caps> {
+        self.caps.iter().rev()
+            .find(|(data_offset, _)| *data_offset <= offset)
+            .map(|(_, caps)| caps)
+    }
+}
+
+/// The position of a trick-mode seek, in which only key frames are read.
+/// Each key frame is read from the data offset of the next random-access index record in the direction of playback.
+/// A key frame consists of the random-access event and any following events that have the same timestamp
+/// or no timestamp, such as the remaining MPEG TS packets of the frame.
+#[derive(Clone, Copy, Debug)]
+struct TrickMode {
+    forward: bool,
+    // The offset in the index of the record of the current key frame.
+    index_offset: u64,
+    // True if the reader is positioned at the record at index_offset but its event has not been read yet.
+    pending: bool,
+    // The timestamp of the key frame whose events are being read, or NONE to read the next key frame.
+    key_frame_timestamp: PravegaTimestamp,
+    // Playback ends at the first record beyond this timestamp, in the direction of playback.
+    limit: PravegaTimestamp,
+}
+
+/// A seek to a timestamp that could not be completed because the index had no records.
+/// It is completed by create() when the first index record is written.
+/// Waiting in the streaming thread, instead of in do_seek, allows the wait to be interrupted by state changes,
+/// because the initial seek is performed by the thread that changes the state.
+#[derive(Clone, Copy, Debug)]
+struct PendingSeek {
+    timestamp: PravegaTimestamp,
+    random_access_only: bool,
+    // With time-ranges, the range of the first clip.
+    first_time_range: Option<TimeRange>,
+    // The wait fails after this time. If None, it waits indefinitely.
+    deadline: Option<Instant>,
+}
+
+/// The position in the list of time ranges of the time-ranges property.
+/// Each time range is played as a clip, and the clips are played back-to-back with continuous running time.
+#[derive(Clone, Copy, Debug)]
+struct Clip {
+    // The position of the time range in time_ranges.
+    index: usize,
+    // The running time at which this clip begins.
+    base: u64,
+    // The largest end (PTS plus duration) of a buffer output from this clip.
+    // If the duration was not persisted by pravegasink, the end is the PTS.
+    last_end: PravegaTimestamp,
+}
+
+/// The partitions of a stream written by pravegasink with rollover enabled.
+/// See [pravega_video::catalog].
+struct Partitions {
+    client_factory: ClientFactory,
+    scope: Scope,
+    stream_name: String,
+    records: Vec<PartitionRecord>,
+    // The position in records of the partition being read.
+    current: usize,
+    // The position in records of the partition that contains the end, and the end offset in its data stream.
+    // This is None if end-mode is unbounded.
+    end: Option<(usize, u64)>,
+}
+
+impl Partitions {
+    fn segments(&self, i: usize) -> (ScopedSegment, ScopedSegment, ScopedSegment) {
+        utils::get_partition_segments(&self.scope, &self.records[i])
+    }
+
+    fn index_searcher(&self, i: usize) -> IndexSearcher<ByteStreamReader> {
+        let (_, index_scoped_segment, _) = self.segments(i);
+        IndexSearcher::new(self.client_factory.create_byte_stream_reader(index_scoped_segment))
+    }
+
+    /// Returns the offset in the data stream of partition i at which reading must stop.
+    /// Partitions before the last one have been sealed, so these are read up to their tail.
+    fn end_offset(&self, i: usize, reader: &mut ByteStreamReader) -> Result<u64, std::io::Error> {
+        match self.end {
+            Some((end, end_offset)) if end == i => Ok(end_offset),
+            _ if i + 1 < self.records.len() => reader.seek(SeekFrom::End(0)),
+            _ => Ok(u64::MAX),
+        }
+    }
+
+    /// Returns true if the partition after the current one may be read.
+    /// The catalog is read again if the current partition is the last known one.
+    fn has_next(&mut self) -> Result<bool, gst::ErrorMessage> {
+        if let Some((end, _)) = self.end {
+            if self.current >= end {
+                return Ok(false);
+            }
+        }
+        if self.current + 1 >= self.records.len() {
+            let records = utils::read_catalog(&self.client_factory, &self.scope, &self.stream_name).map_err(|error| {
+                gst::error_msg!(gst::ResourceError::Read, ["Failed to read Pravega catalog stream: {}", error])
+            })?;
+            if records.len() > self.records.len() {
+                self.records = records;
+            }
+        }
+        Ok(self.current + 1 < self.records.len())
+    }
+}
+
+// Returns the byte offset in the data stream at which the reader must stop, based on end-mode.
+fn get_end_offset(element: &super::PravegaSrc, end_mode: EndMode, end_timestamp: u64, reader: &mut ByteStreamReader,
+        index_searcher: &mut IndexSearcher<ByteStreamReader>) -> u64 {
+    match end_mode {
+        EndMode::Unbounded => u64::MAX,
+        EndMode::Latest => {
+            // When ending at Latest, we will emit up through the very last byte currently in the data stream.
+            reader.seek(SeekFrom::End(0)).unwrap()
+        },
+        EndMode::LatestIndexed => {
+            // Determine Pravega stream offset for this timestamp by searching the index.
+            let index_record = index_searcher.get_last_record().unwrap();
+            gst_info!(CAT, obj: element, "start: end index_record={:?}", index_record);
+            index_record.offset
+        },
+        EndMode::Timestamp => {
+            let end_timestamp = PravegaTimestamp::from_nanoseconds(Some(end_timestamp));
+            // Determine Pravega stream offset for this timestamp by searching the index.
+            let index_record = index_searcher.search_timestamp_after(end_timestamp).unwrap();
+            gst_info!(CAT, obj: element, "start: end index_record={:?}", index_record);
+            index_record.offset
+        },
+    }
+}
+
+// Statistics that are returned by the stats property and posted in stats messages.
+#[derive(Default)]
+struct Stats {
+    bytes_read: u64,
+    events_read: u64,
+    // Events that were skipped because they did not match their checksum.
+    checksum_failures: u64,
+    // The number of times that the reader jumped forward because it fell behind by more than max-lag-sec.
+    lag_skips: u64,
+    // The number of times that the reader jumped forward because its position was truncated.
+    truncation_skips: u64,
+    // The offset in the data stream after the most recent event.
+    offset: Option<u64>,
+    // The data segment being read, used to get the head and tail offsets.
+    data_segment: Option<ScopedSegment>,
+    head_offset: Option<u64>,
+    tail_offset: Option<u64>,
+}
+
+impl Stats {
+    fn to_structure(&self) -> gst::Structure {
+        let mut structure = gst::Structure::builder("application/x-pravegasrc-stats")
+            .field("bytes-read", &self.bytes_read)
+            .field("events-read", &self.events_read)
+            .field("checksum-failures", &self.checksum_failures)
+            .field("lag-skips", &self.lag_skips)
+            .field("truncation-skips", &self.truncation_skips)
+            .build();
+        if let Some(offset) = self.offset {
+            structure.set("offset", &offset);
+        }
+        if let Some(head_offset) = self.head_offset {
+            structure.set("head-offset", &head_offset);
+        }
+        if let Some(tail_offset) = self.tail_offset {
+            structure.set("tail-offset", &tail_offset);
+        }
+        structure
+    }
+}
+
 pub struct PravegaSrc {
     settings: Mutex<Settings>,
     state: Mutex<State>,
+    stats: Mutex<Stats>,
+    stats_reporter: Mutex<Option<StatsReporter>>,
+    // Set by unlock() to interrupt a wait for the first index record.
+    flushing: Mutex<bool>,
+    flushing_cond: Condvar,
 }
 
 static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
@@ -215,6 +571,559 @@ impl PravegaSrc {
         settings.controller = controller;
         Ok(())
     }
+
+    // Opens readers for a data stream and its index, and reads the caps from its metadata stream.
+    fn open_readers(
+        &self,
+        element: &super::PravegaSrc,
+        client_factory: &ClientFactory,
+        scoped_segment: ScopedSegment,
+        index_scoped_segment: ScopedSegment,
+        metadata_scoped_segment: Option<ScopedSegment>,
+    ) -> Result<(ByteStreamReader, IndexSearcher<ByteStreamReader>, MetadataCaps), gst::ErrorMessage> {
+        gst_info!(CAT, obj: element, "open_readers: Using data segment {:?}", scoped_segment);
+        let reader = client_factory.create_byte_stream_reader(scoped_segment);
+        gst_info!(CAT, obj: element, "open_readers: Opened Pravega reader for data");
+
+        gst_info!(CAT, obj: element, "open_readers: Using index segment {:?}", index_scoped_segment);
+        let index_reader = client_factory.create_byte_stream_reader(index_scoped_segment);
+        gst_info!(CAT, obj: element, "open_readers: Opened Pravega reader for index");
+        let index_searcher = IndexSearcher::new(index_reader);
+
+        let metadata = match metadata_scoped_segment {
+            Some(metadata_scoped_segment) => {
+                let metadata_reader = client_factory.create_byte_stream_reader(metadata_scoped_segment);
+                MetadataCaps::open(element, metadata_reader).map_err(|error| {
+                    gst::error_msg!(gst::ResourceError::OpenRead, ["Failed to read Pravega metadata stream: {}", error])
+                })?
+            },
+            None => MetadataCaps::empty(),
+        };
+        Ok((reader, index_searcher, metadata))
+    }
+
+    // Called when reading the data stream at offset fails.
+    // If offset has been truncated from the head of the data stream, seeks the reader to the first random-access point
+    // after the new head and posts a warning message.
+    // Returns true if the reader was moved.
+    fn skip_truncated(&self, element: &super::PravegaSrc, reader: &mut DataReader, offset: u64) -> Result<bool, gst::FlowError> {
+        let head_offset = match reader.get_ref().get_ref().get_ref().current_head() {
+            Ok(head_offset) => head_offset,
+            Err(err) => {
+                gst_debug!(CAT, obj: element, "skip_truncated: unable to get head of data stream: {}", err);
+                return Ok(false);
+            },
+        };
+        if offset >= head_offset {
+            return Ok(false);
+        }
+        let index_searcher = match *self.state.lock().unwrap() {
+            State::Started { ref index_searcher, .. } => index_searcher.clone(),
+            State::Stopped => return Ok(false),
+        };
+        let index_record = index_searcher.lock().unwrap().search_random_access_offset(head_offset).map_err(|err| {
+            gst::element_error!(element, gst::ResourceError::Read,
+                ["Data stream was truncated at offset {} and no later random-access point was found: {}", head_offset, err]);
+            gst::FlowError::Error
+        })?.0;
+        gst::element_warning!(element, gst::ResourceError::Read,
+            ["Data stream was truncated while reading; skipping from offset {} to offset {} at timestamp {}",
+            offset, index_record.offset, index_record.timestamp]);
+        reader.seek(SeekFrom::Start(index_record.offset)).map_err(|err| {
+            gst::element_error!(element, gst::ResourceError::Seek, ["Failed to seek Pravega data stream: {}", err]);
+            gst::FlowError::Error
+        })?;
+        self.stats.lock().unwrap().truncation_skips += 1;
+        Ok(true)
+    }
+
+    // Waits until the next poll of the index, the deadline, or unlock() is called.
+    // Returns false if the deadline has passed, or FlowError::Flushing if the element is flushing.
+    fn wait_for_index(&self, deadline: Option<Instant>) -> Result<bool, gst::FlowError> {
+        let timeout = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Ok(false);
+                }
+                WAIT_FOR_INDEX_POLL_INTERVAL.min(deadline - now)
+            },
+            None => WAIT_FOR_INDEX_POLL_INTERVAL,
+        };
+        let flushing = self.flushing.lock().unwrap();
+        if *flushing {
+            return Err(gst::FlowError::Flushing);
+        }
+        let (flushing, _) = self.flushing_cond.wait_timeout(flushing, timeout).unwrap();
+        if *flushing {
+            return Err(gst::FlowError::Flushing);
+        }
+        Ok(true)
+    }
+
+    fn pending_seek(&self) -> Option<PendingSeek> {
+        match *self.state.lock().unwrap() {
+            State::Started { pending_seek, .. } => pending_seek,
+            State::Stopped => None,
+        }
+    }
+
+    fn set_pending_seek(&self, new_pending_seek: Option<PendingSeek>) {
+        if let State::Started { ref mut pending_seek, .. } = *self.state.lock().unwrap() {
+            *pending_seek = new_pending_seek;
+        }
+    }
+
+    // Waits for the first index record, then positions the reader and updates the segment as do_seek would have.
+    // This is called by create() so that the wait can be interrupted by unlock().
+    fn complete_pending_seek(&self, element: &super::PravegaSrc, reader: &mut DataReader, pending_seek: PendingSeek)
+            -> Result<(), gst::FlowError> {
+        let index_searcher = match *self.state.lock().unwrap() {
+            State::Started { ref index_searcher, .. } => index_searcher.clone(),
+            State::Stopped => return Err(gst::FlowError::Flushing),
+        };
+        gst_info!(CAT, obj: element, "complete_pending_seek: waiting for the first index record; pending_seek={:?}", pending_seek);
+        let index_record = loop {
+            // The index searcher is not locked while waiting so that queries are not blocked.
+            let result = {
+                let mut index_searcher = index_searcher.lock().unwrap();
+                if pending_seek.random_access_only {
+                    index_searcher.search_random_access_timestamp(pending_seek.timestamp)
+                } else {
+                    index_searcher.search_timestamp(pending_seek.timestamp)
+                }
+            };
+            match result {
+                Ok(index_record) => break index_record,
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+                    if !self.wait_for_index(pending_seek.deadline)? {
+                        gst::element_error!(element, gst::ResourceError::NotFound,
+                            ["Timed out waiting for the first record of the Pravega index stream"]);
+                        return Err(gst::FlowError::Error);
+                    }
+                },
+                Err(err) => {
+                    gst::element_error!(element, gst::ResourceError::Read, ["Failed to search Pravega index stream: {}", err]);
+                    return Err(gst::FlowError::Error);
+                },
+            }
+        };
+        gst_info!(CAT, obj: element, "complete_pending_seek: index_record={:?}", index_record);
+        reader.seek(SeekFrom::Start(index_record.offset)).map_err(|err| {
+            gst::element_error!(element, gst::ResourceError::Seek, ["Failed to seek Pravega data stream: {}", err]);
+            gst::FlowError::Error
+        })?;
+        self.set_pending_seek(None);
+        let start_timestamp = match pending_seek.first_time_range {
+            Some(first_time_range) => first_time_range.begin,
+            None => index_record.timestamp,
+        };
+        let mut segment = self.segment(element);
+        segment.set_start(ClockTime(start_timestamp.nanoseconds()));
+        segment.set_time(ClockTime(start_timestamp.nanoseconds()));
+        segment.set_position(0);
+        self.new_segment(element, &segment);
+        if let (Some(clip), Some(first_time_range)) = (self.clip(), pending_seek.first_time_range) {
+            self.post_clip_message(element, "application/x-pravegasrc-clip-start", &clip, &first_time_range);
+        }
+        Ok(())
+    }
+
+    // Returns the segment most recently pushed by new_segment(), or the segment of the base class if there has been
+    // none since the last seek.
+    fn segment(&self, element: &super::PravegaSrc) -> gst::FormattedSegment<gst::format::Time> {
+        self.pushed_segment().unwrap_or_else(|| element.segment().downcast::<gst::format::Time>().unwrap())
+    }
+
+    fn pushed_segment(&self) -> Option<gst::FormattedSegment<gst::format::Time>> {
+        match *self.state.lock().unwrap() {
+            State::Started { ref pushed_segment, .. } => pushed_segment.clone(),
+            State::Stopped => None,
+        }
+    }
+
+    // Pushes a segment downstream and keeps it in the state until the next seek.
+    // gst_base_src_new_segment() would replace the segment of the base class, but it requires GStreamer 1.18,
+    // so the base class segment is left unchanged and the position query is answered from the pushed segment.
+    fn new_segment(&self, element: &super::PravegaSrc, segment: &gst::FormattedSegment<gst::format::Time>) {
+        gst_info!(CAT, obj: element, "new_segment: segment={:?}", segment);
+        if let State::Started { ref mut pushed_segment, .. } = *self.state.lock().unwrap() {
+            *pushed_segment = Some(segment.clone());
+        }
+        let src_pad = element.static_pad("src").unwrap();
+        if !src_pad.push_event(gst::event::Segment::new(segment)) {
+            gst_warning!(CAT, obj: element, "new_segment: segment event was not handled");
+        }
+    }
+
+    // If the last index record is more than max_lag_nanos ahead of timestamp, seeks the reader to the last random-access point
+    // and posts an element message with the skipped duration.
+    // Returns true if the reader was moved.
+    // Only the index of the current partition is checked. A later partition will be reached through next_partition.
+    fn skip_lag(&self, element: &super::PravegaSrc, reader: &mut DataReader, timestamp: PravegaTimestamp, max_lag_nanos: u64)
+            -> Result<bool, gst::FlowError> {
+        let index_searcher = match *self.state.lock().unwrap() {
+            State::Started { ref index_searcher, .. } => index_searcher.clone(),
+            State::Stopped => return Ok(false),
+        };
+        let mut index_searcher = index_searcher.lock().unwrap();
+        let last_record = match index_searcher.get_last_record() {
+            Ok(last_record) => last_record,
+            Err(err) => {
+                gst_debug!(CAT, obj: element, "skip_lag: unable to read last index record: {}", err);
+                return Ok(false);
+            },
+        };
+        let lag = match (last_record.timestamp.nanoseconds(), timestamp.nanoseconds()) {
+            (Some(last), Some(current)) => last.saturating_sub(current),
+            _ => return Ok(false),
+        };
+        gst_trace!(CAT, obj: element, "skip_lag: lag={} ns", lag);
+        if lag <= max_lag_nanos {
+            return Ok(false);
+        }
+        let index_record = index_searcher.search_timestamp_and_return_index_offset_with_filter(
+            PravegaTimestamp::MAX, SearchMethod::Before, SearchFilter::RandomAccessOnly).map_err(|err| {
+                gst::element_error!(element, gst::ResourceError::Read, ["Failed to read Pravega index stream: {}", err]);
+                gst::FlowError::Error
+            })?.0;
+        if index_record.timestamp <= timestamp {
+            return Ok(false);
+        }
+        gst_info!(CAT, obj: element, "skip_lag: lag of {} ns exceeds maximum; jumping from timestamp {} to index_record={:?}",
+            lag, timestamp, index_record);
+        reader.seek(SeekFrom::Start(index_record.offset)).map_err(|err| {
+            gst::element_error!(element, gst::ResourceError::Seek, ["Failed to seek Pravega data stream: {}", err]);
+            gst::FlowError::Error
+        })?;
+        self.stats.lock().unwrap().lag_skips += 1;
+        let skipped_duration = index_record.timestamp.nanoseconds().unwrap() - timestamp.nanoseconds().unwrap();
+        let structure = gst::Structure::builder("application/x-pravegasrc-lag-skip")
+            .field("from-timestamp", &timestamp.nanoseconds().unwrap())
+            .field("to-timestamp", &index_record.timestamp.nanoseconds().unwrap())
+            .field("skipped-duration", &skipped_duration)
+            .build();
+        let _ = element.post_message(gst::message::Element::builder(structure).src(element).build());
+        Ok(true)
+    }
+
+    fn clip(&self) -> Option<Clip> {
+        match *self.state.lock().unwrap() {
+            State::Started { clip, .. } => clip,
+            State::Stopped => None,
+        }
+    }
+
+    fn set_clip(&self, new_clip: Option<Clip>) {
+        if let State::Started { ref mut clip, .. } = *self.state.lock().unwrap() {
+            *clip = new_clip;
+        }
+    }
+
+    fn post_clip_message(&self, element: &super::PravegaSrc, name: &str, clip: &Clip, time_range: &TimeRange) {
+        let structure = gst::Structure::builder(name)
+            .field("index", &(clip.index as u64))
+            .field("begin", &time_range.begin.nanoseconds().unwrap_or_default())
+            .field("end", &time_range.end.nanoseconds().unwrap_or_default())
+            .field("running-time", &clip.base)
+            .build();
+        gst_info!(CAT, obj: element, "post_clip_message: {}", structure);
+        let _ = element.post_message(gst::message::Element::builder(structure).src(element).build());
+    }
+
+    // Positions the reader at the last random-access point at or before timestamp, switching partitions if needed.
+    fn seek_random_access(&self, element: &super::PravegaSrc, reader: &mut DataReader, timestamp: PravegaTimestamp)
+            -> Result<IndexRecord, gst::FlowError> {
+        let (index_searcher, partitions) = match *self.state.lock().unwrap() {
+            State::Started { ref index_searcher, ref partitions, .. } => (index_searcher.clone(), partitions.clone()),
+            State::Stopped => return Err(gst::FlowError::Flushing),
+        };
+        let mut index_searcher = index_searcher.lock().unwrap();
+        if let Some(partitions) = partitions {
+            let mut partitions = partitions.lock().unwrap();
+            let mut i = find_partition(&partitions.records, timestamp);
+            if let Some((end, _)) = partitions.end {
+                i = i.min(end);
+            }
+            if i != partitions.current {
+                self.switch_partition(element, &mut partitions, i, reader, &mut index_searcher).map_err(|err| {
+                    element.post_error_message(err);
+                    gst::FlowError::Error
+                })?;
+            }
+        }
+        let index_record = index_searcher.search_random_access_timestamp(timestamp).map_err(|err| {
+            gst::element_error!(element, gst::ResourceError::Read, ["Failed to search Pravega index stream: {}", err]);
+            gst::FlowError::Error
+        })?;
+        reader.seek(SeekFrom::Start(index_record.offset)).map_err(|err| {
+            gst::element_error!(element, gst::ResourceError::Seek, ["Failed to seek Pravega data stream: {}", err]);
+            gst::FlowError::Error
+        })?;
+        Ok(index_record)
+    }
+
+    // Ends the current clip and starts the next one, if any, by seeking the reader and replacing the segment.
+    // reached_end is true if the current clip ended at an event at or after the end of its time range,
+    // and false if the data ended first.
+    // Returns false if there are no more clips.
+    fn next_clip(&self, element: &super::PravegaSrc, reader: &mut DataReader, clip: Clip, reached_end: bool)
+            -> Result<bool, gst::FlowError> {
+        let (time_range, next_time_range) = {
+            let settings = self.settings.lock().unwrap();
+            (settings.time_ranges[clip.index], settings.time_ranges.get(clip.index + 1).copied())
+        };
+        self.post_clip_message(element, "application/x-pravegasrc-clip-end", &clip, &time_range);
+        let next_time_range = match next_time_range {
+            Some(next_time_range) => next_time_range,
+            None => return Ok(false),
+        };
+        // The running time of the next clip begins where this clip stops, which is the end of its time range
+        // unless the data ended first. It never begins before the end of the last buffer, so that clips do not overlap.
+        let stop = if reached_end && time_range.end > clip.last_end {
+            time_range.end
+        } else {
+            clip.last_end
+        };
+        let duration_nanos = match (stop.nanoseconds(), time_range.begin.nanoseconds()) {
+            (Some(stop), Some(begin)) => stop.saturating_sub(begin),
+            _ => 0,
+        };
+        let next_clip = Clip {
+            index: clip.index + 1,
+            base: clip.base + duration_nanos,
+            last_end: PravegaTimestamp::NONE,
+        };
+        let index_record = self.seek_random_access(element, reader, next_time_range.begin)?;
+        gst_info!(CAT, obj: element, "next_clip: clip={:?}, time_range={:?}, index_record={:?}",
+            next_clip, next_time_range, index_record);
+        // Buffers before the beginning of the range are needed to decode but will be clipped by the segment.
+        let mut segment = self.segment(element);
+        segment.set_start(ClockTime(next_time_range.begin.nanoseconds()));
+        segment.set_time(ClockTime(next_time_range.begin.nanoseconds()));
+        segment.set_position(ClockTime(next_time_range.begin.nanoseconds()));
+        segment.set_base(ClockTime(Some(next_clip.base)));
+        self.new_segment(element, &segment);
+        self.set_clip(Some(next_clip));
+        self.post_clip_message(element, "application/x-pravegasrc-clip-start", &next_clip, &next_time_range);
+        Ok(true)
+    }
+
+    fn trick_mode(&self) -> Option<TrickMode> {
+        match *self.state.lock().unwrap() {
+            State::Started { trick_mode, .. } => trick_mode,
+            State::Stopped => None,
+        }
+    }
+
+    fn set_trick_mode(&self, new_trick_mode: Option<TrickMode>) {
+        if let State::Started { ref mut trick_mode, .. } = *self.state.lock().unwrap() {
+            *trick_mode = new_trick_mode;
+        }
+    }
+
+    // In trick mode, ends the current key frame so that the next event will be read from the next key frame.
+    fn end_key_frame(&self) {
+        if let Some(mut trick_mode) = self.trick_mode() {
+            trick_mode.pending = false;
+            trick_mode.key_frame_timestamp = PravegaTimestamp::NONE;
+            self.set_trick_mode(Some(trick_mode));
+        }
+    }
+
+    // In trick mode, positions the reader at the next key frame in the direction of playback,
+    // continuing in the adjacent partition if needed.
+    // Returns false if there are no more key frames to read.
+    fn seek_next_key_frame(&self, element: &super::PravegaSrc, reader: &mut DataReader, mut trick_mode: TrickMode)
+            -> Result<bool, gst::FlowError> {
+        let (index_searcher, partitions) = match *self.state.lock().unwrap() {
+            State::Started { ref index_searcher, ref partitions, .. } => (index_searcher.clone(), partitions.clone()),
+            State::Stopped => return Ok(false),
+        };
+        let mut index_searcher = index_searcher.lock().unwrap();
+        let (index_record, index_offset) = match index_searcher.next_random_access_record(trick_mode.index_offset, trick_mode.forward) {
+            Ok(next) => next,
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+                let partitions = match partitions {
+                    Some(partitions) => partitions,
+                    None => return Ok(false),
+                };
+                let mut partitions = partitions.lock().unwrap();
+                let i = if trick_mode.forward {
+                    let has_next = partitions.has_next().map_err(|err| {
+                        element.post_error_message(err);
+                        gst::FlowError::Error
+                    })?;
+                    if !has_next {
+                        return Ok(false);
+                    }
+                    partitions.current + 1
+                } else {
+                    if partitions.current == 0 {
+                        return Ok(false);
+                    }
+                    partitions.current - 1
+                };
+                self.switch_partition(element, &mut partitions, i, reader, &mut index_searcher).map_err(|err| {
+                    element.post_error_message(err);
+                    gst::FlowError::Error
+                })?;
+                // Continue at the first or last key frame of the partition.
+                let timestamp = if trick_mode.forward { PravegaTimestamp::MIN } else { PravegaTimestamp::MAX };
+                match index_searcher.search_timestamp_and_return_index_offset_with_filter(
+                        timestamp, SearchMethod::Before, SearchFilter::RandomAccessOnly) {
+                    Ok(next) => next,
+                    Err(_) => return Ok(false),
+                }
+            },
+            Err(err) => {
+                gst::element_error!(element, gst::ResourceError::Read, ["Failed to read Pravega index stream: {}", err]);
+                return Err(gst::FlowError::Error);
+            },
+        };
+        gst_debug!(CAT, obj: element, "seek_next_key_frame: index_record={:?}", index_record);
+        let beyond_limit = if trick_mode.forward {
+            index_record.timestamp > trick_mode.limit
+        } else {
+            index_record.timestamp < trick_mode.limit
+        };
+        if beyond_limit {
+            return Ok(false);
+        }
+        reader.seek(SeekFrom::Start(index_record.offset)).map_err(|err| {
+            gst::element_error!(element, gst::ResourceError::Seek, ["Failed to seek Pravega data stream: {}", err]);
+            gst::FlowError::Error
+        })?;
+        trick_mode.index_offset = index_offset;
+        trick_mode.pending = true;
+        self.set_trick_mode(Some(trick_mode));
+        Ok(true)
+    }
+
+    // Replaces the readers with readers of partition i, positioned at the head of its data stream.
+    fn switch_partition(
+        &self,
+        element: &super::PravegaSrc,
+        partitions: &mut Partitions,
+        i: usize,
+        reader: &mut DataReader,
+        index_searcher: &mut IndexSearcher<ByteStreamReader>,
+    ) -> Result<(), gst::ErrorMessage> {
+        gst_info!(CAT, obj: element, "switch_partition: partition={:?}", partitions.records[i]);
+        let (scoped_segment, index_scoped_segment, metadata_scoped_segment) = partitions.segments(i);
+        {
+            let mut stats = self.stats.lock().unwrap();
+            stats.data_segment = Some(scoped_segment.clone());
+            stats.head_offset = None;
+            stats.tail_offset = None;
+        }
+        let (mut new_reader, new_index_searcher, new_metadata) = self.open_readers(element,
+            &partitions.client_factory, scoped_segment, index_scoped_segment, Some(metadata_scoped_segment))?;
+        let end_offset = partitions.end_offset(i, &mut new_reader).map_err(|error| {
+            gst::error_msg!(gst::ResourceError::OpenRead, ["Failed to read Pravega data stream: {}", error])
+        })?;
+        gst_info!(CAT, obj: element, "switch_partition: end_offset={}", end_offset);
+        let buffer_size = self.settings.lock().unwrap().buffer_size;
+        let limited_reader = SeekableTake::new(new_reader, end_offset).unwrap();
+        let buf_reader = BufReader::with_capacity(buffer_size, limited_reader);
+        *reader = CountingReader::new(buf_reader).unwrap();
+        *index_searcher = new_index_searcher;
+        partitions.current = i;
+        if let State::Started { ref mut metadata, .. } = *self.state.lock().unwrap() {
+            *metadata = Arc::new(Mutex::new(new_metadata));
+        }
+        let head_offset = reader.get_ref().get_ref().get_ref().current_head().map_err(|error| {
+            gst::error_msg!(gst::ResourceError::OpenRead, ["Failed to get head of Pravega data stream: {}", error])
+        })?;
+        reader.seek(SeekFrom::Start(head_offset)).map_err(|error| {
+            gst::error_msg!(gst::ResourceError::Seek, ["Failed to seek Pravega data stream: {}", error])
+        })?;
+        Ok(())
+    }
+
+    // Updates the head and tail offsets of the data stream and posts the stats as an element message.
+    // This is called periodically by the stats thread.
+    fn post_stats(&self, element: &super::PravegaSrc, client_factory: &ClientFactory) {
+        let data_segment = self.stats.lock().unwrap().data_segment.clone();
+        let offsets = data_segment.map(|data_segment| get_head_and_tail_offsets(client_factory, &data_segment));
+        let structure = {
+            let mut stats = self.stats.lock().unwrap();
+            match offsets {
+                Some(Ok((head_offset, tail_offset))) => {
+                    stats.head_offset = Some(head_offset);
+                    stats.tail_offset = Some(tail_offset);
+                },
+                Some(Err(error)) => {
+                    gst_debug!(CAT, obj: element, "post_stats: Unable to get offsets of data stream: {}", error);
+                },
+                None => {},
+            }
+            stats.to_structure()
+        };
+        gst_trace!(CAT, obj: element, "post_stats: {}", structure);
+        let _ = element.post_message(gst::message::Element::builder(structure).src(element).build());
+    }
+
+    // Called when the reader reaches EOF. If there is a partition after the current one, switch to it and return true.
+    fn next_partition(&self, element: &super::PravegaSrc, reader: &mut DataReader) -> Result<bool, gst::FlowError> {
+        let (index_searcher, partitions) = match *self.state.lock().unwrap() {
+            State::Started { ref index_searcher, partitions: Some(ref partitions), .. } => (index_searcher.clone(), partitions.clone()),
+            _ => return Ok(false),
+        };
+        let mut index_searcher = index_searcher.lock().unwrap();
+        let mut partitions = partitions.lock().unwrap();
+        let has_next = partitions.has_next().map_err(|err| {
+            element.post_error_message(err);
+            gst::FlowError::Error
+        })?;
+        if !has_next {
+            return Ok(false);
+        }
+        let next = partitions.current + 1;
+        self.switch_partition(element, &mut partitions, next, reader, &mut index_searcher).map_err(|err| {
+            element.post_error_message(err);
+            gst::FlowError::Error
+        })?;
+        Ok(true)
+    }
+
+    // If the caps recorded in the metadata stream for the event at offset differ from the current caps,
+    // set the new caps on the source pad.
+    // The metadata stream is polled for new records so that caps changes are picked up while tailing.
+    fn update_caps(&self, element: &super::PravegaSrc, offset: u64) {
+        let metadata = match *self.state.lock().unwrap() {
+            State::Started { ref metadata, .. } => metadata.clone(),
+            State::Stopped => return,
+        };
+        let record_caps = {
+            let mut metadata = metadata.lock().unwrap();
+            if let Err(err) = metadata.poll(element) {
+                gst_warning!(CAT, obj: element, "update_caps: Unable to read Pravega metadata stream: {}", err);
+            }
+            metadata.find(offset).cloned()
+        };
+        let new_caps = {
+            let mut state = self.state.lock().unwrap();
+            match *state {
+                State::Started { ref mut caps, .. } => {
+                    match record_caps.as_ref() {
+                        Some(record_caps) if caps.as_ref() != Some(record_caps) => {
+                            *caps = Some(record_caps.clone());
+                            Some(record_caps.clone())
+                        },
+                        _ => None,
+                    }
+                },
+                State::Stopped => None,
+            }
+        };
+        if let Some(caps) = new_caps {
+            gst_info!(CAT, obj: element, "update_caps: Setting caps from metadata at offset {}: {:?}", offset, caps);
+            if let Err(err) = element.set_caps(&caps) {
+                gst_warning!(CAT, obj: element, "update_caps: Unable to set caps: {}", err);
+            }
+        }
+    }
 }
 
 #[glib::object_subclass]
@@ -228,6 +1137,10 @@ impl ObjectSubclass for PravegaSrc {
         Self {
             settings: Mutex::new(Default::default()),
             state: Mutex::new(Default::default()),
+            stats: Mutex::new(Default::default()),
+            stats_reporter: Mutex::new(None),
+            flushing: Mutex::new(false),
+            flushing_cond: Condvar::new(),
         }
     }
 }
@@ -329,6 +1242,118 @@ impl ObjectImpl for PravegaSrc {
                 None,
                 glib::ParamFlags::WRITABLE,
             ),
+            glib::ParamSpec::new_boolean(
+                PROPERTY_NAME_SEEK_RANDOM_ACCESS_ONLY,
+                "Seek random access only",
+                "If true, seeks will only locate index records that are random-access points (key frames). \
+                This avoids starting at index records that were forced at delta units.",
+                DEFAULT_SEEK_RANDOM_ACCESS_ONLY,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_boolean(
+                PROPERTY_NAME_ALLOW_CREATE_STREAM,
+                "Allow create stream",
+                "If true, the data stream and its index will be created if they do not exist. \
+                If false, they must already exist.",
+                DEFAULT_ALLOW_CREATE_STREAM,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_enum(
+                PROPERTY_NAME_RETENTION_TYPE,
+                "Retention type",
+                "The retention policy of the data stream if it is created by this element",
+                PravegaRetentionType::static_type(),
+                DEFAULT_RETENTION_TYPE as i32,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_double(
+                PROPERTY_NAME_RETENTION_DAYS,
+                "Retention days",
+                "The number of days to retain data if retention-type is days",
+                0.0,
+                std::f64::INFINITY,
+                DEFAULT_RETENTION_DAYS,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_uint64(
+                PROPERTY_NAME_RETENTION_BYTES,
+                "Retention bytes",
+                "The number of bytes to retain if retention-type is bytes",
+                0,
+                std::u64::MAX,
+                DEFAULT_RETENTION_BYTES,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_enum(
+                PROPERTY_NAME_CHECKSUM_FAILURE_POLICY,
+                "Checksum failure policy",
+                "The action to take when an event does not match its checksum",
+                ChecksumFailurePolicy::static_type(),
+                DEFAULT_CHECKSUM_FAILURE_POLICY as i32,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_boolean(
+                PROPERTY_NAME_ATTACH_METAS,
+                "Attach metas",
+                "If true, metas that were persisted by pravegasink (see persist-meta-apis) will be attached to buffers. \
+                Metas without a registered serializer are ignored.",
+                DEFAULT_ATTACH_METAS,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_double(
+                PROPERTY_NAME_MAX_LAG_SEC,
+                "Maximum lag in seconds",
+                "If the timestamp of the last index record is ahead of the timestamp being read by more than this, \
+                the reader will jump forward to the last random-access point and the next buffer will have the DISCONT flag. \
+                An element message named application/x-pravegasrc-lag-skip will report the skipped duration. \
+                This is checked at each random-access event. Requires end-mode=unbounded. 0 disables this.",
+                0.0,
+                std::f64::MAX,
+                DEFAULT_MAX_LAG_SEC,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_double(
+                PROPERTY_NAME_WAIT_FOR_INDEX_SEC,
+                "Wait for index in seconds",
+                "If the index has no records when seeking, wait up to this long for pravegasink to write the first one. \
+                This allows a pipeline to be started before the stream is written to. \
+                The wait happens in the streaming thread, so the state change to PAUSED completes asynchronously, \
+                and it is interrupted by flushing seeks and state changes. \
+                If the wait times out, an error is posted. 0 fails the seek immediately.",
+                0.0,
+                std::f64::MAX,
+                DEFAULT_WAIT_FOR_INDEX_SEC,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_string(
+                PROPERTY_NAME_TIME_RANGES,
+                "Time ranges",
+                "A comma-separated list of time ranges to play back-to-back, each in the form begin/end, \
+                such as 2021-04-19T10:02:00Z/2021-04-19T10:05:00Z. \
+                Each range is played from the last random-access point before its beginning until its end. \
+                A new segment with continuous running time and a buffer with the DISCONT flag begin each range. \
+                Element messages named application/x-pravegasrc-clip-start and application/x-pravegasrc-clip-end \
+                report the boundaries. This overrides start-mode, requires end-mode=unbounded, \
+                and cannot be used with max-lag-sec. Seeking is not supported when this is set.",
+                None,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_double(
+                PROPERTY_NAME_STATS_INTERVAL_SEC,
+                "Stats interval in seconds",
+                "The interval at which an element message with the stats will be posted to the bus. 0 disables these messages.",
+                0.0,
+                std::f64::MAX,
+                DEFAULT_STATS_INTERVAL_SEC,
+                glib::ParamFlags::WRITABLE,
+            ),
+            glib::ParamSpec::new_boxed(
+                PROPERTY_NAME_STATS,
+                "Stats",
+                "Statistics about the data read by this element since it was started",
+                gst::Structure::static_type(),
+                glib::ParamFlags::READABLE,
+            ),
         ]});
         PROPERTIES.as_ref()
     }
@@ -477,9 +1502,159 @@ impl ObjectImpl for PravegaSrc {
                     gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_KEYCLOAK_FILE, err);
                 }
             },
+            PROPERTY_NAME_SEEK_RANDOM_ACCESS_ONLY => {
+                let res: Result<(), glib::Error> = match value.get::<bool>() {
+                    Ok(seek_random_access_only) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.seek_random_access_only = seek_random_access_only;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_SEEK_RANDOM_ACCESS_ONLY, err);
+                }
+            },
+            PROPERTY_NAME_ALLOW_CREATE_STREAM => {
+                let res: Result<(), glib::Error> = match value.get::<bool>() {
+                    Ok(allow_create_stream) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.allow_create_stream = allow_create_stream;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_ALLOW_CREATE_STREAM, err);
+                }
+            },
+            PROPERTY_NAME_RETENTION_TYPE => {
+                let res: Result<(), glib::Error> = match value.get::<PravegaRetentionType>() {
+                    Ok(retention_type) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.retention_type = retention_type;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_RETENTION_TYPE, err);
+                }
+            },
+            PROPERTY_NAME_RETENTION_DAYS => {
+                let res: Result<(), glib::Error> = match value.get::<f64>() {
+                    Ok(retention_days) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.retention_days = retention_days;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_RETENTION_DAYS, err);
+                }
+            },
+            PROPERTY_NAME_RETENTION_BYTES => {
+                let res: Result<(), glib::Error> = match value.get::<u64>() {
+                    Ok(retention_bytes) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.retention_bytes = retention_bytes;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_RETENTION_BYTES, err);
+                }
+            },
+            PROPERTY_NAME_CHECKSUM_FAILURE_POLICY => {
+                let res: Result<(), glib::Error> = match value.get::<ChecksumFailurePolicy>() {
+                    Ok(checksum_failure_policy) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.checksum_failure_policy = checksum_failure_policy;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_CHECKSUM_FAILURE_POLICY, err);
+                }
+            },
+            PROPERTY_NAME_ATTACH_METAS => {
+                let res: Result<(), glib::Error> = match value.get::<bool>() {
+                    Ok(attach_metas) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.attach_metas = attach_metas;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_ATTACH_METAS, err);
+                }
+            },
+            PROPERTY_NAME_MAX_LAG_SEC => {
+                let res: Result<(), glib::Error> = match value.get::<f64>() {
+                    Ok(max_lag_sec) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.max_lag_nanos = (max_lag_sec * 1e9) as u64;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_MAX_LAG_SEC, err);
+                }
+            },
+            PROPERTY_NAME_WAIT_FOR_INDEX_SEC => {
+                let res: Result<(), glib::Error> = match value.get::<f64>() {
+                    Ok(wait_for_index_sec) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.wait_for_index_nanos = (wait_for_index_sec * 1e9) as u64;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_WAIT_FOR_INDEX_SEC, err);
+                }
+            },
+            PROPERTY_NAME_TIME_RANGES => {
+                let res = match value.get::<Option<String>>() {
+                    Ok(time_ranges) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        TimeRange::parse_list(&time_ranges.unwrap_or_default())
+                            .map(|time_ranges| settings.time_ranges = time_ranges)
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_TIME_RANGES, err);
+                }
+            },
+            PROPERTY_NAME_STATS_INTERVAL_SEC => {
+                let res: Result<(), glib::Error> = match value.get::<f64>() {
+                    Ok(stats_interval_sec) => {
+                        let mut settings = self.settings.lock().unwrap();
+                        settings.stats_interval_nanos = (stats_interval_sec * 1e9) as u64;
+                        Ok(())
+                    },
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+                if let Err(err) = res {
+                    gst_error!(CAT, obj: obj, "Failed to set property `{}`: {}", PROPERTY_NAME_STATS_INTERVAL_SEC, err);
+                }
+            },
         _ => unimplemented!(),
         };
     }
+
+    fn property(&self, _obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            PROPERTY_NAME_STATS => self.stats.lock().unwrap().to_structure().to_value(),
+            _ => unimplemented!(),
+        }
+    }
 }
 
 impl ElementImpl for PravegaSrc {
@@ -529,11 +1704,38 @@ impl BaseSrcImpl for PravegaSrc {
                 gst::error_msg!(gst::ResourceError::Settings, ["Stream is not defined"])
             })?;
             let index_stream_name = get_index_stream_name(&stream_name);
+            let metadata_stream_name = get_metadata_stream_name(&stream_name);
             let scope = Scope::from(scope_name);
             let stream = Stream::from(stream_name);
             let index_stream = Stream::from(index_stream_name);
-            gst_info!(CAT, obj: element, "start: scope={}, stream={}, index_stream={}", scope, stream, index_stream);
+            let metadata_stream = Stream::from(metadata_stream_name);
+            gst_info!(CAT, obj: element, "start: scope={}, stream={}, index_stream={}, metadata_stream={}",
+                scope, stream, index_stream, metadata_stream);
             gst_info!(CAT, obj: element, "start: start_mode={:?}, end_mode={:?}", settings.start_mode, settings.end_mode);
+            gst_info!(CAT, obj: element, "start: max_lag_nanos={}", settings.max_lag_nanos);
+            if settings.max_lag_nanos > 0 && settings.end_mode != EndMode::Unbounded {
+                return Err(gst::error_msg!(gst::ResourceError::Settings,
+                    ["{} requires {}=unbounded", PROPERTY_NAME_MAX_LAG_SEC, PROPERTY_NAME_END_MODE]));
+            }
+            gst_info!(CAT, obj: element, "start: time_ranges={:?}", settings.time_ranges);
+            if !settings.time_ranges.is_empty() && settings.end_mode != EndMode::Unbounded {
+                return Err(gst::error_msg!(gst::ResourceError::Settings,
+                    ["{} requires {}=unbounded", PROPERTY_NAME_TIME_RANGES, PROPERTY_NAME_END_MODE]));
+            }
+            // Skipping ahead to the live tail would leave the time ranges.
+            if !settings.time_ranges.is_empty() && settings.max_lag_nanos > 0 {
+                return Err(gst::error_msg!(gst::ResourceError::Settings,
+                    ["{} cannot be used with {}", PROPERTY_NAME_TIME_RANGES, PROPERTY_NAME_MAX_LAG_SEC]));
+            }
+            let clip = if settings.time_ranges.is_empty() {
+                None
+            } else {
+                Some(Clip {
+                    index: 0,
+                    base: 0,
+                    last_end: PravegaTimestamp::NONE,
+                })
+            };
 
             let controller = settings.controller.clone().ok_or_else(|| {
                 gst::error_msg!(gst::ResourceError::Settings, ["Controller is not defined"])
@@ -561,82 +1763,135 @@ impl BaseSrcImpl for PravegaSrc {
                 })?;
             }
 
-            // Create data stream.
-            let stream_config = StreamConfiguration {
-                scoped_stream: ScopedStream {
-                    scope: scope.clone(),
-                    stream: stream.clone(),
+            // A stream written by pravegasink with rollover enabled has a catalog of partitions instead of a data stream.
+            let records = match utils::read_catalog(&client_factory, &scope, &stream.name) {
+                Ok(records) => records,
+                Err(error) => {
+                    gst_debug!(CAT, obj: element, "start: Catalog stream is not available: {}", error);
+                    Vec::new()
                 },
-                scaling: Scaling {
-                    scale_type: ScaleType::FixedNumSegments,
-                    min_num_segments: 1,
-                    ..Default::default()
-                },
-                retention: Default::default(),
             };
-            runtime.block_on(controller_client.create_stream(&stream_config)).map_err(|error| {
-                gst::error_msg!(gst::ResourceError::Settings, ["Failed to create Pravega data stream: {:?}", error])
-            })?;
 
-            // Create index stream.
-            let index_stream_config = StreamConfiguration {
-                scoped_stream: ScopedStream {
+            let (scoped_segment, index_scoped_segment, metadata_scoped_segment, mut partitions) = if !records.is_empty() {
+                gst_info!(CAT, obj: element, "start: Reading partitioned stream; partitions={:?}", records);
+                let partitions = Partitions {
+                    client_factory: client_factory.clone(),
                     scope: scope.clone(),
-                    stream: index_stream.clone(),
-                },
-                scaling: Scaling {
-                    scale_type: ScaleType::FixedNumSegments,
-                    min_num_segments: 1,
-                    ..Default::default()
-                },
-                retention: Default::default(),
-            };
-            runtime.block_on(controller_client.create_stream(&index_stream_config)).map_err(|error| {
-                gst::error_msg!(gst::ResourceError::Settings, ["Failed to create Pravega index stream: {:?}", error])
-            })?;
+                    stream_name: stream.name.clone(),
+                    records,
+                    current: 0,
+                    end: None,
+                };
+                let (scoped_segment, index_scoped_segment, metadata_scoped_segment) = partitions.segments(0);
+                (scoped_segment, index_scoped_segment, Some(metadata_scoped_segment), Some(partitions))
+            } else {
+                // Create data stream.
+                gst_info!(CAT, obj: element, "start: allow_create_stream={}", settings.allow_create_stream);
+                gst_info!(CAT, obj: element, "start: retention_type={:?}, retention_days={}, retention_bytes={}",
+                    settings.retention_type, settings.retention_days, settings.retention_bytes);
+                let retention = create_retention(settings.retention_type, settings.retention_days, settings.retention_bytes).map_err(|error| {
+                    gst::error_msg!(gst::ResourceError::Settings, ["{}", error])
+                })?;
+                let stream_config = StreamConfiguration {
+                    scoped_stream: ScopedStream {
+                        scope: scope.clone(),
+                        stream: stream.clone(),
+                    },
+                    scaling: utils::byte_stream_scaling(),
+                    retention: retention.clone(),
+                };
+                if settings.allow_create_stream {
+                    runtime.block_on(controller_client.create_stream(&stream_config)).map_err(|error| {
+                        gst::error_msg!(gst::ResourceError::Settings, ["Failed to create Pravega data stream: {:?}", error])
+                    })?;
+                }
 
-            let scoped_segment = ScopedSegment {
-                scope: scope.clone(),
-                stream: stream.clone(),
-                segment: Segment::from(0),
-            };
-            let mut reader = client_factory.create_byte_stream_reader(scoped_segment);
-            gst_info!(CAT, obj: element, "start: Opened Pravega reader for data");
+                // Create index stream.
+                let index_stream_config = StreamConfiguration {
+                    scoped_stream: ScopedStream {
+                        scope: scope.clone(),
+                        stream: index_stream.clone(),
+                    },
+                    scaling: utils::byte_stream_scaling(),
+                    retention: index_retention(&retention),
+                };
+                if settings.allow_create_stream {
+                    runtime.block_on(controller_client.create_stream(&index_stream_config)).map_err(|error| {
+                        gst::error_msg!(gst::ResourceError::Settings, ["Failed to create Pravega index stream: {:?}", error])
+                    })?;
+                }
 
-            let index_scoped_segment = ScopedSegment {
-                scope: scope.clone(),
-                stream: index_stream.clone(),
-                segment: Segment::from(0),
+                // A failure to get the segment of a stream is reported as NotFound only if the controller confirms
+                // that the stream does not exist. Other failures, such as an unavailable controller, are reported as OpenRead.
+                let segment_error = |kind: &str, scoped_stream: &ScopedStream, error: String| {
+                    match runtime.block_on(controller_client.check_stream_exists(scoped_stream)) {
+                        Ok(false) => gst::error_msg!(gst::ResourceError::NotFound, ["Pravega {} stream {}/{} does not exist: {}",
+                            kind, scoped_stream.scope, scoped_stream.stream, error]),
+                        _ => gst::error_msg!(gst::ResourceError::OpenRead, ["Failed to get segment of Pravega {} stream: {}", kind, error]),
+                    }
+                };
+                let scoped_segment = utils::get_byte_stream_segment(&client_factory, &stream_config.scoped_stream).map_err(|error| {
+                    segment_error("data", &stream_config.scoped_stream, error.to_string())
+                })?;
+                let index_scoped_segment = utils::get_byte_stream_segment(&client_factory, &index_stream_config.scoped_stream).map_err(|error| {
+                    segment_error("index", &index_stream_config.scoped_stream, error.to_string())
+                })?;
+
+                // The metadata stream is optional. It will not exist if the data stream was written by an older pravegasink.
+                let metadata_scoped_stream = ScopedStream {
+                    scope: scope.clone(),
+                    stream: metadata_stream,
+                };
+                let metadata_scoped_segment = match utils::get_byte_stream_segment(&client_factory, &metadata_scoped_stream) {
+                    Ok(metadata_scoped_segment) => Some(metadata_scoped_segment),
+                    Err(error) => {
+                        gst_info!(CAT, obj: element, "start: Metadata stream is not available: {}", error);
+                        None
+                    },
+                };
+                (scoped_segment, index_scoped_segment, metadata_scoped_segment, None)
             };
-            let index_reader = client_factory.create_byte_stream_reader(index_scoped_segment);
-            gst_info!(CAT, obj: element, "start: Opened Pravega reader for index");
 
-            let mut index_searcher = IndexSearcher::new(index_reader);
+            *self.stats.lock().unwrap() = Stats {
+                data_segment: Some(scoped_segment.clone()),
+                ..Default::default()
+            };
+            let (mut reader, mut index_searcher, metadata) = self.open_readers(element, &client_factory,
+                scoped_segment, index_scoped_segment, metadata_scoped_segment)?;
 
             // TODO: Run below based on CAT threshold.
             // gst_debug!(CAT, obj: element, "index_records={:?}", index_searcher.get_index_records());
 
             // end_offset is the byte offset in the data stream.
             // The data stream reader will be configured to never read beyond this offset.
-            let end_offset = match settings.end_mode {
-                EndMode::Unbounded => u64::MAX,
-                EndMode::Latest => {
-                    // When ending at Latest, we will emit up through the very last byte currently in the data stream.
-                    reader.seek(SeekFrom::End(0)).unwrap()
-                },
-                EndMode::LatestIndexed => {
-                    // Determine Pravega stream offset for this timestamp by searching the index.
-                    let index_record = index_searcher.get_last_record().unwrap();
-                    gst_info!(CAT, obj: element, "start: end index_record={:?}", index_record);
-                    index_record.offset
-                },
-                EndMode::Timestamp => {
-                    let end_timestamp = PravegaTimestamp::from_nanoseconds(Some(settings.end_timestamp));
-                    // Determine Pravega stream offset for this timestamp by searching the index.
-                    let index_record = index_searcher.search_timestamp_after(end_timestamp).unwrap();
-                    gst_info!(CAT, obj: element, "start: end index_record={:?}", index_record);
-                    index_record.offset
+            let end_offset = match partitions {
+                Some(ref mut partitions) => {
+                    // Determine the partition that contains the end, then the offset in its data stream.
+                    let end_partition = match settings.end_mode {
+                        EndMode::Unbounded => None,
+                        EndMode::Timestamp => {
+                            let end_timestamp = PravegaTimestamp::from_nanoseconds(Some(settings.end_timestamp));
+                            Some(find_partition(&partitions.records, end_timestamp))
+                        },
+                        _ => Some(partitions.records.len() - 1),
+                    };
+                    if let Some(end_partition) = end_partition {
+                        let end_offset = if end_partition == 0 {
+                            get_end_offset(element, settings.end_mode, settings.end_timestamp, &mut reader, &mut index_searcher)
+                        } else {
+                            let (scoped_segment, index_scoped_segment, _) = partitions.segments(end_partition);
+                            let (mut reader, mut index_searcher, _) = self.open_readers(element, &client_factory,
+                                scoped_segment, index_scoped_segment, None)?;
+                            get_end_offset(element, settings.end_mode, settings.end_timestamp, &mut reader, &mut index_searcher)
+                        };
+                        gst_info!(CAT, obj: element, "start: end_partition={}", end_partition);
+                        partitions.end = Some((end_partition, end_offset));
+                    }
+                    partitions.end_offset(0, &mut reader).map_err(|error| {
+                        gst::error_msg!(gst::ResourceError::OpenRead, ["Failed to read Pravega data stream: {}", error])
+                    })?
                 },
+                None => get_end_offset(element, settings.end_mode, settings.end_timestamp, &mut reader, &mut index_searcher),
             };
             gst_info!(CAT, obj: element, "start: end_offset={}", end_offset);
 
@@ -644,9 +1899,30 @@ impl BaseSrcImpl for PravegaSrc {
             let buf_reader = BufReader::with_capacity(settings.buffer_size, limited_reader);
             let counting_reader = CountingReader::new(buf_reader).unwrap();
 
+            gst_info!(CAT, obj: element, "start: stats_interval_nanos={}", settings.stats_interval_nanos);
+            if settings.stats_interval_nanos > 0 {
+                let element_weak = element.downgrade();
+                let client_factory = client_factory.clone();
+                let reporter = StatsReporter::start("pravegasrc-stats", Duration::from_nanos(settings.stats_interval_nanos), move || {
+                    if let Some(element) = element_weak.upgrade() {
+                        PravegaSrc::from_instance(&element).post_stats(&element, &client_factory);
+                    }
+                }).map_err(|error| {
+                    gst::error_msg!(gst::CoreError::Failed, ["Failed to start stats thread: {}", error])
+                })?;
+                *self.stats_reporter.lock().unwrap() = Some(reporter);
+            }
+
             *state = State::Started {
                 reader: Arc::new(Mutex::new(counting_reader)),
                 index_searcher: Arc::new(Mutex::new(index_searcher)),
+                metadata: Arc::new(Mutex::new(metadata)),
+                caps: None,
+                partitions: partitions.map(|partitions| Arc::new(Mutex::new(partitions))),
+                trick_mode: None,
+                clip,
+                pending_seek: None,
+                pushed_segment: None,
             };
             gst_info!(CAT, obj: element, "start: Started");
             Ok(())
@@ -659,6 +1935,19 @@ impl BaseSrcImpl for PravegaSrc {
         true
     }
 
+    fn unlock(&self, element: &Self::Type) -> Result<(), gst::ErrorMessage> {
+        gst_debug!(CAT, obj: element, "unlock");
+        *self.flushing.lock().unwrap() = true;
+        self.flushing_cond.notify_all();
+        Ok(())
+    }
+
+    fn unlock_stop(&self, element: &Self::Type) -> Result<(), gst::ErrorMessage> {
+        gst_debug!(CAT, obj: element, "unlock_stop");
+        *self.flushing.lock().unwrap() = false;
+        Ok(())
+    }
+
     /// This method is called in the following scenarios:
     /// 1) initial_seek=true: It is first called right after start() returns.
     ///    The input segment times will all be 0.
@@ -672,6 +1961,9 @@ impl BaseSrcImpl for PravegaSrc {
     ///
     /// When using the index:
     /// 1) This method will find the last index record before or equal to the desired time.
+    ///    If seek-random-access-only is true, only index records with random_access=true will be considered.
+    ///    If the index is empty and wait-for-index-sec is set, the seek will be completed by create()
+    ///    when the first record is written.
     /// 2) The Pravega reader offset and the segment times will be set using
     ///    the values from the located index record.
     /// 3) The segment times will be set so that each buffer will have a PTS and position equal to
@@ -680,34 +1972,45 @@ impl BaseSrcImpl for PravegaSrc {
         gst_info!(CAT, obj: src, "do_seek: BEGIN: segment={:?}", segment);
         let result = (|| {
             // Get needed settings, then release lock.
-            let (start_mode, initial_seek_start_timestamp) = {
+            let (start_mode, mut initial_seek_start_timestamp, seek_random_access_only, wait_for_index_nanos, time_ranges) = {
                 let settings = self.settings.lock().unwrap();
-                let start_timestamp = match settings.start_mode {
-                    StartMode::NoSeek => PravegaTimestamp::NONE,
-                    StartMode::Earliest => {
-                        // When starting at Earliest, the index will be used to find to the first random-access point.
-                        PravegaTimestamp::MIN
-                    },
-                    StartMode::Latest => {
-                        // When starting at Latest, the index will be used to find the last random-access point.
-                        PravegaTimestamp::MAX
-                    },
-                    StartMode::Timestamp => {
-                        // The index will be used to find a last random-access point before or on the specified timestamp.
-                        PravegaTimestamp::from_nanoseconds(Some(settings.start_timestamp))
-                    },
-                };
-                (settings.start_mode, start_timestamp)
+                // With time-ranges, the initial seek is to the last random-access point before the first range.
+                if let Some(first_time_range) = settings.time_ranges.first() {
+                    (StartMode::Timestamp, first_time_range.begin, true, settings.wait_for_index_nanos, settings.time_ranges.clone())
+                } else {
+                    let start_timestamp = match settings.start_mode {
+                        StartMode::NoSeek => PravegaTimestamp::NONE,
+                        StartMode::Earliest => {
+                            // When starting at Earliest, the index will be used to find to the first random-access point.
+                            PravegaTimestamp::MIN
+                        },
+                        StartMode::Latest => {
+                            // When starting at Latest, the index will be used to find the last random-access point.
+                            PravegaTimestamp::MAX
+                        },
+                        StartMode::Timestamp => {
+                            // The index will be used to find a last random-access point before or on the specified timestamp.
+                            PravegaTimestamp::from_nanoseconds(Some(settings.start_timestamp))
+                        },
+                    };
+                    (settings.start_mode, start_timestamp, settings.seek_random_access_only, settings.wait_for_index_nanos, Vec::new())
+                }
             };
 
             let mut state = self.state.lock().unwrap();
 
-            let (reader, index_searcher) = match *state {
+            let (reader, index_searcher, partitions) = match *state {
                 State::Started {
                     ref mut reader,
                     ref mut index_searcher,
+                    ref partitions,
+                    ref mut pushed_segment,
                     ..
-                } => (reader, index_searcher),
+                } => {
+                    // The base class will push the segment of this seek.
+                    *pushed_segment = None;
+                    (reader, index_searcher, partitions)
+                },
                 State::Stopped => {
                     panic!("Not started yet");
                 }
@@ -715,6 +2018,7 @@ impl BaseSrcImpl for PravegaSrc {
 
             let reader = reader.clone();
             let index_searcher = index_searcher.clone();
+            let partitions = partitions.clone();
             drop(state);
             let mut reader = reader.lock().unwrap();
             let mut index_searcher = index_searcher.lock().unwrap();
@@ -728,6 +2032,100 @@ impl BaseSrcImpl for PravegaSrc {
                 segment.start().nseconds().unwrap() == 0 &&
                 segment.position().nseconds().unwrap() == 0;
             gst_info!(CAT, obj: src, "do_seek: initial_seek={}", initial_seek);
+            if !time_ranges.is_empty() && !initial_seek {
+                gst_warning!(CAT, obj: src, "do_seek: seeking is not supported when {} is set", PROPERTY_NAME_TIME_RANGES);
+                return false;
+            }
+            // With time-ranges, skip leading ranges without index records so that the first buffer belongs to the first clip.
+            // The initial segment is pushed by the base class and a new segment can only be pushed after it.
+            // A partitioned stream is not checked because its index searcher only covers the current partition.
+            let first_time_range = match self.clip() {
+                Some(mut clip) if initial_seek => {
+                    if partitions.is_none() {
+                        clip.index = time_ranges.iter().position(|time_range| {
+                            match index_searcher.search_timestamp_after(time_range.begin) {
+                                Ok(index_record) => index_record.timestamp < time_range.end,
+                                Err(_) => false,
+                            }
+                        }).unwrap_or(0);
+                        self.set_clip(Some(clip));
+                    }
+                    initial_seek_start_timestamp = time_ranges[clip.index].begin;
+                    Some(time_ranges[clip.index])
+                },
+                _ => None,
+            };
+
+            // With GST_SEEK_FLAG_TRICKMODE_KEY_UNITS, only key frames are read, using the index to skip from one to the next.
+            // Reverse playback always uses this mode because events can only be decoded forwards from a key frame.
+            let trick_mode_key_units = segment.flags().contains(gst::SegmentFlags::TRICKMODE_KEY_UNITS) || segment.rate() < 0.0;
+            if trick_mode_key_units {
+                let forward = segment.rate() > 0.0;
+                let requested_seek_timestamp = if forward {
+                    clocktime_to_pravega(segment.start())
+                } else {
+                    match segment.stop().nseconds() {
+                        Some(stop) => PravegaTimestamp::from_nanoseconds(Some(stop)),
+                        None => PravegaTimestamp::MAX,
+                    }
+                };
+                gst_info!(CAT, obj: src, "do_seek: trick mode seek to timestamp={:?}, rate={}", requested_seek_timestamp, segment.rate());
+                if let Some(ref partitions) = partitions {
+                    let mut partitions = partitions.lock().unwrap();
+                    let mut i = find_partition(&partitions.records, requested_seek_timestamp);
+                    if let Some((end, _)) = partitions.end {
+                        i = i.min(end);
+                    }
+                    if i != partitions.current {
+                        if let Err(err) = self.switch_partition(src, &mut partitions, i, &mut reader, &mut index_searcher) {
+                            gst_error!(CAT, obj: src, "do_seek: unable to switch partition: {:?}", err);
+                            return false;
+                        }
+                    }
+                }
+                let result = index_searcher.search_timestamp_and_return_index_offset_with_filter(
+                    requested_seek_timestamp, SearchMethod::Before, SearchFilter::RandomAccessOnly);
+                gst_info!(CAT, obj: src, "do_seek: index_record={:?}", result);
+                let (index_record, index_offset) = match result {
+                    Ok(result) => result,
+                    Err(_) => return false,
+                };
+                if let Err(err) = reader.seek(SeekFrom::Start(index_record.offset)) {
+                    gst_error!(CAT, obj: src, "do_seek: unable to seek to offset {}: {:?}", index_record.offset, err);
+                    return false;
+                }
+                let limit = if forward {
+                    match segment.stop().nseconds() {
+                        Some(stop) => PravegaTimestamp::from_nanoseconds(Some(stop)),
+                        None => PravegaTimestamp::MAX,
+                    }
+                } else {
+                    clocktime_to_pravega(segment.start())
+                };
+                if forward {
+                    segment.set_start(ClockTime(index_record.timestamp.nanoseconds()));
+                    segment.set_time(ClockTime(index_record.timestamp.nanoseconds()));
+                    segment.set_position(0);
+                } else {
+                    if segment.stop().nseconds().is_none() {
+                        segment.set_stop(ClockTime(index_record.timestamp.nanoseconds()));
+                    }
+                    segment.set_position(segment.stop());
+                }
+                self.set_trick_mode(Some(TrickMode {
+                    forward,
+                    index_offset,
+                    pending: true,
+                    key_frame_timestamp: PravegaTimestamp::NONE,
+                    limit,
+                }));
+                gst_info!(CAT, obj: src, "do_seek: seeked to key frame for trick mode; segment={:?}", segment);
+                return true;
+            }
+            self.set_trick_mode(None);
+            // A new seek replaces a seek that is waiting for the first index record.
+            self.set_pending_seek(None);
+
             let no_seek = initial_seek && start_mode == StartMode::NoSeek;
             let seek_using_index = !no_seek;
             if seek_using_index {
@@ -737,23 +2135,64 @@ impl BaseSrcImpl for PravegaSrc {
                     clocktime_to_pravega(segment.time())
                 };
                 gst_info!(CAT, obj: src, "do_seek: seeking to timestamp={:?}", requested_seek_timestamp);
+                if let Some(ref partitions) = partitions {
+                    // Switch to the partition that contains the timestamp, but not beyond the end.
+                    let mut partitions = partitions.lock().unwrap();
+                    let mut i = find_partition(&partitions.records, requested_seek_timestamp);
+                    if let Some((end, _)) = partitions.end {
+                        i = i.min(end);
+                    }
+                    if i != partitions.current {
+                        if let Err(err) = self.switch_partition(src, &mut partitions, i, &mut reader, &mut index_searcher) {
+                            gst_error!(CAT, obj: src, "do_seek: unable to switch partition: {:?}", err);
+                            return false;
+                        }
+                    }
+                }
                 // Determine the stream offset for this timestamp by searching the index.
-                let index_record = index_searcher.search_timestamp(requested_seek_timestamp);
+                let index_record = if seek_random_access_only {
+                    index_searcher.search_random_access_timestamp(requested_seek_timestamp)
+                } else {
+                    index_searcher.search_timestamp(requested_seek_timestamp)
+                };
                 gst_info!(CAT, obj: src, "do_seek: index_record={:?}", index_record);
                 match index_record {
                     Ok(index_record) => {
-                        segment.set_start(ClockTime(index_record.timestamp.nanoseconds()));
-                        segment.set_time(ClockTime(index_record.timestamp.nanoseconds()));
+                        if let Err(err) = reader.seek(SeekFrom::Start(index_record.offset)) {
+                            gst_error!(CAT, obj: src, "do_seek: unable to seek to offset {}: {:?}", index_record.offset, err);
+                            return false;
+                        }
+                        // With time-ranges, buffers before the beginning of the first range will be clipped by the segment.
+                        let start_timestamp = match first_time_range {
+                            Some(first_time_range) => first_time_range.begin,
+                            None => index_record.timestamp,
+                        };
+                        segment.set_start(ClockTime(start_timestamp.nanoseconds()));
+                        segment.set_time(ClockTime(start_timestamp.nanoseconds()));
                         segment.set_position(0);
-                        reader.seek(SeekFrom::Start(index_record.offset)).unwrap();
                         gst_info!(CAT, obj: src, "do_seek: seeked to indexed position; segment={:?}", segment);
+                        if let (Some(clip), Some(first_time_range)) = (self.clip(), first_time_range) {
+                            self.post_clip_message(src, "application/x-pravegasrc-clip-start", &clip, &first_time_range);
+                        }
+                        true
+                    },
+                    Err(e) if e.kind() == ErrorKind::UnexpectedEof && wait_for_index_nanos > 0 => {
+                        // The index has no records yet. Waiting here would block the state change,
+                        // so create() will wait for the first record and then set the segment.
+                        let pending_seek = PendingSeek {
+                            timestamp: requested_seek_timestamp,
+                            random_access_only: seek_random_access_only,
+                            first_time_range,
+                            deadline: Instant::now().checked_add(Duration::from_nanos(wait_for_index_nanos)),
+                        };
+                        gst_info!(CAT, obj: src, "do_seek: index is empty; pending_seek={:?}", pending_seek);
+                        self.set_pending_seek(Some(pending_seek));
                         true
                     },
                     Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
-                        // This will happen if the index has no records.
+                        // This will happen if the index has no records and wait-for-index-sec is 0.
                         // We cannot set the segment times appropriately.
                         gst_error!(CAT, obj: src, "do_seek: index is empty; segment={:?}", segment);
-                        // TODO: Block until the first index record is read.
                         false
                     },
                     Err(_) => {
@@ -776,8 +2215,45 @@ impl BaseSrcImpl for PravegaSrc {
         result
     }
 
+    /// If the metadata stream records the caps of the data stream, these will be reported instead of ANY,
+    /// so that downstream elements do not need to determine the type of the data.
+    /// Before the first buffer, this reports all caps recorded in the metadata stream.
+    fn caps(&self, src: &Self::Type, filter: Option<&gst::Caps>) -> Option<gst::Caps> {
+        let caps = {
+            let state = self.state.lock().unwrap();
+            match *state {
+                State::Started { ref metadata, ref caps, .. } => {
+                    let metadata = metadata.lock().unwrap();
+                    match caps {
+                        Some(caps) => Some(caps.clone()),
+                        None if !metadata.caps.is_empty() => {
+                            let mut all_caps = gst::Caps::new_empty();
+                            for (_, caps) in metadata.caps.iter() {
+                                all_caps.merge(caps.clone());
+                            }
+                            Some(all_caps)
+                        },
+                        None => None,
+                    }
+                },
+                State::Stopped => None,
+            }
+        };
+        match caps {
+            Some(caps) => {
+                gst_debug!(CAT, obj: src, "caps: Using caps from metadata: {:?}", caps);
+                match filter {
+                    Some(filter) => Some(filter.intersect_with_mode(&caps, gst::CapsIntersectMode::First)),
+                    None => Some(caps),
+                }
+            },
+            None => self.parent_caps(src, filter),
+        }
+    }
+
     fn query(&self, src: &Self::Type, query: &mut gst::QueryRef) -> bool {
         gst_debug!(CAT, obj: src, "query: BEGIN: query={:?}", query);
+        let pushed_segment = self.pushed_segment();
         let result = (|| {
             match query.view_mut() {
                 // The Seeking query will return the current start and end timestamps
@@ -790,27 +2266,40 @@ impl BaseSrcImpl for PravegaSrc {
                         // Get a temporary lock on state to get the index_searcher.
                         // This lock is released before index_searcher performs I/O.
                         let mut state = self.state.lock().unwrap();
-                        let index_searcher = match *state {
+                        let (index_searcher, partitions) = match *state {
                             State::Started {
                                 ref mut index_searcher,
+                                ref partitions,
                                 ..
-                            } => index_searcher,
+                            } => (index_searcher, partitions),
                             State::Stopped => {
                                 return false;
                             }
                         };
                         let index_searcher = index_searcher.clone();
+                        let partitions = partitions.clone();
                         drop(state);
-                        let mut index_searcher = index_searcher.lock().unwrap();
 
-                        let start = match index_searcher.get_first_record() {
+                        // For a partitioned stream, the start is in the first partition and the end is in the last partition.
+                        let (first, last) = match partitions {
+                            Some(partitions) => {
+                                let partitions = partitions.lock().unwrap();
+                                (partitions.index_searcher(0).get_first_record(),
+                                 partitions.index_searcher(partitions.records.len() - 1).get_last_record())
+                            },
+                            None => {
+                                let mut index_searcher = index_searcher.lock().unwrap();
+                                (index_searcher.get_first_record(), index_searcher.get_last_record())
+                            },
+                        };
+                        let start = match first {
                             Ok(start) => start,
                             Err(err) => {
                                 gst_error!(CAT, obj: src, "query: Unable to get first record from index: {}", err);
                                 return false;
                             }
                         };
-                        let end = match index_searcher.get_last_record() {
+                        let end = match last {
                             Ok(end) => end,
                             Err(err) => {
                                 gst_error!(CAT, obj: src, "query: Unable to get last record from index: {}", err);
@@ -823,6 +2312,14 @@ impl BaseSrcImpl for PravegaSrc {
                     };
                     false
                 },
+                // After new_segment(), the segment of the base class is not updated,
+                // so the position that it tracks is converted to stream time with the pushed segment.
+                gst::QueryView::Position(ref mut q) if q.format() == gst::Format::Time && pushed_segment.is_some() => {
+                    let pushed_segment = pushed_segment.unwrap();
+                    let position = src.segment().downcast::<gst::format::Time>().unwrap().position();
+                    q.set(pushed_segment.to_stream_time(position));
+                    true
+                },
                 _ => {
                     BaseSrcImplExt::parent_query(self, src, query)
                 },
@@ -834,6 +2331,9 @@ impl BaseSrcImpl for PravegaSrc {
 
     fn stop(&self, element: &Self::Type) -> Result<(), gst::ErrorMessage> {
         gst_info!(CAT, obj: element, "stop: BEGIN");
+        if let Some(mut reporter) = self.stats_reporter.lock().unwrap().take() {
+            reporter.stop();
+        }
         let result = (|| {
             let mut state = self.state.lock().unwrap();
             if let State::Stopped = *state {
@@ -873,61 +2373,217 @@ impl PushSrcImpl for PravegaSrc {
             let mut reader = reader.lock().unwrap();
             let reader = &mut (*reader);
 
-            let mut event_reader = EventReader::new();
-            let offset = reader.stream_position().unwrap();
-            let required_buffer_length = event_reader.read_required_buffer_length(reader).map_err(|err| {
-                if err.kind() == ErrorKind::UnexpectedEof {
-                    gst_info!(CAT, obj: element, "create: reached EOF when trying to read event length");
-                    gst::FlowError::Eos
-                } else {
-                    gst::element_error!(element, gst::CoreError::Failed, ["Failed to read event length from stream: {}", err]);
-                    gst::FlowError::Error
+            let (checksum_failure_policy, attach_metas, max_lag_nanos) = {
+                let settings = self.settings.lock().unwrap();
+                (settings.checksum_failure_policy, settings.attach_metas, settings.max_lag_nanos)
+            };
+            // Complete a seek that is waiting for the first index record.
+            if let Some(pending_seek) = self.pending_seek() {
+                self.complete_pending_seek(element, reader, pending_seek)?;
+            }
+
+            // Set when a corrupt event, a lagging range, or a truncated range was skipped, or when a new clip begins,
+            // and the next buffer must be marked as discontinuous.
+            let mut discont = false;
+
+            // Loop until an event is read that is not skipped due to a checksum failure, trick mode, lag, truncation,
+            // or the end of a time range.
+            loop {
+                let mut trick_mode = self.trick_mode();
+                if let Some(current_trick_mode) = trick_mode {
+                    if !current_trick_mode.pending && current_trick_mode.key_frame_timestamp.is_none() {
+                        if !self.seek_next_key_frame(element, reader, current_trick_mode)? {
+                            gst_info!(CAT, obj: element, "create: no more key frames in trick mode");
+                            return Err(gst::FlowError::Eos);
+                        }
+                        trick_mode = self.trick_mode();
+                    }
                 }
-            })?;
 
-            // TODO: Read directly into GstBuffer.
-            let mut read_buffer: Vec<u8> = vec![0; required_buffer_length];
-            let event = event_reader.read_event(reader, &mut read_buffer[..]).map_err(|err| {
-                if err.kind() == ErrorKind::UnexpectedEof {
-                    gst_info!(CAT, obj: element, "create: reached EOF when trying to read event payload");
-                    gst::FlowError::Eos
-                } else {
-                    gst::element_error!(element, gst::CoreError::Failed, ["Failed to read event payload from stream: {}", err]);
-                    gst::FlowError::Error
+                let mut event_reader = EventReader::new();
+                let offset = reader.stream_position().unwrap();
+                let required_buffer_length = match event_reader.read_required_buffer_length(reader) {
+                    Ok(required_buffer_length) => required_buffer_length,
+                    Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+                        gst_info!(CAT, obj: element, "create: reached EOF when trying to read event length");
+                        // In trick mode, continue with the next key frame, which may be in another partition.
+                        if let Some(trick_mode) = trick_mode {
+                            if !trick_mode.pending {
+                                self.end_key_frame();
+                                continue;
+                            }
+                            return Err(gst::FlowError::Eos);
+                        }
+                        // A partitioned stream continues with the next partition.
+                        if self.next_partition(element, reader)? {
+                            continue;
+                        }
+                        // With time-ranges, continue with the next clip.
+                        if let Some(clip) = self.clip() {
+                            if self.next_clip(element, reader, clip, false)? {
+                                discont = true;
+                                continue;
+                            }
+                        }
+                        return Err(gst::FlowError::Eos);
+                    },
+                    Err(err) => {
+                        // Retention or truncate-stream may have removed the data at the reader's position.
+                        if self.skip_truncated(element, reader, offset)? {
+                            self.end_key_frame();
+                            discont = true;
+                            continue;
+                        }
+                        gst::element_error!(element, gst::CoreError::Failed, ["Failed to read event length from stream: {}", err]);
+                        return Err(gst::FlowError::Error);
+                    },
+                };
+
+                // TODO: Read directly into GstBuffer.
+                let mut read_buffer: Vec<u8> = vec![0; required_buffer_length];
+                let event = match event_reader.read_event(reader, &mut read_buffer[..]) {
+                    Ok(event) => event,
+                    Err(err @ ReadEventError::ChecksumMismatch { .. }) => {
+                        match checksum_failure_policy {
+                            ChecksumFailurePolicy::Error => {
+                                gst::element_error!(element, gst::StreamError::Failed,
+                                    ["Corrupt event at offset {}: {}", offset, err]);
+                                return Err(gst::FlowError::Error);
+                            },
+                            ChecksumFailurePolicy::Skip => {
+                                gst_warning!(CAT, obj: element, "create: skipping corrupt event at offset {}: {}", offset, err);
+                                self.stats.lock().unwrap().checksum_failures += 1;
+                                self.end_key_frame();
+                            },
+                            ChecksumFailurePolicy::Discont => {
+                                gst_warning!(CAT, obj: element, "create: skipping corrupt event at offset {}: {}", offset, err);
+                                self.stats.lock().unwrap().checksum_failures += 1;
+                                self.end_key_frame();
+                                discont = true;
+                            },
+                        }
+                        continue;
+                    },
+                    Err(err) => {
+                        if err.kind() == ErrorKind::UnexpectedEof {
+                            gst_info!(CAT, obj: element, "create: reached EOF when trying to read event payload");
+                            return Err(gst::FlowError::Eos);
+                        } else if self.skip_truncated(element, reader, offset)? {
+                            self.end_key_frame();
+                            discont = true;
+                            continue;
+                        } else {
+                            gst::element_error!(element, gst::CoreError::Failed, ["Failed to read event payload from stream: {}", err]);
+                            return Err(gst::FlowError::Error);
+                        }
+                    },
+                };
+                gst_memdump!(CAT, obj: element, "create: event={:?}", event);
+                // In trick mode, only the events of the key frame are output.
+                let mut key_frame_start = false;
+                if let Some(mut trick_mode) = trick_mode {
+                    if trick_mode.pending {
+                        trick_mode.pending = false;
+                        trick_mode.key_frame_timestamp = event.header.timestamp;
+                        self.set_trick_mode(Some(trick_mode));
+                        key_frame_start = true;
+                    } else if event.header.random_access
+                            || (event.header.timestamp.is_some() && event.header.timestamp != trick_mode.key_frame_timestamp) {
+                        gst_trace!(CAT, obj: element, "create: end of key frame at offset {}", offset);
+                        self.end_key_frame();
+                        continue;
+                    }
+                } else if let Some(mut clip) = self.clip() {
+                    // With time-ranges, the first event at or after the end of the range ends the clip.
+                    if event.header.timestamp.is_some() {
+                        let end = self.settings.lock().unwrap().time_ranges[clip.index].end;
+                        if event.header.timestamp >= end {
+                            if self.next_clip(element, reader, clip, true)? {
+                                discont = true;
+                                continue;
+                            }
+                            gst_info!(CAT, obj: element, "create: reached the end of the last time range");
+                            return Err(gst::FlowError::Eos);
+                        }
+                        let duration = event.header.extensions.iter()
+                            .find_map(|extension| extension.as_buffer_timing())
+                            .and_then(|timing| timing.duration)
+                            .unwrap_or_default();
+                        let buffer_end = PravegaTimestamp::from_nanoseconds(
+                            event.header.timestamp.nanoseconds().map(|timestamp| timestamp.saturating_add(duration)));
+                        if buffer_end > clip.last_end {
+                            clip.last_end = buffer_end;
+                            self.set_clip(Some(clip));
+                        }
+                    }
+                } else if max_lag_nanos > 0 && event.header.random_access && event.header.timestamp.is_some() {
+                    // Jumping from one random-access event to another keeps the output decodable.
+                    if self.skip_lag(element, reader, event.header.timestamp, max_lag_nanos)? {
+                        discont = true;
+                        continue;
+                    }
                 }
-            })?;
-            gst_memdump!(CAT, obj: element, "create: event={:?}", event);
-            let offset_end = reader.stream_position().unwrap();
-
-            let mut gst_buffer = gst::Buffer::with_size(event.payload.len()).unwrap();
-            {
-                let buffer_ref = gst_buffer.get_mut().unwrap();
-
-                let segment = element
-                    .segment()
-                    .downcast::<gst::format::Time>()
-                    .unwrap();
-                gst_trace!(CAT, obj: element, "create: segment={:?}", segment);
-                let pts = pravega_to_clocktime(event.header.timestamp);
-                gst_log!(CAT, obj: element, "create: timestamp={:?}, pts={}, payload_len={}",
-                    event.header.timestamp, pts, event.payload.len());
-
-                buffer_ref.set_pts(pts);
-                buffer_ref.set_offset(offset);
-                buffer_ref.set_offset_end(offset_end);
-                if !event.header.random_access {
-                    buffer_ref.set_flags(gst::BufferFlags::DELTA_UNIT);
-                }
-                if event.header.discontinuity {
-                    buffer_ref.set_flags(gst::BufferFlags::DISCONT);
-                }
-
-                let mut buffer_map = buffer_ref.map_writable().unwrap();
-                let slice = buffer_map.as_mut_slice();
-                slice.copy_from_slice(event.payload);
-            }
+                self.update_caps(element, offset);
+                let offset_end = reader.stream_position().unwrap();
+                {
+                    let mut stats = self.stats.lock().unwrap();
+                    stats.bytes_read += offset_end - offset;
+                    stats.events_read += 1;
+                    stats.offset = Some(offset_end);
+                }
+
+                let mut gst_buffer = gst::Buffer::with_size(event.payload.len()).unwrap();
+                {
+                    let buffer_ref = gst_buffer.get_mut().unwrap();
+
+                    let segment = element
+                        .segment()
+                        .downcast::<gst::format::Time>()
+                        .unwrap();
+                    gst_trace!(CAT, obj: element, "create: segment={:?}", segment);
+                    let pts = pravega_to_clocktime(event.header.timestamp);
+                    gst_log!(CAT, obj: element, "create: timestamp={:?}, pts={}, payload_len={}",
+                        event.header.timestamp, pts, event.payload.len());
+
+                    buffer_ref.set_pts(pts);
+                    // Restore the DTS and duration if they were persisted by pravegasink.
+                    if let Some(timing) = event.header.extensions.iter().find_map(|extension| extension.as_buffer_timing()) {
+                        if let (Some(pts), Some(dts_offset)) = (pts.nseconds(), timing.dts_offset) {
+                            buffer_ref.set_dts(gst::ClockTime(u64_checked_sub_i64(pts, dts_offset)));
+                        }
+                        buffer_ref.set_duration(gst::ClockTime(timing.duration));
+                    }
+                    buffer_ref.set_offset(offset);
+                    buffer_ref.set_offset_end(offset_end);
+                    if !event.header.random_access {
+                        buffer_ref.set_flags(gst::BufferFlags::DELTA_UNIT);
+                    }
+                    // In trick mode, each key frame follows a gap.
+                    if event.header.discontinuity || discont || key_frame_start {
+                        buffer_ref.set_flags(gst::BufferFlags::DISCONT);
+                    }
+                    if attach_metas {
+                        for (api_name, data) in event.header.extensions.iter().filter_map(|extension| extension.as_gst_meta()) {
+                            match meta_serializer::get(api_name) {
+                                Some(serializer) => {
+                                    if let Err(err) = serializer.deserialize(buffer_ref, data) {
+                                        gst_warning!(CAT, obj: element, "create: unable to attach meta {}: {}", api_name, err);
+                                    }
+                                },
+                                None => {
+                                    gst_debug!(CAT, obj: element, "create: no serializer is registered for meta {}", api_name);
+                                },
+                            }
+                        }
+                    }
 
-            Ok(gst_buffer)
+                    let mut buffer_map = buffer_ref.map_writable().unwrap();
+                    let slice = buffer_map.as_mut_slice();
+                    slice.copy_from_slice(event.payload);
+                }
+
+                return Ok(gst_buffer);
+            }
         })();
         gst_trace!(CAT, obj: element, "create: END: result={:?}", result);
         result
@@ -16,12 +16,14 @@ use std::io::{Error, ErrorKind, Result, Seek, SeekFrom, Write};
 /// A ByteStreamWriter that implements Seek.
 pub struct SeekableByteStreamWriter {
     inner: ByteStreamWriter,
+    fenced: bool,
 }
 
 impl SeekableByteStreamWriter {
     pub fn new(writer: ByteStreamWriter) -> Result<SeekableByteStreamWriter> {
         let writer = SeekableByteStreamWriter {
-            inner: writer
+            inner: writer,
+            fenced: false,
         };
         Ok(writer)
     }
@@ -35,14 +37,27 @@ impl SeekableByteStreamWriter {
     pub fn get_mut(&mut self) -> &mut ByteStreamWriter {
         &mut self.inner
     }
+
+    /// Causes all subsequent writes to fail.
+    /// This is used when this writer is no longer permitted to write to the stream,
+    /// so that any buffered data will not be written when the writer is dropped.
+    pub fn fence(&mut self) {
+        self.fenced = true;
+    }
 }
 
 impl Write for SeekableByteStreamWriter {
     fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if self.fenced {
+            return Err(Error::new(ErrorKind::PermissionDenied, "Writer has been fenced"));
+        }
         self.inner.write(buf)
     }
 
     fn flush(&mut self) -> Result<()> {
+        if self.fenced {
+            return Err(Error::new(ErrorKind::PermissionDenied, "Writer has been fenced"));
+        }
         self.inner.flush()
     }
 }
@@ -0,0 +1,70 @@
+//
+// Copyright (c) Dell Inc., or its subsidiaries. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+
+// Support for the stats property and the periodic stats messages of pravegasink and pravegasrc.
+
+use pravega_client::client_factory::ClientFactory;
+use pravega_client_shared::ScopedSegment;
+use std::io::{Seek, SeekFrom};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+pub const PROPERTY_NAME_STATS: &str = "stats";
+pub const PROPERTY_NAME_STATS_INTERVAL_SEC: &str = "stats-interval-sec";
+pub const DEFAULT_STATS_INTERVAL_SEC: f64 = 0.0;
+
+/// Calls a function at a fixed interval on a background thread until stopped.
+/// Elements use this to post their stats to the bus even when no buffers are flowing.
+pub struct StatsReporter {
+    stop_sender: mpsc::Sender<()>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl StatsReporter {
+    pub fn start<F>(thread_name: &str, interval: Duration, mut report: F) -> std::io::Result<StatsReporter>
+            where F: FnMut() + Send + 'static {
+        let (stop_sender, stop_receiver) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name(thread_name.to_owned())
+            .spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = stop_receiver.recv_timeout(interval) {
+                    report();
+                }
+            })?;
+        Ok(StatsReporter {
+            stop_sender,
+            thread: Some(thread),
+        })
+    }
+
+    /// Signals the thread to stop without waiting for it, because a report may be blocked on Pravega I/O.
+    /// The thread exits after any report in progress.
+    pub fn stop(&mut self) {
+        let _ = self.stop_sender.send(());
+        // Dropping the handle detaches the thread.
+        self.thread.take();
+    }
+}
+
+impl Drop for StatsReporter {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Returns the head and tail offsets of a byte stream segment.
+/// This uses its own reader so that it does not disturb the position of the element's reader or writer.
+pub fn get_head_and_tail_offsets(client_factory: &ClientFactory, segment: &ScopedSegment) -> std::io::Result<(u64, u64)> {
+    let mut reader = client_factory.create_byte_stream_reader(segment.clone());
+    let head_offset = reader.current_head()?;
+    let tail_offset = reader.seek(SeekFrom::End(0))?;
+    Ok((head_offset, tail_offset))
+}
@@ -0,0 +1,142 @@
+//
+// Copyright (c) Dell Inc., or its subsidiaries. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+
+// A GstMeta that carries an arbitrary GstStructure, such as the results of an analytics pipeline.
+// Unlike GstProtectionMeta, it has no meaning to other elements, so it is safe to use for application data.
+// It is registered with the API name GstPravegaStructureMetaAPI, which can be included in the
+// persist-meta-apis property of pravegasink.
+
+use gst::prelude::*;
+use std::fmt;
+
+pub const STRUCTURE_META_API_NAME: &str = "GstPravegaStructureMetaAPI";
+
+#[repr(transparent)]
+pub struct StructureMeta(imp::StructureMeta);
+
+unsafe impl Send for StructureMeta {}
+unsafe impl Sync for StructureMeta {}
+
+impl StructureMeta {
+    /// Attaches a new meta with structure to buffer.
+    pub fn add(buffer: &mut gst::BufferRef, structure: gst::Structure) -> gst::MetaRefMut<Self, gst::meta::Standalone> {
+        unsafe {
+            // The structure is moved into the meta by structure_meta_init.
+            let mut params = std::mem::ManuallyDrop::new(imp::StructureMetaParams { structure });
+            let meta = gst::ffi::gst_buffer_add_meta(
+                buffer.as_mut_ptr(),
+                imp::structure_meta_get_info(),
+                &mut *params as *mut imp::StructureMetaParams as glib::ffi::gpointer,
+            ) as *mut imp::StructureMeta;
+            Self::from_mut_ptr(buffer, meta)
+        }
+    }
+
+    pub fn structure(&self) -> &gst::StructureRef {
+        self.0.structure.as_ref()
+    }
+}
+
+unsafe impl MetaAPI for StructureMeta {
+    type GstType = imp::StructureMeta;
+
+    fn meta_api() -> glib::Type {
+        imp::structure_meta_api_get_type()
+    }
+}
+
+impl fmt::Debug for StructureMeta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StructureMeta")
+            .field("structure", &self.structure())
+            .finish()
+    }
+}
+
+mod imp {
+    use glib::translate::*;
+    use once_cell::sync::Lazy;
+    use std::mem;
+    use std::ptr;
+
+    pub(super) struct StructureMetaParams {
+        pub structure: gst::Structure,
+    }
+
+    #[repr(C)]
+    pub struct StructureMeta {
+        parent: gst::ffi::GstMeta,
+        pub(super) structure: gst::Structure,
+    }
+
+    pub(super) fn structure_meta_api_get_type() -> glib::Type {
+        static TYPE: Lazy<glib::Type> = Lazy::new(|| unsafe {
+            let t = from_glib(gst::ffi::gst_meta_api_type_register(
+                b"GstPravegaStructureMetaAPI\0".as_ptr() as *const _,
+                [ptr::null::<std::os::raw::c_char>()].as_ptr() as *mut *const _,
+            ));
+            assert_ne!(t, glib::Type::INVALID);
+            t
+        });
+        *TYPE
+    }
+
+    unsafe extern "C" fn structure_meta_init(
+        meta: *mut gst::ffi::GstMeta,
+        params: glib::ffi::gpointer,
+        _buffer: *mut gst::ffi::GstBuffer,
+    ) -> glib::ffi::gboolean {
+        assert!(!params.is_null());
+        let meta = &mut *(meta as *mut StructureMeta);
+        let params = ptr::read(params as *const StructureMetaParams);
+        ptr::write(&mut meta.structure, params.structure);
+        true.into_glib()
+    }
+
+    unsafe extern "C" fn structure_meta_free(meta: *mut gst::ffi::GstMeta, _buffer: *mut gst::ffi::GstBuffer) {
+        let meta = &mut *(meta as *mut StructureMeta);
+        ptr::drop_in_place(&mut meta.structure);
+    }
+
+    // The meta is copied when a buffer is copied, or when its metadata is copied to another buffer.
+    unsafe extern "C" fn structure_meta_transform(
+        dest: *mut gst::ffi::GstBuffer,
+        meta: *mut gst::ffi::GstMeta,
+        _buffer: *mut gst::ffi::GstBuffer,
+        _type_: glib::ffi::GQuark,
+        _data: glib::ffi::gpointer,
+    ) -> glib::ffi::gboolean {
+        let meta = &*(meta as *mut StructureMeta);
+        super::StructureMeta::add(gst::BufferRef::from_mut_ptr(dest), meta.structure.clone());
+        true.into_glib()
+    }
+
+    pub(super) fn structure_meta_get_info() -> *const gst::ffi::GstMetaInfo {
+        struct MetaInfo(ptr::NonNull<gst::ffi::GstMetaInfo>);
+        unsafe impl Send for MetaInfo {}
+        unsafe impl Sync for MetaInfo {}
+
+        static META_INFO: Lazy<MetaInfo> = Lazy::new(|| unsafe {
+            MetaInfo(
+                ptr::NonNull::new(gst::ffi::gst_meta_register(
+                    structure_meta_api_get_type().into_glib(),
+                    b"GstPravegaStructureMeta\0".as_ptr() as *const _,
+                    mem::size_of::<StructureMeta>(),
+                    Some(structure_meta_init),
+                    Some(structure_meta_free),
+                    Some(structure_meta_transform),
+                ) as *mut gst::ffi::GstMetaInfo)
+                .expect("Failed to register meta API"),
+            )
+        });
+
+        META_INFO.0.as_ptr()
+    }
+}
@@ -9,8 +9,71 @@
 //
 
 use gst::ClockTime;
+use pravega_client_shared::{Retention, RetentionType};
 use pravega_video::timestamp::PravegaTimestamp;
 
+/// The retention policy of a data stream created by pravegasink or pravegasrc.
+/// Pravega will periodically truncate the head of the stream to enforce this policy.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::GEnum)]
+#[repr(u32)]
+#[genum(type_name = "GstPravegaRetentionType")]
+pub enum PravegaRetentionType {
+    #[genum(
+        name = "The stream will not be truncated.",
+        nick = "none"
+    )]
+    None = 0,
+    #[genum(
+        name = "The stream will retain data for at least retention-days.",
+        nick = "days"
+    )]
+    Days = 1,
+    #[genum(
+        name = "The stream will retain at least retention-bytes.",
+        nick = "bytes"
+    )]
+    Bytes = 2,
+}
+
+pub const DEFAULT_RETENTION_TYPE: PravegaRetentionType = PravegaRetentionType::None;
+pub const DEFAULT_RETENTION_DAYS: f64 = 0.0;
+pub const DEFAULT_RETENTION_BYTES: u64 = 0;
+pub const DEFAULT_ALLOW_CREATE_STREAM: bool = true;
+
+/// Returns the Pravega retention policy for a data stream.
+pub fn create_retention(retention_type: PravegaRetentionType, days: f64, bytes: u64) -> Result<Retention, String> {
+    match retention_type {
+        PravegaRetentionType::None => Ok(Retention::default()),
+        PravegaRetentionType::Days if days > 0.0 => Ok(Retention {
+            retention_type: RetentionType::Time,
+            retention_param: (days * 24.0 * 60.0 * 60.0 * 1000.0) as i64,
+        }),
+        PravegaRetentionType::Bytes if bytes > 0 => Ok(Retention {
+            retention_type: RetentionType::Size,
+            retention_param: bytes as i64,
+        }),
+        _ => Err(format!("Retention type {:?} requires a positive retention-days or retention-bytes", retention_type)),
+    }
+}
+
+/// Returns the retention policy for the index of a data stream with the given retention policy.
+/// The index is small, so it is truncated only by time.
+pub fn index_retention(retention: &Retention) -> Retention {
+    match retention.retention_type {
+        RetentionType::Time => retention.clone(),
+        _ => Retention::default(),
+    }
+}
+
+/// How pravegasink and pravegasrc create the data stream and its index.
+/// Streams are always created with a single fixed segment (see [pravega_video::utils::byte_stream_scaling]).
+#[derive(Debug, Clone)]
+pub struct StreamPolicy {
+    /// If false, the streams must already exist.
+    pub create: bool,
+    pub retention: Retention,
+}
+
 pub fn clocktime_to_pravega(t: ClockTime) -> PravegaTimestamp {
     PravegaTimestamp::from_nanoseconds(t.nanoseconds())
 }
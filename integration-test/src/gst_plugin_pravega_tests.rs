@@ -52,6 +52,11 @@ mod test {
         let summary_written = launch_pipeline_and_get_summary(&pipeline_description).unwrap();
         debug!("summary_written={:?}", summary_written);
 
+        info!("#### Verify index and data stream");
+        let report = verify_stream(test_config.client_config.clone(), test_config.scope.clone(), stream_name.to_owned());
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.events_checked, num_buffers_written);
+
         info!("#### Read video stream from beginning");
         let pipeline_description = format!(
             "pravegasrc {pravega_plugin_properties} \
@@ -68,6 +73,8 @@ mod test {
         let truncate_sec = 1;
         let truncate_before_pts = first_pts_written + truncate_sec * SECOND;
         truncate_stream(test_config.client_config.clone(), test_config.scope.clone(), stream_name.to_owned(), truncate_before_pts);
+        let report = verify_stream(test_config.client_config.clone(), test_config.scope.clone(), stream_name.to_owned());
+        assert!(report.is_ok(), "{}", report);
 
         info!("#### Read video from truncated position");
         let pipeline_description = format!(
@@ -92,6 +99,72 @@ mod test {
         info!("#### END");
     }
 
+    /// Test that a transactional pravegasink indexes the data left at the tail of the data stream by a failed writer.
+    #[test]
+    fn test_transactional_recovery() {
+        gst_init();
+        let test_config = get_test_config();
+        info!("test_config={:?}", test_config);
+        let stream_name = &format!("test-transactional-{}-{}", test_config.test_id, Uuid::new_v4())[..];
+
+        let first_utc = "2001-02-03T04:00:00.000Z".to_owned();
+        let first_pts_written = PravegaTimestamp::try_from(Some(first_utc)).unwrap();
+        let fps = 30;
+        let length_sec = 2;
+        let num_buffers_written = length_sec * fps;
+
+        info!("#### Write video stream to Pravega");
+        let write_pipeline_description = |timestamp_offset: PravegaTimestamp| format!(
+            "videotestsrc name=src timestamp-offset={timestamp_offset} num-buffers={num_buffers} \
+            ! video/x-raw,width=100,height=100,framerate={fps}/1 \
+            ! pravegasink {pravega_plugin_properties} \
+                 transactional=true timestamp-mode=tai sync=false index-min-sec=1.0",
+            pravega_plugin_properties = test_config.pravega_plugin_properties(stream_name),
+            timestamp_offset = timestamp_offset.nanoseconds().unwrap(),
+            num_buffers = num_buffers_written,
+            fps = fps,
+        );
+        launch_pipeline(&write_pipeline_description(first_pts_written)).unwrap();
+
+        info!("#### Simulate a writer that failed before writing the index record of an incomplete group of pictures");
+        let num_unindexed_events = 3;
+        let unindexed_pts = first_pts_written + (length_sec + 1) * SECOND;
+        let unindexed_timestamps: Vec<_> = (0..num_unindexed_events)
+            .map(|i| unindexed_pts + i * 33 * MSECOND)
+            .collect();
+        append_unindexed_events(test_config.client_config.clone(), test_config.scope.clone(), stream_name.to_owned(),
+            &unindexed_timestamps);
+        // The events after the last index record are not covered by the index.
+        let report = verify_stream(test_config.client_config.clone(), test_config.scope.clone(), stream_name.to_owned());
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.events_checked, num_buffers_written);
+
+        info!("#### Restart the writer");
+        let second_pts_written = unindexed_pts + SECOND;
+        launch_pipeline(&write_pipeline_description(second_pts_written)).unwrap();
+
+        info!("#### Verify index and data stream");
+        let report = verify_stream(test_config.client_config.clone(), test_config.scope.clone(), stream_name.to_owned());
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.events_checked, 2 * num_buffers_written + num_unindexed_events);
+
+        info!("#### Read video stream from the unindexed events");
+        let pipeline_description = format!(
+            "pravegasrc {pravega_plugin_properties} \
+              start-mode=timestamp start-timestamp={start_timestamp} end-mode=timestamp end-timestamp={end_timestamp} \
+            ! appsink name=sink sync=false",
+            pravega_plugin_properties = test_config.pravega_plugin_properties(stream_name),
+            start_timestamp = unindexed_pts.nanoseconds().unwrap(),
+            end_timestamp = second_pts_written.nanoseconds().unwrap(),
+        );
+        let summary = launch_pipeline_and_get_summary(&pipeline_description).unwrap();
+        debug!("summary={:?}", summary);
+        assert_timestamp_eq("first_pts", summary.first_pts(), unindexed_pts);
+        assert_eq!(summary.num_buffers(), num_unindexed_events);
+
+        info!("#### END");
+    }
+
     #[test]
     fn test_mpeg_ts_video() {
         let test_config = get_test_config();
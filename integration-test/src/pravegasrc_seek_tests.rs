@@ -191,4 +191,238 @@ mod test {
         // Confirm there are no buffers that should have been skipped.
         assert_eq!(summary.buffers_between(seek_at_pts + 10 * SECOND, seek_to_pts - 10 * SECOND).len(), 0);
     }
+
+    /// Test trick-mode playback with GST_SEEK_FLAG_TRICKMODE_KEY_UNITS, forwards and in reverse.
+    /// Only the events of key frames should be read, in the direction of playback.
+    #[test]
+    fn test_pravegasrc_seek_trick_mode() {
+        let test_config = &get_test_config();
+        info!("test_config={:?}", test_config);
+        let stream_name = &format!("test-pravegasrc-{}-{}", test_config.test_id, Uuid::new_v4())[..];
+        let summary_written = pravegasrc_seek_test_data_gen(test_config, stream_name).unwrap();
+        debug!("summary_written={}", summary_written);
+        let first_pts_written = summary_written.first_valid_pts();
+        let last_pts_written = summary_written.last_valid_pts();
+        let mut key_frame_pts_written = summary_written.non_delta_pts();
+        key_frame_pts_written.dedup();
+        let num_key_frames_written = key_frame_pts_written.len() as u64;
+
+        for rate in [8.0, -8.0].iter() {
+            info!("#### Read video stream in trick mode with rate {}", rate);
+            let pipeline_description = format!("\
+                pravegasrc {pravega_plugin_properties} \
+                  start-mode=earliest \
+                ! appsink name=sink \
+                  sync=false",
+                pravega_plugin_properties = test_config.pravega_plugin_properties(stream_name),
+            );
+            info!("Launch Pipeline: {}", pipeline_description);
+            let pipeline = gst::parse_launch(&pipeline_description).unwrap();
+            let pipeline = pipeline.dynamic_cast::<gst::Pipeline>().unwrap();
+            let summary_list = Arc::new(Mutex::new(Vec::new()));
+            let summary_list_clone = summary_list.clone();
+            let sink = pipeline.by_name("sink").unwrap().downcast::<gst_app::AppSink>().unwrap();
+            sink.set_callbacks(
+                gst_app::AppSinkCallbacks::builder()
+                    .new_sample(move |sink| {
+                        let sample = sink.pull_sample().unwrap();
+                        let buffer = sample.buffer().unwrap();
+                        let summary = BufferSummary {
+                            pts: clocktime_to_pravega(buffer.pts()),
+                            size: buffer.size() as u64,
+                            offset: buffer.offset(),
+                            offset_end: buffer.offset_end(),
+                            flags: buffer.flags(),
+                        };
+                        summary_list_clone.lock().unwrap().push(summary);
+                        Ok(gst::FlowSuccess::Ok)
+                    })
+                    .build()
+            );
+
+            pipeline.set_state(gst::State::Paused).unwrap();
+            let _ = pipeline.state(gst::CLOCK_TIME_NONE);
+            summary_list.lock().unwrap().clear();
+            pipeline.seek(
+                *rate,
+                gst::SeekFlags::FLUSH | gst::SeekFlags::TRICKMODE_KEY_UNITS,
+                gst::SeekType::Set,
+                pravega_to_clocktime(first_pts_written),
+                gst::SeekType::Set,
+                pravega_to_clocktime(last_pts_written),
+            ).unwrap();
+            pipeline.set_state(gst::State::Playing).unwrap();
+            monitor_pipeline_until_eos(&pipeline).unwrap();
+            pipeline.set_state(gst::State::Null).unwrap();
+
+            let summary_list = summary_list.lock().unwrap().clone();
+            let summary = BufferListSummary {
+                buffer_summary_list: summary_list,
+            };
+            debug!("summary={}", summary);
+            let pts_read = summary.valid_pts();
+            // Timestamps must be in the direction of playback.
+            for pair in pts_read.windows(2) {
+                if *rate > 0.0 {
+                    assert!(pair[0] <= pair[1], "pts_read is not ascending: {:?}", pair);
+                } else {
+                    assert!(pair[0] >= pair[1], "pts_read is not descending: {:?}", pair);
+                }
+            }
+            // Each key frame should be read once.
+            let mut key_frame_pts = pts_read.clone();
+            key_frame_pts.dedup();
+            assert_between_u64("key frames read", key_frame_pts.len() as u64,
+                num_key_frames_written - 2, num_key_frames_written);
+            assert!(summary.num_buffers() < summary_written.num_buffers() / 2);
+        }
+    }
+
+    /// Test playing several time ranges back-to-back with the time-ranges property.
+    #[test]
+    fn test_pravegasrc_time_ranges() {
+        let test_config = &get_test_config();
+        info!("test_config={:?}", test_config);
+        let stream_name = &format!("test-pravegasrc-{}-{}", test_config.test_id, Uuid::new_v4())[..];
+        let summary_written = pravegasrc_seek_test_data_gen(test_config, stream_name).unwrap();
+        debug!("summary_written={}", summary_written);
+        let first_pts_written = summary_written.first_valid_pts();
+        let time_ranges = [(10, 15), (30, 32), (50, 55)].iter().map(|(begin, end)| {
+            (first_pts_written + *begin * SECOND, first_pts_written + *end * SECOND)
+        }).collect::<Vec<_>>();
+        let time_ranges_property = time_ranges.iter().map(|(begin, end)| {
+            format!("{}/{}", begin.to_iso_8601().unwrap(), end.to_iso_8601().unwrap())
+        }).collect::<Vec<_>>().join(",");
+        // The test data has 30 frames per second.
+        let frame_nanos = 1_000_000_000 / 30;
+        // Each clip begins at the running time at which the previous clip stops, which is the end of its time range,
+        // or slightly later if its last buffer has a duration that extends beyond the end.
+        let expected_clip_bases = time_ranges.iter().scan(0, |base, (begin, end)| {
+            let clip_base = *base;
+            *base += end.nanoseconds().unwrap() - begin.nanoseconds().unwrap();
+            Some(clip_base)
+        }).collect::<Vec<_>>();
+        info!("expected_clip_bases={:?}", expected_clip_bases);
+        info!("#### Read video stream");
+        let pipeline_description = format!(
+            "pravegasrc {pravega_plugin_properties} \
+              time-ranges={time_ranges} \
+            ! appsink name=sink sync=false",
+            pravega_plugin_properties = test_config.pravega_plugin_properties(stream_name),
+            time_ranges = time_ranges_property,
+        );
+        info!("Launch Pipeline: {}", pipeline_description);
+        let pipeline = gst::parse_launch(&pipeline_description).unwrap();
+        let pipeline = pipeline.dynamic_cast::<gst::Pipeline>().unwrap();
+        // The PTS, flags, and segment of each buffer.
+        let samples = Arc::new(Mutex::new(Vec::new()));
+        let samples_clone = samples.clone();
+        let sink = pipeline.by_name("sink").unwrap().downcast::<gst_app::AppSink>().unwrap();
+        sink.set_callbacks(
+            gst_app::AppSinkCallbacks::builder()
+                .new_sample(move |sink| {
+                    let sample = sink.pull_sample().unwrap();
+                    let buffer = sample.buffer().unwrap();
+                    let segment = sample.segment().unwrap().downcast_ref::<gst::format::Time>().unwrap().clone();
+                    samples_clone.lock().unwrap().push((buffer.pts(), buffer.flags(), segment));
+                    Ok(gst::FlowSuccess::Ok)
+                })
+                .build()
+        );
+        pipeline.set_state(gst::State::Playing).unwrap();
+        // The name, index, and running time of each clip message.
+        let mut clip_messages = Vec::new();
+        let bus = pipeline.bus().unwrap();
+        while let Some(msg) = bus.timed_pop(gst::CLOCK_TIME_NONE) {
+            match msg.view() {
+                gst::MessageView::Eos(..) => break,
+                gst::MessageView::Error(err) => {
+                    let _ = pipeline.set_state(gst::State::Null);
+                    panic!("Error from {:?}: {} ({:?})", err.src().map(|s| s.path_string()), err.error(), err.debug());
+                },
+                gst::MessageView::Element(element) => {
+                    if let Some(structure) = element.structure() {
+                        if structure.name().starts_with("application/x-pravegasrc-clip-") {
+                            debug!("structure={}", structure);
+                            clip_messages.push((
+                                structure.name().to_owned(),
+                                structure.get::<u64>("index").unwrap(),
+                                structure.get::<u64>("running-time").unwrap(),
+                            ));
+                        }
+                    }
+                },
+                _ => (),
+            }
+        }
+        pipeline.set_state(gst::State::Null).unwrap();
+        let samples = samples.lock().unwrap().clone();
+
+        // Each clip is reported by a start and an end message.
+        let expected_clip_messages = (0..time_ranges.len() as u64).flat_map(|index| vec![
+            ("application/x-pravegasrc-clip-start".to_owned(), index),
+            ("application/x-pravegasrc-clip-end".to_owned(), index),
+        ]).collect::<Vec<_>>();
+        assert_eq!(clip_messages.iter().map(|(name, index, _)| (name.clone(), *index)).collect::<Vec<_>>(), expected_clip_messages);
+        let clip_bases = clip_messages.iter().step_by(2).map(|(_, _, running_time)| *running_time).collect::<Vec<_>>();
+        for (index, clip_base) in clip_bases.iter().enumerate() {
+            assert_between_u64("clip_base", *clip_base, expected_clip_bases[index], expected_clip_bases[index] + index as u64 * frame_nanos);
+        }
+        for (_, index, running_time) in clip_messages.iter() {
+            assert_eq!(*running_time, clip_bases[*index as usize]);
+        }
+
+        // Each clip has its own segment, which begins at the time range and continues the running time.
+        let mut clip_samples: Vec<Vec<(gst::ClockTime, gst::BufferFlags, gst::FormattedSegment<gst::format::Time>)>> = Vec::new();
+        for (pts, flags, segment) in samples.iter() {
+            let same_segment = clip_samples.last().map_or(false, |clip| {
+                let (_, _, last_segment) = clip.last().unwrap();
+                last_segment.start() == segment.start() && last_segment.base() == segment.base()
+            });
+            if !same_segment {
+                clip_samples.push(Vec::new());
+            }
+            clip_samples.last_mut().unwrap().push((*pts, *flags, segment.clone()));
+        }
+        assert_eq!(clip_samples.len(), time_ranges.len());
+        let mut last_running_time = None;
+        for (index, clip) in clip_samples.iter().enumerate() {
+            let (begin, end) = time_ranges[index];
+            let (_, first_flags, segment) = &clip[0];
+            debug!("index={}, segment={:?}", index, segment);
+            assert_eq!(segment.start().nseconds(), begin.nanoseconds());
+            assert_eq!(segment.base().nseconds(), Some(clip_bases[index]));
+            // The first buffer of each clip is discontinuous.
+            assert!(first_flags.contains(gst::BufferFlags::DISCONT), "first buffer of clip {} is not DISCONT", index);
+            // Buffers before the beginning of the range are clipped by the segment.
+            let running_times = clip.iter()
+                .filter_map(|(pts, _, segment)| segment.to_running_time(*pts).nseconds())
+                .collect::<Vec<_>>();
+            let clip_duration = end.nanoseconds().unwrap() - begin.nanoseconds().unwrap();
+            for running_time in running_times.iter() {
+                assert_between_u64("running_time", *running_time, clip_bases[index], clip_bases[index] + clip_duration - 1);
+            }
+            // The running time continues from the previous clip without overlapping it.
+            // The gap is a few frames at most, because reordered frames may end a clip early.
+            let first_running_time = *running_times.iter().min().unwrap();
+            if let Some(last_running_time) = last_running_time {
+                assert_between_u64("first_running_time", first_running_time, last_running_time, last_running_time + 10 * frame_nanos);
+            } else {
+                assert_between_u64("first_running_time", first_running_time, 0, 10 * frame_nanos);
+            }
+            last_running_time = running_times.iter().max().copied();
+        }
+
+        let pts_read = samples.iter().map(|(pts, _, _)| clocktime_to_pravega(*pts)).filter(|pts| pts.is_some()).collect::<Vec<_>>();
+        // Each range begins at the key frame before it, at most 1 second earlier.
+        for pts in pts_read.iter() {
+            assert!(time_ranges.iter().any(|(begin, end)| *begin - SECOND <= *pts && *pts < *end),
+                "pts {} is not in any time range", pts);
+        }
+        for (begin, end) in time_ranges.iter() {
+            assert!(pts_read.iter().any(|pts| begin <= pts && pts < end), "no buffers in time range {}/{}", begin, end);
+        }
+        // Clips are played in order.
+        assert!(pts_read.windows(2).all(|pair| pair[0] <= pair[1] + SECOND));
+    }
 }
@@ -11,10 +11,11 @@
 #[cfg(test)]
 mod test {
     use anyhow::Error;
+    use gst::prelude::*;
     use pravega_video::timestamp::{PravegaTimestamp, MSECOND, NSECOND};
     use rstest::rstest;
     use std::convert::TryFrom;
-    use std::time::Instant;
+    use std::time::{Duration, Instant};
     #[allow(unused_imports)]
     use tracing::{error, info, debug, trace};
     use uuid::Uuid;
@@ -193,4 +194,95 @@ mod test {
         debug!("summary={}", summary);
         assert_eq!(summary.num_buffers(), 0);
     }
+
+    /// The reader starts 4 seconds behind the last index record, which exceeds max-lag-sec,
+    /// so it should jump to the last key frame.
+    #[test]
+    fn test_pravegasrc_max_lag() {
+        let test_config = &get_test_config();
+        info!("test_config={:?}", test_config);
+        let stream_name = &format!("test-pravegasrc-{}-{}", test_config.test_id, Uuid::new_v4())[..];
+        let summary_written = pravega_src_test_data_gen(test_config, stream_name).unwrap();
+        let last_key_frame_pts_written = *summary_written.non_delta_pts().last().unwrap();
+        info!("#### Read video stream");
+        let pipeline_description = format!(
+            "pravegasrc {pravega_plugin_properties} \
+              start-mode=earliest \
+              max-lag-sec=2.0 \
+            ! appsink name=sink sync=false",
+            pravega_plugin_properties = test_config.pravega_plugin_properties(stream_name),
+        );
+        let summary = launch_pipeline_and_get_summary(&pipeline_description).unwrap();
+        debug!("summary={}", summary);
+        assert_timestamp_eq("first_valid_pts", summary.first_valid_pts(), last_key_frame_pts_written);
+        assert!(summary.num_buffers_with_valid_pts() < summary_written.num_buffers_with_valid_pts() / 2);
+    }
+
+    /// Start reading before the stream is written to. The seek should wait for the first index record.
+    #[test]
+    fn test_pravegasrc_wait_for_index() {
+        let test_config = &get_test_config();
+        info!("test_config={:?}", test_config);
+        let stream_name = &format!("test-pravegasrc-{}-{}", test_config.test_id, Uuid::new_v4())[..];
+        gst_init();
+        let writer = {
+            let test_config = test_config.clone();
+            let stream_name = stream_name.to_owned();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_secs(5));
+                pravega_src_test_data_gen(&test_config, &stream_name).unwrap()
+            })
+        };
+        info!("#### Read video stream");
+        let pipeline_description = format!(
+            "pravegasrc {pravega_plugin_properties} \
+              start-mode=earliest \
+              wait-for-index-sec=60.0 \
+            ! appsink name=sink sync=false",
+            pravega_plugin_properties = test_config.pravega_plugin_properties(stream_name),
+        );
+        let summary = launch_pipeline_and_get_summary(&pipeline_description).unwrap();
+        debug!("summary={}", summary);
+        let summary_written = writer.join().unwrap();
+        assert_timestamp_eq("first_pts", summary.first_pts(), summary_written.first_valid_pts());
+        assert_eq!(summary.last_valid_pts(), summary_written.last_valid_pts());
+    }
+
+    /// Stop the pipeline while pravegasrc is waiting for the first index record of an empty stream.
+    /// The wait must not block the state changes.
+    #[test]
+    fn test_pravegasrc_wait_for_index_stop() {
+        let test_config = &get_test_config();
+        info!("test_config={:?}", test_config);
+        let stream_name = &format!("test-pravegasrc-{}-{}", test_config.test_id, Uuid::new_v4())[..];
+        gst_init();
+        let pipeline_description = format!(
+            "pravegasrc {pravega_plugin_properties} \
+              start-mode=earliest \
+              wait-for-index-sec=600.0 \
+            ! appsink name=sink sync=false",
+            pravega_plugin_properties = test_config.pravega_plugin_properties(stream_name),
+        );
+        info!("Launch Pipeline: {}", pipeline_description);
+        let pipeline = gst::parse_launch(&pipeline_description).unwrap();
+        let pipeline = pipeline.dynamic_cast::<gst::Pipeline>().unwrap();
+        let start_time = Instant::now();
+        // The state change completes asynchronously when the first buffer is read.
+        assert_eq!(pipeline.set_state(gst::State::Playing).unwrap(), gst::StateChangeSuccess::Async);
+        std::thread::sleep(Duration::from_secs(3));
+        let (result, current_state, pending_state) = pipeline.state(gst::ClockTime::from_seconds(0));
+        info!("result={:?}, current_state={:?}, pending_state={:?}", result, current_state, pending_state);
+        assert_eq!(result, Ok(gst::StateChangeSuccess::Async));
+        info!("### Stop pipeline");
+        pipeline.set_state(gst::State::Null).unwrap();
+        let elapsed = start_time.elapsed();
+        info!("elapsed={:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(60));
+        let bus = pipeline.bus().unwrap();
+        while let Some(msg) = bus.pop() {
+            if let gst::MessageView::Error(err) = msg.view() {
+                panic!("Error from {:?}: {} ({:?})", err.src().map(|s| s.path_string()), err.error(), err.debug());
+            }
+        }
+    }
 }
@@ -16,10 +16,14 @@ use gst::prelude::*;
 use gstpravega::utils::clocktime_to_pravega;
 use pravega_client_config::ClientConfig;
 use pravega_client::client_factory::ClientFactory;
-use pravega_client_shared::{Scope, Stream, Segment, ScopedSegment};
+use pravega_client_shared::{Scope, Stream, ScopedStream};
+use pravega_video::event_serde::{EventWithHeader, EventWriter};
 use pravega_video::index::{IndexSearcher, SearchMethod, get_index_stream_name};
 use pravega_video::timestamp::{PravegaTimestamp, TimeDelta};
+use pravega_video::utils::get_byte_stream_segment;
+use pravega_video::verifier::{StreamVerifier, VerificationReport};
 use std::fmt;
+use std::io::Write;
 use std::sync::{Arc, Mutex};
 #[allow(unused_imports)]
 use tracing::{error, warn, info, debug, trace};
@@ -322,17 +326,15 @@ pub fn truncate_stream(client_config: ClientConfig, scope_name: String, stream_n
     let index_stream = Stream::from(index_stream_name);
     let client_factory = ClientFactory::new(client_config);
     let runtime = client_factory.get_runtime();
-    let scoped_segment = ScopedSegment {
+    let scoped_segment = get_byte_stream_segment(&client_factory, &ScopedStream {
         scope: scope.clone(),
         stream: stream.clone(),
-        segment: Segment::from(0),
-    };
+    }).unwrap();
     let writer = client_factory.create_byte_stream_writer(scoped_segment);
-    let index_scoped_segment = ScopedSegment {
+    let index_scoped_segment = get_byte_stream_segment(&client_factory, &ScopedStream {
         scope: scope.clone(),
         stream: index_stream.clone(),
-        segment: Segment::from(0),
-    };
+    }).unwrap();
     let index_writer = client_factory.create_byte_stream_writer(index_scoped_segment.clone());
     let index_reader = client_factory.create_byte_stream_reader(index_scoped_segment.clone());
     let mut index_searcher = IndexSearcher::new(index_reader);
@@ -344,3 +346,47 @@ pub fn truncate_stream(client_config: ClientConfig, scope_name: String, stream_n
     runtime.block_on(writer.truncate_data_before(index_record.0.offset as i64)).unwrap();
     info!("Data truncated at offset {}", index_record.0.offset);
 }
+
+/// Simulate a writer that failed after writing events to the data stream but before writing their index records.
+/// The first event is a random-access event that would have been indexed.
+/// The remaining events are delta units, so the data stream ends with an incomplete group of pictures.
+pub fn append_unindexed_events(client_config: ClientConfig, scope_name: String, stream_name: String, timestamps: &[PravegaTimestamp]) {
+    info!("Appending {} unindexed events to stream {}/{}", timestamps.len(), scope_name, stream_name);
+    let client_factory = ClientFactory::new(client_config);
+    let scoped_segment = get_byte_stream_segment(&client_factory, &ScopedStream {
+        scope: Scope::from(scope_name),
+        stream: Stream::from(stream_name),
+    }).unwrap();
+    let mut writer = client_factory.create_byte_stream_writer(scoped_segment);
+    let payload = vec![0u8; 1000];
+    for (i, timestamp) in timestamps.iter().enumerate() {
+        let first = i == 0;
+        let event = EventWithHeader::new(&payload[..], *timestamp, first, first, false);
+        EventWriter::with_checksum(false).write(&event, &mut writer).unwrap();
+    }
+    writer.flush().unwrap();
+}
+
+/// Verify that the index and data stream satisfy the constraints defined in index.rs.
+pub fn verify_stream(client_config: ClientConfig, scope_name: String, stream_name: String) -> VerificationReport {
+    info!("Verifying stream {}/{}", scope_name, stream_name);
+    let index_stream_name = get_index_stream_name(&stream_name);
+    let scope = Scope::from(scope_name);
+    let stream = Stream::from(stream_name);
+    let index_stream = Stream::from(index_stream_name);
+    let client_factory = ClientFactory::new(client_config);
+    let scoped_segment = get_byte_stream_segment(&client_factory, &ScopedStream {
+        scope: scope.clone(),
+        stream: stream.clone(),
+    }).unwrap();
+    let index_scoped_segment = get_byte_stream_segment(&client_factory, &ScopedStream {
+        scope: scope.clone(),
+        stream: index_stream.clone(),
+    }).unwrap();
+    let reader = client_factory.create_byte_stream_reader(scoped_segment);
+    let index_reader = client_factory.create_byte_stream_reader(index_scoped_segment);
+    let mut verifier = StreamVerifier::new(reader, index_reader);
+    let report = verifier.verify().unwrap();
+    info!("Verification report:\n{}", report);
+    report
+}
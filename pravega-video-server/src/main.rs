@@ -78,6 +78,7 @@ mod filters {
     }
 
     /// GET /scopes/my_scope/streams/my_stream/ts?begin=0&end=204
+    /// For a partitioned stream, add partition=3 to read from the partition with sequence number 3.
     pub fn get_mpeg_transport_stream(
         db: Db,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
@@ -89,6 +90,7 @@ mod filters {
     }
 
     /// GET /scopes/my_scope/streams/my_stream/m3u8?begin=2021-04-19T00:00:00Z&end=2021-04-20T00:00:00Z
+    /// Add random_access_only=true to only begin segments at random-access points (key frames).
     pub fn get_m3u8_playlist(
         db: Db,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
@@ -171,8 +173,7 @@ mod handlers {
         opts: GetM3u8PlaylistOptions,
         db: Db,
     ) -> Result<impl warp::Reply, Infallible> {
-        let playlist = db.get_m3u8_playlist(scope_name, stream_name, opts).await.unwrap();
-        Ok(warp::reply::with_header(playlist, "content-type", "application/x-mpegURL"))
+        db.get_m3u8_playlist(scope_name, stream_name, opts).await
     }
 
     pub async fn list_video_streams(
@@ -190,23 +191,60 @@ mod models {
     use chrono::{DateTime, Utc};
     use futures::{StreamExt, future};
     use hyper::body::{Body, Bytes};
+    use pravega_client::byte_stream::ByteStreamReader;
     use pravega_client::client_factory::ClientFactory;
-    use pravega_client_shared::{Scope, ScopedSegment, Segment, Stream};
+    use pravega_client_shared::{Scope, ScopedSegment, ScopedStream, Stream};
     use pravega_controller_client::paginator::list_streams;
-    use pravega_video::{event_serde::{EventReader}, index::IndexSearcher};
-    use pravega_video::index::{IndexRecord, IndexRecordReader, SearchMethod, get_index_stream_name};
+    use pravega_video::catalog::{PartitionRecord, find_partition};
+    use pravega_video::event_serde::{EventReader, ReadEventError};
+    use pravega_video::index::{AsyncIndexRecordReader, IndexRecord, SearchFilter, SearchMethod, get_index_stream_name};
+    use pravega_video::index_cache::IndexCache;
     use pravega_video::timestamp::PravegaTimestamp;
+    use pravega_video::utils::{get_byte_stream_segment, get_partition_segments, read_catalog};
     use serde_derive::{Deserialize, Serialize};
+    use std::collections::HashMap;
     use std::convert::Infallible;
+    use std::fmt;
     use std::io::{ErrorKind, Read, Seek, SeekFrom};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use warp::Reply;
+    use warp::http::StatusCode;
+
+    /// An index cache that has not been used for this long will be dropped.
+    const INDEX_CACHE_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+    /// The maximum number of index caches. When exceeded, the least recently used cache will be dropped.
+    /// Each partition of a partitioned stream has its own index cache.
+    const MAX_INDEX_CACHES: usize = 100;
 
     #[derive(Clone)]
     pub struct Db {
         pub client_factory: ClientFactory,
+        /// In-memory copies of index streams, shared by all HTTP clients.
+        /// There is no background refresh. A cache is updated only when a playlist request uses it,
+        /// so each request reads just the index records appended since the previous request for the same stream.
+        index_caches: Arc<Mutex<HashMap<ScopedStream, CachedIndex>>>,
+    }
+
+    struct CachedIndex {
+        index_cache: Arc<IndexCache<ByteStreamReader>>,
+        last_used: Instant,
+    }
+
+    /// A partition whose index cache has been updated for a playlist request.
+    struct PlaylistPartition {
+        scoped_stream: ScopedStream,
+        index_cache: Arc<IndexCache<ByteStreamReader>>,
+        ts_url_prefix: String,
+        media_sequence_number_base: u64,
+        sealed: bool,
     }
 
     pub fn new(client_factory: ClientFactory) -> Db {
-        Db { client_factory }
+        Db {
+            client_factory,
+            index_caches: Arc::new(Mutex::new(HashMap::new())),
+        }
     }
 
     // The query parameters for get_mpeg_transport_stream.
@@ -216,6 +254,8 @@ mod models {
         pub begin: u64,
         /// End byte offset (exclusive)
         pub end: u64,
+        /// Sequence number of the partition, if the stream is partitioned
+        pub partition: Option<u64>,
     }
 
     // The query parameters for get_m3u8_playlist.
@@ -223,6 +263,295 @@ mod models {
     pub struct GetM3u8PlaylistOptions {
         pub begin: Option<DateTime<Utc>>,
         pub end: Option<DateTime<Utc>>,
+        /// If true, segments will only begin at index records with random_access=true.
+        /// Index records that were forced at delta units will be merged into the previous segment.
+        pub random_access_only: Option<bool>,
+    }
+
+    /// The requested byte range begins before the head of the data stream, which has been truncated.
+    #[derive(Debug)]
+    struct TruncatedError {
+        begin: u64,
+        head_offset: u64,
+    }
+
+    impl fmt::Display for TruncatedError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "Offset {} has been truncated; the stream begins at offset {}", self.begin, self.head_offset)
+        }
+    }
+
+    impl std::error::Error for TruncatedError {}
+
+    fn truncated_error(begin: u64, head_offset: u64) -> std::io::Error {
+        std::io::Error::new(ErrorKind::Other, TruncatedError { begin, head_offset })
+    }
+
+    /// The requested time range ends before the first index record, which remains after the index has been truncated.
+    #[derive(Debug)]
+    struct TruncatedTimeRangeError {
+        end: PravegaTimestamp,
+        first_timestamp: PravegaTimestamp,
+    }
+
+    impl fmt::Display for TruncatedTimeRangeError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "End {} has been truncated; the stream begins at {}", self.end, self.first_timestamp)
+        }
+    }
+
+    impl std::error::Error for TruncatedTimeRangeError {}
+
+    fn is_truncated_error(err: &std::io::Error) -> bool {
+        err.get_ref().map_or(false, |err| err.is::<TruncatedError>() || err.is::<TruncatedTimeRangeError>())
+    }
+
+    /// An empty index cannot be searched, so the stream is reported as not found.
+    fn index_search_error(err: std::io::Error, scoped_stream: &ScopedStream) -> std::io::Error {
+        if err.kind() == ErrorKind::UnexpectedEof {
+            std::io::Error::new(ErrorKind::NotFound, format!("{:?} has no index records", scoped_stream))
+        } else {
+            err
+        }
+    }
+
+    /// If a read error occurred because the data at begin was truncated while reading, returns a TruncatedError.
+    fn check_truncated(err: std::io::Error, reader: &std::io::Take<ByteStreamReader>, begin: u64) -> std::io::Error {
+        match reader.get_ref().current_head() {
+            Ok(head_offset) if begin < head_offset => truncated_error(begin, head_offset),
+            _ => err,
+        }
+    }
+
+    /// Drops index caches that have been idle for INDEX_CACHE_IDLE_TIMEOUT
+    /// and then the least recently used caches until there is room for a new one.
+    /// Requests that are using a dropped cache can continue to use it.
+    fn evict_index_caches(index_caches: &mut HashMap<ScopedStream, CachedIndex>, now: Instant) {
+        index_caches.retain(|scoped_stream, cached_index| {
+            let keep = now.duration_since(cached_index.last_used) < INDEX_CACHE_IDLE_TIMEOUT;
+            if !keep {
+                tracing::info!("Dropping idle index cache of {:?}", scoped_stream);
+            }
+            keep
+        });
+        while index_caches.len() >= MAX_INDEX_CACHES {
+            let oldest = index_caches.iter()
+                .min_by_key(|(_, cached_index)| cached_index.last_used)
+                .map(|(scoped_stream, _)| scoped_stream.clone());
+            if let Some(scoped_stream) = oldest {
+                tracing::info!("Dropping least recently used index cache of {:?}", scoped_stream);
+                index_caches.remove(&scoped_stream);
+            }
+        }
+    }
+
+    /// Generates an HLS playlist from the index records of each partition that overlap the requested time range.
+    /// The index caches must have been updated, so this does not block.
+    async fn get_m3u8_playlist_body(
+        partitions: Vec<PlaylistPartition>,
+        begin_timestamp: PravegaTimestamp,
+        end_timestamp: PravegaTimestamp,
+        random_access_only: bool,
+    ) -> Result<String, std::io::Error> {
+        let search_filter = if random_access_only { SearchFilter::RandomAccessOnly } else { SearchFilter::All };
+        // Initial value for target duration. This will be updated with an exponential moving average, then rounded.
+        let mut target_duration_seconds = 10.0;
+
+        let mut playlist_body = String::new();
+        // The longest segment duration. Only used when random_access_only is true.
+        let mut max_duration_seconds: f64 = 0.0;
+        let mut next_segment_discont = false;
+        let mut initial_media_sequence_number: u64 = 0;
+        let mut have_all_data = false;
+
+        for (partition_index, partition) in partitions.iter().enumerate() {
+            let is_first_partition = partition_index == 0;
+            let is_last_partition = partition_index + 1 == partitions.len();
+            let PlaylistPartition { scoped_stream, index_cache, ts_url_prefix, media_sequence_number_base, sealed } = partition;
+            let (media_sequence_number_base, sealed) = (*media_sequence_number_base, *sealed);
+            let partition_begin_timestamp = if is_first_partition { begin_timestamp } else { PravegaTimestamp::MIN };
+            let partition_end_timestamp = if is_last_partition { end_timestamp } else { PravegaTimestamp::MAX };
+
+            let index_head_offset = index_cache.snapshot().head_offset();
+            let mut index_searcher = index_cache.async_searcher();
+            let begin_index_record = index_searcher.search_timestamp_and_return_index_offset_with_filter(
+                partition_begin_timestamp, SearchMethod::After, search_filter).await
+                .map_err(|err| index_search_error(err, scoped_stream))?;
+            let end_index_record = index_searcher.search_timestamp_and_return_index_offset(
+                partition_end_timestamp, SearchMethod::After).await
+                .map_err(|err| index_search_error(err, scoped_stream))?;
+            // The requested time range may have been truncated by retention or truncate-stream.
+            if index_head_offset > 0 && end_index_record.1 <= index_head_offset
+                    && partition_end_timestamp < end_index_record.0.timestamp {
+                return Err(std::io::Error::new(ErrorKind::Other, TruncatedTimeRangeError {
+                    end: partition_end_timestamp,
+                    first_timestamp: end_index_record.0.timestamp,
+                }));
+            }
+            // Determine whether we can possibly get more data in the future.
+            // If the caller specified an end time and we already have an index record beyond this, then
+            // future appends will not affect our result.
+            // TODO: We can also guarantee this if the stream has been sealed.
+            let partition_have_all_data = end_index_record.0.timestamp >= partition_end_timestamp || sealed;
+            tracing::info!("begin_index_record={:?}, end_index_record={:?}, have_all_data={}",
+                    begin_index_record, end_index_record, partition_have_all_data);
+            let mut index_reader = index_searcher.into_inner();
+
+            // Determine begin and end offsets of the index.
+            let index_begin_offset = begin_index_record.1;
+            let index_end_offset = std::cmp::max(index_begin_offset, end_index_record.1 + IndexRecord::RECORD_SIZE as u64);
+            let index_size = index_end_offset - index_begin_offset;
+            tracing::info!("index_begin_offset={}, index_end_offset={}, index_size={}", index_begin_offset, index_end_offset, index_size);
+
+            // Position index reader at current beginning of the index.
+            tokio::io::AsyncSeekExt::seek(&mut index_reader, SeekFrom::Start(index_begin_offset)).await?;
+
+            // Ensure EOF instead of waiting (potentially forever) for appends when we get to the current end.
+            let mut index_reader = tokio::io::AsyncReadExt::take(index_reader, index_size);
+
+            if is_first_partition {
+                // Media Sequence Number will always equal the index record number, even after truncation.
+                initial_media_sequence_number = media_sequence_number_base + index_begin_offset / IndexRecord::RECORD_SIZE as u64;
+                tracing::info!("initial_media_sequence_number={}", initial_media_sequence_number);
+            }
+            if is_last_partition {
+                have_all_data = partition_have_all_data;
+            }
+
+            // The index record at the beginning of the current segment.
+            let mut prev_index_record: Option<IndexRecord> = None;
+            // The index record read immediately before the current one.
+            // This differs from prev_index_record only when random_access_only is true.
+            let mut last_index_record: Option<IndexRecord> = None;
+
+            loop {
+                let mut index_record_reader = AsyncIndexRecordReader::new();
+                let index_record = match index_record_reader.read(&mut index_reader).await {
+                    Ok(n) => n,
+                    Err(e) if e.kind() == ErrorKind::UnexpectedEof && index_reader.limit() == 0 => {
+                        tracing::trace!("Reached requested end");
+                        break;
+                    },
+                    Err(e) => return Err(e),
+                };
+                tracing::trace!("index_record={:?}", index_record);
+                let prev_record_timestamp = last_index_record.map(|r| r.timestamp);
+                last_index_record = Some(index_record);
+                if random_access_only && prev_index_record.is_some()
+                        && !index_record.random_access && !index_record.discontinuity
+                        && !(partition_have_all_data && index_reader.limit() == 0) {
+                    // Segments must begin at a random-access record, so this record will be included in the current segment.
+                    // The final record will end the last segment only if no more data can be appended.
+                    tracing::trace!("Skipping index record that is not a random-access point: {:?}", index_record);
+                    continue;
+                }
+                if let Some(prev_index_record) = prev_index_record {
+                    // If index_record indicates a discontinuity, then assume there is a gap in the data
+                    // between the previous record and this one.
+                    // Any recorded content that falls in this gap may be corrupt so we will not display it.
+                    // Instead, we'll play a short transport stream containing blue video and silent audio.
+                    // The length of this replacement content will be fixed, regardless of the timestamps.
+                    // The EXT-X-GAP tag should be used for this but it doesn't appear to be supported by hls.js.
+                    // It is possible that the duration of the gap in the index is very short or even 0.
+                    // However, we still need to count the gap so that the Media Sequence Numbers
+                    // correspond to the index offset.
+
+                    let mut discont = index_record.discontinuity;
+                    if discont {
+                        tracing::warn!("Detected discontinuity; discontinuity flag set in {:?}", index_record);
+                    } else {
+                        if let Some(timestamp_nanos) = index_record.timestamp.nanoseconds() {
+                            let prev_timestamp_nanos = prev_index_record.timestamp.nanoseconds().ok_or_else(|| {
+                                std::io::Error::new(ErrorKind::InvalidData,
+                                    format!("Missing timestamp in index at offset {}", prev_index_record.offset))
+                            })?;
+                            let prev_record_timestamp_nanos = prev_record_timestamp.and_then(|t| t.nanoseconds()).unwrap_or(prev_timestamp_nanos);
+                            if timestamp_nanos < prev_timestamp_nanos || timestamp_nanos < prev_record_timestamp_nanos {
+                                let rewind_seconds = (prev_record_timestamp_nanos.max(prev_timestamp_nanos) - timestamp_nanos) as f64 * 1e-9;
+                                tracing::warn!("Detected discontinuity; rewind of {:.3} seconds from {} to {}",
+                                rewind_seconds, prev_index_record.timestamp, index_record.timestamp);
+                                discont = true;
+                            } else {
+                                let duration_seconds = (timestamp_nanos - prev_timestamp_nanos) as f64 * 1e-9;
+                                let gap_seconds = (timestamp_nanos - prev_record_timestamp_nanos) as f64 * 1e-9;
+                                // If the timestamp increased by much more than the target duration,
+                                // then assume we have a discontinuity.
+                                if gap_seconds > target_duration_seconds + 1.0 {
+                                    tracing::warn!("Detected discontinuity; {:.3} second gap from {} to {}, target_duration_seconds={:.3}",
+                                        gap_seconds, prev_index_record.timestamp, index_record.timestamp, target_duration_seconds);
+                                    discont = true;
+                                } else {
+                                    if next_segment_discont {
+                                        playlist_body.push_str("#EXT-X-DISCONTINUITY\n");
+                                        next_segment_discont = false;
+                                    }
+                                    let ema_alpha = 0.1;
+                                    target_duration_seconds = ema_alpha * gap_seconds + (1.0 - ema_alpha) * target_duration_seconds;
+                                    max_duration_seconds = max_duration_seconds.max(duration_seconds);
+                                    let begin_offset = prev_index_record.offset;
+                                    let end_offset = index_record.offset;
+                                    // "#EXTINF:10," where 10 is the duration of the segment in seconds
+                                    playlist_body.push_str(&format!("#EXTINF:{},\n", duration_seconds));
+                                    // "#EXT-X-PROGRAM-DATE-TIME:2010-02-19T14:54:23.123456789Z"
+                                    let program_date_time = prev_index_record.timestamp.to_iso_8601().ok_or_else(|| {
+                                        std::io::Error::new(ErrorKind::InvalidData,
+                                            format!("Unable to format timestamp {} in index at offset {}",
+                                                prev_index_record.timestamp, prev_index_record.offset))
+                                    })?;
+                                    playlist_body.push_str(&format!("#EXT-X-PROGRAM-DATE-TIME:{}\n", program_date_time));
+                                    // "ts?begin=0&end=204" where 0 and 204 are the begin and end byte offsets
+                                    playlist_body.push_str(&format!("{}begin={}&end={}\n", ts_url_prefix, begin_offset, end_offset));
+                                }
+                            }
+                        } else {
+                            tracing::warn!("Detected discontinuity; missing timestamp in index at offset {}",
+                                index_record.offset);
+                            discont = true;
+                        }
+                    }
+                    if discont {
+                        // tracing::warn!("Detected discontinuity; index_record={:?}", index_record);
+                        let gap_content_duration_seconds = 5;
+                        playlist_body.push_str("#EXT-X-DISCONTINUITY\n");
+                        playlist_body.push_str(&format!("#EXTINF:{},\n", gap_content_duration_seconds));
+                        playlist_body.push_str(&format!("/static/gap-{}s.ts\n", gap_content_duration_seconds));
+                        next_segment_discont = true;
+                    }
+                }
+                if random_access_only && !index_record.random_access {
+                    // This record has a discontinuity but it is not a random-access point.
+                    // The next segment will begin at the next random-access record.
+                    prev_index_record = None;
+                } else {
+                    prev_index_record = Some(index_record);
+                }
+            }
+        }
+
+        let mut playlist = String::new();
+        // Segments may be longer than the index interval when they have been merged.
+        let target_duration_seconds = if random_access_only {
+            target_duration_seconds.max(max_duration_seconds)
+        } else {
+            target_duration_seconds
+        };
+        let target_duration_seconds = target_duration_seconds.round();
+        tracing::info!("target_duration_seconds={}", target_duration_seconds);
+        playlist.push_str("#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-ALLOW-CACHE:NO\n");
+        playlist.push_str(&format!("#EXT-X-MEDIA-SEQUENCE:{}\n", initial_media_sequence_number));
+        playlist.push_str(&format!("#EXT-X-TARGETDURATION:{}\n", target_duration_seconds));
+        playlist.push_str(&playlist_body);
+
+        // Write ENDLIST if we have all data up to the requested end time.
+        // This will prevent the browser from polling for updated playlists.
+        if have_all_data {
+            playlist.push_str("#EXT-X-ENDLIST\n");
+        }
+        Ok(playlist)
+    }
+
+    fn error_response(status: StatusCode, message: String) -> warp::reply::Response {
+        warp::reply::with_status(message, status).into_response()
     }
 
     #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -239,14 +568,50 @@ mod models {
     }
 
     impl Db {
+        /// Returns the cache for an index stream, opening the index stream if this is the first request for it.
+        /// The caller must call update() on the returned cache to read any new index records.
+        /// The segment of a partition's index stream must be provided because it may be sealed.
+        /// This blocks, so it must be called from spawn_blocking.
+        fn get_index_cache(&self, scoped_stream: &ScopedStream, scoped_segment: Option<ScopedSegment>)
+                -> Result<Arc<IndexCache<ByteStreamReader>>, std::io::Error> {
+            {
+                let mut index_caches = self.index_caches.lock().unwrap();
+                if let Some(cached_index) = index_caches.get_mut(scoped_stream) {
+                    cached_index.last_used = Instant::now();
+                    return Ok(cached_index.index_cache.clone());
+                }
+            }
+            // Open the index stream without holding the lock, so that requests for other streams are not blocked.
+            let scoped_segment = match scoped_segment {
+                Some(scoped_segment) => scoped_segment,
+                None => get_byte_stream_segment(&self.client_factory, scoped_stream)
+                    .map_err(|err| std::io::Error::new(ErrorKind::NotFound, err.to_string()))?,
+            };
+            let index_reader = self.client_factory.create_byte_stream_reader(scoped_segment);
+            tracing::info!("Opened Pravega reader for index cache of {:?}", scoped_stream);
+            let mut index_caches = self.index_caches.lock().unwrap();
+            let now = Instant::now();
+            evict_index_caches(&mut index_caches, now);
+            // If another request opened the same index stream concurrently, its cache is used instead.
+            let cached_index = index_caches.entry(scoped_stream.clone()).or_insert_with(|| CachedIndex {
+                index_cache: Arc::new(IndexCache::new(index_reader)),
+                last_used: now,
+            });
+            cached_index.last_used = now;
+            Ok(cached_index.index_cache.clone())
+        }
+
         pub async fn get_mpeg_transport_stream(
             self,
             scope_name: String,
             stream_name: String,
             opts: GetMpegTransportStreamOptions,
-        ) -> Result<impl warp::Reply, Infallible> {
-            tracing::info!("scope_name={}, stream_name={}, begin={}, end={}", scope_name, stream_name, opts.begin, opts.end);
-            assert!(opts.begin <= opts.end);
+        ) -> Result<warp::reply::Response, Infallible> {
+            tracing::info!("scope_name={}, stream_name={}, begin={}, end={}, partition={:?}",
+                scope_name, stream_name, opts.begin, opts.end, opts.partition);
+            if opts.begin > opts.end {
+                return Ok(error_response(StatusCode::BAD_REQUEST, format!("begin {} is after end {}", opts.begin, opts.end)));
+            }
 
             // TODO: Provide chunks to the HTTP client as a stream instead of buffering the entire response.
 
@@ -255,15 +620,33 @@ mod models {
 
             let chunks = tokio::task::spawn_blocking(move || {
                 let client_factory = self.client_factory;
-                let scoped_segment = ScopedSegment {
-                    scope: Scope::from(scope_name),
-                    stream: Stream::from(stream_name),
-                    segment: Segment::from(0),
+                let scope = Scope::from(scope_name);
+                let scoped_segment = match opts.partition {
+                    Some(sequence) => {
+                        let records = read_catalog(&client_factory, &scope, &stream_name)
+                            .map_err(|err| std::io::Error::new(ErrorKind::NotFound, err.to_string()))?;
+                        let partition = records.iter().find(|record| record.sequence == sequence)
+                            .ok_or_else(|| std::io::Error::new(ErrorKind::NotFound, format!("Partition {} not found", sequence)))?;
+                        get_partition_segments(&scope, partition).0
+                    },
+                    None => {
+                        let scoped_stream = ScopedStream {
+                            scope,
+                            stream: Stream::from(stream_name),
+                        };
+                        get_byte_stream_segment(&client_factory, &scoped_stream)
+                            .map_err(|err| std::io::Error::new(ErrorKind::NotFound, err.to_string()))?
+                    },
                 };
                 let mut reader = client_factory.create_byte_stream_reader(scoped_segment);
                 tracing::info!("Opened Pravega reader");
 
-                reader.seek(SeekFrom::Start(opts.begin)).unwrap();
+                // The range may have been truncated by retention or truncate-stream.
+                let head_offset = reader.current_head()?;
+                if opts.begin < head_offset {
+                    return Err(truncated_error(opts.begin, head_offset));
+                }
+                reader.seek(SeekFrom::Start(opts.begin))?;
                 let limit = opts.end - opts.begin;
                 let mut reader = reader.take(limit);
 
@@ -278,7 +661,7 @@ mod models {
                                 tracing::trace!("Reached requested end");
                                 break;
                             },
-                            Err(e) => return Err(e),
+                            Err(e) => return Err(check_truncated(e, &reader, opts.begin)),
                     };
                     let mut read_buffer: Vec<u8> = vec![0; required_buffer_length];
                     let event = match event_reader.read_event(&mut reader, &mut read_buffer[..]) {
@@ -287,7 +670,11 @@ mod models {
                             tracing::trace!("Reached requested end");
                             break;
                         },
-                        Err(e) => return Err(e),
+                        Err(e @ ReadEventError::ChecksumMismatch { .. }) => {
+                            tracing::warn!("Skipping corrupt event: {}", e);
+                            continue;
+                        },
+                        Err(e) => return Err(check_truncated(e.into(), &reader, opts.begin)),
                     };
                     tracing::trace!("get_mpeg_transport_stream: event={:?}", event);
                     chunks.push(Ok(Bytes::copy_from_slice(&event.payload)));
@@ -296,14 +683,31 @@ mod models {
                 assert!(reader.limit() == 0);
                 Ok(chunks)
             })
-            .await
-            .unwrap()
-            .unwrap();
+            .await;
 
             tracing::trace!("spawn_blocking done");
+            let chunks = match chunks {
+                Ok(Ok(chunks)) => chunks,
+                Ok(Err(err)) if is_truncated_error(&err) => {
+                    tracing::info!("get_mpeg_transport_stream: {}", err);
+                    return Ok(error_response(StatusCode::GONE, err.to_string()));
+                },
+                Ok(Err(err)) if err.kind() == ErrorKind::NotFound => {
+                    tracing::info!("get_mpeg_transport_stream: {}", err);
+                    return Ok(error_response(StatusCode::NOT_FOUND, err.to_string()));
+                },
+                Ok(Err(err)) => {
+                    tracing::error!("get_mpeg_transport_stream: {}", err);
+                    return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()));
+                },
+                Err(err) => {
+                    tracing::error!("get_mpeg_transport_stream: {}", err);
+                    return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()));
+                },
+            };
             let stream = futures_util::stream::iter(chunks);
             let body = Body::wrap_stream(stream);
-            Ok(warp::reply::with_header(warp::reply::Response::new(body), "content-type", "video/MP2T"))
+            Ok(warp::reply::with_header(warp::reply::Response::new(body), "content-type", "video/MP2T").into_response())
         }
 
         pub async fn get_m3u8_playlist(
@@ -311,160 +715,108 @@ mod models {
             scope_name: String,
             stream_name: String,
             opts: GetM3u8PlaylistOptions,
-        ) -> anyhow::Result<String> {
-            tracing::info!("scope_name={}, stream_name={}, begin={:?}, end={:?}", scope_name, stream_name, opts.begin, opts.end);
+        ) -> Result<warp::reply::Response, Infallible> {
+            tracing::info!("scope_name={}, stream_name={}, begin={:?}, end={:?}, random_access_only={:?}",
+                scope_name, stream_name, opts.begin, opts.end, opts.random_access_only);
 
+            let random_access_only = opts.random_access_only.unwrap_or_default();
             let index_stream_name = get_index_stream_name(&stream_name);
             let begin_timestamp = PravegaTimestamp::from(opts.begin).or(PravegaTimestamp::MIN);
             let end_timestamp = PravegaTimestamp::from(opts.end).or(PravegaTimestamp::MAX);
             tracing::info!("begin_timestamp={}, end_timestamp={}", begin_timestamp, end_timestamp);
-            assert!(begin_timestamp <= end_timestamp);
+            if begin_timestamp > end_timestamp {
+                return Ok(error_response(StatusCode::BAD_REQUEST, format!("begin {} is after end {}", begin_timestamp, end_timestamp)));
+            }
 
             // Use spawn_blocking to allow Pravega non-async methods to block this thread.
             // See https://stackoverflow.com/a/65452213/5890553.
-
-            let playlist = tokio::task::spawn_blocking(move || {
-                let client_factory = self.client_factory;
-                let scoped_segment = ScopedSegment {
-                    scope: Scope::from(scope_name),
-                    stream: Stream::from(index_stream_name),
-                    segment: Segment::from(0),
+            // Only reading the catalog and the new index records blocks.
+            // The index is searched and read by the async searcher in the in-memory index cache.
+
+            let partitions = tokio::task::spawn_blocking(move || {
+                let scope = Scope::from(scope_name);
+
+                // A partitioned stream (see pravega_video::catalog) has a catalog stream instead of a data stream.
+                // The playlist will contain the segments of each partition that overlaps the requested time range.
+                // A stream that is not partitioned is handled as a single partition, represented by None.
+                let records = read_catalog(&self.client_factory, &scope, &stream_name).unwrap_or_default();
+                let partitions: Vec<Option<PartitionRecord>> = if records.is_empty() {
+                    vec![None]
+                } else {
+                    let first = find_partition(&records, begin_timestamp);
+                    let last = find_partition(&records, end_timestamp);
+                    records[first..=last].iter().cloned().map(Some).collect()
                 };
-                let index_reader = client_factory.create_byte_stream_reader(scoped_segment);
-                tracing::info!("Opened Pravega reader");
-
-                let mut index_searcher = IndexSearcher::new(index_reader);
-                let begin_index_record = index_searcher.search_timestamp_and_return_index_offset(
-                    begin_timestamp, SearchMethod::After)?;
-                let end_index_record = index_searcher.search_timestamp_and_return_index_offset(
-                    end_timestamp, SearchMethod::After)?;
-                // Determine whether we can possibly get more data in the future.
-                // If the caller specified an end time and we already have an index record beyond this, then
-                // future appends will not affect our result.
-                // TODO: We can also guarantee this if the stream has been sealed.
-                let have_all_data = end_index_record.0.timestamp >= end_timestamp;
-                tracing::info!("begin_index_record={:?}, end_index_record={:?}, have_all_data={}",
-                        begin_index_record, end_index_record, have_all_data);
-                let mut index_reader = index_searcher.into_inner();
-
-                // Determine begin and end offsets of the index.
-                let index_begin_offset = begin_index_record.1;
-                let index_end_offset = end_index_record.1 + IndexRecord::RECORD_SIZE as u64;
-                let index_size = index_end_offset - index_begin_offset;
-                tracing::info!("index_begin_offset={}, index_end_offset={}, index_size={}", index_begin_offset, index_end_offset, index_size);
-
-                // Position index reader at current beginning of the index.
-                index_reader.seek(SeekFrom::Start(index_begin_offset)).unwrap();
-
-                // Ensure EOF instead of waiting (potentially forever) for appends when we get to the current end.
-                let mut index_reader = index_reader.take(index_size);
-
-                // Media Sequence Number will always equal the index record number, even after truncation.
-                let initial_media_sequence_number: u64 = index_begin_offset / IndexRecord::RECORD_SIZE as u64;
-                tracing::info!("initial_media_sequence_number={}", initial_media_sequence_number);
-
-                // Initial value for target duration. This will be updated with an exponential moving average, then rounded.
-                let mut target_duration_seconds = 10.0;
-
-                let mut playlist_body = String::new();
-                let mut prev_index_record: Option<IndexRecord> = None;
-                let mut next_segment_discont = false;
-
-                loop {
-                    let mut index_record_reader = IndexRecordReader::new();
-                    let index_record = match index_record_reader.read(&mut index_reader) {
-                        Ok(n) => n,
-                        Err(e) if e.kind() == ErrorKind::UnexpectedEof && index_reader.limit() == 0 => {
-                            tracing::trace!("Reached requested end");
-                            break;
+                tracing::info!("partitions={:?}", partitions);
+
+                let mut playlist_partitions = Vec::new();
+                for partition in partitions.iter() {
+                    // Media Sequence Numbers continue across partitions.
+                    // The final index record of a partition and the first index record of the next partition
+                    // do not bound a segment, so one is subtracted for each previous partition.
+                    let (scoped_stream, scoped_segment, ts_url_prefix, media_sequence_number_base, sealed) = match partition {
+                        Some(partition) => {
+                            let (_, scoped_segment, _) = get_partition_segments(&scope, partition);
+                            let scoped_stream = ScopedStream {
+                                scope: scoped_segment.scope.clone(),
+                                stream: scoped_segment.stream.clone(),
+                            };
+                            // A partition has been sealed if the catalog contains a later partition.
+                            let sealed = partition.sequence < records.last().unwrap().sequence;
+                            // An empty partition contributes no index records, so the base may be less than the sequence.
+                            let media_sequence_number_base = partition.index_record_base.checked_sub(partition.sequence)
+                                .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidData,
+                                    format!("Partition {} has {} index records before it, which is fewer than the number of previous partitions",
+                                        partition.sequence, partition.index_record_base)))?;
+                            (scoped_stream, Some(scoped_segment), format!("ts?partition={}&", partition.sequence),
+                                media_sequence_number_base, sealed)
+                        },
+                        None => {
+                            let scoped_stream = ScopedStream {
+                                scope: scope.clone(),
+                                stream: Stream::from(index_stream_name.clone()),
+                            };
+                            (scoped_stream, None, "ts?".to_owned(), 0, false)
                         },
-                        Err(e) => return Err(e),
                     };
-                    tracing::trace!("index_record={:?}", index_record);
-                    if let Some(prev_index_record) = prev_index_record {
-                        // If index_record indicates a discontinuity, then assume there is a gap in the data
-                        // between the previous record and this one.
-                        // Any recorded content that falls in this gap may be corrupt so we will not display it.
-                        // Instead, we'll play a short transport stream containing blue video and silent audio.
-                        // The length of this replacement content will be fixed, regardless of the timestamps.
-                        // The EXT-X-GAP tag should be used for this but it doesn't appear to be supported by hls.js.
-                        // It is possible that the duration of the gap in the index is very short or even 0.
-                        // However, we still need to count the gap so that the Media Sequence Numbers
-                        // correspond to the index offset.
-
-                        let mut discont = index_record.discontinuity;
-                        if discont {
-                            tracing::warn!("Detected discontinuity; discontinuity flag set in {:?}", index_record);
-                        } else {
-                            if let Some(timestamp_nanos) = index_record.timestamp.nanoseconds() {
-                                let prev_timestamp_nanos = prev_index_record.timestamp.nanoseconds().unwrap();
-                                if timestamp_nanos < prev_timestamp_nanos {
-                                    let rewind_seconds = (prev_timestamp_nanos - timestamp_nanos) as f64 * 1e-9;
-                                    tracing::warn!("Detected discontinuity; rewind of {:.3} seconds from {} to {}",
-                                    rewind_seconds, prev_index_record.timestamp, index_record.timestamp);
-                                    discont = true;
-                                } else {
-                                    let duration_seconds = (timestamp_nanos - prev_timestamp_nanos) as f64 * 1e-9;
-                                    // If the timestamp increased by much more than the target duration,
-                                    // then assume we have a discontinuity.
-                                    if duration_seconds > target_duration_seconds + 1.0 {
-                                        tracing::warn!("Detected discontinuity; {:.3} second gap from {} to {}, target_duration_seconds={:.3}",
-                                            duration_seconds, prev_index_record.timestamp, index_record.timestamp, target_duration_seconds);
-                                        discont = true;
-                                    } else {
-                                        if next_segment_discont {
-                                            playlist_body.push_str("#EXT-X-DISCONTINUITY\n");
-                                            next_segment_discont = false;
-                                        }
-                                        let ema_alpha = 0.1;
-                                        target_duration_seconds = ema_alpha * duration_seconds + (1.0 - ema_alpha) * target_duration_seconds;
-                                        let begin_offset = prev_index_record.offset;
-                                        let end_offset = index_record.offset;
-                                        // "#EXTINF:10," where 10 is the duration of the segment in seconds
-                                        playlist_body.push_str(&format!("#EXTINF:{},\n", duration_seconds));
-                                        // "#EXT-X-PROGRAM-DATE-TIME:2010-02-19T14:54:23.123456789Z"
-                                        playlist_body.push_str(&format!("#EXT-X-PROGRAM-DATE-TIME:{}\n", prev_index_record.timestamp.to_iso_8601().unwrap()));
-                                        // "ts?begin=0&end=204" where 0 and 204 are the begin and end byte offsets
-                                        playlist_body.push_str(&format!("ts?begin={}&end={}\n", begin_offset, end_offset));
-                                    }
-                                }
-                            } else {
-                                tracing::warn!("Detected discontinuity; missing timestamp in index at offset {}",
-                                    index_record.offset);
-                                discont = true;
-                            }
-                        }
-                        if discont {
-                            // tracing::warn!("Detected discontinuity; index_record={:?}", index_record);
-                            let gap_content_duration_seconds = 5;
-                            playlist_body.push_str("#EXT-X-DISCONTINUITY\n");
-                            playlist_body.push_str(&format!("#EXTINF:{},\n", gap_content_duration_seconds));
-                            playlist_body.push_str(&format!("/static/gap-{}s.ts\n", gap_content_duration_seconds));
-                            next_segment_discont = true;
-                        }
-                    }
-                    prev_index_record = Some(index_record);
-                }
 
-                let mut playlist = String::new();
-                let target_duration_seconds = target_duration_seconds.round();
-                tracing::info!("target_duration_seconds={}", target_duration_seconds);
-                playlist.push_str("#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-ALLOW-CACHE:NO\n");
-                playlist.push_str(&format!("#EXT-X-MEDIA-SEQUENCE:{}\n", initial_media_sequence_number));
-                playlist.push_str(&format!("#EXT-X-TARGETDURATION:{}\n", target_duration_seconds));
-                playlist.push_str(&playlist_body);
-
-                // Write ENDLIST if we have all data up to the requested end time.
-                // This will prevent the browser from polling for updated playlists.
-                if have_all_data {
-                    playlist.push_str("#EXT-X-ENDLIST\n");
+                    // Read only the index records that were appended since the previous request for this stream.
+                    let index_cache = self.get_index_cache(&scoped_stream, scoped_segment)?;
+                    index_cache.update()?;
+                    playlist_partitions.push(PlaylistPartition {
+                        scoped_stream,
+                        index_cache,
+                        ts_url_prefix,
+                        media_sequence_number_base,
+                        sealed,
+                    });
                 }
-                Ok(playlist)
+                Ok::<_, std::io::Error>(playlist_partitions)
             })
-            .await??;
+            .await;
             tracing::trace!("spawn_blocking done");
+            let playlist = match partitions {
+                Ok(Ok(partitions)) => get_m3u8_playlist_body(partitions, begin_timestamp, end_timestamp, random_access_only).await,
+                Ok(Err(err)) => Err(err),
+                Err(err) => Err(std::io::Error::new(ErrorKind::Other, err.to_string())),
+            };
+            let playlist = match playlist {
+                Ok(playlist) => playlist,
+                Err(err) if is_truncated_error(&err) => {
+                    tracing::info!("get_m3u8_playlist: {}", err);
+                    return Ok(error_response(StatusCode::GONE, err.to_string()));
+                },
+                Err(err) if err.kind() == ErrorKind::NotFound => {
+                    tracing::info!("get_m3u8_playlist: {}", err);
+                    return Ok(error_response(StatusCode::NOT_FOUND, err.to_string()));
+                },
+                Err(err) => {
+                    tracing::error!("get_m3u8_playlist: {}", err);
+                    return Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()));
+                },
+            };
             tracing::trace!("playlist={}", playlist);
-            Ok(playlist)
+            Ok(warp::reply::with_header(playlist, "content-type", "application/x-mpegURL").into_response())
         }
 
         pub async fn list_video_streams(
@@ -0,0 +1,263 @@
+//
+// Copyright (c) Dell Inc., or its subsidiaries. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+
+// Module for time-partitioned streams, in which a writer periodically rolls over to a new data stream.
+
+use crate::event_serde::{EventReader, EventWithHeader, EventWriter};
+use crate::timestamp::PravegaTimestamp;
+use crate::utils::CurrentHead;
+use serde::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
+
+pub fn get_catalog_stream_name(stream_name: &str) -> String {
+    format!("{}-catalog", stream_name)
+}
+
+/// Describes one partition of a partitioned stream.
+///
+/// A partitioned stream is identified by a logical stream name that has a catalog stream but no data stream.
+/// Each partition is an ordinary data stream with its own index and metadata streams.
+/// The catalog stream contains one record per partition, in the order that they were written.
+/// Once a writer rolls over to a new partition, the streams of the previous partition are sealed.
+/// The segments are recorded here because the segments of a sealed stream cannot be obtained from the controller.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PartitionRecord {
+    /// Starts at 0 and increases by 1 for each partition.
+    pub sequence: u64,
+    /// The name of the data stream of this partition.
+    pub stream: String,
+    /// The timestamp (see timestamp.rs) at or before the first event in this partition.
+    pub begin_timestamp: Option<u64>,
+    /// The segment numbers of the data, index, and metadata streams.
+    pub data_segment: i64,
+    pub index_segment: i64,
+    pub metadata_segment: i64,
+    /// The total number of index records in all previous partitions.
+    /// This allows index record numbers, such as HLS media sequence numbers, to increase across partitions.
+    pub index_record_base: u64,
+}
+
+impl PartitionRecord {
+    pub fn begin_timestamp(&self) -> PravegaTimestamp {
+        PravegaTimestamp::from_nanoseconds(self.begin_timestamp)
+    }
+}
+
+/// A struct to serialize PartitionRecord for writing to a Pravega byte stream.
+/// The format is the same as StreamMetadataWriter.
+pub struct CatalogWriter {
+}
+
+impl CatalogWriter {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn write<W>(&mut self, record: &PartitionRecord, writer: &mut W) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let payload = serde_json::to_vec(record)?;
+        let event = EventWithHeader::new(&payload[..], PravegaTimestamp::NONE, false, false, false);
+        EventWriter::with_checksum(true).write(&event, writer)
+    }
+}
+
+pub struct CatalogReader {
+}
+
+// A struct to deserialize PartitionRecord that was written to a Pravega byte stream.
+impl CatalogReader {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn read<R>(&mut self, rdr: &mut R) -> Result<PartitionRecord, Error>
+    where
+        R: Read,
+    {
+        let mut event_reader = EventReader::new();
+        let required_buffer_length = event_reader.read_required_buffer_length(rdr)?;
+        let mut buffer = vec![0; required_buffer_length];
+        let event = event_reader.read_event(rdr, &mut buffer[..])?;
+        let record = serde_json::from_slice(event.payload)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        Ok(record)
+    }
+
+    /// Returns all records between the current head and tail of the catalog stream.
+    /// A partial record at the tail is ignored.
+    pub fn read_all<R>(&mut self, rdr: &mut R) -> Result<Vec<PartitionRecord>, Error>
+    where
+        R: Read + Seek + CurrentHead,
+    {
+        let head_offset = rdr.current_head()?;
+        let tail_offset = rdr.seek(SeekFrom::End(0))?;
+        rdr.seek(SeekFrom::Start(head_offset))?;
+        // Ensure EOF instead of waiting for appends at the tail.
+        let mut rdr = rdr.take(tail_offset - head_offset);
+        let mut records = Vec::new();
+        loop {
+            match self.read(&mut rdr) {
+                Ok(record) => records.push(record),
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Returns the position in records of the partition that contains timestamp.
+/// This is the last partition that begins at or before timestamp, or the first partition if there is none.
+pub fn find_partition(records: &[PartitionRecord], timestamp: PravegaTimestamp) -> usize {
+    records.iter().rposition(|record| record.begin_timestamp() <= timestamp).unwrap_or(0)
+}
+
+/// When a writer should roll over to a new partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rollover {
+    /// When the first event of a new UTC hour is indexed.
+    Hourly,
+    /// When the first event of a new UTC day is indexed.
+    Daily,
+    /// When an event is indexed and the data stream has at least this many bytes.
+    Size(u64),
+}
+
+impl Rollover {
+    /// Returns a string that is equal for all timestamps in the same hour or day.
+    pub fn period_key(&self, timestamp: PravegaTimestamp) -> Option<String> {
+        let format = match self {
+            Rollover::Hourly => "%Y%m%d%H",
+            Rollover::Daily => "%Y%m%d",
+            Rollover::Size(_) => return None,
+        };
+        timestamp.to_datetime().map(|t| t.format(format).to_string())
+    }
+
+    /// Returns true if an index record for the event at timestamp and data_offset should instead begin a new partition.
+    /// The current partition began at partition_timestamp.
+    pub fn is_due(&self, partition_timestamp: PravegaTimestamp, timestamp: PravegaTimestamp, data_offset: u64) -> bool {
+        match self {
+            Rollover::Size(size) => data_offset >= *size,
+            _ => {
+                match (self.period_key(partition_timestamp), self.period_key(timestamp)) {
+                    (Some(partition_key), Some(key)) => key > partition_key,
+                    _ => false,
+                }
+            },
+        }
+    }
+
+    /// Returns an error if partitions named with template may not be unique.
+    /// The template must contain {seq} or all of the placeholders that identify the period of a partition.
+    pub fn validate_template(&self, template: &str) -> Result<(), String> {
+        let required: &[&str] = match self {
+            Rollover::Hourly => &["{date}", "{hour}"],
+            Rollover::Daily => &["{date}"],
+            Rollover::Size(_) => &[],
+        };
+        if template.contains("{seq}") || (!required.is_empty() && required.iter().all(|placeholder| template.contains(placeholder))) {
+            Ok(())
+        } else if required.is_empty() {
+            Err(format!("Partition template {} must contain {{seq}}", template))
+        } else {
+            Err(format!("Partition template {} must contain {{seq}} or all of {}", template, required.join(", ")))
+        }
+    }
+}
+
+/// Returns the name of a partition by replacing the following placeholders in template:
+///   {stream}: the logical stream name
+///   {date}: the UTC date of timestamp as YYYY-MM-DD
+///   {hour}: the UTC hour of timestamp as HH
+///   {seq}: the sequence number of the partition
+pub fn format_partition_name(template: &str, stream_name: &str, timestamp: PravegaTimestamp, sequence: u64) -> String {
+    let datetime = timestamp.to_datetime();
+    let date = datetime.map(|t| t.format("%Y-%m-%d").to_string()).unwrap_or_default();
+    let hour = datetime.map(|t| t.format("%H").to_string()).unwrap_or_default();
+    template
+        .replace("{stream}", stream_name)
+        .replace("{date}", &date)
+        .replace("{hour}", &hour)
+        .replace("{seq}", &sequence.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_catalog_writer_reader() {
+        let records = vec![
+            PartitionRecord {
+                sequence: 0,
+                stream: "camera1-2021-04-19".to_owned(),
+                begin_timestamp: Some(1_000),
+                ..Default::default()
+            },
+            PartitionRecord {
+                sequence: 1,
+                stream: "camera1-2021-04-20".to_owned(),
+                begin_timestamp: Some(2_000),
+                index_record_base: 100,
+                ..Default::default()
+            },
+        ];
+        let mut cursor = Cursor::new(Vec::new());
+        let mut writer = CatalogWriter::new();
+        for record in records.iter() {
+            writer.write(record, &mut cursor).unwrap();
+        }
+        // A partial record at the tail must be ignored.
+        let length = cursor.get_ref().len();
+        writer.write(&records[0], &mut cursor).unwrap();
+        cursor.get_mut().truncate(length + 10);
+
+        let read_records = CatalogReader::new().read_all(&mut cursor).unwrap();
+        assert_eq!(read_records, records);
+
+        assert_eq!(find_partition(&read_records, PravegaTimestamp::MIN), 0);
+        assert_eq!(find_partition(&read_records, PravegaTimestamp::from_nanoseconds(Some(1_999))), 0);
+        assert_eq!(find_partition(&read_records, PravegaTimestamp::from_nanoseconds(Some(2_000))), 1);
+        assert_eq!(find_partition(&read_records, PravegaTimestamp::MAX), 1);
+    }
+
+    #[test]
+    fn test_rollover() {
+        let t0 = PravegaTimestamp::try_from(Some("2021-04-19T23:59:59Z")).unwrap();
+        let t1 = PravegaTimestamp::try_from(Some("2021-04-20T00:00:00Z")).unwrap();
+        let t2 = PravegaTimestamp::try_from(Some("2021-04-20T00:59:59Z")).unwrap();
+        assert_eq!(format_partition_name("{stream}-{date}", "camera1", t0, 3), "camera1-2021-04-19");
+        assert_eq!(format_partition_name("{stream}-{date}-{hour}", "camera1", t1, 3), "camera1-2021-04-20-00");
+        assert_eq!(format_partition_name("{stream}-{seq}", "camera1", t1, 3), "camera1-3");
+
+        assert!(Rollover::Daily.is_due(t0, t1, 0));
+        assert!(!Rollover::Daily.is_due(t1, t2, 0));
+        assert!(!Rollover::Daily.is_due(t1, t0, 0));
+        assert!(Rollover::Hourly.is_due(t0, t1, 0));
+        assert!(!Rollover::Hourly.is_due(t1, t2, 0));
+        assert!(!Rollover::Size(1000).is_due(t0, t1, 999));
+        assert!(Rollover::Size(1000).is_due(t1, t1, 1000));
+
+        assert!(Rollover::Daily.validate_template("{stream}-{date}").is_ok());
+        assert!(Rollover::Daily.validate_template("{stream}-{seq}").is_ok());
+        assert!(Rollover::Daily.validate_template("{stream}-{hour}").is_err());
+        assert!(Rollover::Hourly.validate_template("{stream}-{date}-{hour}").is_ok());
+        assert!(Rollover::Hourly.validate_template("{stream}-{hour}-{seq}").is_ok());
+        assert!(Rollover::Hourly.validate_template("{stream}-{date}").is_err());
+        assert!(Rollover::Hourly.validate_template("{stream}-{hour}").is_err());
+        assert!(Rollover::Size(1000).validate_template("{stream}-{date}-{hour}").is_err());
+        assert!(Rollover::Size(1000).validate_template("{stream}-{seq}").is_ok());
+    }
+}
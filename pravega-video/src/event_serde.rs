@@ -11,8 +11,11 @@
 // Module for serialization of events for writing to a Pravega byte stream.
 
 use std::convert::TryInto;
+use std::fmt;
 use std::io::{Error, ErrorKind, Read, Write};
 use enumflags2::BitFlags;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use crate::checksum::{crc32c, crc32c_update};
 use crate::timestamp::PravegaTimestamp;
 
 #[derive(BitFlags, Copy, Clone, Debug, PartialEq)]
@@ -21,14 +24,99 @@ pub(crate) enum EventHeaderFlags {
     IncludeInIndex         = 0b00000001,
     RandomAccessIndicator  = 0b00000010,
     DiscontinuityIndicator = 0b00000100,
+    Checksum               = 0b00001000,
+    Extensions             = 0b00010000,
 }
 
-#[derive(Debug, PartialEq)]
+/// The extension type of a serialized GstMeta. See [HeaderExtension::gst_meta].
+pub const EXTENSION_TYPE_GST_META: u16 = 1;
+/// The extension type of the DTS and duration of a buffer. See [HeaderExtension::buffer_timing].
+pub const EXTENSION_TYPE_BUFFER_TIMING: u16 = 2;
+
+/// The decoding timestamp (DTS) and duration of a buffer, for streams in which these can't be derived from the PTS,
+/// such as H.264 with B-frames.
+/// The event timestamp is the PTS. The DTS is stored relative to it so that it does not depend on how the writer
+/// converted the PTS to a timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BufferTiming {
+    /// The PTS minus the DTS, in nanoseconds. This is usually 0 or positive.
+    pub dts_offset: Option<i64>,
+    /// The duration of the buffer, in nanoseconds.
+    pub duration: Option<u64>,
+}
+
+/// A typed value that is stored in the event header, between the fixed header fields and the payload.
+/// Readers ignore extensions with types that they do not recognize.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderExtension {
+    pub extension_type: u16,
+    pub value: Vec<u8>,
+}
+
+impl HeaderExtension {
+    pub fn new(extension_type: u16, value: Vec<u8>) -> Self {
+        Self {
+            extension_type,
+            value,
+        }
+    }
+
+    /// Returns an extension that contains a GstMeta that was serialized as data.
+    /// The name identifies the serializer, and is usually the API name of the meta.
+    /// The value is the name length (16-bit BE unsigned int), the UTF-8 name, and the data.
+    pub fn gst_meta(name: &str, data: &[u8]) -> Self {
+        let name_length: u16 = name.len().try_into().unwrap();
+        let mut value = Vec::with_capacity(2 + name.len() + data.len());
+        value.extend_from_slice(&name_length.to_be_bytes()[..]);
+        value.extend_from_slice(name.as_bytes());
+        value.extend_from_slice(data);
+        Self::new(EXTENSION_TYPE_GST_META, value)
+    }
+
+    /// Returns the name and data of an extension created by [HeaderExtension::gst_meta].
+    pub fn as_gst_meta(&self) -> Option<(&str, &[u8])> {
+        if self.extension_type != EXTENSION_TYPE_GST_META || self.value.len() < 2 {
+            return None;
+        }
+        let name_length = u16::from_be_bytes(self.value[0..2].try_into().unwrap()) as usize;
+        if self.value.len() < 2 + name_length {
+            return None;
+        }
+        let name = std::str::from_utf8(&self.value[2..2 + name_length]).ok()?;
+        Some((name, &self.value[2 + name_length..]))
+    }
+
+    /// Returns an extension that contains the DTS and duration of a buffer.
+    /// The value is the DTS offset (64-bit BE signed int, i64::MIN if none)
+    /// and the duration (64-bit BE unsigned int, u64::MAX if none).
+    pub fn buffer_timing(timing: &BufferTiming) -> Self {
+        let mut value = Vec::with_capacity(16);
+        value.extend_from_slice(&timing.dts_offset.unwrap_or(i64::MIN).to_be_bytes()[..]);
+        value.extend_from_slice(&timing.duration.unwrap_or(u64::MAX).to_be_bytes()[..]);
+        Self::new(EXTENSION_TYPE_BUFFER_TIMING, value)
+    }
+
+    /// Returns the buffer timing of an extension created by [HeaderExtension::buffer_timing].
+    pub fn as_buffer_timing(&self) -> Option<BufferTiming> {
+        if self.extension_type != EXTENSION_TYPE_BUFFER_TIMING || self.value.len() < 16 {
+            return None;
+        }
+        let dts_offset = i64::from_be_bytes(self.value[0..8].try_into().unwrap());
+        let duration = u64::from_be_bytes(self.value[8..16].try_into().unwrap());
+        Some(BufferTiming {
+            dts_offset: if dts_offset == i64::MIN { None } else { Some(dts_offset) },
+            duration: if duration == u64::MAX { None } else { Some(duration) },
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct EventHeader {
     pub timestamp: PravegaTimestamp,
     pub include_in_index: bool,
     pub random_access: bool,
     pub discontinuity: bool,
+    pub extensions: Vec<HeaderExtension>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -53,9 +141,9 @@ pub struct EventWithHeader<'a> {
    |    number of bytes from reserved to the end of the payload    |
    |                                                               |
    +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
-   |                                                         |D|R|I|
-   |                    reserved (set to 0)                  |I|A|N|
-   |                                                         |S|N|D|
+   |                                                     |E|C|D|R|I|
+   |                    reserved (set to 0)              |X|R|I|A|N|
+   |                                                     |T|C|S|N|D|
    +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
    |                                                               |
    |                                                               |
@@ -65,6 +153,18 @@ pub struct EventWithHeader<'a> {
    |                                                               |
    |                                                               |
    +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+   |              checksum (32-bit BE unsigned int)                |
+   |                  CRC-32C, present only if CRC                 |
+   |                                                               |
+   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+   |          extensions_length (32-bit BE unsigned int)           |
+   |                   present only if EXT                         |
+   |                                                               |
+   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+   |                                                               |
+   |                  extensions (variable length)                 |
+   |                                                               |
+   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
    |                                                               |
    |                    payload (variable length)                  |
    |                                                               |
@@ -81,6 +181,13 @@ pub struct EventWithHeader<'a> {
    reserved:
       All reserved bits must be 0.
       These may be utilized in the future for other purposes.
+   EXT - extensions indicator:
+      True (1) if the extensions_length and extensions fields follow the timestamp and checksum.
+      Events written without extensions have this bit set to 0 and have neither field.
+   CRC - checksum indicator:
+      True (1) if a checksum field follows the timestamp.
+      Events written without a checksum have this bit set to 0 and have no checksum field,
+      so they remain readable by readers that do not know about checksums.
    DIS - discontinuity indicator:
       True (1) if this event is or may be discontinuous from the previous event.
       This should usually be true for the first event written by a new process.
@@ -107,6 +214,19 @@ pub struct EventWithHeader<'a> {
       For video frames, the timestamp will reflect when the image was captured by the camera.
       If DTS can differ from PTS, this timestamp should be the PTS.
       This allows different streams to be correlated precisely.
+   checksum:
+      Only present if CRC is 1.
+      The CRC-32C (Castagnoli) of the bytes from type code through timestamp, followed by all bytes after the checksum.
+      Encoded as a 32-bit big-endian unsigned int.
+   extensions_length:
+      Only present if EXT is 1.
+      The number of bytes in the extensions field.
+      Encoded as a 32-bit big-endian unsigned int.
+   extensions:
+      Only present if EXT is 1.
+      A sequence of extensions (see HeaderExtension), each encoded as
+      extension type (16-bit BE unsigned int), value length (32-bit BE unsigned int), and value.
+      Extension types are defined by the EXTENSION_TYPE constants.
    payload:
       Can be 0 or more MPEG TS packets, or any other payload.
       When encoding an MPEG transport stream, this is currently a single 188-byte MPEG TS packet.
@@ -114,11 +234,24 @@ pub struct EventWithHeader<'a> {
       which means it must be 8 MiB or smaller.
 */
 pub struct EventWriter {
+    // If true, a checksum will be written with each event.
+    checksum: bool,
 }
 
 impl EventWriter {
     pub fn new() -> Self {
-        Self {}
+        Self::with_checksum(false)
+    }
+
+    pub fn with_checksum(checksum: bool) -> Self {
+        Self {
+            checksum,
+        }
+    }
+
+    /// Returns the number of bytes that write() will write for the event.
+    pub fn serialized_length(&self, event: &EventWithHeader) -> usize {
+        event.payload.len() + extensions_length(&event.header.extensions) + if self.checksum { 24 } else { 20 }
     }
 
     pub fn write<'a, W>(&mut self, event: &EventWithHeader<'a>, writer: &mut W) -> Result<(), Error>
@@ -135,19 +268,47 @@ impl EventWriter {
         if event.header.discontinuity {
             flags |= EventHeaderFlags::DiscontinuityIndicator;
         }
+        let (fixed_header_length, max_payload_size) = if self.checksum {
+            flags |= EventHeaderFlags::Checksum;
+            (24, EventWithHeader::MAX_PAYLOAD_SIZE_WITH_CHECKSUM)
+        } else {
+            (20, EventWithHeader::MAX_PAYLOAD_SIZE)
+        };
+        let extensions_length = extensions_length(&event.header.extensions);
+        if extensions_length > 0 {
+            flags |= EventHeaderFlags::Extensions;
+        }
         let payload_length = event.payload.len();
-        if payload_length > EventWithHeader::MAX_PAYLOAD_SIZE {
+        if payload_length + extensions_length > max_payload_size {
             return Err(Error::new(ErrorKind::InvalidInput, format!("Payload of {} bytes exceeds {} bytes",
-                payload_length, EventWithHeader::MAX_PAYLOAD_SIZE)));
+                payload_length, max_payload_size.saturating_sub(extensions_length))));
         }
-        let event_length: u32 = (payload_length + 12).try_into().unwrap();
-        let write_length = payload_length + 20;
+        let header_length = fixed_header_length + extensions_length;
+        let event_length: u32 = (payload_length + header_length - 8).try_into().unwrap();
+        let write_length = payload_length + header_length;
         let mut bytes_to_write: Vec<u8> = vec![0; write_length];
         bytes_to_write[4..8].copy_from_slice(&event_length.to_be_bytes()[..]);
         bytes_to_write[11..12].copy_from_slice(&flags.bits().to_be_bytes()[..]);
         bytes_to_write[12..20].copy_from_slice(&event.header.timestamp.nanoseconds().unwrap_or_default().to_be_bytes()[..]);
-        bytes_to_write[20..20+payload_length].copy_from_slice(&event.payload[..]);
-        writer.write_all(&bytes_to_write).unwrap();
+        if extensions_length > 0 {
+            let mut pos = fixed_header_length;
+            let length: u32 = (extensions_length - 4).try_into().unwrap();
+            bytes_to_write[pos..pos+4].copy_from_slice(&length.to_be_bytes()[..]);
+            pos += 4;
+            for extension in event.header.extensions.iter() {
+                let value_length: u32 = extension.value.len().try_into().unwrap();
+                bytes_to_write[pos..pos+2].copy_from_slice(&extension.extension_type.to_be_bytes()[..]);
+                bytes_to_write[pos+2..pos+6].copy_from_slice(&value_length.to_be_bytes()[..]);
+                bytes_to_write[pos+6..pos+6+extension.value.len()].copy_from_slice(&extension.value[..]);
+                pos += 6 + extension.value.len();
+            }
+        }
+        bytes_to_write[header_length..header_length+payload_length].copy_from_slice(&event.payload[..]);
+        if self.checksum {
+            let checksum = crc32c_update(crc32c(&bytes_to_write[0..20]), &bytes_to_write[24..]);
+            bytes_to_write[20..24].copy_from_slice(&checksum.to_be_bytes()[..]);
+        }
+        writer.write_all(&bytes_to_write)?;
         Ok(())
     }
 }
@@ -179,6 +340,25 @@ impl EventReader {
         R: Read,
     {
         rdr.read_exact(&mut self.event_length_bytes[0..8])?;
+        self.decode_event_length()
+    }
+
+    // Reads the rest of event, including the rest of the EventHeader and the payload.
+    // This must be called after read_required_buffer_length() has been called to determine the event length.
+    // The reader must be positioned at the byte immediatley after event_length.
+    // If the event has a checksum that does not match, ReadEventError::ChecksumMismatch is returned.
+    // In this case, the entire event has been consumed and the reader is positioned at the next event.
+    pub fn read_event<'a, R>(&mut self, rdr: &mut R, buffer: &'a mut [u8]) -> Result<EventWithHeader<'a>, ReadEventError>
+    where
+        R: Read,
+    {
+        self.prepare_buffer(buffer)?;
+        rdr.read_exact(&mut buffer[8..self.required_buffer_length])?;
+        self.decode_event(buffer)
+    }
+
+    // Decodes the event length from event_length_bytes, which must have been read from the stream.
+    fn decode_event_length(&mut self) -> Result<usize, Error> {
         let event_length_bytes: [u8; 4] = self.event_length_bytes[4..8].try_into().unwrap();
         self.event_length = u32::from_be_bytes(event_length_bytes) as usize;
         // Event length must be between 12 and MAX_ATOMIC_WRITE_SIZE - 8.
@@ -189,43 +369,188 @@ impl EventReader {
         Ok(self.required_buffer_length)
     }
 
-    // Reads the rest of event, including the rest of the EventHeader and the payload.
-    // This must be called after read_required_buffer_length() has been called to determine the event length.
-    // The reader must be positioned at the byte immediatley after event_length.
-    pub fn read_event<'a, R>(&mut self, rdr: &mut R, buffer: &'a mut [u8]) -> Result<EventWithHeader<'a>, Error>
-    where
-        R: Read,
-    {
+    // Checks that the buffer can hold the entire event and copies the bytes that have already been read into it.
+    // The caller must then fill buffer[8..required_buffer_length] from the stream.
+    fn prepare_buffer(&self, buffer: &mut [u8]) -> Result<(), Error> {
         if buffer.len() < self.required_buffer_length {
             return Err(Error::new(ErrorKind::InvalidInput, "Buffer too small"))
         }
-        //  Note that bytes 0..8 of buffer are unused. However, this keeps the byte ranges consistent with the writer.
-        rdr.read_exact(&mut buffer[8..self.required_buffer_length])?;
-        let flags = BitFlags::<EventHeaderFlags>::from_bits(buffer[11]).unwrap();
+        // Bytes 0..8 of buffer are a copy of the bytes read by read_required_buffer_length().
+        // This keeps the byte ranges consistent with the writer.
+        buffer[0..8].copy_from_slice(&self.event_length_bytes[..]);
+        Ok(())
+    }
+
+    // Decodes an event from a buffer that was prepared by prepare_buffer() and filled from the stream.
+    fn decode_event<'a>(&self, buffer: &'a [u8]) -> Result<EventWithHeader<'a>, ReadEventError> {
+        let flags = BitFlags::<EventHeaderFlags>::from_bits(buffer[11])
+            .map_err(|_| Error::new(ErrorKind::InvalidData, format!("Invalid event flags {:#010b}", buffer[11])))?;
         let include_in_index = flags.contains(EventHeaderFlags::IncludeInIndex);
         let random_access = flags.contains(EventHeaderFlags::RandomAccessIndicator);
         let discontinuity = flags.contains(EventHeaderFlags::DiscontinuityIndicator);
         let timestamp = u64::from_be_bytes(buffer[12..20].try_into().unwrap());
         let timestamp = if timestamp == 0 { None } else { Some(timestamp) };
-        let payload_length = self.event_length - 12;
-        let payload = &buffer[20..20+payload_length];
+        let body = if flags.contains(EventHeaderFlags::Checksum) {
+            if self.event_length < 16 {
+                return Err(Error::new(ErrorKind::InvalidData, format!("Invalid event length {}", self.event_length)).into())
+            }
+            let expected = u32::from_be_bytes(buffer[20..24].try_into().unwrap());
+            let body = &buffer[24..self.required_buffer_length];
+            let actual = crc32c_update(crc32c(&buffer[0..20]), body);
+            if actual != expected {
+                return Err(ReadEventError::ChecksumMismatch { expected, actual })
+            }
+            body
+        } else {
+            &buffer[20..self.required_buffer_length]
+        };
+        let (extensions, payload) = if flags.contains(EventHeaderFlags::Extensions) {
+            decode_extensions(body)?
+        } else {
+            (Vec::new(), body)
+        };
         Ok(EventWithHeader {
             header: EventHeader {
                 timestamp: PravegaTimestamp::from_nanoseconds(timestamp),
                 include_in_index,
                 random_access,
                 discontinuity,
+                extensions,
             },
             payload,
         })
     }
 }
 
+// Returns the number of bytes in the extensions_length and extensions fields.
+fn extensions_length(extensions: &[HeaderExtension]) -> usize {
+    if extensions.is_empty() {
+        0
+    } else {
+        4 + extensions.iter().map(|extension| 6 + extension.value.len()).sum::<usize>()
+    }
+}
+
+// Decodes the extensions_length and extensions fields at the beginning of body.
+// Returns the extensions and the remainder of body, which is the payload.
+fn decode_extensions(body: &[u8]) -> Result<(Vec<HeaderExtension>, &[u8]), Error> {
+    let invalid = || Error::new(ErrorKind::InvalidData, "Invalid event header extensions");
+    if body.len() < 4 {
+        return Err(invalid());
+    }
+    let length = u32::from_be_bytes(body[0..4].try_into().unwrap()) as usize;
+    if body.len() < 4 + length {
+        return Err(invalid());
+    }
+    let mut extensions = Vec::new();
+    let mut pos = 4;
+    while pos < 4 + length {
+        if pos + 6 > 4 + length {
+            return Err(invalid());
+        }
+        let extension_type = u16::from_be_bytes(body[pos..pos+2].try_into().unwrap());
+        let value_length = u32::from_be_bytes(body[pos+2..pos+6].try_into().unwrap()) as usize;
+        if pos + 6 + value_length > 4 + length {
+            return Err(invalid());
+        }
+        extensions.push(HeaderExtension::new(extension_type, body[pos+6..pos+6+value_length].to_vec()));
+        pos += 6 + value_length;
+    }
+    Ok((extensions, &body[4 + length..]))
+}
+
+/// The async counterpart of EventReader, for use with a tokio AsyncRead.
+/// It decodes events exactly as EventReader does.
+pub struct AsyncEventReader {
+    inner: EventReader,
+}
+
+impl AsyncEventReader {
+    pub fn new() -> Self {
+        Self {
+            inner: EventReader::new(),
+        }
+    }
+
+    // See EventReader::read_required_buffer_length().
+    pub async fn read_required_buffer_length<R>(&mut self, rdr: &mut R) -> Result<usize, Error>
+    where
+        R: AsyncRead + Unpin,
+    {
+        rdr.read_exact(&mut self.inner.event_length_bytes[0..8]).await?;
+        self.inner.decode_event_length()
+    }
+
+    // See EventReader::read_event().
+    pub async fn read_event<'a, R>(&mut self, rdr: &mut R, buffer: &'a mut [u8]) -> Result<EventWithHeader<'a>, ReadEventError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        self.inner.prepare_buffer(buffer)?;
+        rdr.read_exact(&mut buffer[8..self.inner.required_buffer_length]).await?;
+        self.inner.decode_event(buffer)
+    }
+}
+
+/// The error returned by EventReader::read_event().
+/// A corrupt event is reported separately so that callers can skip it and continue with the next event.
+#[derive(Debug)]
+pub enum ReadEventError {
+    /// The event does not match its checksum.
+    /// The entire event has been consumed and the reader is positioned at the next event.
+    ChecksumMismatch {
+        /// The checksum recorded in the event.
+        expected: u32,
+        /// The checksum calculated from the event.
+        actual: u32,
+    },
+    /// The event could not be read or it is malformed.
+    Io(Error),
+}
+
+impl ReadEventError {
+    /// Returns the kind of the equivalent std::io::Error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ReadEventError::ChecksumMismatch { .. } => ErrorKind::InvalidData,
+            ReadEventError::Io(err) => err.kind(),
+        }
+    }
+}
+
+impl fmt::Display for ReadEventError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadEventError::ChecksumMismatch { expected, actual } =>
+                write!(f, "Event checksum mismatch: expected {:#010x}, actual {:#010x}", expected, actual),
+            ReadEventError::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ReadEventError {}
+
+impl From<Error> for ReadEventError {
+    fn from(err: Error) -> Self {
+        ReadEventError::Io(err)
+    }
+}
+
+impl From<ReadEventError> for Error {
+    fn from(err: ReadEventError) -> Self {
+        match err {
+            ReadEventError::Io(err) => err,
+            err => Error::new(ErrorKind::InvalidData, err),
+        }
+    }
+}
+
 impl<'a> EventWithHeader<'a> {
     // Maximum size of the entire frame from type code through payload.
     // Corresponds to pravega_client_rust::event_stream_writer::EventStreamWriter.
-    const MAX_ATOMIC_WRITE_SIZE: usize = 8 * 1024 * 1024;
+    pub const MAX_ATOMIC_WRITE_SIZE: usize = 8 * 1024 * 1024;
     const MAX_PAYLOAD_SIZE: usize = EventWithHeader::MAX_ATOMIC_WRITE_SIZE - 20;
+    const MAX_PAYLOAD_SIZE_WITH_CHECKSUM: usize = EventWithHeader::MAX_ATOMIC_WRITE_SIZE - 24;
 
     pub fn new(payload: &'a [u8], timestamp: PravegaTimestamp,
         include_in_index: bool, random_access: bool, discontinuity: bool) -> Self {
@@ -235,6 +560,7 @@ impl<'a> EventWithHeader<'a> {
                 include_in_index,
                 random_access,
                 discontinuity,
+                extensions: Vec::new(),
             },
             payload: payload,
         }
@@ -243,7 +569,7 @@ impl<'a> EventWithHeader<'a> {
 
 #[cfg(test)]
 mod test {
-    use crate::event_serde::{EventWithHeader, EventWriter, EventReader};
+    use crate::event_serde::{EventWithHeader, EventWriter, EventReader, AsyncEventReader, BufferTiming, HeaderExtension, ReadEventError};
     use crate::timestamp::PravegaTimestamp;
     use tracing::{info, trace};
     use rand::{RngCore, SeedableRng};
@@ -285,4 +611,156 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_event_writer_reader_with_checksum() {
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        for payload_length in [0, 1, 188, EventWithHeader::MAX_PAYLOAD_SIZE_WITH_CHECKSUM, EventWithHeader::MAX_PAYLOAD_SIZE].iter() {
+            let mut payload = vec![0; *payload_length];
+            rng.fill_bytes(&mut payload[..]);
+            let event = EventWithHeader::new(
+                &payload[..],
+                PravegaTimestamp::from_nanoseconds(Some(1_600_000_000_000_000_000)),
+                true, false, true);
+            let mut serialized_bytes_cursor = Cursor::new(vec![]);
+            let mut event_writer = EventWriter::with_checksum(true);
+            let result = event_writer.write(&event, &mut serialized_bytes_cursor).map_err(|e| e.kind());
+            if *payload_length > EventWithHeader::MAX_PAYLOAD_SIZE_WITH_CHECKSUM {
+                assert_eq!(result, Err(ErrorKind::InvalidInput))
+            } else {
+                assert_eq!(result, Ok(()));
+                assert_eq!(serialized_bytes_cursor.get_ref().len(), 24 + payload.len());
+                assert_eq!(event_writer.serialized_length(&event), 24 + payload.len());
+                serialized_bytes_cursor.set_position(0);
+                let mut event_reader = EventReader::new();
+                let required_buffer_length = event_reader.read_required_buffer_length(&mut serialized_bytes_cursor).unwrap();
+                assert_eq!(required_buffer_length, 24 + payload.len());
+                let mut read_buffer: Vec<u8> = vec![0; required_buffer_length];
+                let deserialized_event = event_reader.read_event(&mut serialized_bytes_cursor, &mut read_buffer[..]).unwrap();
+                assert_eq!(event, deserialized_event);
+            }
+        }
+    }
+
+    #[test]
+    fn test_event_reader_checksum_mismatch() {
+        let payloads: [&[u8]; 3] = [b"first", b"second", b"third"];
+        let mut serialized_bytes_cursor = Cursor::new(vec![]);
+        let mut event_writer = EventWriter::with_checksum(true);
+        for (i, payload) in payloads.iter().enumerate() {
+            let event = EventWithHeader::new(payload, PravegaTimestamp::from_nanoseconds(Some(1000 + i as u64)), false, false, false);
+            event_writer.write(&event, &mut serialized_bytes_cursor).unwrap();
+        }
+        // Corrupt the last byte of the payload of the second event.
+        let second_event_end = 2 * 24 + payloads[0].len() + payloads[1].len();
+        serialized_bytes_cursor.get_mut()[second_event_end - 1] ^= 0x01;
+        serialized_bytes_cursor.set_position(0);
+        let mut read_payloads = vec![];
+        let mut checksum_errors = 0;
+        for _ in 0..payloads.len() {
+            let mut event_reader = EventReader::new();
+            let required_buffer_length = event_reader.read_required_buffer_length(&mut serialized_bytes_cursor).unwrap();
+            let mut read_buffer: Vec<u8> = vec![0; required_buffer_length];
+            match event_reader.read_event(&mut serialized_bytes_cursor, &mut read_buffer[..]) {
+                Ok(event) => read_payloads.push(event.payload.to_vec()),
+                Err(ReadEventError::ChecksumMismatch { .. }) => checksum_errors += 1,
+                Err(e) => panic!("Unexpected error {}", e),
+            }
+        }
+        // The corrupt event must be skipped without losing the position of the next event.
+        assert_eq!(checksum_errors, 1);
+        assert_eq!(read_payloads, vec![payloads[0].to_vec(), payloads[2].to_vec()]);
+    }
+
+    #[test]
+    fn test_event_writer_reader_with_extensions() {
+        for checksum in [false, true].iter() {
+            let mut event = EventWithHeader::new(b"payload", PravegaTimestamp::from_nanoseconds(Some(1000)), true, true, false);
+            event.header.extensions.push(HeaderExtension::gst_meta("GstProtectionMetaAPI", b"data"));
+            event.header.extensions.push(HeaderExtension::new(100, Vec::new()));
+            let mut cursor = Cursor::new(Vec::new());
+            let mut event_writer = EventWriter::with_checksum(*checksum);
+            event_writer.write(&event, &mut cursor).unwrap();
+            assert_eq!(cursor.get_ref().len(), event_writer.serialized_length(&event));
+            cursor.set_position(0);
+            let mut event_reader = EventReader::new();
+            let required_buffer_length = event_reader.read_required_buffer_length(&mut cursor).unwrap();
+            let mut read_buffer: Vec<u8> = vec![0; required_buffer_length];
+            let deserialized_event = event_reader.read_event(&mut cursor, &mut read_buffer[..]).unwrap();
+            assert_eq!(event, deserialized_event);
+            assert_eq!(deserialized_event.header.extensions[0].as_gst_meta(), Some(("GstProtectionMetaAPI", &b"data"[..])));
+            assert_eq!(deserialized_event.header.extensions[1].as_gst_meta(), None);
+        }
+    }
+
+    #[test]
+    fn test_buffer_timing_extension() {
+        let timings = [
+            BufferTiming { dts_offset: Some(66_666_666), duration: Some(33_333_333) },
+            BufferTiming { dts_offset: Some(-1), duration: None },
+            BufferTiming { dts_offset: None, duration: Some(0) },
+            BufferTiming::default(),
+        ];
+        for timing in timings.iter() {
+            let mut event = EventWithHeader::new(b"payload", PravegaTimestamp::from_nanoseconds(Some(1000)), false, false, false);
+            event.header.extensions.push(HeaderExtension::buffer_timing(timing));
+            let mut cursor = Cursor::new(Vec::new());
+            EventWriter::new().write(&event, &mut cursor).unwrap();
+            cursor.set_position(0);
+            let mut event_reader = EventReader::new();
+            let required_buffer_length = event_reader.read_required_buffer_length(&mut cursor).unwrap();
+            let mut read_buffer: Vec<u8> = vec![0; required_buffer_length];
+            let deserialized_event = event_reader.read_event(&mut cursor, &mut read_buffer[..]).unwrap();
+            assert_eq!(deserialized_event.header.extensions[0].as_buffer_timing(), Some(*timing));
+            assert_eq!(deserialized_event.header.extensions[0].as_gst_meta(), None);
+        }
+    }
+
+    #[test]
+    fn test_event_reader_mixed_checksum() {
+        // Events written without a checksum must remain readable when mixed with events that have one.
+        let mut serialized_bytes_cursor = Cursor::new(vec![]);
+        let event1 = EventWithHeader::new(b"without", PravegaTimestamp::from_nanoseconds(Some(1)), true, true, false);
+        let event2 = EventWithHeader::new(b"with", PravegaTimestamp::from_nanoseconds(Some(2)), false, false, false);
+        EventWriter::new().write(&event1, &mut serialized_bytes_cursor).unwrap();
+        EventWriter::with_checksum(true).write(&event2, &mut serialized_bytes_cursor).unwrap();
+        serialized_bytes_cursor.set_position(0);
+        for expected_event in [event1, event2].iter() {
+            let mut event_reader = EventReader::new();
+            let required_buffer_length = event_reader.read_required_buffer_length(&mut serialized_bytes_cursor).unwrap();
+            let mut read_buffer: Vec<u8> = vec![0; required_buffer_length];
+            let event = event_reader.read_event(&mut serialized_bytes_cursor, &mut read_buffer[..]).unwrap();
+            assert_eq!(*expected_event, event);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_event_reader() {
+        let mut serialized_bytes_cursor = Cursor::new(vec![]);
+        let mut events = vec![];
+        let payloads: [&[u8]; 3] = [b"", b"first", b"second"];
+        for (i, payload) in payloads.iter().enumerate() {
+            let event = EventWithHeader::new(payload, PravegaTimestamp::from_nanoseconds(Some(1000 + i as u64)), true, i == 1, i == 0);
+            EventWriter::with_checksum(i == 2).write(&event, &mut serialized_bytes_cursor).unwrap();
+            events.push(event);
+        }
+        // Corrupt the payload of the last event.
+        let length = serialized_bytes_cursor.get_ref().len();
+        serialized_bytes_cursor.get_mut()[length - 1] ^= 0x01;
+        serialized_bytes_cursor.set_position(0);
+        for (i, expected_event) in events.iter().enumerate() {
+            let mut event_reader = AsyncEventReader::new();
+            let required_buffer_length = event_reader.read_required_buffer_length(&mut serialized_bytes_cursor).await.unwrap();
+            let mut read_buffer: Vec<u8> = vec![0; required_buffer_length];
+            let result = event_reader.read_event(&mut serialized_bytes_cursor, &mut read_buffer[..]).await;
+            if i == 2 {
+                assert!(matches!(result, Err(ReadEventError::ChecksumMismatch { .. })));
+            } else {
+                assert_eq!(result.unwrap(), *expected_event);
+            }
+        }
+        let mut event_reader = AsyncEventReader::new();
+        let result = event_reader.read_required_buffer_length(&mut serialized_bytes_cursor).await;
+        assert_eq!(result.map_err(|e| e.kind()), Err(ErrorKind::UnexpectedEof));
+    }
 }
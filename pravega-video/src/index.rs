@@ -14,8 +14,10 @@ use crate::event_serde::EventHeaderFlags;
 use crate::timestamp::PravegaTimestamp;
 use crate::utils::CurrentHead;
 use enumflags2::BitFlags;
+use std::cmp;
 use std::convert::TryInto;
 use std::io::{BufReader, Error, ErrorKind, Read, Write, Seek, SeekFrom};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
 use tracing::{debug, trace};
 
 pub fn get_index_stream_name(stream_name: &str) -> String {
@@ -149,9 +151,15 @@ impl IndexRecordReader {
     where
         R: Read,
     {
-        let mut buffer: Vec<u8> = vec![0; IndexRecord::RECORD_SIZE];
+        let mut buffer = [0; IndexRecord::RECORD_SIZE];
         rdr.read_exact(&mut buffer[..])?;
-        let flags = BitFlags::<EventHeaderFlags>::from_bits(buffer[3]).unwrap();
+        Self::decode(&buffer)
+    }
+
+    // Decodes an IndexRecord from the bytes read from the stream.
+    fn decode(buffer: &[u8; IndexRecord::RECORD_SIZE]) -> Result<IndexRecord, Error> {
+        let flags = BitFlags::<EventHeaderFlags>::from_bits(buffer[3])
+            .map_err(|_| Error::new(ErrorKind::InvalidData, format!("Invalid index record flags {:#010b}", buffer[3])))?;
         let random_access = flags.contains(EventHeaderFlags::RandomAccessIndicator);
         let discontinuity = flags.contains(EventHeaderFlags::DiscontinuityIndicator);
         let timestamp = u64::from_be_bytes(buffer[4..12].try_into().unwrap());
@@ -167,6 +175,254 @@ impl IndexRecordReader {
     }
 }
 
+/// The async counterpart of IndexRecordReader, for use with a tokio AsyncRead.
+pub struct AsyncIndexRecordReader {
+}
+
+impl AsyncIndexRecordReader {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub async fn read<R>(&mut self, rdr: &mut R) -> Result<IndexRecord, Error>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut buffer = [0; IndexRecord::RECORD_SIZE];
+        rdr.read_exact(&mut buffer[..]).await?;
+        IndexRecordReader::decode(&buffer)
+    }
+}
+
+/// Returns the number of complete records in an index that spans first_index_offset to tail_offset,
+/// and the offset of the last complete record.
+/// Any partial record at the tail is ignored.
+/// If the index has no records, returns an UnexpectedEof error.
+fn get_record_range(first_index_offset: u64, tail_offset: u64) -> Result<(u64, u64), Error> {
+    let record_size = IndexRecord::RECORD_SIZE as u64;
+    if tail_offset < first_index_offset + record_size {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "Index has no records"));
+    }
+    let num_records = (tail_offset - first_index_offset) / record_size;
+    let last_index_offset = first_index_offset + (num_records - 1) * record_size;
+    Ok((num_records, last_index_offset))
+}
+
+/// The next step of a search in an index.
+enum SearchStep {
+    /// The search needs the index record at this offset.
+    Read(u64),
+    /// The search is done. This contains the located index record and its offset.
+    Done(Result<(IndexRecord, u64), Error>),
+}
+
+/// A search that requests one index record at a time.
+/// This does not perform any I/O, so that IndexSearcher and AsyncIndexSearcher can share the search logic.
+trait SearchSteps {
+    fn start(&mut self) -> SearchStep;
+    fn advance(&mut self, index_record: IndexRecord) -> SearchStep;
+}
+
+/// The state of a TimestampSearch.
+/// Each index record is paired with its offset.
+enum TimestampSearchState {
+    /// Reading the last record.
+    Last,
+    /// Reading the first record.
+    First { last: Option<(IndexRecord, u64)> },
+    /// Reading the last record after the first record.
+    /// This only happens when searching for PravegaTimestamp::MIN and the first record has no timestamp.
+    LastAfterFirst { first: (IndexRecord, u64) },
+    /// Reading the record at mid.
+    Bisect { low: (IndexRecord, u64), high: (IndexRecord, u64), mid: u64 },
+    /// Reading the records after prev and before mid because the timestamps rewind between low and mid.
+    Scan { prev: (IndexRecord, u64), high: (IndexRecord, u64), mid: (IndexRecord, u64) },
+    Done,
+}
+
+/// A binary search for a timestamp between first_index_offset and last_index_offset (inclusive).
+/// See IndexSearcher::search_timestamp_and_return_index_offset.
+struct TimestampSearch {
+    timestamp: PravegaTimestamp,
+    method: SearchMethod,
+    first_index_offset: u64,
+    last_index_offset: u64,
+    state: TimestampSearchState,
+}
+
+impl TimestampSearch {
+    fn new(timestamp: PravegaTimestamp, method: SearchMethod, first_index_offset: u64, last_index_offset: u64) -> Self {
+        Self {
+            timestamp,
+            method,
+            first_index_offset,
+            last_index_offset,
+            state: TimestampSearchState::Done,
+        }
+    }
+
+    fn done(&mut self, found: (IndexRecord, u64)) -> SearchStep {
+        self.state = TimestampSearchState::Done;
+        SearchStep::Done(Ok(found))
+    }
+
+    /// The low record always has a timestamp less than the desired timestamp and
+    /// the high record always has a timestamp greater than or equal to the desired timestamp.
+    fn bisect(&mut self, low: (IndexRecord, u64), high: (IndexRecord, u64)) -> SearchStep {
+        let record_size = IndexRecord::RECORD_SIZE as u64;
+        if high.1 - low.1 > record_size {
+            let mid = low.1 + (high.1 - low.1) / record_size / 2 * record_size;
+            self.state = TimestampSearchState::Bisect { low, high, mid };
+            SearchStep::Read(mid)
+        } else if self.timestamp == high.0.timestamp {
+            // Exact match
+            self.done(high)
+        } else {
+            // Approximate match returns index record before or after desired timestamp, depending on method.
+            match self.method {
+                SearchMethod::Before => self.done(low),
+                SearchMethod::After => self.done(high),
+            }
+        }
+    }
+}
+
+impl SearchSteps for TimestampSearch {
+    fn start(&mut self) -> SearchStep {
+        if self.timestamp > PravegaTimestamp::MIN {
+            // TODO: Reading the last record may fail due to https://github.com/pravega/pravega-client-rust/issues/163.
+            self.state = TimestampSearchState::Last;
+            SearchStep::Read(self.last_index_offset)
+        } else {
+            self.state = TimestampSearchState::First { last: None };
+            SearchStep::Read(self.first_index_offset)
+        }
+    }
+
+    fn advance(&mut self, index_record: IndexRecord) -> SearchStep {
+        let record_size = IndexRecord::RECORD_SIZE as u64;
+        match std::mem::replace(&mut self.state, TimestampSearchState::Done) {
+            TimestampSearchState::Last => {
+                let last = (index_record, self.last_index_offset);
+                // Return last record if desired timestamp is after or equal to it.
+                if index_record.timestamp <= self.timestamp {
+                    return self.done(last);
+                }
+                self.state = TimestampSearchState::First { last: Some(last) };
+                SearchStep::Read(self.first_index_offset)
+            },
+            TimestampSearchState::First { last } => {
+                let first = (index_record, self.first_index_offset);
+                // Return first record if desired timestamp is before or equal to it.
+                if self.timestamp <= index_record.timestamp {
+                    return self.done(first);
+                }
+                match last {
+                    Some(last) => self.bisect(first, last),
+                    None => {
+                        self.state = TimestampSearchState::LastAfterFirst { first };
+                        SearchStep::Read(self.last_index_offset)
+                    },
+                }
+            },
+            TimestampSearchState::LastAfterFirst { first } => {
+                self.bisect(first, (index_record, self.last_index_offset))
+            },
+            TimestampSearchState::Bisect { low, high, mid } => {
+                let mid = (index_record, mid);
+                if index_record.timestamp < low.0.timestamp && low.1 + record_size < mid.1 {
+                    // The timestamps rewind between low and mid, so a sequential scan may stop before mid.
+                    debug!("TimestampSearch::advance: timestamps rewind between offsets {} and {}; scanning", low.1, mid.1);
+                    self.state = TimestampSearchState::Scan { prev: low, high, mid };
+                    return SearchStep::Read(low.1 + record_size);
+                }
+                if index_record.timestamp < self.timestamp {
+                    self.bisect(mid, high)
+                } else {
+                    self.bisect(low, mid)
+                }
+            },
+            TimestampSearchState::Scan { prev, high, mid } => {
+                let current = (index_record, prev.1 + record_size);
+                if self.timestamp < index_record.timestamp {
+                    // Approximate match returns index record before or after desired timestamp, depending on method.
+                    match self.method {
+                        SearchMethod::Before => self.done(prev),
+                        SearchMethod::After => self.done(current),
+                    }
+                } else if self.timestamp == index_record.timestamp {
+                    // Exact match
+                    self.done(current)
+                } else if current.1 + record_size < mid.1 {
+                    self.state = TimestampSearchState::Scan { prev: current, high, mid };
+                    SearchStep::Read(current.1 + record_size)
+                } else {
+                    // No record before mid reaches the desired timestamp, and mid is lower than low.
+                    self.bisect(mid, high)
+                }
+            },
+            TimestampSearchState::Done => SearchStep::Done(Err(Error::new(ErrorKind::Other, "Search is done"))),
+        }
+    }
+}
+
+/// A sequential search for a random-access record, beginning next to the record at index_offset.
+/// With SearchMethod::Before, this reads backwards to the head, then forwards from index_offset.
+/// With SearchMethod::After, this reads forwards to the tail.
+/// See IndexSearcher::search_timestamp_and_return_index_offset_with_filter.
+struct RandomAccessSearch {
+    first_index_offset: u64,
+    tail_offset: u64,
+    index_offset: u64,
+    forward: bool,
+    next_index_offset: u64,
+}
+
+impl RandomAccessSearch {
+    fn new(method: SearchMethod, first_index_offset: u64, tail_offset: u64, index_offset: u64) -> Self {
+        let record_size = IndexRecord::RECORD_SIZE as u64;
+        let backward = matches!(method, SearchMethod::Before) && index_offset >= first_index_offset + record_size;
+        Self {
+            first_index_offset,
+            tail_offset,
+            index_offset,
+            forward: !backward,
+            next_index_offset: if backward { index_offset - record_size } else { index_offset + record_size },
+        }
+    }
+
+    fn read_next(&self) -> SearchStep {
+        if self.forward && self.next_index_offset + IndexRecord::RECORD_SIZE as u64 > self.tail_offset {
+            SearchStep::Done(Err(Error::new(ErrorKind::NotFound, "Index has no random-access records")))
+        } else {
+            SearchStep::Read(self.next_index_offset)
+        }
+    }
+}
+
+impl SearchSteps for RandomAccessSearch {
+    fn start(&mut self) -> SearchStep {
+        self.read_next()
+    }
+
+    fn advance(&mut self, index_record: IndexRecord) -> SearchStep {
+        let record_size = IndexRecord::RECORD_SIZE as u64;
+        if index_record.random_access {
+            return SearchStep::Done(Ok((index_record, self.next_index_offset)));
+        }
+        if self.forward {
+            self.next_index_offset += record_size;
+        } else if self.next_index_offset >= self.first_index_offset + record_size {
+            self.next_index_offset -= record_size;
+        } else {
+            // There is no random-access record before the desired timestamp.
+            self.forward = true;
+            self.next_index_offset = self.index_offset + record_size;
+        }
+        self.read_next()
+    }
+}
+
 // A struct for searching an index.
 // The index can be stored in any object that implements Read and Seek, including a Pravega stream.
 pub struct IndexSearcher<R: Read + Seek + CurrentHead> {
@@ -174,7 +430,7 @@ pub struct IndexSearcher<R: Read + Seek + CurrentHead> {
     reader: BufReader<R>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum SearchMethod {
     /// If a non-exact match is found, return the index record immediately before the desired timestamp.
     Before,
@@ -182,6 +438,15 @@ pub enum SearchMethod {
     After,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchFilter {
+    /// Consider all index records.
+    All,
+    /// Only consider index records with random_access=true.
+    /// Use this to locate a position from which the stream can be decoded without errors.
+    RandomAccessOnly,
+}
+
 impl<R: Read + Seek + CurrentHead> IndexSearcher<R> {
     pub fn new(reader: R) -> Self {
         Self {
@@ -196,72 +461,179 @@ impl<R: Read + Seek + CurrentHead> IndexSearcher<R> {
     /// If the desired timestamp exceeds the first and last timestamp in the index, returns the nearest index record.
     /// If the index has no records, returns an UnexpectedEof error.
     /// Otherwise, it uses the specified SearchMethod.
-    /// TODO: Add flag to not consider records with random_access=false.
+    ///
+    /// Because all index records have the same size, this performs a binary search over the records
+    /// between the current head and tail of the index.
+    /// For an index with non-decreasing timestamps, this returns the same result as a sequential scan from the head,
+    /// which stops at the first record with a timestamp greater or equal to the desired timestamp.
+    /// If the timestamps are not monotonic (for example, after a discontinuity that rewound the clock),
+    /// the binary search may read a record with a lower timestamp than the low end of the search range.
+    /// It then scans the records between them sequentially, because a sequential scan may stop there.
+    /// A rewind that the binary search does not encounter is not detected, so the result is always a record
+    /// at which the timestamps cross the desired timestamp, but not necessarily the first such record.
+    /// This considers all index records, including those with random_access=false.
     /// TODO: Make this method private.
     pub fn search_timestamp_and_return_index_offset(&mut self, timestamp: PravegaTimestamp, method: SearchMethod)
             -> Result<(IndexRecord, u64), Error> {
 
         let result = (|| {
-            let mut index_record_reader = IndexRecordReader::new();
-
             let first_index_offset = self.reader.get_ref().current_head()?;
             let tail_offset = self.reader.seek(SeekFrom::End(0))?;
-            if tail_offset < first_index_offset + IndexRecord::RECORD_SIZE as u64 {
-                return Err(Error::new(ErrorKind::UnexpectedEof, "Index has no records"));
+            let (_, last_index_offset) = get_record_range(first_index_offset, tail_offset)?;
+            self.run(TimestampSearch::new(timestamp, method, first_index_offset, last_index_offset))
+        })();
+        debug!("IndexSearcher::search_timestamp_and_return_index_offset({}, {:?}) = {:?}", timestamp, method, result);
+        result
+    }
+
+    /// Reads the index records requested by a search until it is done.
+    /// Consecutive records are read without seeking so that the buffer is not discarded.
+    fn run<S: SearchSteps>(&mut self, mut search: S) -> Result<(IndexRecord, u64), Error> {
+        let mut index_record_reader = IndexRecordReader::new();
+        let mut position = None;
+        let mut step = search.start();
+        loop {
+            match step {
+                SearchStep::Read(index_offset) => {
+                    if position != Some(index_offset) {
+                        self.reader.seek(SeekFrom::Start(index_offset))?;
+                    }
+                    let index_record = index_record_reader.read(&mut self.reader)?;
+                    position = Some(index_offset + IndexRecord::RECORD_SIZE as u64);
+                    trace!("IndexSearcher::run: index_offset={}, index_record={:?}", index_offset, index_record);
+                    step = search.advance(index_record);
+                },
+                SearchStep::Done(result) => return result,
             }
+        }
+    }
 
-            // Get last record.
-            if timestamp > PravegaTimestamp::MIN {
-                let index_offset = self.reader.seek(SeekFrom::Start(tail_offset - IndexRecord::RECORD_SIZE as u64))?;
-                // TODO: Below may fail due to https://github.com/pravega/pravega-client-rust/issues/163.
-                let index_record = index_record_reader.read(&mut self.reader)?;
-                // Return last record if desired timestamp is after or equal to it.
-                if index_record.timestamp <= timestamp {
-                    return Ok((index_record, index_offset));
+    /// Same as search_timestamp_and_return_index_offset but only returns index records that match the filter.
+    /// With SearchFilter::RandomAccessOnly, if the record located by search_timestamp_and_return_index_offset
+    /// does not have random_access=true, the index is read sequentially
+    /// in the direction of the SearchMethod (backwards for Before, forwards for After)
+    /// until a random-access record is found.
+    /// If there is no random-access record before the desired timestamp, SearchMethod::Before
+    /// returns the first random-access record after it.
+    /// If no random-access record can be found, returns a NotFound error.
+    pub fn search_timestamp_and_return_index_offset_with_filter(&mut self, timestamp: PravegaTimestamp,
+            method: SearchMethod, filter: SearchFilter) -> Result<(IndexRecord, u64), Error> {
+        let (index_record, index_offset) = self.search_timestamp_and_return_index_offset(timestamp, method)?;
+        if filter == SearchFilter::All || index_record.random_access {
+            return Ok((index_record, index_offset));
+        }
+        let result = (|| {
+            let first_index_offset = self.reader.get_ref().current_head()?;
+            let tail_offset = self.reader.seek(SeekFrom::End(0))?;
+            self.run(RandomAccessSearch::new(method, first_index_offset, tail_offset, index_offset))
+        })();
+        debug!("IndexSearcher::search_timestamp_and_return_index_offset_with_filter({}, {:?}, {:?}) = {:?}",
+            timestamp, method, filter, result);
+        result
+    }
+
+    /// Returns the nearest random-access index record after (if forward) or before (if not forward)
+    /// the index record at index_offset, along with its offset in the index.
+    /// This is used to read only key frames, such as for trick-mode playback.
+    /// Returns an UnexpectedEof error if there is no such record.
+    pub fn next_random_access_record(&mut self, index_offset: u64, forward: bool) -> Result<(IndexRecord, u64), Error> {
+        let result = (|| {
+            let record_size = IndexRecord::RECORD_SIZE as u64;
+            let first_index_offset = self.reader.get_ref().current_head()?;
+            if forward {
+                let tail_offset = self.reader.seek(SeekFrom::End(0))?;
+                let mut next_index_offset = cmp::max(index_offset + record_size, first_index_offset);
+                self.reader.seek(SeekFrom::Start(next_index_offset))?;
+                let mut index_record_reader = IndexRecordReader::new();
+                while next_index_offset + record_size <= tail_offset {
+                    let next_index_record = index_record_reader.read(&mut self.reader)?;
+                    if next_index_record.random_access {
+                        return Ok((next_index_record, next_index_offset));
+                    }
+                    next_index_offset += record_size;
+                }
+            } else {
+                let mut prev_index_offset = index_offset;
+                while prev_index_offset >= first_index_offset + record_size {
+                    prev_index_offset -= record_size;
+                    let prev_index_record = self.read_record_at(prev_index_offset)?;
+                    if prev_index_record.random_access {
+                        return Ok((prev_index_record, prev_index_offset));
+                    }
                 }
             }
+            Err(Error::new(ErrorKind::UnexpectedEof, "No more random-access index records"))
+        })();
+        trace!("IndexSearcher::next_random_access_record({}, {}) = {:?}", index_offset, forward, result);
+        result
+    }
 
-            // Read first record.
-            let mut prev_index_offset = self.reader.seek(SeekFrom::Start(first_index_offset))?;
-            let mut prev_index_record = index_record_reader.read(&mut self.reader)?;
-            // Return first record if desired timestamp is before or equal to it.
-            if timestamp <= prev_index_record.timestamp {
-                return Ok((prev_index_record, prev_index_offset));
+    /// Returns the first random-access index record with a data offset greater than or equal to data_offset,
+    /// along with its offset in the index.
+    /// This is used to resume reading after the data stream has been truncated at data_offset.
+    /// Because data offsets increase with the index offset, this performs a binary search for the first record
+    /// at or after data_offset, then reads forward to the next random-access record.
+    /// Returns an UnexpectedEof error if there is no such record.
+    pub fn search_random_access_offset(&mut self, data_offset: u64) -> Result<(IndexRecord, u64), Error> {
+        let result = (|| {
+            let record_size = IndexRecord::RECORD_SIZE as u64;
+            let first_index_offset = self.reader.get_ref().current_head()?;
+            let tail_offset = self.reader.seek(SeekFrom::End(0))?;
+            let (_, last_index_offset) = get_record_range(first_index_offset, tail_offset)?;
+            // All records before low have a data offset less than data_offset and
+            // all records at or after high have a data offset greater or equal to data_offset.
+            let (mut low, mut high) = (first_index_offset, last_index_offset + record_size);
+            while low < high {
+                let mid = low + (high - low) / record_size / 2 * record_size;
+                let mid_record = self.read_record_at(mid)?;
+                trace!("IndexSearcher::search_random_access_offset: mid={}, index_record={:?}", mid, mid_record);
+                if mid_record.offset < data_offset {
+                    low = mid + record_size;
+                } else {
+                    high = mid;
+                }
             }
-
-            // Read index from the beginning until we find a timestamp greater or equal to the desired timestamp.
-            // TODO: Use binary search or Newton's method.
-            loop {
-                let index_record = index_record_reader.read(&mut self.reader)?;
-                let index_offset = prev_index_offset + IndexRecord::RECORD_SIZE as u64;
-                trace!("IndexSearcher::search_timestamp_and_return_index_offset: index_record={:?}", index_record);
-                if timestamp < index_record.timestamp {
-                    // Approximate match returns index record before or after desired timestamp, depending on method.
-                    return match method {
-                        SearchMethod::Before => Ok((prev_index_record, prev_index_offset)),
-                        SearchMethod::After => Ok((index_record, index_offset)),
-                    }
-                } else if timestamp == index_record.timestamp {
-                    // Exact match
-                    return Ok((index_record, index_offset));
+            let mut next_index_offset = high;
+            self.reader.seek(SeekFrom::Start(next_index_offset))?;
+            let mut index_record_reader = IndexRecordReader::new();
+            while next_index_offset + record_size <= tail_offset {
+                let next_index_record = index_record_reader.read(&mut self.reader)?;
+                if next_index_record.random_access {
+                    return Ok((next_index_record, next_index_offset));
                 }
-                prev_index_offset = index_offset;
-                prev_index_record = index_record;
+                next_index_offset += record_size;
             }
+            Err(Error::new(ErrorKind::UnexpectedEof, format!("No random-access index records at or after offset {}", data_offset)))
         })();
-        debug!("IndexSearcher::search_timestamp_and_return_index_offset({}, {:?}) = {:?}", timestamp, method, result);
+        debug!("IndexSearcher::search_random_access_offset({}) = {:?}", data_offset, result);
         result
     }
 
+    /// Read the index record at the specified offset in the index.
+    fn read_record_at(&mut self, index_offset: u64) -> Result<IndexRecord, Error> {
+        self.reader.seek(SeekFrom::Start(index_offset))?;
+        IndexRecordReader::new().read(&mut self.reader)
+    }
+
     /// If a non-exact match is found, return the index record immediately before the desired timestamp.
-    /// This is expected to be used to determine the offset at which to start reading.
-    /// TODO: This should only consider index records with random_access=true.
+    /// This will consider any index record, including those with random_access=false.
+    /// To ensure that decoding can begin at the located offset, use search_random_access_timestamp.
     pub fn search_timestamp(&mut self, timestamp: PravegaTimestamp) -> Result<IndexRecord, Error> {
         let result = self.search_timestamp_and_return_index_offset(timestamp, SearchMethod::Before);
         debug!("IndexSearcher::search_timestamp({}) = {:?}", timestamp, result);
         result.map(|x| x.0)
     }
 
+    /// Return the random-access index record on or immediately before the desired timestamp.
+    /// This is expected to be used to determine the offset at which to start reading
+    /// so that the first event read can be decoded without errors.
+    pub fn search_random_access_timestamp(&mut self, timestamp: PravegaTimestamp) -> Result<IndexRecord, Error> {
+        let result = self.search_timestamp_and_return_index_offset_with_filter(
+            timestamp, SearchMethod::Before, SearchFilter::RandomAccessOnly);
+        debug!("IndexSearcher::search_random_access_timestamp({}) = {:?}", timestamp, result);
+        result.map(|x| x.0)
+    }
+
     /// If a non-exact match is found, return the index record immediately after the desired timestamp.
     /// This will consider any index record, including those with random_access=false.
     /// This is expected to be used to determine the offset at which to stop reading.
@@ -271,14 +643,14 @@ impl<R: Read + Seek + CurrentHead> IndexSearcher<R> {
         result.map(|x| x.0)
     }
 
-    /// This is expected to be used to determine the offset at which to start reading.
-    /// TODO: This should only consider index records with random_access=true.
+    /// Returns the first record in the index.
+    /// This will consider any index record, including those with random_access=false.
     pub fn get_first_record(&mut self) -> Result<IndexRecord, Error> {
         self.search_timestamp(PravegaTimestamp::MIN)
     }
 
-    /// This is expected to be used to determine the offset at which to start reading.
-    /// TODO: This should only consider index records with random_access=true.
+    /// Returns the last record in the index.
+    /// This will consider any index record, including those with random_access=false.
     pub fn get_last_record(&mut self) -> Result<IndexRecord, Error> {
         self.search_timestamp(PravegaTimestamp::MAX)
     }
@@ -306,12 +678,112 @@ impl<R: Read + Seek + CurrentHead> IndexSearcher<R> {
     }
 }
 
+/// The async counterpart of IndexSearcher, for an index stored in any object that implements tokio AsyncRead and AsyncSeek.
+/// It shares the search logic with IndexSearcher, so it returns the same results.
+pub struct AsyncIndexSearcher<R: AsyncRead + AsyncSeek + Unpin + CurrentHead> {
+    reader: tokio::io::BufReader<R>,
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin + CurrentHead> AsyncIndexSearcher<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: tokio::io::BufReader::with_capacity(8*1024, reader),
+        }
+    }
+
+    /// See IndexSearcher::search_timestamp_and_return_index_offset.
+    pub async fn search_timestamp_and_return_index_offset(&mut self, timestamp: PravegaTimestamp, method: SearchMethod)
+            -> Result<(IndexRecord, u64), Error> {
+        let result = async {
+            let first_index_offset = self.reader.get_ref().current_head()?;
+            let tail_offset = self.reader.seek(SeekFrom::End(0)).await?;
+            let (_, last_index_offset) = get_record_range(first_index_offset, tail_offset)?;
+            self.run(TimestampSearch::new(timestamp, method, first_index_offset, last_index_offset)).await
+        }.await;
+        debug!("AsyncIndexSearcher::search_timestamp_and_return_index_offset({}, {:?}) = {:?}", timestamp, method, result);
+        result
+    }
+
+    /// See IndexSearcher::search_timestamp_and_return_index_offset_with_filter.
+    pub async fn search_timestamp_and_return_index_offset_with_filter(&mut self, timestamp: PravegaTimestamp,
+            method: SearchMethod, filter: SearchFilter) -> Result<(IndexRecord, u64), Error> {
+        let (index_record, index_offset) = self.search_timestamp_and_return_index_offset(timestamp, method).await?;
+        if filter == SearchFilter::All || index_record.random_access {
+            return Ok((index_record, index_offset));
+        }
+        let result = async {
+            let first_index_offset = self.reader.get_ref().current_head()?;
+            let tail_offset = self.reader.seek(SeekFrom::End(0)).await?;
+            self.run(RandomAccessSearch::new(method, first_index_offset, tail_offset, index_offset)).await
+        }.await;
+        debug!("AsyncIndexSearcher::search_timestamp_and_return_index_offset_with_filter({}, {:?}, {:?}) = {:?}",
+            timestamp, method, filter, result);
+        result
+    }
+
+    /// See IndexSearcher::run.
+    async fn run<S: SearchSteps>(&mut self, mut search: S) -> Result<(IndexRecord, u64), Error> {
+        let mut index_record_reader = AsyncIndexRecordReader::new();
+        let mut position = None;
+        let mut step = search.start();
+        loop {
+            match step {
+                SearchStep::Read(index_offset) => {
+                    if position != Some(index_offset) {
+                        self.reader.seek(SeekFrom::Start(index_offset)).await?;
+                    }
+                    let index_record = index_record_reader.read(&mut self.reader).await?;
+                    position = Some(index_offset + IndexRecord::RECORD_SIZE as u64);
+                    trace!("AsyncIndexSearcher::run: index_offset={}, index_record={:?}", index_offset, index_record);
+                    step = search.advance(index_record);
+                },
+                SearchStep::Done(result) => return result,
+            }
+        }
+    }
+
+    /// See IndexSearcher::search_timestamp.
+    pub async fn search_timestamp(&mut self, timestamp: PravegaTimestamp) -> Result<IndexRecord, Error> {
+        self.search_timestamp_and_return_index_offset(timestamp, SearchMethod::Before).await.map(|x| x.0)
+    }
+
+    /// See IndexSearcher::search_random_access_timestamp.
+    pub async fn search_random_access_timestamp(&mut self, timestamp: PravegaTimestamp) -> Result<IndexRecord, Error> {
+        self.search_timestamp_and_return_index_offset_with_filter(
+            timestamp, SearchMethod::Before, SearchFilter::RandomAccessOnly).await.map(|x| x.0)
+    }
+
+    /// See IndexSearcher::search_timestamp_after.
+    pub async fn search_timestamp_after(&mut self, timestamp: PravegaTimestamp) -> Result<IndexRecord, Error> {
+        self.search_timestamp_and_return_index_offset(timestamp, SearchMethod::After).await.map(|x| x.0)
+    }
+
+    /// See IndexSearcher::get_first_record.
+    pub async fn get_first_record(&mut self) -> Result<IndexRecord, Error> {
+        self.search_timestamp(PravegaTimestamp::MIN).await
+    }
+
+    /// See IndexSearcher::get_last_record.
+    pub async fn get_last_record(&mut self) -> Result<IndexRecord, Error> {
+        self.search_timestamp(PravegaTimestamp::MAX).await
+    }
+
+    /// Unwraps this `AsyncIndexSearcher<R>`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::index::{IndexRecord, IndexRecordWriter, IndexRecordReader, IndexSearcher, SearchMethod};
+    use crate::index::{IndexRecord, IndexRecordWriter, IndexRecordReader, IndexSearcher, SearchFilter, SearchMethod,
+        AsyncIndexRecordReader, AsyncIndexSearcher};
     use crate::timestamp::PravegaTimestamp;
+    use crate::utils::CurrentHead;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
     use tracing::info;
-    use std::io::Cursor;
+    use std::io::{Cursor, ErrorKind, Read, Seek, SeekFrom};
 
     #[test]
     fn test_index_writer_reader() {
@@ -401,4 +873,352 @@ mod test {
             }
         }
     }
+
+    /// Sequential search through a list of index records.
+    /// This is the reference implementation for the binary search in IndexSearcher.
+    fn linear_search(records: &[IndexRecord], timestamp: PravegaTimestamp, method: SearchMethod) -> (IndexRecord, u64) {
+        let offset = |i: usize| (i * IndexRecord::RECORD_SIZE) as u64;
+        let last = records.len() - 1;
+        if timestamp > PravegaTimestamp::MIN && records[last].timestamp <= timestamp {
+            return (records[last], offset(last));
+        }
+        if timestamp <= records[0].timestamp {
+            return (records[0], offset(0));
+        }
+        for i in 1..records.len() {
+            if timestamp < records[i].timestamp {
+                return match method {
+                    SearchMethod::Before => (records[i - 1], offset(i - 1)),
+                    SearchMethod::After => (records[i], offset(i)),
+                };
+            } else if timestamp == records[i].timestamp {
+                return (records[i], offset(i));
+            }
+        }
+        unreachable!()
+    }
+
+    fn create_index(timestamps: &[u64]) -> (Vec<IndexRecord>, Cursor<Vec<u8>>) {
+        let mut index_records = Vec::new();
+        let mut memory_index_cursor = Cursor::new(Vec::new());
+        let mut index_record_writer = IndexRecordWriter::new();
+        for (i, timestamp) in timestamps.iter().enumerate() {
+            let rec = IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(*timestamp)),
+                1000 * i as u64, true, false);
+            index_record_writer.write(&rec, &mut memory_index_cursor).unwrap();
+            index_records.push(rec);
+        }
+        (index_records, memory_index_cursor)
+    }
+
+    #[test]
+    fn test_index_searcher_binary_search() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        for num_recs in [1, 2, 3, 4, 5, 17, 1000].iter() {
+            // Create an index with non-decreasing timestamps, including some duplicates.
+            let mut timestamps = Vec::new();
+            let mut timestamp = 1_600_000_000_000_000_000;
+            for _ in 0..*num_recs {
+                timestamps.push(timestamp);
+                timestamp += rng.gen_range(0..3) * 1000;
+            }
+            let (index_records, memory_index_cursor) = create_index(&timestamps);
+            let mut index_searcher = IndexSearcher::new(memory_index_cursor);
+            let min_timestamp = timestamps[0] - 2000;
+            let max_timestamp = timestamps[num_recs - 1] + 2000;
+            for search_timestamp in (min_timestamp..max_timestamp).step_by(500).chain([0, 1, u64::MAX].iter().cloned()) {
+                let search_timestamp = PravegaTimestamp::from_nanoseconds(Some(search_timestamp));
+                for method in [SearchMethod::Before, SearchMethod::After].iter() {
+                    let expected = linear_search(&index_records, search_timestamp, *method);
+                    let found = index_searcher.search_timestamp_and_return_index_offset(search_timestamp, *method).unwrap();
+                    assert_eq!(found, expected, "num_recs={}, search_timestamp={:?}, method={:?}", num_recs, search_timestamp, method);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_index_searcher_non_monotonic() {
+        // The clock rewinds after the 5th record.
+        let timestamps: Vec<u64> = vec![100, 200, 300, 400, 500, 150, 250, 350, 450, 550, 650, 750, 850]
+            .iter().map(|t| 1_600_000_000_000_000_000 + t).collect();
+        let (index_records, memory_index_cursor) = create_index(&timestamps);
+        let mut index_searcher = IndexSearcher::new(memory_index_cursor);
+        for search_timestamp in timestamps[0] - 10..timestamps[timestamps.len() - 1] + 10 {
+            let search_timestamp = PravegaTimestamp::from_nanoseconds(Some(search_timestamp));
+            for method in [SearchMethod::Before, SearchMethod::After].iter() {
+                let found = index_searcher.search_timestamp_and_return_index_offset(search_timestamp, *method).unwrap();
+                assert_transition(&index_records, found, search_timestamp, *method);
+            }
+        }
+
+
+        // The clock rewinds after the 7th record.
+        // The binary search reads 50 (the 8th record) which is less than 100 (the 1st record),
+        // so it scans the records between them and returns the same record as a sequential scan.
+        let timestamps: Vec<u64> = vec![100, 200, 300, 400, 500, 600, 700, 50, 150, 250, 350, 450, 550, 650, 750]
+            .iter().map(|t| 1_600_000_000_000_000_000 + t).collect();
+        let (index_records, memory_index_cursor) = create_index(&timestamps);
+        let mut index_searcher = IndexSearcher::new(memory_index_cursor);
+        for t in [320, 400, 620, 720].iter() {
+            let search_timestamp = PravegaTimestamp::from_nanoseconds(Some(1_600_000_000_000_000_000 + t));
+            for method in [SearchMethod::Before, SearchMethod::After].iter() {
+                let found = index_searcher.search_timestamp_and_return_index_offset(search_timestamp, *method).unwrap();
+                assert_eq!(found, linear_search(&index_records, search_timestamp, *method), "t={}, method={:?}", t, method);
+            }
+        }
+    }
+
+    /// Asserts that the located record is an exact match or is at a transition across the desired timestamp.
+    fn assert_transition(records: &[IndexRecord], found: (IndexRecord, u64), timestamp: PravegaTimestamp, method: SearchMethod) {
+        let i = (found.1 / IndexRecord::RECORD_SIZE as u64) as usize;
+        assert_eq!(found.0, records[i]);
+        let last = records.len() - 1;
+        let at_transition = records[i].timestamp == timestamp
+            || (i == 0 && timestamp < records[0].timestamp)
+            || (i == last && records[last].timestamp < timestamp)
+            || match method {
+                SearchMethod::Before => i < last && records[i].timestamp < timestamp && timestamp < records[i + 1].timestamp,
+                SearchMethod::After => i > 0 && records[i - 1].timestamp < timestamp && timestamp < records[i].timestamp,
+            };
+        assert!(at_transition, "i={}, timestamp={:?}, method={:?}, records={:?}", i, timestamp, method, records);
+    }
+
+    #[test]
+    fn test_index_searcher_non_monotonic_random() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        for num_recs in [2, 3, 5, 17, 200].iter() {
+            // Create an index in which the clock occasionally rewinds.
+            let mut timestamps = Vec::new();
+            let mut timestamp: u64 = 1_600_000_000_000_000_000;
+            for _ in 0..*num_recs {
+                timestamps.push(timestamp);
+                if rng.gen_range(0..10) == 0 {
+                    timestamp -= rng.gen_range(1..20) * 1000;
+                } else {
+                    timestamp += rng.gen_range(0..3) * 1000;
+                }
+            }
+            let (index_records, memory_index_cursor) = create_index(&timestamps);
+            let mut index_searcher = IndexSearcher::new(memory_index_cursor);
+            let min_timestamp = *timestamps.iter().min().unwrap() - 2000;
+            let max_timestamp = *timestamps.iter().max().unwrap() + 2000;
+            for search_timestamp in (min_timestamp..max_timestamp).step_by(500) {
+                let search_timestamp = PravegaTimestamp::from_nanoseconds(Some(search_timestamp));
+                for method in [SearchMethod::Before, SearchMethod::After].iter() {
+                    let found = index_searcher.search_timestamp_and_return_index_offset(search_timestamp, *method).unwrap();
+                    assert_transition(&index_records, found, search_timestamp, *method);
+                }
+            }
+        }
+    }
+
+    /// A reader that counts the number of bytes read.
+    struct CountingReader {
+        inner: Cursor<Vec<u8>>,
+        bytes_read: u64,
+    }
+
+    impl Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.bytes_read += n as u64;
+            Ok(n)
+        }
+    }
+
+    impl Seek for CountingReader {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    impl CurrentHead for CountingReader {}
+
+    #[test]
+    fn test_index_searcher_reads_few_records() {
+        // An index of 20 MB in which the clock rewinds once in the middle.
+        let num_recs = 1_000_000;
+        let timestamps: Vec<u64> = (0..num_recs)
+            .map(|i| 1_600_000_000_000_000_000 + if i < num_recs / 2 { 1000 * i } else { 1000 * i - 100_000 })
+            .collect();
+        let (index_records, memory_index_cursor) = create_index(&timestamps);
+        let mut index_searcher = IndexSearcher::new(CountingReader { inner: memory_index_cursor, bytes_read: 0 });
+        for i in [1, num_recs / 3, num_recs / 2 + 50, num_recs - 2].iter() {
+            let search_timestamp = PravegaTimestamp::from_nanoseconds(Some(timestamps[*i as usize] + 500));
+            let found = index_searcher.search_timestamp_and_return_index_offset(search_timestamp, SearchMethod::Before).unwrap();
+            assert_eq!(found, linear_search(&index_records, search_timestamp, SearchMethod::Before));
+        }
+        let bytes_read = index_searcher.into_inner().bytes_read;
+        assert!(bytes_read < 1024 * 1024, "bytes_read={}", bytes_read);
+    }
+
+    #[test]
+    fn test_index_searcher_random_access_only() {
+        let base = 1_600_000_000_000_000_000;
+        let random_access = [false, true, false, false, true, true, false];
+        let mut index_records = Vec::new();
+        let mut memory_index_cursor = Cursor::new(Vec::new());
+        let mut index_record_writer = IndexRecordWriter::new();
+        for (i, random_access) in random_access.iter().enumerate() {
+            let rec = IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(base + 1000 * i as u64)),
+                100 * i as u64, *random_access, false);
+            index_record_writer.write(&rec, &mut memory_index_cursor).unwrap();
+            index_records.push(rec);
+        }
+        let mut index_searcher = IndexSearcher::new(memory_index_cursor);
+        let search = |index_searcher: &mut IndexSearcher<Cursor<Vec<u8>>>, t: u64, method: SearchMethod| {
+            let found = index_searcher.search_timestamp_and_return_index_offset_with_filter(
+                PravegaTimestamp::from_nanoseconds(Some(base + t)), method, SearchFilter::RandomAccessOnly).unwrap();
+            assert!(found.0.random_access);
+            assert_eq!(found.0, index_records[(found.1 / IndexRecord::RECORD_SIZE as u64) as usize]);
+            found.1 / IndexRecord::RECORD_SIZE as u64
+        };
+        // Before the first random-access record, the first random-access record is returned.
+        assert_eq!(search(&mut index_searcher, 0, SearchMethod::Before), 1);
+        assert_eq!(search(&mut index_searcher, 1000, SearchMethod::Before), 1);
+        assert_eq!(search(&mut index_searcher, 3500, SearchMethod::Before), 1);
+        assert_eq!(search(&mut index_searcher, 4000, SearchMethod::Before), 4);
+        assert_eq!(search(&mut index_searcher, 6000, SearchMethod::Before), 5);
+        assert_eq!(search(&mut index_searcher, 99000, SearchMethod::Before), 5);
+        assert_eq!(search(&mut index_searcher, 1500, SearchMethod::After), 4);
+        assert_eq!(search(&mut index_searcher, 4500, SearchMethod::After), 5);
+        assert_eq!(index_searcher.search_random_access_timestamp(
+            PravegaTimestamp::from_nanoseconds(Some(base + 2500))).unwrap(), index_records[1]);
+        // There is no random-access record after the last record.
+        let result = index_searcher.search_timestamp_and_return_index_offset_with_filter(
+            PravegaTimestamp::from_nanoseconds(Some(base + 5500)), SearchMethod::After, SearchFilter::RandomAccessOnly);
+        assert_eq!(result.map_err(|e| e.kind()), Err(ErrorKind::NotFound));
+        // Without a filter, any record can be returned.
+        assert_eq!(index_searcher.search_timestamp(PravegaTimestamp::from_nanoseconds(Some(base + 2500))).unwrap(),
+            index_records[2]);
+    }
+
+    #[test]
+    fn test_index_searcher_next_random_access_record() {
+        let base = 1_600_000_000_000_000_000;
+        let random_access = [false, true, false, false, true, true, false];
+        let mut index_records = Vec::new();
+        let mut memory_index_cursor = Cursor::new(Vec::new());
+        let mut index_record_writer = IndexRecordWriter::new();
+        for (i, random_access) in random_access.iter().enumerate() {
+            let rec = IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(base + 1000 * i as u64)),
+                100 * i as u64, *random_access, false);
+            index_record_writer.write(&rec, &mut memory_index_cursor).unwrap();
+            index_records.push(rec);
+        }
+        let mut index_searcher = IndexSearcher::new(memory_index_cursor);
+        let record_size = IndexRecord::RECORD_SIZE as u64;
+        let next = |index_searcher: &mut IndexSearcher<Cursor<Vec<u8>>>, i: u64, forward: bool| {
+            index_searcher.next_random_access_record(i * record_size, forward)
+                .map(|(index_record, index_offset)| {
+                    assert_eq!(index_record, index_records[(index_offset / record_size) as usize]);
+                    index_offset / record_size
+                })
+                .map_err(|e| e.kind())
+        };
+        assert_eq!(next(&mut index_searcher, 0, true), Ok(1));
+        assert_eq!(next(&mut index_searcher, 1, true), Ok(4));
+        assert_eq!(next(&mut index_searcher, 4, true), Ok(5));
+        assert_eq!(next(&mut index_searcher, 5, true), Err(ErrorKind::UnexpectedEof));
+        assert_eq!(next(&mut index_searcher, 6, false), Ok(5));
+        assert_eq!(next(&mut index_searcher, 5, false), Ok(4));
+        assert_eq!(next(&mut index_searcher, 4, false), Ok(1));
+        assert_eq!(next(&mut index_searcher, 1, false), Err(ErrorKind::UnexpectedEof));
+        assert_eq!(next(&mut index_searcher, 0, false), Err(ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn test_index_searcher_search_random_access_offset() {
+        let base = 1_600_000_000_000_000_000;
+        let random_access = [true, false, true, false, false, true];
+        let mut memory_index_cursor = Cursor::new(Vec::new());
+        let mut index_record_writer = IndexRecordWriter::new();
+        for (i, random_access) in random_access.iter().enumerate() {
+            let rec = IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(base + 1000 * i as u64)),
+                100 * i as u64, *random_access, false);
+            index_record_writer.write(&rec, &mut memory_index_cursor).unwrap();
+        }
+        let mut index_searcher = IndexSearcher::new(memory_index_cursor);
+        let record_size = IndexRecord::RECORD_SIZE as u64;
+        let search = |index_searcher: &mut IndexSearcher<Cursor<Vec<u8>>>, data_offset: u64| {
+            index_searcher.search_random_access_offset(data_offset)
+                .map(|(index_record, index_offset)| {
+                    assert_eq!(index_record.offset, 100 * index_offset / record_size);
+                    index_record.offset
+                })
+                .map_err(|e| e.kind())
+        };
+        assert_eq!(search(&mut index_searcher, 0), Ok(0));
+        assert_eq!(search(&mut index_searcher, 1), Ok(200));
+        assert_eq!(search(&mut index_searcher, 200), Ok(200));
+        assert_eq!(search(&mut index_searcher, 250), Ok(500));
+        assert_eq!(search(&mut index_searcher, 501), Err(ErrorKind::UnexpectedEof));
+        for data_offset in 0..600 {
+            let expected = random_access.iter().enumerate()
+                .find(|(i, random_access)| **random_access && 100 * *i as u64 >= data_offset)
+                .map(|(i, _)| 100 * i as u64)
+                .ok_or(ErrorKind::UnexpectedEof);
+            assert_eq!(search(&mut index_searcher, data_offset), expected, "data_offset={}", data_offset);
+        }
+        assert_eq!(search(&mut IndexSearcher::new(Cursor::new(Vec::new())), 0), Err(ErrorKind::UnexpectedEof));
+    }
+
+    #[tokio::test]
+    async fn test_async_index_record_reader() {
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        let base = 1_600_000_000_000_000_000;
+        let mut memory_index_cursor = Cursor::new(Vec::new());
+        let mut index_record_writer = IndexRecordWriter::new();
+        let mut index_records = Vec::new();
+        for i in 0..17 {
+            let rec = IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(base + 1000 * i as u64)),
+                100 * i as u64, rng.gen_bool(0.5), rng.gen_bool(0.1));
+            index_record_writer.write(&rec, &mut memory_index_cursor).unwrap();
+            index_records.push(rec);
+        }
+        memory_index_cursor.set_position(0);
+        let mut async_index_record_reader = AsyncIndexRecordReader::new();
+        for rec in index_records.iter() {
+            assert_eq!(async_index_record_reader.read(&mut memory_index_cursor).await.unwrap(), *rec);
+        }
+        // A partial record at the tail is not returned.
+        let mut async_cursor = Cursor::new(vec![0; IndexRecord::RECORD_SIZE - 1]);
+        let result = async_index_record_reader.read(&mut async_cursor).await;
+        assert_eq!(result.map_err(|e| e.kind()), Err(ErrorKind::UnexpectedEof));
+    }
+
+    #[tokio::test]
+    async fn test_async_index_searcher() {
+        let mut rng = ChaCha8Rng::seed_from_u64(4);
+        for num_recs in [1, 2, 17, 200].iter() {
+            // Create an index with some duplicate and decreasing timestamps and some records without random access.
+            let base = 1_600_000_000_000_000_000;
+            let mut memory_index_cursor = Cursor::new(Vec::new());
+            let mut index_record_writer = IndexRecordWriter::new();
+            for i in 0..*num_recs {
+                let timestamp = base + 1000 * i as u64 - rng.gen_range(0..3) * 1000;
+                let rec = IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(timestamp)),
+                    100 * i as u64, rng.gen_bool(0.5), false);
+                index_record_writer.write(&rec, &mut memory_index_cursor).unwrap();
+            }
+            let mut index_searcher = IndexSearcher::new(memory_index_cursor.clone());
+            let mut async_index_searcher = AsyncIndexSearcher::new(memory_index_cursor);
+            for t in (0..1000 * (*num_recs as u64 + 2)).step_by(250) {
+                let timestamp = PravegaTimestamp::from_nanoseconds(Some(base + t - 2000));
+                for method in [SearchMethod::Before, SearchMethod::After].iter() {
+                    for filter in [SearchFilter::All, SearchFilter::RandomAccessOnly].iter() {
+                        let expected = index_searcher.search_timestamp_and_return_index_offset_with_filter(
+                            timestamp, *method, *filter).map_err(|e| e.kind());
+                        let found = async_index_searcher.search_timestamp_and_return_index_offset_with_filter(
+                            timestamp, *method, *filter).await.map_err(|e| e.kind());
+                        assert_eq!(found, expected, "num_recs={}, timestamp={:?}, method={:?}, filter={:?}",
+                            num_recs, timestamp, method, filter);
+                    }
+                }
+            }
+            assert_eq!(async_index_searcher.get_first_record().await.unwrap(), index_searcher.get_first_record().unwrap());
+            assert_eq!(async_index_searcher.get_last_record().await.unwrap(), index_searcher.get_last_record().unwrap());
+        }
+    }
 }
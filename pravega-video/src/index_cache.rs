@@ -0,0 +1,387 @@
+//
+// Copyright (c) Dell Inc., or its subsidiaries. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+
+// Module for keeping an in-memory copy of an index stream.
+
+use crate::index::{AsyncIndexSearcher, IndexRecord, IndexSearcher};
+use crate::utils::CurrentHead;
+use std::cmp;
+use std::convert::TryFrom;
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+use tracing::debug;
+
+/// An in-memory copy of an index stream that can be shared by any number of threads.
+///
+/// The first call to update() reads the entire index.
+/// Each subsequent call reads only the records that were appended since the previous call
+/// and drops any records that have been truncated from the index stream.
+/// Searches are performed on an immutable snapshot of the cache (see snapshot(), searcher() and async_searcher()),
+/// so they never perform I/O and they do not block updates.
+pub struct IndexCache<R: Read + Seek + CurrentHead> {
+    reader: Mutex<R>,
+    snapshot: Mutex<IndexSnapshot>,
+}
+
+impl<R: Read + Seek + CurrentHead> IndexCache<R> {
+    /// Creates an empty cache. Call update() to populate it.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: Mutex::new(reader),
+            snapshot: Mutex::new(IndexSnapshot::new(0, Arc::new(Vec::new()))),
+        }
+    }
+
+    /// Reads new index records from the index stream and drops any records that have been truncated.
+    /// A partial record at the tail of the index stream is ignored until it has been completely written.
+    /// Concurrent calls are serialized.
+    pub fn update(&self) -> Result<(), Error> {
+        let record_size = IndexRecord::RECORD_SIZE as u64;
+        let mut reader = self.reader.lock().unwrap();
+        let head_offset = reader.current_head()?;
+        let tail_offset = reader.seek(SeekFrom::End(0))?;
+        let (cached_head_offset, cached_tail_offset) = {
+            let snapshot = self.snapshot.lock().unwrap();
+            (snapshot.head_offset, snapshot.tail_offset())
+        };
+
+        // If all cached records have been truncated, or if the index stream is shorter than the cache,
+        // discard the cache and read the index from its current head.
+        let reset = head_offset >= cached_tail_offset || tail_offset < cached_tail_offset;
+        let read_offset = if reset { head_offset } else { cached_tail_offset };
+        let read_length = (tail_offset.saturating_sub(read_offset) / record_size) * record_size;
+        let mut new_bytes = vec![0; read_length as usize];
+        if read_length > 0 {
+            reader.seek(SeekFrom::Start(read_offset))?;
+            reader.read_exact(&mut new_bytes[..])?;
+        }
+
+        let mut snapshot = self.snapshot.lock().unwrap();
+        if reset {
+            *snapshot = IndexSnapshot::new(read_offset, Arc::new(new_bytes));
+        } else {
+            let truncated_records = (head_offset.saturating_sub(cached_head_offset) + record_size - 1) / record_size;
+            let truncated_length = truncated_records * record_size;
+            if truncated_length > 0 || read_length > 0 {
+                // This copies the cached records only if a previous snapshot is still in use.
+                let bytes = Arc::make_mut(&mut snapshot.bytes);
+                bytes.drain(..truncated_length as usize);
+                bytes.extend_from_slice(&new_bytes[..]);
+                snapshot.head_offset = cached_head_offset + truncated_length;
+            }
+        }
+        debug!("IndexCache::update: head_offset={}, tail_offset={}, cached_head_offset={}, cached_tail_offset={}",
+            head_offset, tail_offset, snapshot.head_offset, snapshot.tail_offset());
+        Ok(())
+    }
+
+    /// Returns the current contents of the cache.
+    /// The returned snapshot does not change when the cache is updated.
+    pub fn snapshot(&self) -> IndexSnapshot {
+        self.snapshot.lock().unwrap().clone()
+    }
+
+    /// Returns an IndexSearcher for the current contents of the cache.
+    /// Index offsets returned by the searcher are offsets in the index stream.
+    pub fn searcher(&self) -> IndexSearcher<IndexSnapshot> {
+        IndexSearcher::new(self.snapshot())
+    }
+
+    /// Returns an AsyncIndexSearcher for the current contents of the cache.
+    /// Because the snapshot is in memory, searches never wait, so they can run on any async runtime thread.
+    pub fn async_searcher(&self) -> AsyncIndexSearcher<IndexSnapshot> {
+        AsyncIndexSearcher::new(self.snapshot())
+    }
+}
+
+/// An immutable copy of the complete records in an index stream between a head and tail offset.
+/// It implements Read and Seek, and the tokio AsyncRead and AsyncSeek, using the same offsets as the index stream.
+/// Unlike a Pravega byte stream reader, reading at the tail returns EOF instead of waiting for more data.
+#[derive(Debug, Clone)]
+pub struct IndexSnapshot {
+    head_offset: u64,
+    bytes: Arc<Vec<u8>>,
+    position: u64,
+}
+
+impl IndexSnapshot {
+    fn new(head_offset: u64, bytes: Arc<Vec<u8>>) -> Self {
+        Self {
+            head_offset,
+            bytes,
+            position: head_offset,
+        }
+    }
+
+    /// The offset of the first record in the snapshot.
+    pub fn head_offset(&self) -> u64 {
+        self.head_offset
+    }
+
+    /// The offset immediately after the last record in the snapshot.
+    pub fn tail_offset(&self) -> u64 {
+        self.head_offset + self.bytes.len() as u64
+    }
+}
+
+impl Read for IndexSnapshot {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if self.position < self.head_offset {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                format!("Offset {} has been truncated; the index begins at offset {}", self.position, self.head_offset)));
+        }
+        let begin = cmp::min(self.position - self.head_offset, self.bytes.len() as u64) as usize;
+        let length = cmp::min(buf.len(), self.bytes.len() - begin);
+        buf[..length].copy_from_slice(&self.bytes[begin..begin + length]);
+        self.position += length as u64;
+        Ok(length)
+    }
+}
+
+impl Seek for IndexSnapshot {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => checked_add_signed(self.tail_offset(), delta),
+            SeekFrom::Current(delta) => checked_add_signed(self.position, delta),
+        };
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            },
+            None => Err(Error::new(ErrorKind::InvalidInput, "Invalid seek to a negative or overflowing position")),
+        }
+    }
+}
+
+impl AsyncRead for IndexSnapshot {
+    fn poll_read(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<Result<(), Error>> {
+        let length = Read::read(self.get_mut(), buf.initialize_unfilled())?;
+        buf.advance(length);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for IndexSnapshot {
+    fn start_seek(self: Pin<&mut Self>, pos: SeekFrom) -> Result<(), Error> {
+        Seek::seek(self.get_mut(), pos).map(|_| ())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<u64, Error>> {
+        Poll::Ready(Ok(self.position))
+    }
+}
+
+impl CurrentHead for IndexSnapshot {
+    fn current_head(&self) -> std::io::Result<u64> {
+        Ok(self.head_offset)
+    }
+}
+
+fn checked_add_signed(base: u64, delta: i64) -> Option<u64> {
+    u64::try_from(base as i128 + delta as i128).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::index::{IndexRecordWriter, SearchFilter, SearchMethod};
+    use crate::timestamp::PravegaTimestamp;
+    use std::io::Cursor;
+
+    /// An in-memory byte stream that can be appended to and truncated while a reader is open.
+    #[derive(Clone, Default)]
+    struct SharedStream {
+        data: Arc<Mutex<Vec<u8>>>,
+        head: Arc<Mutex<u64>>,
+        position: u64,
+    }
+
+    impl Read for SharedStream {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            let data = self.data.lock().unwrap();
+            let mut cursor = Cursor::new(&data[..]);
+            cursor.set_position(self.position);
+            let n = cursor.read(buf)?;
+            self.position += n as u64;
+            Ok(n)
+        }
+    }
+
+    impl Seek for SharedStream {
+        fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error> {
+            let data = self.data.lock().unwrap();
+            let mut cursor = Cursor::new(&data[..]);
+            cursor.set_position(self.position);
+            self.position = cursor.seek(pos)?;
+            Ok(self.position)
+        }
+    }
+
+    impl CurrentHead for SharedStream {
+        fn current_head(&self) -> std::io::Result<u64> {
+            Ok(*self.head.lock().unwrap())
+        }
+    }
+
+    fn append_records(stream: &SharedStream, first: u64, count: u64) -> Vec<IndexRecord> {
+        let mut records = Vec::new();
+        let mut data = stream.data.lock().unwrap();
+        let mut writer = Cursor::new(Vec::new());
+        for i in first..first + count {
+            let record = IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(1_600_000_000_000_000_000 + 1000 * i)),
+                100 * i, i % 3 == 0, false);
+            IndexRecordWriter::new().write(&record, &mut writer).unwrap();
+            records.push(record);
+        }
+        data.extend_from_slice(writer.get_ref());
+        records
+    }
+
+    #[test]
+    fn test_index_cache_tailing() {
+        let stream = SharedStream::default();
+        let cache = IndexCache::new(stream.clone());
+        cache.update().unwrap();
+        assert_eq!(cache.searcher().get_first_record().map_err(|e| e.kind()), Err(ErrorKind::UnexpectedEof));
+
+        let mut records = append_records(&stream, 0, 5);
+        cache.update().unwrap();
+        let snapshot_with_5 = cache.snapshot();
+        records.extend(append_records(&stream, 5, 10));
+        // A partial record at the tail must be ignored.
+        stream.data.lock().unwrap().extend_from_slice(&[0; 7]);
+        cache.update().unwrap();
+        assert_eq!(cache.snapshot().tail_offset(), (15 * IndexRecord::RECORD_SIZE) as u64);
+        assert_eq!(cache.searcher().get_index_records().unwrap().len(), 15);
+
+        // The cache must return the same results as a searcher that reads the stream.
+        let mut stream_searcher = IndexSearcher::new(Cursor::new(stream.data.lock().unwrap()[..15 * IndexRecord::RECORD_SIZE].to_vec()));
+        let mut cache_searcher = cache.searcher();
+        for t in (0..20_000).step_by(250) {
+            let timestamp = PravegaTimestamp::from_nanoseconds(Some(1_600_000_000_000_000_000 - 2000 + t));
+            for method in [SearchMethod::Before, SearchMethod::After].iter() {
+                for filter in [SearchFilter::All, SearchFilter::RandomAccessOnly].iter() {
+                    assert_eq!(
+                        cache_searcher.search_timestamp_and_return_index_offset_with_filter(timestamp, *method, *filter)
+                            .map_err(|e| e.kind()),
+                        stream_searcher.search_timestamp_and_return_index_offset_with_filter(timestamp, *method, *filter)
+                            .map_err(|e| e.kind()));
+                }
+            }
+        }
+        assert_eq!(cache_searcher.get_last_record().unwrap(), records[14]);
+
+        // An earlier snapshot is not affected by updates.
+        let mut searcher_with_5 = IndexSearcher::new(snapshot_with_5);
+        assert_eq!(searcher_with_5.get_last_record().unwrap(), records[4]);
+    }
+
+    #[test]
+    fn test_index_cache_truncation() {
+        let record_size = IndexRecord::RECORD_SIZE as u64;
+        let stream = SharedStream::default();
+        let cache = IndexCache::new(stream.clone());
+        let records = append_records(&stream, 0, 10);
+        cache.update().unwrap();
+        let old_snapshot = cache.snapshot();
+
+        // Truncate the first 4 records.
+        *stream.head.lock().unwrap() = 4 * record_size;
+        cache.update().unwrap();
+        let mut searcher = cache.searcher();
+        assert_eq!(searcher.search_timestamp_and_return_index_offset(PravegaTimestamp::MIN, SearchMethod::Before).unwrap(),
+            (records[4], 4 * record_size));
+        assert_eq!(searcher.get_index_records().unwrap().len(), 6);
+        let mut snapshot = cache.snapshot();
+        snapshot.seek(SeekFrom::Start(0)).unwrap();
+        assert_eq!(snapshot.read(&mut [0; 1]).map_err(|e| e.kind()), Err(ErrorKind::InvalidInput));
+        assert_eq!(IndexSearcher::new(old_snapshot).get_first_record().unwrap(), records[0]);
+
+        // Truncate all cached records and append new ones.
+        *stream.head.lock().unwrap() = 10 * record_size;
+        let new_records = append_records(&stream, 10, 2);
+        cache.update().unwrap();
+        assert_eq!(cache.searcher().get_index_records().unwrap(),
+            vec![(new_records[0], 10 * record_size), (new_records[1], 11 * record_size)]);
+    }
+
+    #[tokio::test]
+    async fn test_index_cache_async_searcher() {
+        let record_size = IndexRecord::RECORD_SIZE as u64;
+        let stream = SharedStream::default();
+        let cache = IndexCache::new(stream.clone());
+        let records = append_records(&stream, 0, 20);
+        *stream.head.lock().unwrap() = 4 * record_size;
+        cache.update().unwrap();
+        let mut searcher = cache.searcher();
+        let mut async_searcher = cache.async_searcher();
+        for t in (0..25_000).step_by(250) {
+            let timestamp = PravegaTimestamp::from_nanoseconds(Some(1_600_000_000_000_000_000 - 2000 + t));
+            for method in [SearchMethod::Before, SearchMethod::After].iter() {
+                for filter in [SearchFilter::All, SearchFilter::RandomAccessOnly].iter() {
+                    assert_eq!(
+                        async_searcher.search_timestamp_and_return_index_offset_with_filter(timestamp, *method, *filter).await
+                            .map_err(|e| e.kind()),
+                        searcher.search_timestamp_and_return_index_offset_with_filter(timestamp, *method, *filter)
+                            .map_err(|e| e.kind()));
+                }
+            }
+        }
+        assert_eq!(async_searcher.get_first_record().await.unwrap(), records[4]);
+        assert_eq!(async_searcher.get_last_record().await.unwrap(), records[19]);
+    }
+
+    #[test]
+    fn test_index_cache_rewinds() {
+        let record_size = IndexRecord::RECORD_SIZE as u64;
+        let stream = SharedStream::default();
+        let cache = IndexCache::new(stream.clone());
+        // The clock rewinds after the 3rd and 6th records.
+        let append = |timestamps: &[u64]| {
+            let mut data = stream.data.lock().unwrap();
+            let mut writer = Cursor::new(Vec::new());
+            for t in timestamps {
+                let record = IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(1_600_000_000_000_000_000 + t)),
+                    data.len() as u64 + writer.get_ref().len() as u64, true, false);
+                IndexRecordWriter::new().write(&record, &mut writer).unwrap();
+            }
+            data.extend_from_slice(writer.get_ref());
+        };
+        append(&[1000, 2000, 3000, 1500]);
+        cache.update().unwrap();
+        append(&[2500, 3500, 500, 4000]);
+        cache.update().unwrap();
+
+        let compare = |cache: &IndexCache<SharedStream>| {
+            let head = *stream.head.lock().unwrap() as usize;
+            let mut stream_searcher = IndexSearcher::new(Cursor::new(stream.data.lock().unwrap()[head..].to_vec()));
+            let mut cache_searcher = cache.searcher();
+            for t in (0..5000).step_by(250) {
+                let timestamp = PravegaTimestamp::from_nanoseconds(Some(1_600_000_000_000_000_000 + t));
+                for method in [SearchMethod::Before, SearchMethod::After].iter() {
+                    let expected = stream_searcher.search_timestamp_and_return_index_offset(timestamp, *method).unwrap();
+                    let found = cache_searcher.search_timestamp_and_return_index_offset(timestamp, *method).unwrap();
+                    assert_eq!(found, (expected.0, expected.1 + head as u64), "timestamp={}, method={:?}", timestamp, method);
+                }
+            }
+        };
+        compare(&cache);
+
+        // Truncate the first rewind.
+        *stream.head.lock().unwrap() = 4 * record_size;
+        cache.update().unwrap();
+        compare(&cache);
+    }
+}
@@ -0,0 +1,445 @@
+//
+// Copyright (c) Dell Inc., or its subsidiaries. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+
+// Module for rebuilding an index from the events in a data stream.
+
+use crate::event_serde::EventReader;
+use crate::index::{IndexRecord, IndexRecordWriter};
+use crate::timestamp::PravegaTimestamp;
+use crate::utils::CurrentHead;
+use std::io::{BufReader, Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use tracing::{debug, info, warn};
+
+/// The result of rebuilding an index.
+#[derive(Debug, Default, Clone)]
+pub struct RebuildSummary {
+    pub data_head: u64,
+    pub data_tail: u64,
+    pub events_read: u64,
+    pub index_records_written: u64,
+    pub first_index_record: Option<IndexRecord>,
+    /// The final end-of-stream record.
+    pub last_index_record: Option<IndexRecord>,
+    /// The number of bytes at the tail of the data stream that do not contain a complete event.
+    /// These bytes are excluded from the rebuilt index.
+    pub incomplete_tail_bytes: u64,
+}
+
+/// Rebuilds an index by scanning a data stream written by EventWriter.
+///
+/// The data stream is read from its current head to its current tail.
+/// pravegasink sets IND on exactly the events that it indexed, so only events with IND set are indexed.
+/// Of these, index records are created using the same rules as pravegasink:
+///   - an event with RAN set is indexed if at least `index_min_nanos` has elapsed since the last index record,
+///   - an event without RAN set is indexed if more than `index_max_nanos` has elapsed since the last index record,
+///   - events without a timestamp are never indexed.
+///
+/// Additionally, an event with IND and DIS set is always indexed so that the discontinuity is visible in the index.
+/// This matches the first index record written by each pravegasink instance.
+/// If an event with DIS set is not indexed, the discontinuity is recorded in the next index record.
+///
+/// After the last event, a final index record is written with the offset of the end of the last complete event.
+/// Its timestamp is the timestamp of the last event plus the most recent interval between event timestamps,
+/// which estimates the duration of the last event. If this interval is unknown, 1 nanosecond is used.
+pub struct IndexRebuilder<R: Read + Seek + CurrentHead> {
+    reader: BufReader<R>,
+    index_min_nanos: u64,
+    index_max_nanos: u64,
+}
+
+impl<R: Read + Seek + CurrentHead> IndexRebuilder<R> {
+    pub fn new(reader: R, index_min_nanos: u64, index_max_nanos: u64) -> Self {
+        Self {
+            reader: BufReader::with_capacity(128 * 1024, reader),
+            index_min_nanos,
+            index_max_nanos,
+        }
+    }
+
+    /// Write a new index for the data stream to `index_writer`.
+    /// The caller is responsible for ensuring that `index_writer` does not contain any prior index records.
+    pub fn rebuild<W: Write>(&mut self, index_writer: &mut W) -> Result<RebuildSummary, Error> {
+        let summary = RebuildSummary {
+            data_head: self.reader.get_ref().current_head()?,
+            data_tail: self.reader.seek(SeekFrom::End(0))?,
+            ..Default::default()
+        };
+        info!("rebuild: data_head={}, data_tail={}, index_min_nanos={}, index_max_nanos={}",
+            summary.data_head, summary.data_tail, self.index_min_nanos, self.index_max_nanos);
+        let data_head = summary.data_head;
+        self.index_events(summary, data_head, None, index_writer)
+    }
+
+    /// Append index records to an existing index for the events that follow its last record.
+    ///
+    /// This is used to recover from a writer that failed after writing data but before writing
+    /// the index record that ends it.
+    /// The event at the offset of `last_index_record` is not indexed again,
+    /// unless `last_index_record` is a final index record that does not describe it.
+    /// If the data stream ends at the offset of `last_index_record`, nothing is written.
+    pub fn rebuild_tail<W: Write>(&mut self, last_index_record: &IndexRecord, index_writer: &mut W) -> Result<RebuildSummary, Error> {
+        let summary = RebuildSummary {
+            data_head: self.reader.get_ref().current_head()?,
+            data_tail: self.reader.seek(SeekFrom::End(0))?,
+            ..Default::default()
+        };
+        info!("rebuild_tail: data_head={}, data_tail={}, last_index_record={:?}",
+            summary.data_head, summary.data_tail, last_index_record);
+        if last_index_record.offset < summary.data_head || last_index_record.offset > summary.data_tail {
+            return Err(Error::new(ErrorKind::InvalidInput, format!(
+                "Offset of the last index record ({}) is not between the head ({}) and tail ({}) of the data stream",
+                last_index_record.offset, summary.data_head, summary.data_tail)));
+        }
+        self.index_events(summary, last_index_record.offset, Some(last_index_record), index_writer)
+    }
+
+    // Index the events from start_offset to the tail of the data stream, followed by a final index record.
+    // If last_index_record is provided, the event at start_offset has already been indexed.
+    fn index_events<W: Write>(&mut self, mut summary: RebuildSummary, start_offset: u64,
+            last_index_record: Option<&IndexRecord>, index_writer: &mut W) -> Result<RebuildSummary, Error> {
+        if self.index_min_nanos > self.index_max_nanos {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("index_min_nanos ({}) must be <= index_max_nanos ({})",
+                self.index_min_nanos, self.index_max_nanos)));
+        }
+        self.reader.seek(SeekFrom::Start(start_offset))?;
+
+        let mut event_reader = EventReader::new();
+        let mut index_record_writer = IndexRecordWriter::new();
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut offset = start_offset;
+        let mut last_index_time = last_index_record.map_or(PravegaTimestamp::NONE, |record| record.timestamp);
+        // Set when a discontinuity is found in an event that could not be indexed.
+        let mut pending_discontinuity = false;
+        // Used to calculate the timestamp of the final index record.
+        let mut last_timestamp = PravegaTimestamp::NONE;
+        let mut max_timestamp = PravegaTimestamp::NONE;
+        let mut last_interval_nanos: u64 = 0;
+
+        while offset < summary.data_tail {
+            if offset + 8 > summary.data_tail {
+                break;
+            }
+            let required_buffer_length = event_reader.read_required_buffer_length(&mut self.reader).map_err(|err| {
+                Error::new(err.kind(), format!("Unable to read event at offset {}: {}", offset, err))
+            })?;
+            let event_end_offset = offset + required_buffer_length as u64;
+            if event_end_offset > summary.data_tail {
+                break;
+            }
+            if buffer.len() < required_buffer_length {
+                buffer.resize(required_buffer_length, 0);
+            }
+            let event = event_reader.read_event(&mut self.reader, &mut buffer[..]).map_err(|err| {
+                Error::new(err.kind(), format!("Unable to read event at offset {}: {}", offset, err))
+            })?;
+            summary.events_read += 1;
+            let header = &event.header;
+
+            if header.discontinuity {
+                last_timestamp = PravegaTimestamp::NONE;
+                last_interval_nanos = 0;
+            }
+            // If the last index record is a final index record written by a writer that stopped,
+            // it does not describe the event at its offset, which must be indexed to replace it.
+            let (already_indexed, replaces_final) = match last_index_record {
+                Some(record) if offset == start_offset => {
+                    let matches = header.include_in_index
+                        && header.timestamp == record.timestamp
+                        && header.random_access == record.random_access
+                        && header.discontinuity == record.discontinuity;
+                    (matches, !matches)
+                },
+                _ => (false, false),
+            };
+            let include_in_index = !already_indexed && header.include_in_index && match header.timestamp.nanoseconds() {
+                Some(timestamp) => {
+                    match last_index_time.nanoseconds() {
+                        Some(last_index_time) if !header.discontinuity && !replaces_final => {
+                            if header.random_access {
+                                timestamp >= last_index_time.saturating_add(self.index_min_nanos)
+                            } else {
+                                timestamp > last_index_time.saturating_add(self.index_max_nanos)
+                            }
+                        },
+                        _ => true,
+                    }
+                },
+                None => false,
+            };
+
+            if include_in_index {
+                let index_record = IndexRecord::new(header.timestamp, offset,
+                    header.random_access, header.discontinuity || pending_discontinuity);
+                index_record_writer.write(&index_record, index_writer)?;
+                debug!("rebuild: Wrote index record {:?}", index_record);
+                summary.index_records_written += 1;
+                if summary.first_index_record.is_none() {
+                    summary.first_index_record = Some(index_record);
+                }
+                last_index_time = header.timestamp;
+                pending_discontinuity = false;
+                max_timestamp = header.timestamp;
+            } else if header.discontinuity && !already_indexed {
+                pending_discontinuity = true;
+            }
+
+            if let Some(timestamp) = header.timestamp.nanoseconds() {
+                if let Some(last_timestamp) = last_timestamp.nanoseconds() {
+                    if timestamp > last_timestamp {
+                        last_interval_nanos = timestamp - last_timestamp;
+                    }
+                }
+                last_timestamp = header.timestamp;
+                max_timestamp = max_timestamp.max(header.timestamp);
+            }
+            offset = event_end_offset;
+        }
+
+        summary.incomplete_tail_bytes = summary.data_tail - offset;
+        if summary.incomplete_tail_bytes > 0 {
+            warn!("rebuild: Ignoring {} bytes at offset {} that do not contain a complete event",
+                summary.incomplete_tail_bytes, offset);
+        }
+
+        // Write final index record.
+        // The timestamp must be greater than the timestamp of every event since the last index record.
+        // If a discontinuity was found after the last index record, it is recorded in the final index record.
+        if let (Some(last_timestamp), Some(max_timestamp)) = (last_timestamp.nanoseconds(), max_timestamp.nanoseconds()) {
+            let final_timestamp = (last_timestamp + last_interval_nanos.max(1)).max(max_timestamp + 1);
+            let index_record = IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(final_timestamp)), offset,
+                false, pending_discontinuity);
+            index_record_writer.write(&index_record, index_writer)?;
+            summary.index_records_written += 1;
+            summary.last_index_record = Some(index_record);
+        }
+        index_writer.flush()?;
+
+        info!("rebuild: Read {} events and wrote {} index records", summary.events_read, summary.index_records_written);
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_serde::{EventWithHeader, EventWriter};
+    use crate::index::IndexSearcher;
+    use crate::verifier::StreamVerifier;
+    use std::io::Cursor;
+
+    /// Write events with the given timestamps, RAN and DIS flags.
+    /// IND is set on all events with a timestamp.
+    fn create_data(events: &[(u64, bool, bool)]) -> (Cursor<Vec<u8>>, Vec<u64>) {
+        let events: Vec<_> = events.iter()
+            .map(|(timestamp, random_access, discontinuity)| (*timestamp, *timestamp != 0, *random_access, *discontinuity))
+            .collect();
+        create_data_with_ind(&events)
+    }
+
+    /// Write events with the given timestamps, IND, RAN and DIS flags.
+    fn create_data_with_ind(events: &[(u64, bool, bool, bool)]) -> (Cursor<Vec<u8>>, Vec<u64>) {
+        let mut data = Cursor::new(Vec::new());
+        let mut offsets = Vec::new();
+        let mut event_writer = EventWriter::new();
+        let payload = [0xAA; 188];
+        for (timestamp, include_in_index, random_access, discontinuity) in events.iter() {
+            offsets.push(data.seek(SeekFrom::Current(0)).unwrap());
+            let timestamp = PravegaTimestamp::from_nanoseconds(if *timestamp == 0 { None } else { Some(*timestamp) });
+            let event = EventWithHeader::new(&payload[..], timestamp, *include_in_index, *random_access, *discontinuity);
+            event_writer.write(&event, &mut data).unwrap();
+        }
+        offsets.push(data.seek(SeekFrom::Current(0)).unwrap());
+        (data, offsets)
+    }
+
+    fn rebuild(data: Cursor<Vec<u8>>, index_min_nanos: u64, index_max_nanos: u64) -> (RebuildSummary, Vec<IndexRecord>, Cursor<Vec<u8>>) {
+        let mut index = Cursor::new(Vec::new());
+        let mut rebuilder = IndexRebuilder::new(data, index_min_nanos, index_max_nanos);
+        let summary = rebuilder.rebuild(&mut index).unwrap();
+        let data = rebuilder.reader.into_inner();
+        let mut index_searcher = IndexSearcher::new(index);
+        let records = index_searcher.get_index_records().unwrap().into_iter().map(|r| r.0).collect();
+        let index = index_searcher.into_inner();
+        let report = StreamVerifier::new(data, index.clone()).verify().unwrap();
+        assert!(report.violations.is_empty(), "{:?}", report.violations);
+        (summary, records, index)
+    }
+
+    fn rec(timestamp: u64, offset: u64, random_access: bool, discontinuity: bool) -> IndexRecord {
+        IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(timestamp)), offset, random_access, discontinuity)
+    }
+
+    #[test]
+    fn test_index_rebuilder_min_interval() {
+        // Key frames every 3 events, 100 ns apart.
+        let events: Vec<_> = (0..10).map(|i| (1000 + i * 100, i % 3 == 0, i == 0)).collect();
+        let (data, offsets) = create_data(&events);
+        let (summary, records, _) = rebuild(data, 500, 10_000);
+        // The key frame at 1300 is skipped because it is within 500 ns of the key frame at 1000.
+        assert_eq!(records, vec![
+            rec(1000, offsets[0], true, true),
+            rec(1600, offsets[6], true, false),
+            rec(2000, offsets[10], false, false),
+        ]);
+        assert_eq!(summary.events_read, 10);
+        assert_eq!(summary.index_records_written, 3);
+        assert_eq!(summary.incomplete_tail_bytes, 0);
+    }
+
+    #[test]
+    fn test_index_rebuilder_max_interval() {
+        // Only the first event is a key frame.
+        let events: Vec<_> = (0..10).map(|i| (1000 + i * 100, i == 0, i == 0)).collect();
+        let (data, offsets) = create_data(&events);
+        let (_, records, _) = rebuild(data, 0, 250);
+        assert_eq!(records, vec![
+            rec(1000, offsets[0], true, true),
+            rec(1300, offsets[3], false, false),
+            rec(1600, offsets[6], false, false),
+            rec(1900, offsets[9], false, false),
+            rec(2000, offsets[10], false, false),
+        ]);
+    }
+
+    #[test]
+    fn test_index_rebuilder_discontinuity() {
+        // A new writer starts with an earlier clock. An event without a timestamp has DIS set.
+        let events = vec![
+            (1000, true, true),
+            (1100, false, false),
+            (500, false, true),
+            (600, true, false),
+            (0, false, true),
+            (700, false, false),
+        ];
+        let (data, offsets) = create_data(&events);
+        let (_, records, _) = rebuild(data, 0, 10_000);
+        assert_eq!(records, vec![
+            rec(1000, offsets[0], true, true),
+            rec(500, offsets[2], false, true),
+            rec(600, offsets[3], true, false),
+            rec(701, offsets[6], false, true),
+        ]);
+    }
+
+    #[test]
+    fn test_index_rebuilder_include_in_index() {
+        // Only events with IND set were indexed by the writer, even though other key frames are available.
+        let events = vec![
+            (1000, true, true, true),
+            (1100, false, true, false),
+            (1200, false, false, false),
+            (1300, true, true, false),
+            (1400, true, false, false),
+            (1500, false, true, false),
+        ];
+        let (data, offsets) = create_data_with_ind(&events);
+        let (_, records, _) = rebuild(data, 0, 10_000);
+        assert_eq!(records, vec![
+            rec(1000, offsets[0], true, true),
+            rec(1300, offsets[3], true, false),
+            rec(1600, offsets[6], false, false),
+        ]);
+    }
+
+    #[test]
+    fn test_index_rebuilder_incomplete_tail() {
+        let events: Vec<_> = (0..4).map(|i| (1000 + i * 100, true, i == 0)).collect();
+        let (mut data, offsets) = create_data(&events);
+        data.get_mut().truncate(offsets[3] as usize + 50);
+        let (summary, records, _) = rebuild(data, 0, 10_000);
+        assert_eq!(summary.incomplete_tail_bytes, 50);
+        assert_eq!(summary.events_read, 3);
+        assert_eq!(records.last().unwrap(), &rec(1300, offsets[3], false, false));
+    }
+
+    #[test]
+    fn test_index_rebuilder_tail() {
+        // A writer failed after writing events 3 through 9 but before writing the index records that end them.
+        let events: Vec<_> = (0..10).map(|i| (1000 + i * 100, i % 3 == 0, i == 0)).collect();
+        let (data, offsets) = create_data(&events);
+        let mut index = Cursor::new(Vec::new());
+        for record in [rec(1000, offsets[0], true, true), rec(1300, offsets[3], true, false)].iter() {
+            IndexRecordWriter::new().write(record, &mut index).unwrap();
+        }
+        let mut rebuilder = IndexRebuilder::new(data, 0, 10_000);
+        let summary = rebuilder.rebuild_tail(&rec(1300, offsets[3], true, false), &mut index).unwrap();
+        assert_eq!(summary.events_read, 7);
+        assert_eq!(summary.index_records_written, 3);
+        let data = rebuilder.reader.into_inner();
+        let mut index_searcher = IndexSearcher::new(index);
+        let records: Vec<_> = index_searcher.get_index_records().unwrap().into_iter().map(|r| r.0).collect();
+        assert_eq!(records, vec![
+            rec(1000, offsets[0], true, true),
+            rec(1300, offsets[3], true, false),
+            rec(1600, offsets[6], true, false),
+            rec(1900, offsets[9], true, false),
+            rec(2000, offsets[10], false, false),
+        ]);
+        let report = StreamVerifier::new(data.clone(), index_searcher.into_inner()).verify().unwrap();
+        assert!(report.violations.is_empty(), "{:?}", report.violations);
+
+        // Nothing is written if the index already ends at the tail of the data stream.
+        let mut index = Cursor::new(Vec::new());
+        let summary = IndexRebuilder::new(data, 0, 10_000)
+            .rebuild_tail(&rec(2000, offsets[10], false, false), &mut index).unwrap();
+        assert_eq!(summary.index_records_written, 0);
+        assert!(index.get_ref().is_empty());
+    }
+
+    #[test]
+    fn test_index_rebuilder_tail_after_final_record() {
+        // A writer stopped and wrote a final index record at 1200.
+        // The next writer failed after writing events 2 through 4 but before writing any index records.
+        let events = vec![
+            (1000, true, true, true),
+            (1100, false, false, false),
+            (1300, true, true, false),
+            (1400, false, false, false),
+            (1500, false, false, false),
+        ];
+        let (data, offsets) = create_data_with_ind(&events);
+        let mut index = Cursor::new(Vec::new());
+        let final_record = rec(1200, offsets[2], false, false);
+        for record in [rec(1000, offsets[0], true, true), final_record].iter() {
+            IndexRecordWriter::new().write(record, &mut index).unwrap();
+        }
+        let mut rebuilder = IndexRebuilder::new(data, 500, 10_000);
+        let summary = rebuilder.rebuild_tail(&final_record, &mut index).unwrap();
+        assert_eq!(summary.events_read, 3);
+        assert_eq!(summary.index_records_written, 2);
+        let data = rebuilder.reader.into_inner();
+        let mut index_searcher = IndexSearcher::new(index);
+        let records: Vec<_> = index_searcher.get_index_records().unwrap().into_iter().map(|r| r.0).collect();
+        // The event at 1300 is indexed even though it is within index_min_nanos of the final index record.
+        assert_eq!(records, vec![
+            rec(1000, offsets[0], true, true),
+            rec(1200, offsets[2], false, false),
+            rec(1300, offsets[2], true, false),
+            rec(1600, offsets[5], false, false),
+        ]);
+        let report = StreamVerifier::new(data, index_searcher.into_inner()).verify().unwrap();
+        assert!(report.violations.is_empty(), "{:?}", report.violations);
+    }
+
+    #[test]
+    fn test_index_rebuilder_empty() {
+        let (summary, records, _) = rebuild(Cursor::new(Vec::new()), 0, 10_000);
+        assert_eq!(summary.index_records_written, 0);
+        assert!(records.is_empty());
+    }
+
+    #[test]
+    fn test_index_rebuilder_invalid_interval() {
+        let mut rebuilder = IndexRebuilder::new(Cursor::new(Vec::new()), 2, 1);
+        let err = rebuilder.rebuild(&mut Cursor::new(Vec::new())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,381 @@
+//
+// Copyright (c) Dell Inc., or its subsidiaries. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+
+// Module for converting between UTC and TAI using a leap second table.
+
+use anyhow::{anyhow, bail};
+use once_cell::sync::Lazy;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+
+/// If this environment variable is set, the leap second table will be loaded from this file
+/// when it is first used. The file must be in the format of the IERS leap-seconds.list file.
+const ENV_VAR_NAME_LEAP_SECONDS_FILE: &str = "PRAVEGA_VIDEO_LEAP_SECONDS_FILE";
+
+/// Difference between NTP and Unix epochs.
+const UNIX_TO_NTP_SECONDS: u64 = (70 * 365 + 17) * 24 * 60 * 60;
+
+const NANOSECONDS_PER_SECOND: u64 = 1_000_000_000;
+
+/// The leap second schedule as published by IERS in leap-seconds.list.
+/// Each entry contains the NTP time (seconds since 1900-01-01 00:00:00 UTC) at which the offset becomes effective
+/// and the value of TAI - UTC in seconds.
+/// See [https://www.ietf.org/timezones/data/leap-seconds.list].
+const BUILTIN_LEAP_SECONDS: [(u64, u64); 28] = [
+    (2272060800, 10), // 1 Jan 1972
+    (2287785600, 11), // 1 Jul 1972
+    (2303683200, 12), // 1 Jan 1973
+    (2335219200, 13), // 1 Jan 1974
+    (2366755200, 14), // 1 Jan 1975
+    (2398291200, 15), // 1 Jan 1976
+    (2429913600, 16), // 1 Jan 1977
+    (2461449600, 17), // 1 Jan 1978
+    (2492985600, 18), // 1 Jan 1979
+    (2524521600, 19), // 1 Jan 1980
+    (2571782400, 20), // 1 Jul 1981
+    (2603318400, 21), // 1 Jul 1982
+    (2634854400, 22), // 1 Jul 1983
+    (2698012800, 23), // 1 Jul 1985
+    (2776982400, 24), // 1 Jan 1988
+    (2840140800, 25), // 1 Jan 1990
+    (2871676800, 26), // 1 Jan 1991
+    (2918937600, 27), // 1 Jul 1992
+    (2950473600, 28), // 1 Jul 1993
+    (2982009600, 29), // 1 Jul 1994
+    (3029443200, 30), // 1 Jan 1996
+    (3076704000, 31), // 1 Jul 1997
+    (3124137600, 32), // 1 Jan 1999
+    (3345062400, 33), // 1 Jan 2006
+    (3439756800, 34), // 1 Jan 2009
+    (3550089600, 35), // 1 Jul 2012
+    (3644697600, 36), // 1 Jul 2015
+    (3692217600, 37), // 1 Jan 2017
+];
+
+static CURRENT_TABLE: Lazy<RwLock<Arc<LeapSecondTable>>> = Lazy::new(|| {
+    let table = match std::env::var(ENV_VAR_NAME_LEAP_SECONDS_FILE) {
+        Ok(path) if !path.is_empty() => {
+            match LeapSecondTable::load(&path) {
+                Ok(table) => {
+                    info!("Loaded leap second table from {}; TAI - UTC is {} seconds", path, table.last_entry().tai_minus_utc);
+                    table
+                },
+                Err(err) => {
+                    warn!("Unable to load leap second table from {}; using built-in table: {}", path, err);
+                    LeapSecondTable::builtin()
+                },
+            }
+        },
+        _ => LeapSecondTable::builtin(),
+    };
+    RwLock::new(Arc::new(table))
+});
+
+/// Returns the leap second table that is used by PravegaTimestamp for all UTC conversions.
+pub fn current_table() -> Arc<LeapSecondTable> {
+    CURRENT_TABLE.read().unwrap().clone()
+}
+
+/// Replace the leap second table that is used by PravegaTimestamp for all UTC conversions.
+pub fn set_current_table(table: LeapSecondTable) {
+    *CURRENT_TABLE.write().unwrap() = Arc::new(table);
+}
+
+/// Load an IERS leap-seconds.list file and use it for all UTC conversions.
+pub fn load_leap_seconds_file<P: AsRef<Path>>(path: P) -> anyhow::Result<()> {
+    let table = LeapSecondTable::load(path)?;
+    set_current_table(table);
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LeapSecondEntry {
+    /// The UTC time at which this offset becomes effective, as seconds since the Unix epoch 1970-01-01 00:00:00 UTC,
+    /// not including leap seconds.
+    pub unix_seconds: u64,
+    /// TAI - UTC in seconds.
+    pub tai_minus_utc: u64,
+}
+
+/// A leap second schedule.
+///
+/// Conversions from Unix (POSIX) and NTP time use the convention of the IERS leap-seconds.list file,
+/// where a positive leap second (23:59:60) has the same Unix time as the first second of the following day.
+/// Such Unix times are ambiguous and cannot be converted to TAI.
+/// Times before 1972-01-01 use the first offset in the table (10 seconds).
+#[derive(Debug, PartialEq, Clone)]
+pub struct LeapSecondTable {
+    entries: Vec<LeapSecondEntry>,
+    /// The expiration date of the table, as seconds since the Unix epoch.
+    expiration_unix_seconds: Option<u64>,
+}
+
+impl LeapSecondTable {
+    /// Returns the leap second table compiled into this library.
+    pub fn builtin() -> Self {
+        let entries = BUILTIN_LEAP_SECONDS.iter().map(|(ntp_seconds, tai_minus_utc)| LeapSecondEntry {
+            unix_seconds: ntp_seconds - UNIX_TO_NTP_SECONDS,
+            tai_minus_utc: *tai_minus_utc,
+        }).collect();
+        Self {
+            entries,
+            expiration_unix_seconds: None,
+        }
+    }
+
+    /// Parse the contents of an IERS leap-seconds.list file.
+    /// Data lines contain the NTP time and the value of TAI - UTC, optionally followed by a comment.
+    /// The expiration date is read from the line beginning with "#@".
+    /// The hash in the line beginning with "#h" is not verified.
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut entries: Vec<LeapSecondEntry> = Vec::new();
+        let mut expiration_unix_seconds = None;
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if let Some(expiration) = line.strip_prefix("#@") {
+                let ntp_seconds: u64 = expiration.trim().parse()
+                    .map_err(|e| anyhow!("Invalid expiration on line {}: {}", line_number + 1, e))?;
+                expiration_unix_seconds = ntp_seconds.checked_sub(UNIX_TO_NTP_SECONDS);
+                continue;
+            }
+            let data = line.split('#').next().unwrap_or_default().trim();
+            if data.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = data.split_whitespace().collect();
+            if fields.len() != 2 {
+                bail!("Expected 2 fields on line {} but found {}", line_number + 1, fields.len());
+            }
+            let ntp_seconds: u64 = fields[0].parse()
+                .map_err(|e| anyhow!("Invalid NTP time on line {}: {}", line_number + 1, e))?;
+            let tai_minus_utc: u64 = fields[1].parse()
+                .map_err(|e| anyhow!("Invalid TAI - UTC on line {}: {}", line_number + 1, e))?;
+            let unix_seconds = ntp_seconds.checked_sub(UNIX_TO_NTP_SECONDS)
+                .ok_or_else(|| anyhow!("NTP time on line {} is before the Unix epoch", line_number + 1))?;
+            if let Some(prev) = entries.last() {
+                if unix_seconds <= prev.unix_seconds {
+                    bail!("Entries are not in increasing order on line {}", line_number + 1);
+                }
+            }
+            entries.push(LeapSecondEntry { unix_seconds, tai_minus_utc });
+        }
+        if entries.is_empty() {
+            bail!("Leap second table has no entries");
+        }
+        Ok(Self {
+            entries,
+            expiration_unix_seconds,
+        })
+    }
+
+    /// Load an IERS leap-seconds.list file.
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text)
+    }
+
+    pub fn entries(&self) -> &[LeapSecondEntry] {
+        &self.entries
+    }
+
+    /// Returns the expiration date of the table as seconds since the Unix epoch, if known.
+    /// Conversions of times after this date may be incorrect if a leap second has since been scheduled.
+    pub fn expiration_unix_seconds(&self) -> Option<u64> {
+        self.expiration_unix_seconds
+    }
+
+    fn last_entry(&self) -> &LeapSecondEntry {
+        self.entries.last().unwrap()
+    }
+
+    /// Returns the index of the last entry that is effective at or before the given Unix time,
+    /// or None if the time is before the first entry.
+    fn entry_index_at_unix_seconds(&self, unix_seconds: u64) -> Option<usize> {
+        match self.entries.binary_search_by_key(&unix_seconds, |e| e.unix_seconds) {
+            Ok(i) => Some(i),
+            Err(0) => None,
+            Err(i) => Some(i - 1),
+        }
+    }
+
+    /// Returns TAI - UTC in seconds at the given UTC time (seconds since the Unix epoch, not including leap seconds).
+    /// If the time is the first second after a leap second, this returns the new offset.
+    pub fn tai_minus_utc_at_unix_seconds(&self, unix_seconds: u64) -> u64 {
+        let i = self.entry_index_at_unix_seconds(unix_seconds).unwrap_or(0);
+        self.entries[i].tai_minus_utc
+    }
+
+    /// Convert a Unix (POSIX) time in nanoseconds to TAI nanoseconds since 1970-01-01 00:00:00 TAI.
+    /// Returns an error if the Unix time is ambiguous because it occurs during a positive leap second,
+    /// or if it does not exist because it was skipped by a negative leap second.
+    pub fn unix_to_tai_nanoseconds(&self, unix_nanoseconds: u64) -> anyhow::Result<u64> {
+        let unix_seconds = unix_nanoseconds / NANOSECONDS_PER_SECOND;
+        if let Some(i) = self.entry_index_at_unix_seconds(unix_seconds) {
+            if i > 0 {
+                let entry = self.entries[i];
+                let prev = self.entries[i - 1];
+                if entry.tai_minus_utc > prev.tai_minus_utc {
+                    let leap = entry.tai_minus_utc - prev.tai_minus_utc;
+                    if unix_seconds < entry.unix_seconds + leap {
+                        bail!("Unix time {} ns is ambiguous because it occurs during a positive leap second", unix_nanoseconds);
+                    }
+                }
+            }
+            if let Some(next) = self.entries.get(i + 1) {
+                let entry = self.entries[i];
+                if next.tai_minus_utc < entry.tai_minus_utc {
+                    let leap = entry.tai_minus_utc - next.tai_minus_utc;
+                    if unix_seconds + leap >= next.unix_seconds {
+                        bail!("Unix time {} ns does not exist because it was skipped by a negative leap second", unix_nanoseconds);
+                    }
+                }
+            }
+        }
+        self.unix_to_tai_nanoseconds_lenient(unix_nanoseconds)
+    }
+
+    /// Convert a Unix (POSIX) time in nanoseconds to TAI nanoseconds, resolving times near a leap second
+    /// instead of rejecting them.
+    /// An ambiguous time during a positive leap second is mapped to the first second of the following day.
+    /// A time that was skipped by a negative leap second uses the offset before the leap second.
+    /// Returns an error only if the result would overflow.
+    pub fn unix_to_tai_nanoseconds_lenient(&self, unix_nanoseconds: u64) -> anyhow::Result<u64> {
+        let unix_seconds = unix_nanoseconds / NANOSECONDS_PER_SECOND;
+        let tai_minus_utc = self.tai_minus_utc_at_unix_seconds(unix_seconds);
+        unix_nanoseconds.checked_add(tai_minus_utc * NANOSECONDS_PER_SECOND)
+            .ok_or_else(|| anyhow!("Unix time {} ns cannot be represented as TAI", unix_nanoseconds))
+    }
+
+    /// Convert the UTC time 23:59:60 (with a fraction) to TAI nanoseconds.
+    /// unix_seconds is the Unix time of the following second (00:00:00 of the next day).
+    /// Returns an error if a positive leap second did not occur at this time.
+    pub fn leap_second_to_tai_nanoseconds(&self, unix_seconds: u64, subsec_nanoseconds: u32) -> anyhow::Result<u64> {
+        match self.entries.binary_search_by_key(&unix_seconds, |e| e.unix_seconds) {
+            Ok(i) if i > 0 && self.entries[i].tai_minus_utc > self.entries[i - 1].tai_minus_utc => {
+                let tai_seconds = unix_seconds - 1 + self.entries[i].tai_minus_utc;
+                Ok(tai_seconds * NANOSECONDS_PER_SECOND + subsec_nanoseconds as u64)
+            },
+            _ => bail!("There is no leap second before Unix time {} seconds", unix_seconds),
+        }
+    }
+
+    /// Convert TAI nanoseconds since 1970-01-01 00:00:00 TAI to a Unix (POSIX) time in nanoseconds.
+    /// Returns the Unix time and a flag that is true if the time occurs during a positive leap second (23:59:60).
+    /// During a leap second, the Unix time will be in the first second of the following day.
+    pub fn tai_to_unix_nanoseconds(&self, tai_nanoseconds: u64) -> anyhow::Result<(u64, bool)> {
+        let tai_seconds = tai_nanoseconds / NANOSECONDS_PER_SECOND;
+        // Find the last entry that is effective at or before this TAI time.
+        let i = self.entries.iter()
+            .rposition(|e| e.unix_seconds + e.tai_minus_utc <= tai_seconds)
+            .unwrap_or(0);
+        let entry = self.entries[i];
+        let (tai_minus_utc, leap_second) = match self.entries.get(i + 1) {
+            // This TAI time is after the previous offset ends but before the next offset is effective.
+            Some(next) if next.tai_minus_utc > entry.tai_minus_utc
+                && tai_seconds >= next.unix_seconds + entry.tai_minus_utc => (entry.tai_minus_utc, true),
+            _ => (entry.tai_minus_utc, false),
+        };
+        let unix_nanoseconds = tai_nanoseconds.checked_sub(tai_minus_utc * NANOSECONDS_PER_SECOND)
+            .ok_or_else(|| anyhow!("TAI time {} ns is before the Unix epoch", tai_nanoseconds))?;
+        Ok((unix_nanoseconds, leap_second))
+    }
+}
+
+impl Default for LeapSecondTable {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SECOND: u64 = 1_000_000_000;
+    // 2017-01-01 00:00:00 UTC
+    const UNIX_2017: u64 = 1_483_228_800;
+
+    #[test]
+    fn test_builtin_table() {
+        let table = LeapSecondTable::builtin();
+        assert_eq!(table.entries().len(), 28);
+        assert_eq!(table.entries()[0], LeapSecondEntry { unix_seconds: 63_072_000, tai_minus_utc: 10 });
+        assert_eq!(table.entries()[27], LeapSecondEntry { unix_seconds: UNIX_2017, tai_minus_utc: 37 });
+        // 2001-02-03 04:00:00 UTC
+        assert_eq!(table.tai_minus_utc_at_unix_seconds(981_172_800), 32);
+        assert_eq!(table.tai_minus_utc_at_unix_seconds(UNIX_2017 - 1), 36);
+        assert_eq!(table.tai_minus_utc_at_unix_seconds(UNIX_2017), 37);
+        assert_eq!(table.tai_minus_utc_at_unix_seconds(0), 10);
+    }
+
+    #[test]
+    fn test_unix_to_tai() {
+        let table = LeapSecondTable::builtin();
+        // 2016-12-31 23:59:59.5 UTC
+        assert_eq!(table.unix_to_tai_nanoseconds((UNIX_2017 - 1) * SECOND + SECOND / 2).unwrap(),
+            (UNIX_2017 - 1 + 36) * SECOND + SECOND / 2);
+        // Ambiguous: this could be 2016-12-31 23:59:60.5 or 2017-01-01 00:00:00.5 UTC.
+        assert!(table.unix_to_tai_nanoseconds(UNIX_2017 * SECOND + SECOND / 2).is_err());
+        assert_eq!(table.unix_to_tai_nanoseconds_lenient(UNIX_2017 * SECOND + SECOND / 2).unwrap(),
+            (UNIX_2017 + 37) * SECOND + SECOND / 2);
+        // 2017-01-01 00:00:01 UTC
+        assert_eq!(table.unix_to_tai_nanoseconds((UNIX_2017 + 1) * SECOND).unwrap(),
+            (UNIX_2017 + 1 + 37) * SECOND);
+    }
+
+    #[test]
+    fn test_tai_to_unix() {
+        let table = LeapSecondTable::builtin();
+        // 2016-12-31 23:59:59.5 UTC
+        assert_eq!(table.tai_to_unix_nanoseconds((UNIX_2017 - 1 + 36) * SECOND + SECOND / 2).unwrap(),
+            ((UNIX_2017 - 1) * SECOND + SECOND / 2, false));
+        // 2016-12-31 23:59:60.5 UTC
+        assert_eq!(table.tai_to_unix_nanoseconds((UNIX_2017 + 36) * SECOND + SECOND / 2).unwrap(),
+            (UNIX_2017 * SECOND + SECOND / 2, true));
+        // 2017-01-01 00:00:00.5 UTC
+        assert_eq!(table.tai_to_unix_nanoseconds((UNIX_2017 + 37) * SECOND + SECOND / 2).unwrap(),
+            (UNIX_2017 * SECOND + SECOND / 2, false));
+        assert!(table.tai_to_unix_nanoseconds(SECOND).is_err());
+    }
+
+    #[test]
+    fn test_leap_second_to_tai() {
+        let table = LeapSecondTable::builtin();
+        assert_eq!(table.leap_second_to_tai_nanoseconds(UNIX_2017, 500_000_000).unwrap(),
+            (UNIX_2017 + 36) * SECOND + SECOND / 2);
+        assert!(table.leap_second_to_tai_nanoseconds(UNIX_2017 + 86400, 0).is_err());
+    }
+
+    #[test]
+    fn test_parse_leap_seconds_list() {
+        let text = "\
+#	Updated through IERS Bulletin C 64
+#$	 3676924800
+#@	 3928521600
+#
+3644697600	36	# 1 Jul 2015
+3692217600	37	# 1 Jan 2017
+3928521600	38	# hypothetical
+#h	16edd0f0 3666784f 37db6bdd e74ced87 59af48f1
+";
+        let table = LeapSecondTable::parse(text).unwrap();
+        assert_eq!(table.entries().len(), 3);
+        assert_eq!(table.expiration_unix_seconds(), Some(3928521600 - UNIX_TO_NTP_SECONDS));
+        let unix_seconds = 3928521600 - UNIX_TO_NTP_SECONDS;
+        assert_eq!(table.unix_to_tai_nanoseconds((unix_seconds + 1) * SECOND).unwrap(),
+            (unix_seconds + 1 + 38) * SECOND);
+        assert!(table.unix_to_tai_nanoseconds(unix_seconds * SECOND).is_err());
+
+        assert!(LeapSecondTable::parse("# no entries\n").is_err());
+        assert!(LeapSecondTable::parse("3692217600 37\n3644697600 36\n").is_err());
+        assert!(LeapSecondTable::parse("3692217600\n").is_err());
+    }
+}
@@ -0,0 +1,268 @@
+//
+// Copyright (c) Dell Inc., or its subsidiaries. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+
+// Module for ensuring that only one writer appends to a stream at a time.
+
+use serde::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+
+pub fn get_lease_table_name(stream_name: &str) -> String {
+    format!("{}-lease", stream_name)
+}
+
+/// The value stored for a writer lease.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lease {
+    /// A unique identifier of the writer that holds the lease.
+    pub holder: String,
+    /// The lease expires at this time, in milliseconds since the Unix epoch, unless it is renewed.
+    pub expires_millis: u64,
+}
+
+/// The version of a stored lease. Each successful write produces a new version.
+pub type LeaseVersion = i64;
+
+/// A store that holds a single lease and supports conditional updates.
+pub trait LeaseStore {
+    /// Returns the current lease and its version, or None if there is no lease.
+    fn read(&mut self) -> Result<Option<(Lease, LeaseVersion)>, Error>;
+
+    /// Writes the lease if the current version is `expected_version`.
+    /// If `expected_version` is None, the lease is written only if there is no lease.
+    /// Returns the new version, or None if the condition was not met.
+    fn write(&mut self, lease: &Lease, expected_version: Option<LeaseVersion>) -> Result<Option<LeaseVersion>, Error>;
+
+    /// Removes the lease if the current version is `expected_version`.
+    /// Returns false if the condition was not met.
+    fn remove(&mut self, expected_version: LeaseVersion) -> Result<bool, Error>;
+}
+
+/// The result of WriterLease::try_acquire.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AcquireResult {
+    Acquired,
+    /// Another writer holds a lease that has not expired.
+    Held(Lease),
+}
+
+/// A lease that allows a single writer to append to a stream.
+///
+/// A writer must acquire the lease before writing and renew it before it expires.
+/// A lease that has not been renewed before it expires may be acquired by another writer.
+/// Expiration is determined by the clocks of the writers, so the lease duration must be
+/// much larger than the expected clock skew between writers.
+///
+/// All updates are conditional on the version that was read, so if two writers attempt to acquire
+/// an expired lease at the same time, only one will succeed.
+pub struct WriterLease<S: LeaseStore> {
+    store: S,
+    holder: String,
+    duration: Duration,
+    // The version of the lease held by this writer.
+    version: Option<LeaseVersion>,
+    // The time that the lease was last acquired or renewed.
+    renewed_millis: u64,
+}
+
+impl<S: LeaseStore> WriterLease<S> {
+    pub fn new(store: S, holder: String, duration: Duration) -> Self {
+        Self {
+            store,
+            holder,
+            duration,
+            version: None,
+            renewed_millis: 0,
+        }
+    }
+
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.version.is_some()
+    }
+
+    /// Acquires the lease if there is no lease, if it has expired, or if it is held by this writer.
+    pub fn try_acquire(&mut self, now_millis: u64) -> Result<AcquireResult, Error> {
+        let current = self.store.read()?;
+        let expected_version = match current {
+            Some((lease, version)) => {
+                if lease.holder != self.holder && lease.expires_millis > now_millis {
+                    debug!("try_acquire: Lease is held by another writer: {:?}", lease);
+                    return Ok(AcquireResult::Held(lease));
+                }
+                if lease.holder != self.holder {
+                    warn!("try_acquire: Taking over expired lease {:?}", lease);
+                }
+                Some(version)
+            },
+            None => None,
+        };
+        match self.store.write(&self.new_lease(now_millis), expected_version)? {
+            Some(version) => {
+                info!("try_acquire: Acquired lease; holder={}, version={}", self.holder, version);
+                self.version = Some(version);
+                self.renewed_millis = now_millis;
+                Ok(AcquireResult::Acquired)
+            },
+            None => {
+                // Another writer updated the lease after we read it.
+                match self.store.read()? {
+                    Some((lease, _)) => Ok(AcquireResult::Held(lease)),
+                    None => Err(Error::new(ErrorKind::Interrupted, "Lease changed while it was being acquired")),
+                }
+            },
+        }
+    }
+
+    /// Returns true if at least a third of the lease duration has elapsed since it was last renewed.
+    pub fn needs_renewal(&self, now_millis: u64) -> bool {
+        now_millis >= self.renewed_millis + self.duration.as_millis() as u64 / 3
+    }
+
+    /// Returns true if the lease is not held or if it has not been renewed within the lease duration,
+    /// in which case another writer may have acquired it.
+    /// This does not read the lease store, so it can be checked before every write.
+    pub fn is_expired(&self, now_millis: u64) -> bool {
+        !self.is_held() || now_millis >= self.renewed_millis + self.duration.as_millis() as u64
+    }
+
+    /// Extends the lease held by this writer.
+    /// Returns an error of kind PermissionDenied if the lease has been lost to another writer.
+    pub fn renew(&mut self, now_millis: u64) -> Result<(), Error> {
+        let version = self.version.ok_or_else(|| Error::new(ErrorKind::PermissionDenied, "Lease is not held"))?;
+        match self.store.write(&self.new_lease(now_millis), Some(version))? {
+            Some(version) => {
+                debug!("renew: Renewed lease; holder={}, version={}", self.holder, version);
+                self.version = Some(version);
+                self.renewed_millis = now_millis;
+                Ok(())
+            },
+            None => {
+                self.version = None;
+                let current = self.store.read()?;
+                Err(Error::new(ErrorKind::PermissionDenied, format!("Lease has been lost; current lease is {:?}", current)))
+            },
+        }
+    }
+
+    /// Releases the lease so that another writer can acquire it immediately.
+    pub fn release(&mut self) -> Result<(), Error> {
+        if let Some(version) = self.version.take() {
+            if self.store.remove(version)? {
+                info!("release: Released lease; holder={}", self.holder);
+            } else {
+                warn!("release: Lease was not released because it has been acquired by another writer");
+            }
+        }
+        Ok(())
+    }
+
+    fn new_lease(&self, now_millis: u64) -> Lease {
+        Lease {
+            holder: self.holder.clone(),
+            expires_millis: now_millis + self.duration.as_millis() as u64,
+        }
+    }
+}
+
+/// Returns the current time in milliseconds since the Unix epoch.
+pub fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// A lease store shared by multiple writers in the same process.
+    #[derive(Clone, Default)]
+    struct MemoryLeaseStore {
+        lease: Arc<Mutex<Option<(Lease, LeaseVersion)>>>,
+    }
+
+    impl LeaseStore for MemoryLeaseStore {
+        fn read(&mut self) -> Result<Option<(Lease, LeaseVersion)>, Error> {
+            Ok(self.lease.lock().unwrap().clone())
+        }
+
+        fn write(&mut self, lease: &Lease, expected_version: Option<LeaseVersion>) -> Result<Option<LeaseVersion>, Error> {
+            let mut current = self.lease.lock().unwrap();
+            let current_version = current.as_ref().map(|(_, version)| *version);
+            if current_version != expected_version {
+                return Ok(None);
+            }
+            let version = current_version.unwrap_or_default() + 1;
+            *current = Some((lease.clone(), version));
+            Ok(Some(version))
+        }
+
+        fn remove(&mut self, expected_version: LeaseVersion) -> Result<bool, Error> {
+            let mut current = self.lease.lock().unwrap();
+            if current.as_ref().map(|(_, version)| *version) != Some(expected_version) {
+                return Ok(false);
+            }
+            *current = None;
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn test_writer_lease() {
+        let store = MemoryLeaseStore::default();
+        let duration = Duration::from_secs(30);
+        let mut writer1 = WriterLease::new(store.clone(), "writer1".to_owned(), duration);
+        let mut writer2 = WriterLease::new(store.clone(), "writer2".to_owned(), duration);
+
+        assert_eq!(writer1.try_acquire(1_000).unwrap(), AcquireResult::Acquired);
+        assert!(writer1.is_held());
+        let lease1 = Lease { holder: "writer1".to_owned(), expires_millis: 31_000 };
+        assert_eq!(writer2.try_acquire(2_000).unwrap(), AcquireResult::Held(lease1));
+        assert!(!writer2.is_held());
+
+        // Renewal extends the lease.
+        assert!(!writer1.is_expired(30_999));
+        assert!(writer1.is_expired(31_000));
+        assert!(!writer1.needs_renewal(10_000));
+        assert!(writer1.needs_renewal(11_000));
+        writer1.renew(11_000).unwrap();
+        assert!(!writer1.is_expired(31_000));
+        let lease1 = Lease { holder: "writer1".to_owned(), expires_millis: 41_000 };
+        assert_eq!(writer2.try_acquire(35_000).unwrap(), AcquireResult::Held(lease1));
+
+        // After the lease expires, another writer can acquire it and the original writer loses it.
+        assert_eq!(writer2.try_acquire(41_000).unwrap(), AcquireResult::Acquired);
+        assert_eq!(writer1.renew(42_000).unwrap_err().kind(), ErrorKind::PermissionDenied);
+        assert!(!writer1.is_held());
+        assert!(writer1.is_expired(42_000));
+
+        // Releasing allows another writer to acquire the lease immediately.
+        writer2.release().unwrap();
+        assert_eq!(writer1.try_acquire(43_000).unwrap(), AcquireResult::Acquired);
+        // Releasing a lost lease does not remove the lease of another writer.
+        writer2.release().unwrap();
+        assert!(store.lease.lock().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_writer_lease_reacquire() {
+        // A writer that restarts with the same holder can acquire its own unexpired lease.
+        let store = MemoryLeaseStore::default();
+        let mut writer = WriterLease::new(store.clone(), "writer1".to_owned(), Duration::from_secs(30));
+        assert_eq!(writer.try_acquire(1_000).unwrap(), AcquireResult::Acquired);
+        let mut restarted = WriterLease::new(store, "writer1".to_owned(), Duration::from_secs(30));
+        assert_eq!(restarted.try_acquire(2_000).unwrap(), AcquireResult::Acquired);
+        assert_eq!(writer.renew(3_000).unwrap_err().kind(), ErrorKind::PermissionDenied);
+    }
+}
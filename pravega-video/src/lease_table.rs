@@ -0,0 +1,70 @@
+//
+// Copyright (c) Dell Inc., or its subsidiaries. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+
+// Module for storing a writer lease in a Pravega table.
+
+use crate::lease::{Lease, LeaseStore, LeaseVersion};
+use pravega_client::client_factory::ClientFactory;
+use pravega_client::tablemap::{TableError, TableMap};
+use pravega_client_shared::Scope;
+use std::io::{Error, ErrorKind};
+
+const LEASE_KEY: &str = "writer";
+// The key version that requires that the key does not exist.
+const KEY_NOT_EXISTS: LeaseVersion = -1;
+// The segment offset that disables the offset condition.
+const NO_OFFSET_CONDITION: i64 = -1;
+
+/// A LeaseStore that uses conditional updates of a Pravega table.
+pub struct TableLeaseStore {
+    client_factory: ClientFactory,
+    table: TableMap,
+}
+
+impl TableLeaseStore {
+    /// Opens the table, creating it if needed.
+    pub fn new(client_factory: &ClientFactory, scope: Scope, table_name: String) -> Self {
+        let table = client_factory.get_runtime().block_on(client_factory.create_table_map(scope, table_name));
+        Self {
+            client_factory: client_factory.clone(),
+            table,
+        }
+    }
+}
+
+impl LeaseStore for TableLeaseStore {
+    fn read(&mut self) -> Result<Option<(Lease, LeaseVersion)>, Error> {
+        let key = LEASE_KEY.to_owned();
+        self.client_factory.get_runtime().block_on(self.table.get(&key)).map_err(table_error)
+    }
+
+    fn write(&mut self, lease: &Lease, expected_version: Option<LeaseVersion>) -> Result<Option<LeaseVersion>, Error> {
+        let key = LEASE_KEY.to_owned();
+        let key_version = expected_version.unwrap_or(KEY_NOT_EXISTS);
+        match self.client_factory.get_runtime().block_on(self.table.insert_conditionally(&key, lease, key_version, NO_OFFSET_CONDITION)) {
+            Ok(version) => Ok(Some(version)),
+            Err(TableError::IncorrectKeyVersion { .. }) | Err(TableError::KeyDoesNotExist { .. }) => Ok(None),
+            Err(err) => Err(table_error(err)),
+        }
+    }
+
+    fn remove(&mut self, expected_version: LeaseVersion) -> Result<bool, Error> {
+        let key = LEASE_KEY.to_owned();
+        match self.client_factory.get_runtime().block_on(self.table.remove_conditionally(&key, expected_version, NO_OFFSET_CONDITION)) {
+            Ok(()) => Ok(true),
+            Err(TableError::IncorrectKeyVersion { .. }) | Err(TableError::KeyDoesNotExist { .. }) => Ok(false),
+            Err(err) => Err(table_error(err)),
+        }
+    }
+}
+
+fn table_error(err: TableError) -> Error {
+    Error::new(ErrorKind::Other, format!("Pravega table error: {:?}", err))
+}
@@ -8,8 +8,18 @@
 // http://www.apache.org/licenses/LICENSE-2.0
 //
 
+pub mod catalog;
+pub mod checksum;
 pub mod event_serde;
 pub mod index;
+pub mod index_cache;
+pub mod index_rebuilder;
+pub mod leap_seconds;
+pub mod lease;
+pub mod lease_table;
+pub mod metadata;
+pub mod spool;
 pub mod timestamp;
 pub mod tracing;
 pub mod utils;
+pub mod verifier;
@@ -0,0 +1,178 @@
+//
+// Copyright (c) Dell Inc., or its subsidiaries. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+
+// Module for writing and reading stream metadata in a Pravega stream.
+
+use crate::event_serde::{EventReader, EventWithHeader, EventWriter};
+use crate::timestamp::PravegaTimestamp;
+use crate::utils::CurrentHead;
+use serde::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
+
+pub fn get_metadata_stream_name(stream_name: &str) -> String {
+    format!("{}-metadata", stream_name)
+}
+
+/// Describes the contents of a data stream, as recorded by the writer.
+///
+/// A writer appends a new record to the metadata stream whenever the description changes,
+/// for example when caps are renegotiated or when a new writer process begins appending to the data stream.
+/// Each record applies to the events in the data stream beginning at data_offset,
+/// up to the data_offset of the next record.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StreamMetadata {
+    /// The offset in the data stream of the first event described by this record.
+    pub data_offset: u64,
+    /// The negotiated GStreamer caps of the events, in the format produced by gst_caps_to_string().
+    pub caps: Option<String>,
+    /// GStreamer tags, in the format produced by gst_tag_list_to_string().
+    pub tags: Option<String>,
+    /// The encoder that produced the events, if known.
+    pub encoder: Option<String>,
+    /// The host name of the writer.
+    pub host: Option<String>,
+    /// The name and version of the writer, such as "pravegasink 0.7.0".
+    pub writer: Option<String>,
+}
+
+/**
+   A struct to serialize StreamMetadata for writing to a Pravega byte stream.
+
+   Each record is written as an event (see event_serde.rs) with a checksum.
+   The payload is the JSON representation of StreamMetadata.
+   Unknown JSON fields are ignored by the reader, so fields can be added in the future.
+*/
+pub struct StreamMetadataWriter {
+}
+
+impl StreamMetadataWriter {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn write<W>(&mut self, metadata: &StreamMetadata, writer: &mut W) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let payload = serde_json::to_vec(metadata)?;
+        let event = EventWithHeader::new(&payload[..], PravegaTimestamp::NONE, false, false, false);
+        EventWriter::with_checksum(true).write(&event, writer)
+    }
+}
+
+pub struct StreamMetadataReader {
+}
+
+// A struct to deserialize StreamMetadata that was written to a Pravega byte stream.
+impl StreamMetadataReader {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn read<R>(&mut self, rdr: &mut R) -> Result<StreamMetadata, Error>
+    where
+        R: Read,
+    {
+        let mut event_reader = EventReader::new();
+        let required_buffer_length = event_reader.read_required_buffer_length(rdr)?;
+        let mut buffer = vec![0; required_buffer_length];
+        let event = event_reader.read_event(rdr, &mut buffer[..])?;
+        let metadata = serde_json::from_slice(event.payload)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        Ok(metadata)
+    }
+
+    /// Returns all records between the current head and tail of the metadata stream.
+    /// A partial record at the tail is ignored.
+    pub fn read_all<R>(&mut self, rdr: &mut R) -> Result<Vec<StreamMetadata>, Error>
+    where
+        R: Read + Seek + CurrentHead,
+    {
+        let head_offset = rdr.current_head()?;
+        let tail_offset = rdr.seek(SeekFrom::End(0))?;
+        rdr.seek(SeekFrom::Start(head_offset))?;
+        // Ensure EOF instead of waiting for appends at the tail.
+        let mut rdr = rdr.take(tail_offset - head_offset);
+        let mut records = Vec::new();
+        loop {
+            match self.read(&mut rdr) {
+                Ok(metadata) => records.push(metadata),
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Returns the record that applies to the event at data_offset in the data stream.
+/// This is the last record with a data_offset less than or equal to data_offset.
+/// Returns None if data_offset precedes all records.
+pub fn find_stream_metadata(records: &[StreamMetadata], data_offset: u64) -> Option<&StreamMetadata> {
+    records.iter().rev().find(|record| record.data_offset <= data_offset)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_stream_metadata_writer_reader() {
+        let records = vec![
+            StreamMetadata {
+                data_offset: 0,
+                caps: Some("video/mpegts, systemstream=(boolean)true, packetsize=(int)188".to_owned()),
+                tags: Some("taglist, encoder=(string)x264;".to_owned()),
+                encoder: Some("x264".to_owned()),
+                host: Some("camera1".to_owned()),
+                writer: Some("pravegasink 0.7.0".to_owned()),
+            },
+            StreamMetadata {
+                data_offset: 1000,
+                caps: Some("video/x-h264, stream-format=(string)byte-stream".to_owned()),
+                ..Default::default()
+            },
+        ];
+        let mut cursor = Cursor::new(Vec::new());
+        let mut writer = StreamMetadataWriter::new();
+        for record in records.iter() {
+            writer.write(record, &mut cursor).unwrap();
+        }
+        // A partial record at the tail must be ignored.
+        let length = cursor.get_ref().len();
+        writer.write(&records[0], &mut cursor).unwrap();
+        cursor.get_mut().truncate(length + 10);
+
+        let read_records = StreamMetadataReader::new().read_all(&mut cursor).unwrap();
+        assert_eq!(read_records, records);
+
+        assert_eq!(find_stream_metadata(&read_records, 0), Some(&records[0]));
+        assert_eq!(find_stream_metadata(&read_records, 999), Some(&records[0]));
+        assert_eq!(find_stream_metadata(&read_records, 1000), Some(&records[1]));
+        assert_eq!(find_stream_metadata(&read_records[1..], 999), None);
+    }
+
+    #[test]
+    fn test_stream_metadata_unknown_fields() {
+        // Records written by a future version may have additional fields.
+        let payload = br#"{"data_offset":5,"caps":"audio/x-raw","future_field":[1,2]}"#;
+        let mut cursor = Cursor::new(Vec::new());
+        let event = EventWithHeader::new(&payload[..], PravegaTimestamp::NONE, false, false, false);
+        EventWriter::new().write(&event, &mut cursor).unwrap();
+        cursor.set_position(0);
+        let metadata = StreamMetadataReader::new().read(&mut cursor).unwrap();
+        assert_eq!(metadata, StreamMetadata {
+            data_offset: 5,
+            caps: Some("audio/x-raw".to_owned()),
+            ..Default::default()
+        });
+    }
+}
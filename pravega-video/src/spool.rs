@@ -0,0 +1,319 @@
+//
+// Copyright (c) Dell Inc., or its subsidiaries. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+
+// Module for buffering events on local disk while Pravega is unavailable.
+
+use crate::event_serde::{EventHeader, EventReader, EventWithHeader, EventWriter, ReadEventError};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+const CHUNK_FILE_EXTENSION: &str = "spool";
+const MIN_CHUNK_SIZE: u64 = 64 * 1024;
+const MAX_CHUNK_SIZE: u64 = 64 * 1024 * 1024;
+
+/// An event that was read from a spool.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpooledEvent {
+    pub header: EventHeader,
+    pub payload: Vec<u8>,
+}
+
+impl SpooledEvent {
+    pub fn new(event: &EventWithHeader) -> Self {
+        Self {
+            header: event.header.clone(),
+            payload: event.payload.to_vec(),
+        }
+    }
+
+    pub fn as_event(&self) -> EventWithHeader<'_> {
+        EventWithHeader {
+            header: self.header.clone(),
+            payload: &self.payload[..],
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Chunk {
+    sequence: u64,
+    path: PathBuf,
+    size: u64,
+}
+
+/// A first-in first-out queue of events stored in a directory on local disk.
+///
+/// Events are appended to chunk files using the same format as a Pravega data stream (see event_serde.rs),
+/// with a checksum. The events in a chunk are read all at once and the chunk is removed after
+/// the events have been written elsewhere. Events remain in the spool if the process restarts.
+///
+/// To limit disk usage, the oldest chunks are deleted when the spool exceeds `max_size` bytes
+/// or when a chunk was last written more than `max_age` ago.
+/// The first event read after deleted events has the discontinuity indicator set.
+pub struct Spool {
+    dir: PathBuf,
+    max_size: u64,
+    max_age: Option<Duration>,
+    chunk_size: u64,
+    // Chunks from oldest to newest.
+    chunks: VecDeque<Chunk>,
+    // Writer for the newest chunk. If None, the next append will create a new chunk.
+    writer: Option<BufWriter<File>>,
+    // True if events were deleted before the oldest chunk.
+    discontinuity: bool,
+}
+
+impl Spool {
+    /// Opens a spool, creating the directory if needed. Events from a previous process are retained.
+    pub fn open<P: AsRef<Path>>(dir: P, max_size: u64, max_age: Option<Duration>) -> Result<Self, Error> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let mut chunks = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(CHUNK_FILE_EXTENSION) {
+                continue;
+            }
+            let sequence = path.file_stem().and_then(|s| s.to_str()).and_then(|s| s.parse::<u64>().ok());
+            if let Some(sequence) = sequence {
+                let size = fs::metadata(&path)?.len();
+                chunks.push(Chunk { sequence, path, size });
+            }
+        }
+        chunks.sort_by_key(|chunk| chunk.sequence);
+        let chunk_size = (max_size / 8).clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE);
+        let mut spool = Self {
+            dir,
+            max_size,
+            max_age,
+            chunk_size,
+            chunks: chunks.into(),
+            writer: None,
+            // Events may have been deleted by a previous process.
+            discontinuity: true,
+        };
+        info!("open: dir={:?}, chunks={}, size={}", spool.dir, spool.chunks.len(), spool.size());
+        spool.enforce_limits()?;
+        Ok(spool)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.iter().all(|chunk| chunk.size == 0)
+    }
+
+    /// The total size of all chunks in bytes.
+    pub fn size(&self) -> u64 {
+        self.chunks.iter().map(|chunk| chunk.size).sum()
+    }
+
+    /// Appends an event to the newest chunk and deletes the oldest chunks if limits are exceeded.
+    pub fn append(&mut self, event: &EventWithHeader) -> Result<(), Error> {
+        let mut event_writer = EventWriter::with_checksum(true);
+        let length = event_writer.serialized_length(event) as u64;
+        let needs_new_chunk = match (self.writer.as_ref(), self.chunks.back()) {
+            (Some(_), Some(chunk)) => chunk.size > 0 && chunk.size + length > self.chunk_size,
+            _ => true,
+        };
+        if needs_new_chunk {
+            self.close_writer()?;
+            let sequence = self.chunks.back().map_or(0, |chunk| chunk.sequence + 1);
+            let path = self.dir.join(format!("{:020}.{}", sequence, CHUNK_FILE_EXTENSION));
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            self.writer = Some(BufWriter::new(file));
+            self.chunks.push_back(Chunk { sequence, path, size: 0 });
+        }
+        let writer = self.writer.as_mut().unwrap();
+        event_writer.write(event, writer)?;
+        writer.flush()?;
+        self.chunks.back_mut().unwrap().size += length;
+        self.enforce_limits()
+    }
+
+    /// Returns the events in the oldest chunk, or None if the spool is empty.
+    /// The chunk is not removed until remove_oldest_chunk() is called.
+    /// If this is the newest chunk, subsequent events will be appended to a new chunk.
+    pub fn read_oldest_chunk(&mut self) -> Result<Option<Vec<SpooledEvent>>, Error> {
+        self.enforce_limits()?;
+        if self.chunks.len() == 1 {
+            self.close_writer()?;
+        }
+        let chunk = match self.chunks.front() {
+            Some(chunk) => chunk,
+            None => return Ok(None),
+        };
+        let mut reader = BufReader::new(File::open(&chunk.path)?);
+        let mut events = Vec::new();
+        let mut discontinuity = self.discontinuity;
+        loop {
+            let mut event_reader = EventReader::new();
+            let required_buffer_length = match event_reader.read_required_buffer_length(&mut reader) {
+                Ok(length) => length,
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            };
+            let mut buffer = vec![0; required_buffer_length];
+            match event_reader.read_event(&mut reader, &mut buffer[..]) {
+                Ok(event) => {
+                    let mut header = event.header.clone();
+                    header.discontinuity |= discontinuity;
+                    discontinuity = false;
+                    events.push(SpooledEvent { header, payload: event.payload.to_vec() });
+                },
+                Err(err @ ReadEventError::ChecksumMismatch { .. }) => {
+                    warn!("read_oldest_chunk: Skipping corrupt event in {:?}: {}", chunk.path, err);
+                    discontinuity = true;
+                },
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+                    warn!("read_oldest_chunk: Ignoring incomplete event at the end of {:?}", chunk.path);
+                    break;
+                },
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(Some(events))
+    }
+
+    /// Removes the oldest chunk after its events have been written elsewhere.
+    pub fn remove_oldest_chunk(&mut self) -> Result<(), Error> {
+        if self.chunks.len() == 1 {
+            self.close_writer()?;
+        }
+        if let Some(chunk) = self.chunks.pop_front() {
+            fs::remove_file(&chunk.path)?;
+            self.discontinuity = false;
+        }
+        Ok(())
+    }
+
+    fn close_writer(&mut self) -> Result<(), Error> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    // Delete the oldest chunks while the spool is larger than max_size or the oldest chunk is older than max_age.
+    // The chunk being written is never deleted due to its age.
+    fn enforce_limits(&mut self) -> Result<(), Error> {
+        loop {
+            let oldest = match self.chunks.front() {
+                Some(chunk) => chunk,
+                None => return Ok(()),
+            };
+            let is_newest = self.chunks.len() == 1;
+            let too_large = self.size() > self.max_size;
+            let too_old = !(is_newest && self.writer.is_some()) && match self.max_age {
+                Some(max_age) => {
+                    let modified = fs::metadata(&oldest.path)?.modified()?;
+                    SystemTime::now().duration_since(modified).map_or(false, |age| age > max_age)
+                },
+                None => false,
+            };
+            if !too_large && !too_old {
+                return Ok(());
+            }
+            warn!("enforce_limits: Deleting spool chunk {:?} with {} bytes; too_large={}, too_old={}",
+                oldest.path, oldest.size, too_large, too_old);
+            if is_newest {
+                self.close_writer()?;
+            }
+            let chunk = self.chunks.pop_front().unwrap();
+            fs::remove_file(&chunk.path)?;
+            self.discontinuity = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::timestamp::PravegaTimestamp;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pravega-video-spool-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn event(payload: &[u8], timestamp: u64, discontinuity: bool) -> EventWithHeader<'_> {
+        EventWithHeader::new(payload, PravegaTimestamp::from_nanoseconds(Some(timestamp)), true, true, discontinuity)
+    }
+
+    fn read_all(spool: &mut Spool) -> Vec<SpooledEvent> {
+        let mut events = Vec::new();
+        while let Some(chunk_events) = spool.read_oldest_chunk().unwrap() {
+            events.extend(chunk_events);
+            spool.remove_oldest_chunk().unwrap();
+        }
+        events
+    }
+
+    #[test]
+    fn test_spool_order_and_reopen() {
+        let dir = temp_dir("reopen");
+        let payload = vec![7; 20_000];
+        {
+            let mut spool = Spool::open(&dir, 1024 * 1024, None).unwrap();
+            assert!(spool.is_empty());
+            for i in 0..10 {
+                spool.append(&event(&payload[..], 1000 + i, false)).unwrap();
+            }
+            assert!(spool.chunks.len() > 1);
+            let first_chunk = spool.read_oldest_chunk().unwrap().unwrap();
+            assert_eq!(first_chunk[0].header.timestamp, PravegaTimestamp::from_nanoseconds(Some(1000)));
+            // The first event of a new spool is marked as discontinuous.
+            assert!(first_chunk[0].header.discontinuity);
+            assert!(first_chunk[1..].iter().all(|e| !e.header.discontinuity));
+            spool.remove_oldest_chunk().unwrap();
+            // A partial event at the end of the newest chunk is ignored.
+            let newest = spool.chunks.back().unwrap().path.clone();
+            spool.close_writer().unwrap();
+            OpenOptions::new().append(true).open(&newest).unwrap().write_all(&[0, 0, 0, 0, 0, 0, 1]).unwrap();
+            spool.append(&event(&payload[..], 1010, false)).unwrap();
+            drop(spool);
+            // Append to the spool while the oldest remaining chunk is being drained.
+            let mut spool = Spool::open(&dir, 1024 * 1024, None).unwrap();
+            let mut events = spool.read_oldest_chunk().unwrap().unwrap();
+            spool.append(&event(&payload[..], 1011, false)).unwrap();
+            spool.remove_oldest_chunk().unwrap();
+            events.extend(read_all(&mut spool));
+            let timestamps: Vec<_> = events.iter().map(|e| e.header.timestamp.nanoseconds().unwrap()).collect();
+            assert_eq!(timestamps[0], 1000 + first_chunk.len() as u64);
+            assert_eq!(timestamps.last(), Some(&1011));
+            assert!(timestamps.windows(2).all(|w| w[0] < w[1]));
+            assert_eq!(events[0].payload, payload);
+            assert!(spool.is_empty());
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_spool_max_size() {
+        let dir = temp_dir("max_size");
+        let payload = vec![1; 30_000];
+        let mut spool = Spool::open(&dir, 200_000, None).unwrap();
+        read_all(&mut spool);
+        for i in 0..20 {
+            spool.append(&event(&payload[..], 1000 + i, false)).unwrap();
+            assert!(spool.size() <= 200_000);
+        }
+        let events = read_all(&mut spool);
+        assert!(events.len() < 20);
+        assert_eq!(events.last().unwrap().header.timestamp, PravegaTimestamp::from_nanoseconds(Some(1019)));
+        // The first event after deleted events is marked as discontinuous.
+        assert!(events[0].header.discontinuity);
+        assert!(events[1..].iter().all(|e| !e.header.discontinuity));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -9,6 +9,7 @@
 //
 
 use anyhow;
+use crate::leap_seconds;
 use std::convert::{TryInto, TryFrom};
 use std::fmt;
 use std::ops::{Add, Mul, Sub, Div};
@@ -28,12 +29,6 @@ impl PravegaTimestamp {
     // See [https://stackoverflow.com/a/29138806/5890553].
     const UNIX_TO_NTP_SECONDS: u64 = (70 * 365 + 17) * 24 * 60 * 60;
 
-    // UTC to TAI offset.
-    // Below is valid for dates between 2017-01-01 and the next leap second.
-    // Beyond this range, we must use a table that incorporates the leap second schedule.
-    // See [https://en.wikipedia.org/wiki/International_Atomic_Time].
-    const UTC_TO_TAI_SECONDS: u64 = 37;
-
     // Create a PravegaTimestamp from the number of nanoseconds since the TAI epoch 1970-01-01 00:00:00 TAI.
     pub fn from_nanoseconds(nanoseconds: Option<u64>) -> PravegaTimestamp {
         PravegaTimestamp(nanoseconds)
@@ -41,29 +36,59 @@ impl PravegaTimestamp {
 
     /// Create a PravegaTimestamp from the number of nanoseconds since the NTP epoch 1900-01-01 00:00:00 UTC,
     /// minus leap seconds.
-    /// A time that cannot be represented will return a None timestamp.
-    /// TODO: Return an error if time cannot be represented.
+    /// A time during a positive leap second is mapped to the first second of the following day.
+    /// A time before the Unix epoch will return a None timestamp.
     pub fn from_ntp_nanoseconds(nanoseconds: Option<u64>) -> PravegaTimestamp {
-        match nanoseconds {
-            Some(nanoseconds) => {
-                if nanoseconds >= PravegaTimestamp::UNIX_TO_NTP_SECONDS * 1_000_000_000 {
-                    PravegaTimestamp::from_unix_nanoseconds(Some(nanoseconds - PravegaTimestamp::UNIX_TO_NTP_SECONDS * 1_000_000_000))
-                } else {
-                    PravegaTimestamp(None)
-                }
-            },
-            None => PravegaTimestamp(None),
+        let unix_nanoseconds = nanoseconds.and_then(|n| n.checked_sub(PravegaTimestamp::UNIX_TO_NTP_SECONDS * 1_000_000_000));
+        PravegaTimestamp::from_unix_nanoseconds(unix_nanoseconds)
+    }
+
+    /// Create a PravegaTimestamp from the number of nanoseconds since the NTP epoch 1900-01-01 00:00:00 UTC,
+    /// minus leap seconds.
+    /// Returns an error if the time is before the Unix epoch or if it is ambiguous due to a leap second.
+    pub fn try_from_ntp_nanoseconds(nanoseconds: u64) -> anyhow::Result<PravegaTimestamp> {
+        match nanoseconds.checked_sub(PravegaTimestamp::UNIX_TO_NTP_SECONDS * 1_000_000_000) {
+            Some(unix_nanoseconds) => PravegaTimestamp::try_from_unix_nanoseconds(unix_nanoseconds),
+            None => anyhow::bail!("NTP time {} ns is before the Unix epoch", nanoseconds),
         }
     }
 
     /// Create a PravegaTimestamp from the number of nanoseconds since the Unix epoch 1970-01-01 00:00:00 UTC,
     /// minus leap seconds.
-    /// TODO: Return an error if time cannot be represented.
+    /// A time during a positive leap second is ambiguous and is mapped to the first second of the following day,
+    /// so that clocks that repeat a second still produce a timestamp.
+    /// A time that cannot be represented will return a None timestamp.
     pub fn from_unix_nanoseconds(nanoseconds: Option<u64>) -> PravegaTimestamp {
-        match nanoseconds {
-            Some(nanoseconds) => PravegaTimestamp(Some(nanoseconds + PravegaTimestamp::UTC_TO_TAI_SECONDS * 1_000_000_000)),
-            None => PravegaTimestamp(None),
-        }
+        let nanoseconds = nanoseconds.and_then(|n| leap_seconds::current_table().unix_to_tai_nanoseconds_lenient(n).ok());
+        PravegaTimestamp(nanoseconds)
+    }
+
+    /// Create a PravegaTimestamp from the number of nanoseconds since the Unix epoch 1970-01-01 00:00:00 UTC,
+    /// minus leap seconds.
+    /// The leap second table in [crate::leap_seconds] is used to determine the UTC to TAI offset.
+    /// Returns an error if the time is ambiguous because it occurs during a positive leap second.
+    pub fn try_from_unix_nanoseconds(nanoseconds: u64) -> anyhow::Result<PravegaTimestamp> {
+        let nanoseconds = leap_seconds::current_table().unix_to_tai_nanoseconds(nanoseconds)?;
+        Ok(PravegaTimestamp(Some(nanoseconds)))
+    }
+
+    /// Create a PravegaTimestamp from a UTC date and time.
+    /// Unlike Unix time, this can unambiguously represent a leap second (23:59:60).
+    pub fn try_from_datetime<Tz: chrono::TimeZone>(t: &chrono::DateTime<Tz>) -> anyhow::Result<PravegaTimestamp> {
+        let t = t.with_timezone(&chrono::Utc);
+        let unix_seconds = u64::try_from(t.timestamp())?;
+        let subsec_nanoseconds = t.timestamp_subsec_nanos();
+        let table = leap_seconds::current_table();
+        let nanoseconds = if subsec_nanoseconds >= 1_000_000_000 {
+            // Chrono represents the leap second 23:59:60 as 23:59:59 with more than 1 billion nanoseconds.
+            table.leap_second_to_tai_nanoseconds(unix_seconds + 1, subsec_nanoseconds - 1_000_000_000)?
+        } else {
+            let tai_seconds = unix_seconds + table.tai_minus_utc_at_unix_seconds(unix_seconds);
+            tai_seconds.checked_mul(1_000_000_000)
+                .and_then(|n| n.checked_add(subsec_nanoseconds as u64))
+                .ok_or_else(|| anyhow::anyhow!("Time {} cannot be represented", t))?
+        };
+        Ok(PravegaTimestamp(Some(nanoseconds)))
     }
 
     pub fn now() -> PravegaTimestamp {
@@ -87,30 +112,50 @@ impl PravegaTimestamp {
         self.0
     }
 
-    /// TODO: Return an error if time cannot be represented.
+    /// Return the number of nanoseconds since the Unix epoch 1970-01-01 00:00:00 UTC, minus leap seconds.
+    /// A time that cannot be represented will return None.
     pub fn to_unix_nanoseconds(&self) -> Option<u64> {
+        self.try_to_unix_nanoseconds().ok()
+    }
+
+    /// Return the number of nanoseconds since the Unix epoch 1970-01-01 00:00:00 UTC, minus leap seconds.
+    /// During a positive leap second, this returns a time in the first second of the following day.
+    pub fn try_to_unix_nanoseconds(&self) -> anyhow::Result<u64> {
         match self.nanoseconds() {
-            Some(nanoseconds) => {
-                if nanoseconds >= PravegaTimestamp::UTC_TO_TAI_SECONDS * 1_000_000_000 {
-                    Some(nanoseconds - PravegaTimestamp::UTC_TO_TAI_SECONDS * 1_000_000_000)
-                } else {
-                    None
-                }
-            },
-            None => None,
+            Some(nanoseconds) => Ok(leap_seconds::current_table().tai_to_unix_nanoseconds(nanoseconds)?.0),
+            None => anyhow::bail!("Timestamp is None"),
         }
     }
 
+    /// Convert to a UTC date and time.
+    /// During a positive leap second, the result will have more than 1 billion nanoseconds,
+    /// which chrono formats as 23:59:60.
+    /// Times before the Unix epoch, which are outside the leap second table, use the first offset in the table.
+    pub fn to_datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let nanoseconds = self.nanoseconds()?;
+        let table = leap_seconds::current_table();
+        let (unix_seconds, subsec_nanoseconds, leap_second) = match table.tai_to_unix_nanoseconds(nanoseconds) {
+            Ok((unix_nanoseconds, leap_second)) => {
+                let unix_seconds = i64::try_from(unix_nanoseconds / 1_000_000_000).ok()?;
+                (unix_seconds, (unix_nanoseconds % 1_000_000_000) as u32, leap_second)
+            },
+            Err(_) => {
+                let tai_minus_utc = table.tai_minus_utc_at_unix_seconds(0) as i128 * 1_000_000_000;
+                let unix_nanoseconds = nanoseconds as i128 - tai_minus_utc;
+                let unix_seconds = i64::try_from(unix_nanoseconds.div_euclid(1_000_000_000)).ok()?;
+                (unix_seconds, unix_nanoseconds.rem_euclid(1_000_000_000) as u32, false)
+            },
+        };
+        let naive = if leap_second {
+            chrono::NaiveDateTime::from_timestamp_opt(unix_seconds - 1, subsec_nanoseconds + 1_000_000_000)?
+        } else {
+            chrono::NaiveDateTime::from_timestamp_opt(unix_seconds, subsec_nanoseconds)?
+        };
+        Some(chrono::DateTime::from_utc(naive, chrono::Utc))
+    }
+
     pub fn to_iso_8601(&self) -> Option<String> {
-        match self.nanoseconds() {
-            Some(_) => {
-                let system_time: SystemTime = (*self).into();
-                let datetime: chrono::DateTime<chrono::offset::Utc> = system_time.into();
-                let formatted_time = datetime.format("%Y-%m-%dT%T.%9fZ");
-                Some(format!("{}", formatted_time))
-                },
-            None => None,
-        }
+        self.to_datetime().map(|datetime| format!("{}", datetime.format("%Y-%m-%dT%T.%9fZ")))
     }
 
     /// Convert to format h:mm:ss.fffffffff
@@ -162,7 +207,7 @@ impl From<SystemTime> for PravegaTimestamp {
 impl From<Option<chrono::DateTime<chrono::Utc>>> for PravegaTimestamp {
     fn from(t: Option<chrono::DateTime<chrono::Utc>>) -> PravegaTimestamp {
         match t {
-            Some(t) => PravegaTimestamp::from_unix_nanoseconds(u64::try_from(t.timestamp_nanos()).ok()),
+            Some(t) => PravegaTimestamp::try_from_datetime(&t).unwrap_or_default(),
             None => PravegaTimestamp::NONE,
         }
     }
@@ -175,8 +220,7 @@ impl TryFrom<Option<&str>> for PravegaTimestamp {
         match t {
             Some(t) => {
                 let dt = chrono::DateTime::parse_from_rfc3339(t)?;
-                let nanos = u64::try_from(dt.timestamp_nanos())?;
-                Ok(PravegaTimestamp::from_unix_nanoseconds(Some(nanos)))
+                PravegaTimestamp::try_from_datetime(&dt)
             },
             None => Ok(PravegaTimestamp::NONE),
         }
@@ -195,8 +239,7 @@ impl TryFrom<&String> for PravegaTimestamp {
     type Error = anyhow::Error;
     fn try_from(t:&String) -> Result<Self, Self::Error> {
         let dt = chrono::DateTime::parse_from_rfc3339(t)?;
-        let nanos = u64::try_from(dt.timestamp_nanos())?;
-        Ok(PravegaTimestamp::from_unix_nanoseconds(Some(nanos)))
+        PravegaTimestamp::try_from_datetime(&dt)
     }
 }
 
@@ -210,16 +253,13 @@ impl TryFrom<String> for PravegaTimestamp {
 /// Returns the timestamp in a friendly human-readable format.
 /// This is currently the same format as to_iso_8601() but may change in the future.
 /// For example: 2001-02-03T04:00:04.200000000Z
+/// A time that cannot be converted to UTC is shown as TAI nanoseconds.
 impl fmt::Display for PravegaTimestamp {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        match self.nanoseconds() {
-            Some(_) => {
-                let system_time: SystemTime = (*self).into();
-                let datetime: chrono::DateTime<chrono::offset::Utc> = system_time.into();
-                let formatted_time = datetime.format("%Y-%m-%dT%T.%9fZ");
-                f.write_fmt(format_args!("{}", formatted_time))
-                },
-            None => f.write_str("None"),
+        match (self.to_iso_8601(), self.nanoseconds()) {
+            (Some(formatted_time), _) => f.write_str(&formatted_time),
+            (None, Some(nanoseconds)) => f.write_fmt(format_args!("{} ns", nanoseconds)),
+            (None, None) => f.write_str("None"),
         }
     }
 }
@@ -230,9 +270,7 @@ impl fmt::Debug for PravegaTimestamp {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match self.nanoseconds() {
             Some(nanoseconds) => {
-                let system_time: SystemTime = (*self).into();
-                let datetime: chrono::DateTime<chrono::offset::Utc> = system_time.into();
-                let formatted_time = datetime.format("%Y-%m-%dT%T.%9fZ");
+                let formatted_time = self.to_iso_8601().unwrap_or_default();
                 f.write_fmt(format_args!("{} ({} ns, {})", formatted_time, nanoseconds, self.to_hms().unwrap_or_default()))
                 },
             None => f.write_str("None"),
@@ -275,6 +313,38 @@ impl Add<Duration> for PravegaTimestamp {
     }
 }
 
+/// A range of timestamps that begins at `begin` (inclusive) and ends at `end` (exclusive).
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct TimeRange {
+    pub begin: PravegaTimestamp,
+    pub end: PravegaTimestamp,
+}
+
+impl TimeRange {
+    /// Parses a comma-separated list of ISO 8601 time intervals in the form begin/end,
+    /// where begin and end are RFC 3339 times such as 2021-04-19T10:02:00Z.
+    /// The order of the list is preserved.
+    pub fn parse_list(s: &str) -> anyhow::Result<Vec<TimeRange>> {
+        s.split(',')
+            .map(|range| range.trim())
+            .filter(|range| !range.is_empty())
+            .map(|range| {
+                let mut parts = range.split('/');
+                let (begin, end) = match (parts.next(), parts.next(), parts.next()) {
+                    (Some(begin), Some(end), None) => (begin, end),
+                    _ => return Err(anyhow::anyhow!("Time range {} must have the form begin/end", range)),
+                };
+                let begin = PravegaTimestamp::try_from(Some(begin.trim()))?;
+                let end = PravegaTimestamp::try_from(Some(end.trim()))?;
+                if begin >= end {
+                    return Err(anyhow::anyhow!("Time range {} must end after it begins", range));
+                }
+                Ok(TimeRange { begin, end })
+            })
+            .collect()
+    }
+}
+
 /// A time delta (difference), represented as a positive or negative number of nanoseconds.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
 pub struct TimeDelta(pub Option<i128>);
@@ -452,29 +522,27 @@ mod test {
     fn test_pravega_timestamp() {
         let s1 = "2001-02-03T04:00:00.000000000Z";
         let pt1 = PravegaTimestamp::try_from(Some(s1)).unwrap();
-        println!("s1 ={}", s1);
-        println!("pt1={}", pt1);
-        println!("pt1={:?}", pt1);
+        assert_eq!(format!("{}", pt1), s1);
+        assert_eq!(format!("{:?}", pt1), "2001-02-03T04:00:00.000000000Z (981172832000000000 ns, 272548:00:32.000000000)");
         let s2 = pt1.to_iso_8601().unwrap();
         assert_eq!(s1, s2);
 
+        // Times before the first entry in the leap second table can still be formatted.
         let dpt2 = PravegaTimestamp::from_nanoseconds(Some(1_000_000_000));
-        println!("dpt2={}", dpt2);
-        println!("dpt2={:?}", dpt2);
+        assert_eq!(format!("{}", dpt2), "1969-12-31T23:59:51.000000000Z");
+        assert_eq!(format!("{:?}", dpt2), "1969-12-31T23:59:51.000000000Z (1000000000 ns, 0:00:01.000000000)");
+        assert_eq!(PravegaTimestamp::MIN.to_iso_8601().unwrap(), "1969-12-31T23:59:50.000000000Z");
+        assert_eq!(format!("{}", PravegaTimestamp::NONE), "None");
         let pt3 = pt1 + dpt2;
-        println!("pt3={:?}", pt3);
         assert_eq!(pt3.to_iso_8601().unwrap(), "2001-02-03T04:00:01.000000000Z");
 
         let dur4 = Duration::from_nanos(1_000_500_000);
-        println!("dur4={:?}", dur4);
         let pt5 = pt1 + dur4;
-        println!("pt5={:?}", pt5);
         assert_eq!(pt5.to_iso_8601().unwrap(), "2001-02-03T04:00:01.000500000Z");
 
         let delta6 = 3 * SECOND;
-        println!("delta6={}", delta6);
+        assert_eq!(format!("{}", delta6), "+0:00:03.000000000");
         let pt7: PravegaTimestamp = pt1 + delta6;
-        println!("pt7={:?}", pt7);
         assert_eq!(pt7.to_iso_8601().unwrap(), "2001-02-03T04:00:03.000000000Z");
 
         let delta8 = pt7 - pt1;
@@ -490,4 +558,53 @@ mod test {
         let delta11: TimeDelta = delta8 / 10;
         assert_eq!(delta11.to_hms().unwrap(), "+0:00:00.300000000");
     }
+
+    #[test]
+    fn test_pravega_timestamp_leap_seconds() {
+        // Before 2017, TAI - UTC was less than 37 seconds.
+        let pt1 = PravegaTimestamp::try_from(Some("2001-02-03T04:00:00.000000000Z")).unwrap();
+        assert_eq!(pt1.nanoseconds().unwrap(), (981_172_800 + 32) * 1_000_000_000);
+        assert_eq!(pt1.to_unix_nanoseconds().unwrap(), 981_172_800 * 1_000_000_000);
+        let pt2 = PravegaTimestamp::from_unix_nanoseconds(Some(981_172_800 * 1_000_000_000));
+        assert_eq!(pt1, pt2);
+
+        // The leap second at the end of 2016 can be parsed and formatted.
+        let s3 = "2016-12-31T23:59:60.500000000Z";
+        let pt3 = PravegaTimestamp::try_from(Some(s3)).unwrap();
+        assert_eq!(pt3.nanoseconds().unwrap(), (1_483_228_800 + 36) * 1_000_000_000 + 500_000_000);
+        assert_eq!(pt3.to_iso_8601().unwrap(), s3);
+        let pt4 = PravegaTimestamp::try_from(Some("2016-12-31T23:59:59.500000000Z")).unwrap();
+        let pt5 = PravegaTimestamp::try_from(Some("2017-01-01T00:00:00.500000000Z")).unwrap();
+        assert_eq!(pt3 - pt4, SECOND);
+        assert_eq!(pt5 - pt3, SECOND);
+        assert_eq!(pt5.to_iso_8601().unwrap(), "2017-01-01T00:00:00.500000000Z");
+
+        // A Unix time during the leap second is ambiguous.
+        // The infallible conversion maps it to the first second of the following day.
+        assert!(PravegaTimestamp::try_from_unix_nanoseconds(1_483_228_800_500_000_000).is_err());
+        assert_eq!(PravegaTimestamp::from_unix_nanoseconds(Some(1_483_228_800_500_000_000)), pt5);
+        assert!(PravegaTimestamp::try_from(Some("2017-06-30T23:59:60Z")).is_err());
+
+        // NTP time is converted the same way.
+        let ntp_nanoseconds = (981_172_800 + PravegaTimestamp::UNIX_TO_NTP_SECONDS) * 1_000_000_000;
+        assert_eq!(PravegaTimestamp::from_ntp_nanoseconds(Some(ntp_nanoseconds)), pt1);
+        assert!(PravegaTimestamp::try_from_ntp_nanoseconds(0).is_err());
+        assert_eq!(PravegaTimestamp::from_ntp_nanoseconds(Some(0)), PravegaTimestamp::NONE);
+        let ntp_leap_nanoseconds = (1_483_228_800 + PravegaTimestamp::UNIX_TO_NTP_SECONDS) * 1_000_000_000 + 500_000_000;
+        assert_eq!(PravegaTimestamp::from_ntp_nanoseconds(Some(ntp_leap_nanoseconds)), pt5);
+    }
+
+    #[test]
+    fn test_time_range_parse_list() {
+        let ranges = TimeRange::parse_list(
+            "2021-04-19T10:02:00Z/2021-04-19T10:05:00Z, 2021-04-19T10:40:00Z/2021-04-19T10:41:00Z").unwrap();
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges[0].begin, PravegaTimestamp::try_from(Some("2021-04-19T10:02:00Z")).unwrap());
+        assert_eq!(ranges[0].end - ranges[0].begin, 3 * MINUTE);
+        assert_eq!(ranges[1].end - ranges[1].begin, MINUTE);
+        assert!(TimeRange::parse_list("").unwrap().is_empty());
+        assert!(TimeRange::parse_list("2021-04-19T10:05:00Z/2021-04-19T10:02:00Z").is_err());
+        assert!(TimeRange::parse_list("2021-04-19T10:02:00Z").is_err());
+        assert!(TimeRange::parse_list("2021-04-19T10:02:00Z/invalid").is_err());
+    }
 }
@@ -14,9 +14,16 @@ use std::net::{SocketAddr, AddrParseError};
 use std::time::{Duration, UNIX_EPOCH};
 use std::env;
 
+use anyhow::anyhow;
 use pravega_client::byte_stream::ByteStreamReader;
+use pravega_client::client_factory::ClientFactory;
 use pravega_client_config::ClientConfigBuilder;
 use pravega_client_config::ClientConfig;
+use pravega_client_shared::{Scaling, ScaleType, Scope, ScopedSegment, ScopedStream, Segment, Stream};
+
+use crate::catalog::{CatalogReader, PartitionRecord, get_catalog_stream_name};
+use crate::index::get_index_stream_name;
+use crate::metadata::get_metadata_stream_name;
 
 const ENV_VAR_NAME_AUTH_KEYCLOAK: &str = "pravega_client_auth_keycloak";
 const ENV_VAR_NAME_AUTH_METHOD: &str = "pravega_client_auth_method";
@@ -37,6 +44,69 @@ impl CurrentHead for ByteStreamReader {
 
 impl<T> CurrentHead for std::io::Cursor<T> {}
 
+/// Returns the scaling policy of a stream that stores a byte stream, including data, index, metadata and catalog streams.
+///
+/// A byte stream is stored in exactly one segment and readers do not follow successor segments,
+/// so these streams have a single segment and never scale automatically.
+pub fn byte_stream_scaling() -> Scaling {
+    Scaling {
+        scale_type: ScaleType::FixedNumSegments,
+        min_num_segments: 1,
+        ..Default::default()
+    }
+}
+
+/// Returns the segment that stores the byte stream of a Pravega stream.
+///
+/// A byte stream is stored in exactly one active segment.
+/// A new stream has a single segment numbered 0 but, if the stream has been manually scaled,
+/// the only active segment will be a successor of the original segment.
+/// Offsets in the data and index streams are relative to the returned segment.
+/// Successors are not followed, so if a data stream moves to a successor segment,
+/// its index must be rebuilt with [crate::index_rebuilder::IndexRebuilder].
+/// Streams created by this crate use [byte_stream_scaling] so that this does not happen automatically.
+///
+/// Returns an error if the stream has no active segments (it has been sealed)
+/// or if it has been split into multiple active segments, which cannot be read as a byte stream.
+pub fn get_byte_stream_segment(client_factory: &ClientFactory, scoped_stream: &ScopedStream) -> anyhow::Result<ScopedSegment> {
+    let controller_client = client_factory.get_controller_client();
+    let stream_segments = client_factory.get_runtime().block_on(controller_client.get_current_segments(scoped_stream))
+        .map_err(|err| anyhow!("Unable to get segments of stream {}/{}: {:?}", scoped_stream.scope, scoped_stream.stream, err))?;
+    let segments = stream_segments.get_segments();
+    match segments.len() {
+        1 => Ok(segments[0].clone()),
+        0 => Err(anyhow!("Stream {}/{} does not have any active segments",
+            scoped_stream.scope, scoped_stream.stream)),
+        n => Err(anyhow!("Stream {}/{} has {} active segments but a byte stream requires exactly 1",
+            scoped_stream.scope, scoped_stream.stream, n)),
+    }
+}
+
+/// Returns the segments of the data, index, and metadata streams of a partition (see [crate::catalog]).
+/// Unlike [get_byte_stream_segment], this does not require the streams to be active, so it can be used for sealed partitions.
+pub fn get_partition_segments(scope: &Scope, partition: &PartitionRecord) -> (ScopedSegment, ScopedSegment, ScopedSegment) {
+    let scoped_segment = |stream_name: String, segment_number: i64| ScopedSegment {
+        scope: scope.clone(),
+        stream: Stream::from(stream_name),
+        segment: Segment::from(segment_number),
+    };
+    (scoped_segment(partition.stream.clone(), partition.data_segment),
+     scoped_segment(get_index_stream_name(&partition.stream), partition.index_segment),
+     scoped_segment(get_metadata_stream_name(&partition.stream), partition.metadata_segment))
+}
+
+/// Returns the partitions recorded in the catalog stream of a partitioned stream (see [crate::catalog]).
+/// Returns an error if the catalog stream does not exist, which means that the stream is not partitioned.
+pub fn read_catalog(client_factory: &ClientFactory, scope: &Scope, stream_name: &str) -> anyhow::Result<Vec<PartitionRecord>> {
+    let scoped_stream = ScopedStream {
+        scope: scope.clone(),
+        stream: Stream::from(get_catalog_stream_name(stream_name)),
+    };
+    let scoped_segment = get_byte_stream_segment(client_factory, &scoped_stream)?;
+    let mut reader = client_factory.create_byte_stream_reader(scoped_segment);
+    Ok(CatalogReader::new().read_all(&mut reader)?)
+}
+
 pub fn parse_controller_uri(controller: String) -> Result<SocketAddr, AddrParseError> {
     controller.parse::<SocketAddr>()
 }
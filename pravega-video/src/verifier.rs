@@ -0,0 +1,540 @@
+//
+// Copyright (c) Dell Inc., or its subsidiaries. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+
+// Module for verifying that an index and its data stream satisfy the constraints defined in index.rs.
+
+use crate::event_serde::EventReader;
+use crate::index::{IndexRecord, IndexRecordReader};
+use crate::timestamp::PravegaTimestamp;
+use crate::utils::CurrentHead;
+use std::fmt;
+use std::io::{BufReader, Error, Read, Seek, SeekFrom};
+use tracing::{debug, info, warn};
+
+/// A single violation of the index constraints.
+/// Offsets named `index_offset` refer to the index stream. All other offsets refer to the data stream.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Violation {
+    /// The index ends with an incomplete record.
+    PartialIndexRecord {
+        index_offset: u64,
+        length: u64,
+    },
+    /// An index record has a timestamp of 0.
+    MissingIndexTimestamp {
+        index_offset: u64,
+        record: IndexRecord,
+    },
+    /// The offset of an index record is less than the offset of the previous index record.
+    IndexOffsetDecreased {
+        index_offset: u64,
+        record: IndexRecord,
+        prev_record: IndexRecord,
+    },
+    /// The timestamp of an index record is less than the timestamp of the previous index record
+    /// but the record does not have DIS set.
+    IndexTimestampDecreased {
+        index_offset: u64,
+        record: IndexRecord,
+        prev_record: IndexRecord,
+    },
+    /// The data stream has been truncated beyond the offset of an index record.
+    DataTruncated {
+        index_offset: u64,
+        offset: u64,
+        data_head: u64,
+    },
+    /// The offset of an index record is in the middle of the event that begins at `event_offset`.
+    OffsetNotOnEventBoundary {
+        index_offset: u64,
+        offset: u64,
+        event_offset: u64,
+        event_end_offset: u64,
+    },
+    /// The event that begins at `offset` could not be read by EventReader.
+    UnreadableEvent {
+        offset: u64,
+        error: String,
+    },
+    /// The event that begins at `offset` extends beyond the offset of the last index record.
+    EventBeyondReadableRange {
+        offset: u64,
+        readable_end_offset: u64,
+    },
+    /// The event at the offset of an index record does not have the same timestamp and flags
+    /// as the index record, or it does not have IND set.
+    IndexedEventMismatch {
+        index_offset: u64,
+        record: IndexRecord,
+        event_timestamp: PravegaTimestamp,
+        event_include_in_index: bool,
+        event_random_access: bool,
+        event_discontinuity: bool,
+    },
+    /// An event timestamp is less than the timestamp of the preceding index record,
+    /// or equal to or greater than the timestamp of the following index record.
+    /// When the following index record has DIS set, `window_end` is None and only the lower bound is checked.
+    EventTimestampOutOfWindow {
+        offset: u64,
+        timestamp: PravegaTimestamp,
+        window_begin: PravegaTimestamp,
+        window_end: PravegaTimestamp,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::PartialIndexRecord { index_offset, length } =>
+                write!(f, "Partial index record of {} bytes at index offset {}", length, index_offset),
+            Violation::MissingIndexTimestamp { index_offset, record } =>
+                write!(f, "Index record at index offset {} has no timestamp: {:?}", index_offset, record),
+            Violation::IndexOffsetDecreased { index_offset, record, prev_record } =>
+                write!(f, "Index record at index offset {} has offset {} which is less than previous offset {}",
+                    index_offset, record.offset, prev_record.offset),
+            Violation::IndexTimestampDecreased { index_offset, record, prev_record } =>
+                write!(f, "Index record at index offset {} has timestamp {} which is less than previous timestamp {} without DIS",
+                    index_offset, record.timestamp, prev_record.timestamp),
+            Violation::DataTruncated { index_offset, offset, data_head } =>
+                write!(f, "Index record at index offset {} has offset {} but data stream is truncated at {}",
+                    index_offset, offset, data_head),
+            Violation::OffsetNotOnEventBoundary { index_offset, offset, event_offset, event_end_offset } =>
+                write!(f, "Index record at index offset {} has offset {} which is inside the event from {} to {}",
+                    index_offset, offset, event_offset, event_end_offset),
+            Violation::UnreadableEvent { offset, error } =>
+                write!(f, "Unable to read event at offset {}: {}", offset, error),
+            Violation::EventBeyondReadableRange { offset, readable_end_offset } =>
+                write!(f, "Event at offset {} extends beyond the last index record offset {}", offset, readable_end_offset),
+            Violation::IndexedEventMismatch { index_offset, record, event_timestamp,
+                    event_include_in_index, event_random_access, event_discontinuity } =>
+                write!(f, "Index record at index offset {} does not match event: record={:?}, event timestamp={}, IND={}, RAN={}, DIS={}",
+                    index_offset, record, event_timestamp, event_include_in_index, event_random_access, event_discontinuity),
+            Violation::EventTimestampOutOfWindow { offset, timestamp, window_begin, window_end } =>
+                write!(f, "Event at offset {} has timestamp {} which is outside of the index window from {} to {}",
+                    offset, timestamp, window_begin, window_end),
+        }
+    }
+}
+
+/// The result of verifying an index and its data stream.
+#[derive(Debug, Default, Clone)]
+pub struct VerificationReport {
+    pub index_head: u64,
+    pub index_tail: u64,
+    pub data_head: u64,
+    pub first_index_record: Option<IndexRecord>,
+    pub last_index_record: Option<IndexRecord>,
+    pub index_records_checked: u64,
+    pub events_checked: u64,
+    pub violations: Vec<Violation>,
+}
+
+impl VerificationReport {
+    /// Returns true if no violations were found.
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Index: head={}, tail={}, records checked={}", self.index_head, self.index_tail, self.index_records_checked)?;
+        writeln!(f, "First index record: {:?}", self.first_index_record)?;
+        writeln!(f, "Last index record: {:?}", self.last_index_record)?;
+        writeln!(f, "Data: head={}, events checked={}", self.data_head, self.events_checked)?;
+        writeln!(f, "Violations: {}", self.violations.len())?;
+        for violation in self.violations.iter() {
+            writeln!(f, "  {}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+/// Verifies that an index and its data stream satisfy the constraints defined in index.rs.
+///
+/// The entire index is read sequentially. The data stream is read from the offset of the first index record
+/// to the offset of the last index record. For each pair of consecutive index records, this checks that:
+///   - the offset of each index record is the start of an event that can be read by EventReader,
+///   - the event at the offset of an index record has IND set and the same timestamp, RAN and DIS as the record,
+///   - the timestamp of each event is within the window defined by the timestamps of the two records.
+///
+/// Constraint 3 (continuous sampling) cannot be verified from the stored bytes.
+/// Events without a timestamp are not checked against their window.
+///
+/// Errors reading the index are returned as an error.
+/// Errors reading the data stream are recorded as violations and verification resumes at the next index record.
+pub struct StreamVerifier<D: Read + Seek + CurrentHead, I: Read + Seek + CurrentHead> {
+    data_reader: BufReader<D>,
+    index_reader: BufReader<I>,
+    event_reader: EventReader,
+    buffer: Vec<u8>,
+}
+
+impl<D: Read + Seek + CurrentHead, I: Read + Seek + CurrentHead> StreamVerifier<D, I> {
+    pub fn new(data_reader: D, index_reader: I) -> Self {
+        Self {
+            data_reader: BufReader::with_capacity(128 * 1024, data_reader),
+            index_reader: BufReader::with_capacity(8 * 1024, index_reader),
+            event_reader: EventReader::new(),
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn verify(&mut self) -> Result<VerificationReport, Error> {
+        let record_size = IndexRecord::RECORD_SIZE as u64;
+        let mut report = VerificationReport {
+            index_head: self.index_reader.get_ref().current_head()?,
+            index_tail: self.index_reader.seek(SeekFrom::End(0))?,
+            data_head: self.data_reader.get_ref().current_head()?,
+            ..Default::default()
+        };
+        let index_length = report.index_tail.saturating_sub(report.index_head);
+        let num_records = index_length / record_size;
+        debug!("verify: index_head={}, index_tail={}, num_records={}, data_head={}",
+            report.index_head, report.index_tail, num_records, report.data_head);
+        if num_records == 0 {
+            self.check_partial_index_record(&mut report, num_records);
+            return Ok(report);
+        }
+
+        // The readable range of the data stream ends at the offset of the last index record.
+        self.index_reader.seek(SeekFrom::Start(report.index_head + (num_records - 1) * record_size))?;
+        let mut index_record_reader = IndexRecordReader::new();
+        let last_record = index_record_reader.read(&mut self.index_reader)?;
+        let readable_end_offset = last_record.offset;
+        report.last_index_record = Some(last_record);
+
+        self.index_reader.seek(SeekFrom::Start(report.index_head))?;
+        let mut prev_record: Option<IndexRecord> = None;
+        // The data stream offset of the next event to read, or None if the next event is unknown.
+        let mut data_offset: Option<u64> = None;
+        for i in 0..num_records {
+            let index_offset = report.index_head + i * record_size;
+            let record = index_record_reader.read(&mut self.index_reader)?;
+            report.index_records_checked += 1;
+            self.check_index_record(&mut report, index_offset, &record, prev_record.as_ref());
+
+            if let Some(prev_record) = prev_record {
+                if let Some(offset) = data_offset {
+                    data_offset = self.verify_window(&mut report, index_offset, &prev_record, &record, offset, readable_end_offset);
+                }
+            } else {
+                report.first_index_record = Some(record);
+            }
+
+            // Resynchronize with the data stream at this index record.
+            // If the previous event ended after this record's offset, the violation has already been recorded
+            // and we continue from the end of that event because it is known to be an event boundary.
+            let offset_decreased = matches!(prev_record, Some(p) if record.offset < p.offset);
+            let resync = match data_offset {
+                Some(offset) => offset < record.offset || offset_decreased,
+                None => true,
+            };
+            if resync {
+                data_offset = if record.offset < report.data_head {
+                    report.violations.push(Violation::DataTruncated {
+                        index_offset,
+                        offset: record.offset,
+                        data_head: report.data_head,
+                    });
+                    None
+                } else {
+                    match self.data_reader.seek(SeekFrom::Start(record.offset)) {
+                        Ok(_) => Some(record.offset),
+                        Err(err) => {
+                            report.violations.push(Violation::UnreadableEvent {
+                                offset: record.offset,
+                                error: err.to_string(),
+                            });
+                            None
+                        },
+                    }
+                };
+            }
+            prev_record = Some(record);
+        }
+        self.check_partial_index_record(&mut report, num_records);
+
+        if report.is_ok() {
+            info!("verify: No violations found in {} index records and {} events",
+                report.index_records_checked, report.events_checked);
+        } else {
+            warn!("verify: Found {} violations in {} index records and {} events",
+                report.violations.len(), report.index_records_checked, report.events_checked);
+        }
+        Ok(report)
+    }
+
+    fn check_partial_index_record(&self, report: &mut VerificationReport, num_records: u64) {
+        let record_size = IndexRecord::RECORD_SIZE as u64;
+        let index_length = report.index_tail.saturating_sub(report.index_head);
+        if index_length % record_size != 0 {
+            report.violations.push(Violation::PartialIndexRecord {
+                index_offset: report.index_head + num_records * record_size,
+                length: index_length % record_size,
+            });
+        }
+    }
+
+    fn check_index_record(&self, report: &mut VerificationReport, index_offset: u64,
+                          record: &IndexRecord, prev_record: Option<&IndexRecord>) {
+        if record.timestamp.is_none() {
+            report.violations.push(Violation::MissingIndexTimestamp {
+                index_offset,
+                record: *record,
+            });
+        }
+        if let Some(prev_record) = prev_record {
+            if record.offset < prev_record.offset {
+                report.violations.push(Violation::IndexOffsetDecreased {
+                    index_offset,
+                    record: *record,
+                    prev_record: *prev_record,
+                });
+            }
+            if record.timestamp.is_some() && prev_record.timestamp.is_some()
+                    && record.timestamp < prev_record.timestamp && !record.discontinuity {
+                report.violations.push(Violation::IndexTimestampDecreased {
+                    index_offset,
+                    record: *record,
+                    prev_record: *prev_record,
+                });
+            }
+        }
+    }
+
+    /// Reads all events from `offset` up to the offset of `record`.
+    /// The data reader must be positioned at `offset`, which should equal the offset of `prev_record`.
+    /// Returns the offset of the next event or None if the data stream could not be read.
+    fn verify_window(&mut self, report: &mut VerificationReport, index_offset: u64,
+                     prev_record: &IndexRecord, record: &IndexRecord,
+                     offset: u64, readable_end_offset: u64) -> Option<u64> {
+        let window_begin = prev_record.timestamp;
+        let window_end = if record.discontinuity { PravegaTimestamp::NONE } else { record.timestamp };
+        let mut offset = offset;
+        while offset < record.offset {
+            // Reads beyond the readable range may block indefinitely so they must be avoided.
+            if offset + 8 > readable_end_offset {
+                report.violations.push(Violation::EventBeyondReadableRange { offset, readable_end_offset });
+                return None;
+            }
+            let required_buffer_length = match self.event_reader.read_required_buffer_length(&mut self.data_reader) {
+                Ok(length) => length,
+                Err(err) => {
+                    report.violations.push(Violation::UnreadableEvent { offset, error: err.to_string() });
+                    return None;
+                },
+            };
+            let event_end_offset = offset + required_buffer_length as u64;
+            if event_end_offset > readable_end_offset {
+                report.violations.push(Violation::EventBeyondReadableRange { offset, readable_end_offset });
+                return None;
+            }
+            if self.buffer.len() < required_buffer_length {
+                self.buffer.resize(required_buffer_length, 0);
+            }
+            let event = match self.event_reader.read_event(&mut self.data_reader, &mut self.buffer[..]) {
+                Ok(event) => event,
+                Err(err) => {
+                    report.violations.push(Violation::UnreadableEvent { offset, error: err.to_string() });
+                    return None;
+                },
+            };
+            report.events_checked += 1;
+
+            if offset == prev_record.offset {
+                let header = &event.header;
+                if !header.include_in_index
+                        || header.timestamp != prev_record.timestamp
+                        || header.random_access != prev_record.random_access
+                        || header.discontinuity != prev_record.discontinuity {
+                    report.violations.push(Violation::IndexedEventMismatch {
+                        index_offset: index_offset - IndexRecord::RECORD_SIZE as u64,
+                        record: *prev_record,
+                        event_timestamp: header.timestamp,
+                        event_include_in_index: header.include_in_index,
+                        event_random_access: header.random_access,
+                        event_discontinuity: header.discontinuity,
+                    });
+                }
+            }
+
+            let timestamp = event.header.timestamp;
+            if timestamp.is_some() && window_begin.is_some() {
+                let before_window = timestamp < window_begin;
+                let after_window = window_end.is_some() && timestamp >= window_end;
+                if before_window || after_window {
+                    report.violations.push(Violation::EventTimestampOutOfWindow {
+                        offset,
+                        timestamp,
+                        window_begin,
+                        window_end,
+                    });
+                }
+            }
+
+            if event_end_offset > record.offset {
+                report.violations.push(Violation::OffsetNotOnEventBoundary {
+                    index_offset,
+                    offset: record.offset,
+                    event_offset: offset,
+                    event_end_offset,
+                });
+            }
+            offset = event_end_offset;
+        }
+        Some(offset)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event_serde::{EventWithHeader, EventWriter};
+    use crate::index::IndexRecordWriter;
+    use std::io::Cursor;
+
+    /// Writes events to a data stream the way pravegasink does, indexing every event with RAN set.
+    /// Returns the data stream, the index and the offset of each event.
+    fn create_stream(timestamps: &[u64], random_access: &[bool]) -> (Cursor<Vec<u8>>, Cursor<Vec<u8>>, Vec<u64>) {
+        let mut data = Cursor::new(Vec::new());
+        let mut index = Cursor::new(Vec::new());
+        let mut offsets = Vec::new();
+        let mut event_writer = EventWriter::new();
+        let mut index_record_writer = IndexRecordWriter::new();
+        let payload = [0xAA; 188];
+        for (i, (timestamp, random_access)) in timestamps.iter().zip(random_access.iter()).enumerate() {
+            let timestamp = PravegaTimestamp::from_nanoseconds(Some(*timestamp));
+            let offset = data.seek(SeekFrom::Current(0)).unwrap();
+            offsets.push(offset);
+            let discontinuity = i == 0;
+            if *random_access {
+                let rec = IndexRecord::new(timestamp, offset, true, discontinuity);
+                index_record_writer.write(&rec, &mut index).unwrap();
+            }
+            let event = EventWithHeader::new(&payload[..], timestamp, *random_access, *random_access, discontinuity);
+            event_writer.write(&event, &mut data).unwrap();
+        }
+        // Write the final index record at the end of the data, as pravegasink does when stopping.
+        let offset = data.seek(SeekFrom::Current(0)).unwrap();
+        let final_timestamp = PravegaTimestamp::from_nanoseconds(Some(timestamps.last().unwrap() + 1));
+        index_record_writer.write(&IndexRecord::new(final_timestamp, offset, false, false), &mut index).unwrap();
+        (data, index, offsets)
+    }
+
+    fn write_index(records: &[IndexRecord]) -> Cursor<Vec<u8>> {
+        let mut index = Cursor::new(Vec::new());
+        let mut index_record_writer = IndexRecordWriter::new();
+        for rec in records.iter() {
+            index_record_writer.write(rec, &mut index).unwrap();
+        }
+        index
+    }
+
+    #[test]
+    fn test_verifier_valid_stream() {
+        let timestamps: Vec<u64> = (1..=10).map(|i| i * 1000).collect();
+        let random_access = [true, false, false, true, false, false, true, false, false, false];
+        let (data, index, _) = create_stream(&timestamps, &random_access);
+        let mut verifier = StreamVerifier::new(data, index);
+        let report = verifier.verify().unwrap();
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.index_records_checked, 4);
+        assert_eq!(report.events_checked, 10);
+        assert_eq!(report.first_index_record.unwrap().timestamp, PravegaTimestamp::from_nanoseconds(Some(1000)));
+        assert_eq!(report.last_index_record.unwrap().timestamp, PravegaTimestamp::from_nanoseconds(Some(10001)));
+    }
+
+    #[test]
+    fn test_verifier_empty_index() {
+        let mut verifier = StreamVerifier::new(Cursor::new(Vec::new()), Cursor::new(Vec::new()));
+        let report = verifier.verify().unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.index_records_checked, 0);
+    }
+
+    #[test]
+    fn test_verifier_event_timestamp_out_of_window() {
+        // The third event has a timestamp before its index window.
+        let timestamps: Vec<u64> = vec![1000, 2000, 500, 4000, 5000];
+        let random_access = [true, false, false, true, false];
+        let (data, index, offsets) = create_stream(&timestamps, &random_access);
+        let report = StreamVerifier::new(data, index).verify().unwrap();
+        assert_eq!(report.violations, vec![
+            Violation::EventTimestampOutOfWindow {
+                offset: offsets[2],
+                timestamp: PravegaTimestamp::from_nanoseconds(Some(500)),
+                window_begin: PravegaTimestamp::from_nanoseconds(Some(1000)),
+                window_end: PravegaTimestamp::from_nanoseconds(Some(4000)),
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_verifier_offset_not_on_event_boundary() {
+        let timestamps: Vec<u64> = vec![1000, 2000, 3000, 4000];
+        let random_access = [true, false, false, false];
+        let (data, _, offsets) = create_stream(&timestamps, &random_access);
+        let data_length = data.get_ref().len() as u64;
+        let index = write_index(&[
+            IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(1000)), 0, true, true),
+            IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(2500)), offsets[1] + 10, true, false),
+            IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(4001)), data_length, false, false),
+        ]);
+        let report = StreamVerifier::new(data, index).verify().unwrap();
+        assert_eq!(report.events_checked, 4);
+        assert_eq!(report.violations, vec![
+            Violation::OffsetNotOnEventBoundary {
+                index_offset: 20,
+                offset: offsets[1] + 10,
+                event_offset: offsets[1],
+                event_end_offset: offsets[2],
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_verifier_unreadable_event() {
+        let timestamps: Vec<u64> = vec![1000, 2000, 3000, 4000];
+        let random_access = [true, false, true, false];
+        let (mut data, index, offsets) = create_stream(&timestamps, &random_access);
+        // Corrupt the event length of the second event.
+        data.get_mut()[offsets[1] as usize + 4..offsets[1] as usize + 8].copy_from_slice(&[0xFF; 4]);
+        let report = StreamVerifier::new(data, index).verify().unwrap();
+        // Verification resumes at the next index record.
+        assert_eq!(report.events_checked, 3);
+        assert_eq!(report.violations.len(), 1);
+        assert!(matches!(report.violations[0], Violation::UnreadableEvent { offset, .. } if offset == offsets[1]));
+    }
+
+    #[test]
+    fn test_verifier_index_records() {
+        let timestamps: Vec<u64> = vec![1000, 2000, 3000, 4000];
+        let random_access = [true, true, true, true];
+        let (data, _, offsets) = create_stream(&timestamps, &random_access);
+        let data_length = data.get_ref().len() as u64;
+        let mut index = write_index(&[
+            IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(1000)), offsets[0], true, true),
+            // Timestamp does not match the event.
+            IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(1500)), offsets[1], true, false),
+            // Timestamp decreases without DIS.
+            IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(1400)), offsets[2], true, false),
+            IndexRecord::new(PravegaTimestamp::from_nanoseconds(Some(4001)), data_length, false, false),
+        ]);
+        index.get_mut().extend_from_slice(&[0; 7]);
+        let report = StreamVerifier::new(data, index).verify().unwrap();
+        assert_eq!(report.violations.len(), 5, "{}", report);
+        assert!(matches!(report.violations[0], Violation::IndexTimestampDecreased { index_offset: 40, .. }));
+        assert!(matches!(report.violations[1], Violation::IndexedEventMismatch { index_offset: 20, .. }));
+        assert!(matches!(report.violations[2], Violation::EventTimestampOutOfWindow { offset, .. } if offset == offsets[1]));
+        assert!(matches!(report.violations[3], Violation::IndexedEventMismatch { index_offset: 40, .. }));
+        assert_eq!(report.violations[4], Violation::PartialIndexRecord { index_offset: 80, length: 7 });
+    }
+}